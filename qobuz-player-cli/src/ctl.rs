@@ -0,0 +1,80 @@
+use std::path::Path;
+
+use clap::Subcommand;
+use qobuz_player_controls::AppResult;
+use qobuz_player_ipc::client::Connection;
+use serde_json::{Value, json};
+
+#[derive(Subcommand, Debug)]
+pub enum CtlCommand {
+    /// Resume playback
+    Play,
+
+    /// Pause playback
+    Pause,
+
+    /// Toggle between playing and paused
+    PlayPause,
+
+    /// Skip to the next track
+    Next,
+
+    /// Go to the previous track
+    Previous,
+
+    /// Print the player status as JSON
+    Status,
+
+    /// Print the queue as JSON
+    Queue,
+
+    /// Print the volume, or set it when a value between 0 and 1 is given
+    Volume { volume: Option<f32> },
+
+    /// Play a Qobuz share link or qobuz: uri
+    PlayUrl {
+        url: String,
+
+        #[clap(long)]
+        /// Add to the queue instead of replacing it
+        queue: bool,
+    },
+
+    /// Print player events as JSON lines until the player exits
+    Follow,
+}
+
+pub async fn run(socket_path: &Path, command: CtlCommand) -> AppResult<()> {
+    let mut connection = Connection::connect(socket_path).await?;
+
+    let (method, params) = match command {
+        CtlCommand::Play => ("play", Value::Null),
+        CtlCommand::Pause => ("pause", Value::Null),
+        CtlCommand::PlayPause => ("play_pause", Value::Null),
+        CtlCommand::Next => ("next", Value::Null),
+        CtlCommand::Previous => ("previous", Value::Null),
+        CtlCommand::Status => ("status", Value::Null),
+        CtlCommand::Queue => ("queue", Value::Null),
+        CtlCommand::Volume { volume } => ("volume", json!({ "volume": volume })),
+        CtlCommand::PlayUrl { url, queue } => ("play_url", json!({ "url": url, "queue": queue })),
+        CtlCommand::Follow => {
+            connection.call("follow", Value::Null).await?;
+
+            while let Some(notification) = connection.next_notification().await? {
+                println!(
+                    "{}",
+                    json!({ "type": notification.method, "data": notification.params })
+                );
+            }
+
+            return Ok(());
+        }
+    };
+
+    let result = connection.call(method, params).await?;
+    if !result.is_null() {
+        println!("{result}");
+    }
+
+    Ok(())
+}
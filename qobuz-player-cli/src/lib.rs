@@ -1,10 +1,23 @@
 use clap::{Args, Subcommand};
 use qobuz_player_controls::{
-    AppResult, AudioQuality, client::Client, database::Database,
-    notification::NotificationBroadcast, player::Player,
+    AppResult, AudioQuality,
+    client::Client,
+    database::Database,
+    models::Link,
+    notification::NotificationBroadcast,
+    player_builder::{PlayerBuilder, PlayerHandle},
 };
-use std::{path::PathBuf, sync::Arc, time::Duration};
-use tokio_schedule::{Job, every};
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
+
+#[cfg(unix)]
+mod ctl;
+#[cfg(unix)]
+pub use ctl::CtlCommand;
 
 #[derive(Args, Debug)]
 pub struct SharedArgs {
@@ -22,6 +35,73 @@ pub struct SharedArgs {
     /// Use provided device for audio output, instead of default.
     /// Use qobuz-player list-devices for output device list
     pub output_device_id: Option<String>,
+
+    #[clap(long)]
+    /// Start playing a Qobuz share link or qobuz: uri (album, playlist, track, artist or label)
+    pub play_url: Option<String>,
+
+    #[clap(long, env = "QOBUZ_PLAYER_SOCKET")]
+    /// Path of the control socket used by qobuz-player ctl.
+    /// Defaults to qobuz-player.sock in the runtime directory
+    pub control_socket: Option<PathBuf>,
+
+    #[clap(flatten)]
+    pub mpd: MpdArgs,
+
+    #[clap(long)]
+    /// Act as a UPnP/DLNA media renderer with this name, so control points
+    /// like BubbleUPnP can play on the player
+    pub upnp_name: Option<String>,
+
+    #[clap(long, default_value_t = 0)]
+    /// Port of the UPnP renderer's description and control server.
+    /// 0 picks a free port
+    pub upnp_port: u16,
+
+    #[clap(flatten)]
+    pub mqtt: MqttArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct MpdArgs {
+    #[clap(long)]
+    /// Serve the MPD protocol on this port, so MPD clients can control the player.
+    /// MPD itself uses 6600
+    pub mpd_port: Option<u16>,
+
+    #[clap(long, default_value_t = IpAddr::V4(Ipv4Addr::LOCALHOST))]
+    /// Address the MPD server listens on. Use 0.0.0.0 to reach it from other
+    /// devices, together with --mpd-password
+    pub mpd_address: IpAddr,
+
+    #[clap(long, env = "QOBUZ_PLAYER_MPD_PASSWORD", hide_env_values = true)]
+    /// Password MPD clients have to send before anything else
+    pub mpd_password: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct MqttArgs {
+    #[clap(long)]
+    /// Publish the player to this MQTT broker and take commands from it,
+    /// with Home Assistant discovery
+    pub mqtt_host: Option<String>,
+
+    #[clap(long, default_value_t = qobuz_player_mqtt::DEFAULT_PORT)]
+    pub mqtt_port: u16,
+
+    #[clap(long, env = "QOBUZ_PLAYER_MQTT_USERNAME")]
+    pub mqtt_username: Option<String>,
+
+    #[clap(long, env = "QOBUZ_PLAYER_MQTT_PASSWORD", hide_env_values = true)]
+    pub mqtt_password: Option<String>,
+
+    #[clap(long, default_value = qobuz_player_mqtt::DEFAULT_TOPIC_PREFIX)]
+    /// Prefix of the state and command topics
+    pub mqtt_topic_prefix: String,
+
+    #[clap(long, default_value = qobuz_player_mqtt::DEFAULT_DISCOVERY_PREFIX)]
+    /// Prefix Home Assistant reads discovery configs from
+    pub mqtt_discovery_prefix: String,
 }
 
 #[derive(Args, Debug)]
@@ -39,9 +119,9 @@ pub struct RfidArgs {
     /// Use other qobuz-player with web for rfid database
     pub rfid_server_base_address: Option<String>,
 
-    #[clap(long)]
-    /// Secret for optional qobuz-player rfid server
-    pub rfid_server_secret: Option<String>,
+    #[clap(long, alias = "rfid-server-secret")]
+    /// API token for optional qobuz-player rfid server
+    pub rfid_server_token: Option<String>,
 }
 
 #[derive(Args, Debug)]
@@ -81,6 +161,17 @@ pub enum SharedCommands {
         #[clap(value_enum)]
         quality: AudioQuality,
     },
+
+    /// Control the running player through its control socket
+    #[cfg(unix)]
+    Ctl {
+        #[clap(long, env = "QOBUZ_PLAYER_SOCKET")]
+        /// Path of the control socket
+        socket: Option<PathBuf>,
+
+        #[clap(subcommand)]
+        command: CtlCommand,
+    },
 }
 
 pub async fn handle_shared_commands(
@@ -108,6 +199,11 @@ pub async fn handle_shared_commands(
             println!("Max audio quality saved.");
             Ok(())
         }
+        #[cfg(unix)]
+        SharedCommands::Ctl { socket, command } => {
+            let socket = socket.unwrap_or_else(qobuz_player_ipc::default_socket_path);
+            ctl::run(&socket, command).await
+        }
     }
 }
 
@@ -133,34 +229,6 @@ pub async fn get_client(
     Ok(client)
 }
 
-pub fn spawn_clean_up(database: Arc<Database>, audio_cache_time_to_live: u32) {
-    if audio_cache_time_to_live != 0 {
-        let clean_up_schedule = every(1).hour().perform(move || {
-            let database = database.clone();
-            async move {
-                if let Ok(deleted_paths) = database
-                    .clean_up_cache_entries(time::Duration::hours(audio_cache_time_to_live.into()))
-                    .await
-                {
-                    for path in deleted_paths {
-                        _ = tokio::fs::remove_file(path.as_path()).await;
-                    }
-                };
-            }
-        });
-
-        tokio::spawn(clean_up_schedule);
-    }
-}
-
-fn default_audio_cache(path: Option<PathBuf>) -> PathBuf {
-    path.unwrap_or_else(|| {
-        let mut cache_dir = std::env::temp_dir();
-        cache_dir.push("qobuz-player-cache");
-        cache_dir
-    })
-}
-
 pub async fn default_audio_quality(
     database: &Database,
     args: Option<AudioQuality>,
@@ -174,6 +242,9 @@ pub async fn default_audio_quality(
     }
 }
 
+/// Start the player on its own thread. Cached audio not played within
+/// `audio_cache_time_to_live` hours is removed.
+#[allow(clippy::too_many_arguments)]
 pub async fn create_player(
     audio_cache: Option<PathBuf>,
     database: Arc<Database>,
@@ -182,25 +253,165 @@ pub async fn create_player(
     state_change_delay_ms: Option<u64>,
     sample_rate_change_delay_ms: Option<u64>,
     output_device_id: Option<String>,
-) -> AppResult<Player> {
-    let tracklist = database.get_tracklist().await.unwrap_or_default();
-    let volume = database.get_volume().await.unwrap_or(1.0);
-    let audio_cache = default_audio_cache(audio_cache);
-
-    let state_change_delay = state_change_delay_ms.map(Duration::from_millis);
-    let sample_rate_change_delay = sample_rate_change_delay_ms.map(Duration::from_millis);
-
-    let player = Player::new(
-        tracklist,
-        client,
-        volume,
-        broadcast,
-        audio_cache,
-        database,
-        state_change_delay,
-        sample_rate_change_delay,
-        output_device_id,
-    )?;
-
-    Ok(player)
+    audio_cache_time_to_live: u32,
+) -> AppResult<PlayerHandle> {
+    let mut builder = PlayerBuilder::new()
+        .database(database)
+        .client(client)
+        .broadcast(broadcast)
+        .audio_cache_time_to_live(Duration::from_secs(
+            u64::from(audio_cache_time_to_live) * 60 * 60,
+        ));
+
+    if let Some(audio_cache) = audio_cache {
+        builder = builder.audio_cache(audio_cache);
+    }
+    if let Some(delay) = state_change_delay_ms {
+        builder = builder.state_change_delay(Duration::from_millis(delay));
+    }
+    if let Some(delay) = sample_rate_change_delay_ms {
+        builder = builder.sample_rate_change_delay(Duration::from_millis(delay));
+    }
+    if let Some(device_id) = output_device_id {
+        builder = builder.output_device_id(device_id);
+    }
+
+    builder.spawn().await
+}
+
+/// Start playing the link passed with --play-url, if any
+pub fn play_url(player: &PlayerHandle, url: Option<&str>) -> AppResult<()> {
+    if let Some(url) = url {
+        player.controls().play_link(Link::parse(url)?);
+    }
+
+    Ok(())
+}
+
+/// Serve the control socket for qobuz-player ctl. A second player keeps
+/// running without one when the socket is taken.
+#[cfg(unix)]
+pub fn spawn_control_socket(player: &PlayerHandle, socket_path: Option<PathBuf>) {
+    let socket_path = socket_path.unwrap_or_else(qobuz_player_ipc::default_socket_path);
+    let controls = player.controls();
+    let position_receiver = player.position();
+    let tracklist_receiver = player.tracklist();
+    let volume_receiver = player.volume();
+    let status_receiver = player.status();
+    let events = player.events();
+
+    tokio::spawn(async move {
+        if let Err(err) = qobuz_player_ipc::server::init(
+            socket_path,
+            controls,
+            position_receiver,
+            tracklist_receiver,
+            volume_receiver,
+            status_receiver,
+            events,
+        )
+        .await
+        {
+            tracing::warn!("Control socket disabled: {err}");
+        }
+    });
+}
+
+/// Serve the MPD protocol when --mpd-port is given
+pub fn spawn_mpd_server(player: &PlayerHandle, client: Arc<Client>, args: MpdArgs) {
+    let Some(port) = args.mpd_port else {
+        return;
+    };
+
+    let config = qobuz_player_mpd::MpdConfig {
+        address: SocketAddr::new(args.mpd_address, port),
+        password: args.mpd_password,
+    };
+
+    let controls = player.controls();
+    let position_receiver = player.position();
+    let tracklist_receiver = player.tracklist();
+    let volume_receiver = player.volume();
+    let status_receiver = player.status();
+
+    tokio::spawn(async move {
+        if let Err(err) = qobuz_player_mpd::init(
+            config,
+            client,
+            controls,
+            position_receiver,
+            tracklist_receiver,
+            volume_receiver,
+            status_receiver,
+        )
+        .await
+        {
+            tracing::error!("MPD server stopped: {err}");
+        }
+    });
+}
+
+/// Run the UPnP renderer when --upnp-name is given
+pub fn spawn_upnp_renderer(player: &PlayerHandle, name: Option<String>, port: u16) {
+    let Some(name) = name else {
+        return;
+    };
+
+    let controls = player.controls();
+    let position_receiver = player.position();
+    let tracklist_receiver = player.tracklist();
+    let volume_receiver = player.volume();
+    let status_receiver = player.status();
+
+    tokio::spawn(async move {
+        if let Err(err) = qobuz_player_upnp::init(
+            qobuz_player_upnp::RendererConfig::new(name, port),
+            controls,
+            position_receiver,
+            tracklist_receiver,
+            volume_receiver,
+            status_receiver,
+        )
+        .await
+        {
+            tracing::error!("UPnP renderer stopped: {err}");
+        }
+    });
+}
+
+/// Connect to the MQTT broker when --mqtt-host is given
+pub fn spawn_mqtt_client(player: &PlayerHandle, args: MqttArgs) {
+    let Some(host) = args.mqtt_host else {
+        return;
+    };
+
+    let config = qobuz_player_mqtt::MqttConfig {
+        host,
+        port: args.mqtt_port,
+        username: args.mqtt_username,
+        password: args.mqtt_password,
+        topic_prefix: args.mqtt_topic_prefix,
+        discovery_prefix: args.mqtt_discovery_prefix,
+    };
+
+    let controls = player.controls();
+    let position_receiver = player.position();
+    let tracklist_receiver = player.tracklist();
+    let volume_receiver = player.volume();
+    let status_receiver = player.status();
+
+    tokio::spawn(async move {
+        if let Err(err) = qobuz_player_mqtt::init(
+            config,
+            controls,
+            position_receiver,
+            tracklist_receiver,
+            volume_receiver,
+            status_receiver,
+        )
+        .await
+        {
+            tracing::error!("MQTT client stopped: {err}");
+        }
+    });
 }
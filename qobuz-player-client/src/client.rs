@@ -2,13 +2,16 @@ use crate::{
     Error, Result,
     qobuz_models::{
         TrackInfo,
-        album::Album,
-        album_suggestion::{AlbumOfTheWeekQuery, AlbumSuggestionResponse, ReleaseQuery},
+        album::{Album, Albums},
+        album_suggestion::{
+            AlbumOfTheWeekQuery, AlbumSuggestion, AlbumSuggestionResponse, ReleaseQuery,
+        },
         artist::{Artist, ArtistsResponse},
         artist_page::ArtistPage,
         favorites::Favorites,
         featured::{FeaturedAlbumsResponse, FeaturedPlaylistsResponse},
         genre::{GenreFeaturedPlaylists, GenreResponse},
+        label::LabelPage,
         playlist::{Playlist, UserPlaylistsResult},
         search_results::SearchAllResults,
         track::Track,
@@ -21,6 +24,7 @@ use crate::{
     },
 };
 use axum::{extract::Query, response::Html, routing::get};
+use futures::{StreamExt, stream::BoxStream};
 use regex::Regex;
 use reqwest::{
     Method, Response, StatusCode,
@@ -40,11 +44,42 @@ use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
 
 const RNG_INIT: &str = "abb21364945c0583309667d13ca3d93a";
 
+/// Number of items requested per page when walking a whole list
+pub const PAGE_SIZE: u32 = 500;
+
+/// Pages of a list, fetched one request at a time as the stream is polled.
+/// Ends after the last page.
+pub type PageStream<'a, T> = BoxStream<'a, Result<Vec<T>>>;
+
+/// Where the client sends its requests. The default is the Qobuz service.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoints {
+    /// Base url of the json api, ending with a slash
+    pub api_base_url: String,
+    /// Web player that serves the login page and the bundle the app id is
+    /// read from
+    pub web_player_url: String,
+    /// Replaces the scheme and host of the segment urls returned by
+    /// `file/url`. `None` keeps the urls as returned.
+    pub segment_host: Option<String>,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Self {
+            api_base_url: "https://www.qobuz.com/api.json/0.2/".to_string(),
+            web_player_url: "https://play.qobuz.com".to_string(),
+            segment_host: None,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Client {
     session: Option<StartResponse>,
     app_id: String,
     base_url: String,
+    segment_host: Option<String>,
     http_client: reqwest::Client,
     user_token: String,
     user_id: i64,
@@ -87,6 +122,7 @@ impl From<Option<i64>> for AudioQuality {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ReleaseType {
     Albums,
     EPsAndSingles,
@@ -107,6 +143,23 @@ impl ReleaseType {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FavoriteType {
+    Albums,
+    Tracks,
+    Artists,
+}
+
+impl FavoriteType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            FavoriteType::Albums => "albums",
+            FavoriteType::Tracks => "tracks",
+            FavoriteType::Artists => "artists",
+        }
+    }
+}
+
 pub enum FeaturedAlbumType {
     PressAwards,
     MostStreamed,
@@ -194,6 +247,7 @@ enum Endpoint {
     GenreList,
     GenreFeatured,
     GenrePlaylists,
+    Label,
 }
 
 impl Display for Endpoint {
@@ -226,6 +280,7 @@ impl Display for Endpoint {
             Endpoint::GenreList => "genre/list",
             Endpoint::GenreFeatured => "album/getFeatured",
             Endpoint::GenrePlaylists => "discover/playlists",
+            Endpoint::Label => "label/get",
         };
 
         f.write_str(endpoint)
@@ -237,7 +292,7 @@ pub async fn browser_oauth_login(headless: bool, app_id: &str) -> Result<OAuthRe
     let port = listener.local_addr().map_err(|_| Error::Login)?.port();
     drop(listener);
 
-    let oauth_url = build_oauth_url(app_id, port);
+    let oauth_url = oauth_url(app_id, &format!("http://localhost:{port}"));
 
     let (tx, mut rx) = tokio::sync::mpsc::channel::<String>(1);
 
@@ -353,25 +408,39 @@ impl Client {
         user_auth_token: &str,
         user_id: i64,
         max_audio_quality: AudioQuality,
+    ) -> Result<Client> {
+        Self::new_with_endpoints(
+            user_auth_token,
+            user_id,
+            max_audio_quality,
+            Endpoints::default(),
+        )
+        .await
+    }
+
+    pub async fn new_with_endpoints(
+        user_auth_token: &str,
+        user_id: i64,
+        max_audio_quality: AudioQuality,
+        endpoints: Endpoints,
     ) -> Result<Client> {
         let http_client = reqwest::Client::builder()
             .cookie_store(true)
             .build()
             .expect("infallible");
 
-        let Secrets { app_id } = get_secrets(&http_client).await?;
+        let Secrets { app_id } = get_secrets(&http_client, &endpoints.web_player_url).await?;
 
         tracing::debug!("Got login secrets, app_id: {}", app_id);
 
-        let base_url = "https://www.qobuz.com/api.json/0.2/".to_string();
-
         let client = Client {
             http_client,
             session: None,
             user_token: user_auth_token.to_string(),
             user_id,
             app_id,
-            base_url,
+            base_url: endpoints.api_base_url,
+            segment_host: endpoints.segment_host,
             max_audio_quality,
         };
 
@@ -450,26 +519,85 @@ impl Client {
         self.get(&endpoint, Some(&params)).await
     }
 
-    pub async fn user_playlists(&self) -> Result<UserPlaylistsResult> {
+    pub async fn user_playlists_page(
+        &self,
+        offset: u32,
+        limit: u32,
+    ) -> Result<UserPlaylistsResult> {
         let endpoint = format!("{}{}", self.base_url, Endpoint::UserPlaylist);
-        let params = vec![("limit", "500"), ("extra", "tracks"), ("offset", "0")];
+        let offset = offset.to_string();
+        let limit = limit.to_string();
+        let params = vec![
+            ("limit", limit.as_str()),
+            ("extra", "tracks"),
+            ("offset", offset.as_str()),
+        ];
 
         self.get(&endpoint, Some(&params)).await
     }
 
-    pub async fn playlist(&self, playlist_id: u32) -> Result<Playlist> {
+    /// All playlists of the user, page by page
+    pub fn user_playlists(&self) -> PageStream<'_, Playlist> {
+        pages(move |offset, limit| async move {
+            let page = self.user_playlists_page(offset, limit).await?.playlists;
+            Ok::<_, Error>((page.items, page.total))
+        })
+    }
+
+    pub async fn playlist_page(
+        &self,
+        playlist_id: u32,
+        offset: u32,
+        limit: u32,
+    ) -> Result<Playlist> {
         let endpoint = format!("{}{}", self.base_url, Endpoint::Playlist);
         let id_string = playlist_id.to_string();
+        let offset = offset.to_string();
+        let limit = limit.to_string();
         let params = vec![
-            ("limit", "500"),
+            ("limit", limit.as_str()),
             ("extra", "tracks"),
             ("playlist_id", id_string.as_str()),
-            ("offset", "0"),
+            ("offset", offset.as_str()),
         ];
 
         self.get(&endpoint, Some(&params)).await
     }
 
+    /// Tracks of a playlist, page by page
+    pub fn playlist_tracks(&self, playlist_id: u32) -> PageStream<'_, Track> {
+        pages(move |offset, limit| async move {
+            let tracks = self
+                .playlist_page(playlist_id, offset, limit)
+                .await?
+                .tracks
+                .unwrap_or_default();
+            Ok::<_, Error>((tracks.items, tracks.total))
+        })
+    }
+
+    /// A playlist with all of its tracks
+    pub async fn playlist(&self, playlist_id: u32) -> Result<Playlist> {
+        let mut playlist = self.playlist_page(playlist_id, 0, PAGE_SIZE).await?;
+
+        if let Some(tracks) = playlist.tracks.as_mut() {
+            while (tracks.items.len() as i64) < tracks.total {
+                let page = self
+                    .playlist_page(playlist_id, tracks.items.len() as u32, PAGE_SIZE)
+                    .await?
+                    .tracks
+                    .unwrap_or_default();
+
+                if page.items.is_empty() {
+                    break;
+                }
+                tracks.items.extend(page.items);
+            }
+        }
+
+        Ok(playlist)
+    }
+
     pub async fn create_playlist(
         &self,
         name: String,
@@ -738,7 +866,12 @@ impl Client {
         .await
         {
             Ok(response) => match serde_json::from_str::<TrackInfo>(response.as_str()) {
-                Ok(item) => Ok(item),
+                Ok(mut item) => {
+                    if let Some(segment_host) = &self.segment_host {
+                        item.url_template = replace_host(&item.url_template, segment_host);
+                    }
+                    Ok(item)
+                }
                 Err(error) => {
                     tracing::debug!("TrackURL deserialize error: {}", error);
                     tracing::debug!("Response was: {}", response);
@@ -747,21 +880,59 @@ impl Client {
                     })
                 }
             },
-            Err(error) => Err(Error::Api {
-                message: error.to_string(),
-            }),
+            Err(error) => Err(error),
         }
     }
 
-    pub async fn favorites(&self, limit: i32) -> Result<Favorites> {
+    /// One page of one kind of favorites. Only the requested kind is filled in.
+    pub async fn favorites_page(
+        &self,
+        favorite_type: FavoriteType,
+        offset: u32,
+        limit: u32,
+    ) -> Result<Favorites> {
         let endpoint = format!("{}{}", self.base_url, Endpoint::Favorites);
-
+        let offset = offset.to_string();
         let limit = limit.to_string();
-        let params = vec![("limit", limit.as_str())];
+        let params = vec![
+            ("type", favorite_type.as_str()),
+            ("offset", offset.as_str()),
+            ("limit", limit.as_str()),
+        ];
 
         self.get(&endpoint, Some(&params)).await
     }
 
+    pub fn favorite_albums(&self) -> PageStream<'_, Album> {
+        pages(move |offset, limit| async move {
+            let page = self
+                .favorites_page(FavoriteType::Albums, offset, limit)
+                .await?
+                .albums;
+            Ok::<_, Error>((page.items, page.total))
+        })
+    }
+
+    pub fn favorite_tracks(&self) -> PageStream<'_, Track> {
+        pages(move |offset, limit| async move {
+            let page = self
+                .favorites_page(FavoriteType::Tracks, offset, limit)
+                .await?
+                .tracks;
+            Ok::<_, Error>((page.items, page.total))
+        })
+    }
+
+    pub fn favorite_artists(&self) -> PageStream<'_, Artist> {
+        pages(move |offset, limit| async move {
+            let page = self
+                .favorites_page(FavoriteType::Artists, offset, limit)
+                .await?
+                .artists;
+            Ok::<_, Error>((page.items, page.total))
+        })
+    }
+
     pub async fn add_favorite_track(&self, id: u32) -> Result<SuccessfulResponse> {
         let endpoint = format!("{}{}", self.base_url, Endpoint::FavoriteAdd);
         let mut form_data = HashMap::new();
@@ -832,14 +1003,52 @@ impl Client {
         self.post(&endpoint, form_data).await
     }
 
-    pub async fn search_all(&self, query: &str, limit: i32) -> Result<SearchAllResults> {
+    pub async fn search_all(
+        &self,
+        query: &str,
+        offset: u32,
+        limit: u32,
+    ) -> Result<SearchAllResults> {
         let endpoint = format!("{}{}", self.base_url, Endpoint::Search);
+        let offset = offset.to_string();
         let limit = limit.to_string();
-        let params = vec![("query", query), ("limit", &limit)];
+        let params = vec![
+            ("query", query),
+            ("offset", offset.as_str()),
+            ("limit", limit.as_str()),
+        ];
 
         self.get(&endpoint, Some(&params)).await
     }
 
+    /// Search results, `page_size` per kind and page. Ends when every kind
+    /// of result is exhausted.
+    pub fn search_all_pages<'a>(
+        &'a self,
+        query: &'a str,
+        page_size: u32,
+    ) -> BoxStream<'a, Result<SearchAllResults>> {
+        futures::stream::try_unfold(Some(0), move |offset| async move {
+            let Some(offset) = offset else {
+                return Ok(None);
+            };
+
+            let results = self.search_all(query, offset, page_size).await?;
+            let next_offset = offset + page_size;
+            let has_more = [
+                results.albums.total,
+                results.tracks.total,
+                results.artists.total,
+                results.playlists.total,
+            ]
+            .into_iter()
+            .any(|total| total > next_offset as i64);
+
+            Ok::<_, Error>(Some((results, has_more.then_some(next_offset))))
+        })
+        .boxed()
+    }
+
     pub async fn album(&self, album_id: &str) -> Result<Album> {
         let endpoint = format!("{}{}", self.base_url, Endpoint::Album);
         let params = vec![
@@ -860,6 +1069,43 @@ impl Client {
         self.get(&endpoint, Some(&params)).await
     }
 
+    pub async fn label(&self, label_id: u32) -> Result<LabelPage> {
+        let endpoint = format!("{}{}", self.base_url, Endpoint::Label);
+        let label_id_string = label_id.to_string();
+        let params = vec![("label_id", label_id_string.as_str())];
+
+        self.get(&endpoint, Some(&params)).await
+    }
+
+    pub async fn label_albums_page(
+        &self,
+        label_id: u32,
+        offset: u32,
+        limit: u32,
+    ) -> Result<Albums> {
+        let endpoint = format!("{}{}", self.base_url, Endpoint::Label);
+        let label_id_string = label_id.to_string();
+        let offset = offset.to_string();
+        let limit = limit.to_string();
+        let params = vec![
+            ("label_id", label_id_string.as_str()),
+            ("extra", "albums"),
+            ("offset", &offset),
+            ("limit", &limit),
+        ];
+
+        let label: LabelPage = self.get(&endpoint, Some(&params)).await?;
+        Ok(label.albums.unwrap_or_default())
+    }
+
+    /// All albums released on a label, page by page
+    pub fn label_albums(&self, label_id: u32) -> PageStream<'_, Album> {
+        pages(move |offset, limit| async move {
+            let page = self.label_albums_page(label_id, offset, limit).await?;
+            Ok::<_, Error>((page.items, page.total))
+        })
+    }
+
     pub async fn suggested_albums(&self, album_id: &str) -> Result<AlbumSuggestionResponse> {
         let endpoint = format!("{}{}", self.base_url, Endpoint::AlbumSuggest);
         let params = vec![("album_id", album_id)];
@@ -887,12 +1133,7 @@ impl Client {
     where
         T: serde::de::DeserializeOwned,
     {
-        let response = self
-            .make_get_call(endpoint, params)
-            .await
-            .map_err(|error| Error::Api {
-                message: error.to_string(),
-            })?;
+        let response = self.make_get_call(endpoint, params).await?;
 
         let str = response.as_str();
         let item = match serde_json::from_str::<T>(str) {
@@ -912,12 +1153,7 @@ impl Client {
     where
         T: serde::de::DeserializeOwned,
     {
-        let response = self
-            .make_post_call(endpoint, params)
-            .await
-            .map_err(|error| Error::Api {
-                message: error.to_string(),
-            })?;
+        let response = self.make_post_call(endpoint, params).await?;
 
         let item = serde_json::from_str::<T>(response.as_str()).map_err(|error| {
             Error::DeserializeJSON {
@@ -947,14 +1183,16 @@ impl Client {
         self.get(&endpoint, Some(&params)).await
     }
 
-    pub async fn artist_releases(
+    pub async fn artist_releases_page(
         &self,
         artist_id: u32,
         release_type: ReleaseType,
-        limit: Option<i32>,
+        offset: u32,
+        limit: u32,
     ) -> Result<ReleaseQuery> {
         let endpoint = format!("{}{}", self.base_url, Endpoint::ArtistReleases);
-        let limit = limit.unwrap_or(100).to_string();
+        let offset = offset.to_string();
+        let limit = limit.to_string();
 
         let artistid_string = artist_id.to_string();
 
@@ -963,13 +1201,35 @@ impl Client {
             ("limit", &limit),
             ("release_type", release_type.as_str()),
             ("sort", "release_date"),
-            ("offset", "0"),
+            ("offset", &offset),
             ("track_size", "1"),
         ];
 
         self.get(&endpoint, Some(&params)).await
     }
 
+    /// All releases of an artist of one type, page by page
+    pub fn artist_releases(
+        &self,
+        artist_id: u32,
+        release_type: ReleaseType,
+    ) -> PageStream<'_, AlbumSuggestion> {
+        futures::stream::try_unfold(Some(0), move |offset| async move {
+            let Some(offset) = offset else {
+                return Ok(None);
+            };
+
+            let page = self
+                .artist_releases_page(artist_id, release_type, offset, PAGE_SIZE)
+                .await?;
+            let next_offset = offset + page.items.len() as u32;
+            let has_more = page.has_more && !page.items.is_empty();
+
+            Ok::<_, Error>(Some((page.items, has_more.then_some(next_offset))))
+        })
+        .boxed()
+    }
+
     async fn make_get_call(
         &self,
         endpoint: &str,
@@ -1002,6 +1262,44 @@ impl Client {
     }
 }
 
+/// Walk a list with `offset` and `limit` parameters. `fetch` returns a page
+/// and the total length of the list.
+fn pages<'a, T, F, Fut>(mut fetch: F) -> PageStream<'a, T>
+where
+    T: Send + 'a,
+    F: FnMut(u32, u32) -> Fut + Send + 'a,
+    Fut: Future<Output = Result<(Vec<T>, i64)>> + Send + 'a,
+{
+    futures::stream::try_unfold(Some(0), move |offset| {
+        let page = offset.map(|offset| (offset, fetch(offset, PAGE_SIZE)));
+        async move {
+            let Some((offset, page)) = page else {
+                return Ok(None);
+            };
+
+            let (items, total) = page.await?;
+            let next_offset = offset + items.len() as u32;
+            let has_more = !items.is_empty() && (next_offset as i64) < total;
+
+            Ok::<_, Error>(Some((items, has_more.then_some(next_offset))))
+        }
+    })
+    .boxed()
+}
+
+/// Replace the scheme and host of `url`, keeping the path and query
+fn replace_host(url: &str, host: &str) -> String {
+    let path_start = url
+        .find("://")
+        .map(|scheme_end| scheme_end + 3)
+        .and_then(|host_start| url[host_start..].find('/').map(|i| host_start + i));
+
+    match path_start {
+        Some(path_start) => format!("{}{}", host.trim_end_matches('/'), &url[path_start..]),
+        None => url.to_string(),
+    }
+}
+
 fn get_request_sig(method: &str, args: BTreeMap<&str, String>, now_string: &str) -> String {
     let mut n = String::new();
     for (k, v) in args.iter() {
@@ -1026,8 +1324,10 @@ async fn handle_response(response: Response) -> Result<String> {
         let res = response.text().await.unwrap_or_default();
         Ok(res)
     } else {
+        let status = response.status().as_u16();
         Err(Error::Api {
             message: response.text().await.unwrap_or_default(),
+            status: Some(status),
         })
     }
 }
@@ -1101,16 +1401,27 @@ pub struct OAuthResult {
 
 /// Fetch the app_id from the Qobuz web player bundle.
 pub async fn get_app_id() -> Result<String> {
+    get_app_id_with_endpoints(&Endpoints::default()).await
+}
+
+pub async fn get_app_id_with_endpoints(endpoints: &Endpoints) -> Result<String> {
     let http_client = reqwest::Client::new();
-    let Secrets { app_id } = get_secrets(&http_client).await?;
+    let Secrets { app_id } = get_secrets(&http_client, &endpoints.web_player_url).await?;
     Ok(app_id)
 }
 
 /// Exchange an OAuth authorization code for a user_auth_token.
 pub async fn exchange_oauth_code(code: &str, app_id: &str) -> Result<OAuthResult> {
+    exchange_oauth_code_with_endpoints(code, app_id, &Endpoints::default()).await
+}
+
+pub async fn exchange_oauth_code_with_endpoints(
+    code: &str,
+    app_id: &str,
+    endpoints: &Endpoints,
+) -> Result<OAuthResult> {
     let http_client = reqwest::Client::new();
-    let base_url = "https://www.qobuz.com/api.json/0.2/";
-    let endpoint = format!("{base_url}oauth/callback");
+    let endpoint = format!("{}oauth/callback", endpoints.api_base_url);
     let params = vec![("code", code), ("private_key", OAUTH_PRIVATE_KEY)];
 
     let response = make_get_call(&endpoint, Some(&params), &http_client, app_id, None, None).await;
@@ -1148,18 +1459,23 @@ pub async fn exchange_oauth_code(code: &str, app_id: &str) -> Result<OAuthResult
     })
 }
 
-/// Build the OAuth URL that the user should open in their browser.
-fn build_oauth_url(app_id: &str, redirect_port: u16) -> String {
-    let redirect = format!("http://localhost:{redirect_port}");
-    format!("https://www.qobuz.com/signin/oauth?ext_app_id={app_id}&redirect_url={redirect}",)
+/// Build the OAuth URL that the user should open in their browser. Qobuz
+/// sends the browser back to `redirect_url` with a `code_autorisation` for
+/// [`exchange_oauth_code`].
+pub fn oauth_url(app_id: &str, redirect_url: &str) -> String {
+    let mut url = url::Url::parse("https://www.qobuz.com/signin/oauth").expect("valid url");
+    url.query_pairs_mut()
+        .append_pair("ext_app_id", app_id)
+        .append_pair("redirect_url", redirect_url);
+    url.into()
 }
 
 struct Secrets {
     app_id: String,
 }
 
-async fn get_secrets(client: &reqwest::Client) -> Result<Secrets> {
-    let play_url = "https://play.qobuz.com";
+async fn get_secrets(client: &reqwest::Client, play_url: &str) -> Result<Secrets> {
+    let play_url = play_url.trim_end_matches('/');
 
     let login_html = client
         .get(format!("{play_url}/login"))
@@ -16,8 +16,12 @@ pub enum Error {
     Login,
     #[snafu(display("Failed to create client"))]
     Create,
+    /// `status` is the HTTP status of the Qobuz response, if there was one
     #[snafu(display("{message}"))]
-    Api { message: String },
+    Api {
+        message: String,
+        status: Option<u16>,
+    },
     #[snafu(display("Failed to deserialize json: {message}"))]
     DeserializeJSON { message: String },
     #[snafu(display("Unable to start stream: {message}"))]
@@ -31,9 +35,11 @@ impl From<reqwest::Error> for Error {
         match status {
             Some(status) => Error::Api {
                 message: status.to_string(),
+                status: Some(status.as_u16()),
             },
             None => Error::Api {
                 message: "Unable to connect to Qobuz api".to_string(),
+                status: None,
             },
         }
     }
@@ -8,6 +8,7 @@ pub mod artist_page;
 pub mod favorites;
 pub mod featured;
 pub mod genre;
+pub mod label;
 pub mod playlist;
 pub mod search_results;
 pub mod track;
@@ -16,8 +17,11 @@ pub mod track;
 pub struct Composer {
     pub id: i64,
     pub name: String,
+    #[serde(default)]
     pub slug: String,
+    #[serde(default)]
     pub albums_count: i64,
+    #[serde(default)]
     pub image: Option<Image>,
 }
 
@@ -49,6 +53,8 @@ pub enum UrlType {
     Album { id: String },
     Playlist { id: i64 },
     Track { id: i32 },
+    Artist { id: i64 },
+    Label { id: i64 },
 }
 
 #[derive(Snafu, Debug)]
@@ -65,42 +71,66 @@ pub enum UrlTypeError {
 
 pub type ParseUrlResult<T, E = UrlTypeError> = Result<T, E>;
 
+/// Parse a `play.qobuz.com`/`open.qobuz.com`/`www.qobuz.com` share link, or a
+/// `qobuz:<kind>:<id>` uri.
 pub fn parse_url(string_url: &str) -> ParseUrlResult<UrlType> {
-    let url = url::Url::parse(string_url).map_err(|_| UrlTypeError::InvalidUrl)?;
+    let url = url::Url::parse(string_url.trim()).map_err(|_| UrlTypeError::InvalidUrl)?;
 
-    let host = url.host_str().ok_or(UrlTypeError::InvalidUrl)?;
-    let mut path = url.path_segments().ok_or(UrlTypeError::InvalidUrl)?;
-
-    if host != "play.qobuz.com" && host != "open.qobuz.com" {
-        return Err(UrlTypeError::WrongDomain);
+    if url.scheme() == "qobuz" {
+        let mut path = url.path().split(':');
+        let kind = path.next().ok_or(UrlTypeError::InvalidPath)?;
+        let id = path.next().ok_or(UrlTypeError::InvalidPath)?;
+        return url_type(kind, id);
     }
 
-    match path.next() {
-        Some("album") => {
-            tracing::debug!("this is an album");
-            let id = path.next().ok_or(UrlTypeError::InvalidPath)?.to_string();
-            Ok(UrlType::Album { id })
-        }
-        Some("playlist") => {
-            tracing::debug!("this is a playlist");
-            let id_str = path.next().ok_or(UrlTypeError::InvalidPath)?;
-            let id = id_str
-                .parse::<i64>()
-                .map_err(|_| UrlTypeError::InvalidPath)?;
-            Ok(UrlType::Playlist { id })
-        }
-        Some("track") => {
-            tracing::debug!("this is a track");
-            let id_str = path.next().ok_or(UrlTypeError::InvalidPath)?;
-            let id = id_str
-                .parse::<i32>()
-                .map_err(|_| UrlTypeError::InvalidPath)?;
-            Ok(UrlType::Track { id })
+    let host = url.host_str().ok_or(UrlTypeError::InvalidUrl)?;
+    let path: Vec<_> = url
+        .path_segments()
+        .ok_or(UrlTypeError::InvalidUrl)?
+        .filter(|segment| !segment.is_empty())
+        .collect();
+
+    match host {
+        "play.qobuz.com" | "open.qobuz.com" => {
+            let kind = path.first().ok_or(UrlTypeError::InvalidPath)?;
+            let id = path.get(1).ok_or(UrlTypeError::InvalidPath)?;
+            url_type(kind, id)
         }
-        None => {
-            tracing::debug!("no path, cannot use path");
-            Err(UrlTypeError::InvalidPath)
+        // Store links look like `/us-en/album/<slug>/<id>`, with the id last
+        "www.qobuz.com" | "qobuz.com" => {
+            let kind = path
+                .iter()
+                .find(|segment| {
+                    matches!(
+                        **segment,
+                        "album" | "playlist" | "track" | "artist" | "interpreter" | "label"
+                    )
+                })
+                .ok_or(UrlTypeError::InvalidPath)?;
+            let id = path.last().ok_or(UrlTypeError::InvalidPath)?;
+            url_type(kind, id)
         }
+        _ => Err(UrlTypeError::WrongDomain),
+    }
+}
+
+fn url_type(kind: &str, id: &str) -> ParseUrlResult<UrlType> {
+    tracing::debug!("this is a {kind}");
+
+    match kind {
+        "album" => Ok(UrlType::Album { id: id.to_string() }),
+        "playlist" => Ok(UrlType::Playlist {
+            id: id.parse().map_err(|_| UrlTypeError::InvalidPath)?,
+        }),
+        "track" => Ok(UrlType::Track {
+            id: id.parse().map_err(|_| UrlTypeError::InvalidPath)?,
+        }),
+        "artist" | "interpreter" => Ok(UrlType::Artist {
+            id: id.parse().map_err(|_| UrlTypeError::InvalidPath)?,
+        }),
+        "label" => Ok(UrlType::Label {
+            id: id.parse().map_err(|_| UrlTypeError::InvalidPath)?,
+        }),
         _ => Err(UrlTypeError::Unknown),
     }
 }
@@ -1,4 +1,9 @@
-use crate::qobuz_models::{Image, artist::Artist, track::Tracks};
+use crate::qobuz_models::{
+    Composer, Image,
+    album_suggestion::{Genre, Label},
+    artist::Artist,
+    track::Tracks,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -15,6 +20,23 @@ pub struct Album {
     pub title: String,
     pub tracks: Option<Tracks>,
     pub tracks_count: i64,
+    #[serde(default)]
+    pub label: Option<Label>,
+    #[serde(default)]
+    pub genre: Option<Genre>,
+    #[serde(default)]
+    pub composer: Option<Composer>,
+    #[serde(default)]
+    pub copyright: Option<String>,
+    #[serde(default)]
+    pub upc: Option<String>,
+    #[serde(default)]
+    pub media_count: Option<u32>,
+    #[serde(default)]
+    pub maximum_bit_depth: Option<u32>,
+    /// In kHz
+    #[serde(default)]
+    pub maximum_sampling_rate: Option<f64>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -33,7 +33,7 @@ pub struct AlbumOfTheWeekQuery {
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ReleaseQuery {
-    has_more: bool,
+    pub has_more: bool,
     pub items: Vec<AlbumSuggestion>,
 }
 
@@ -45,6 +45,7 @@ pub struct PhysicalSupport {
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Genre {
+    #[serde(default)]
     pub path: Vec<i64>,
     pub name: String,
     pub id: i64,
@@ -52,8 +53,8 @@ pub struct Genre {
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Label {
-    id: i64,
-    name: String,
+    pub id: i64,
+    pub name: String,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -4,8 +4,11 @@ use serde::{Deserialize, Serialize};
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Favorites {
+    #[serde(default)]
     pub albums: Albums,
+    #[serde(default)]
     pub tracks: Tracks,
+    #[serde(default)]
     pub artists: Artists,
 }
 
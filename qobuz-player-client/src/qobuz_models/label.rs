@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+
+use crate::qobuz_models::album::Albums;
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LabelPage {
+    pub id: i64,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub albums_count: Option<i64>,
+    /// Only returned when requested with `extra=albums`
+    #[serde(default)]
+    pub albums: Option<Albums>,
+}
@@ -1,4 +1,4 @@
-use crate::qobuz_models::album::Album;
+use crate::qobuz_models::{Composer, album::Album};
 use serde::{Deserialize, Serialize};
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -21,6 +21,25 @@ pub struct Track {
     pub track_number: u32,
     pub parental_warning: bool,
     pub playlist_track_id: Option<u64>,
+    #[serde(default)]
+    pub composer: Option<Composer>,
+    /// The classical work the track is a part of
+    #[serde(default)]
+    pub work: Option<String>,
+    /// Credits as `Name, Role, Role - Name, Role`
+    #[serde(default)]
+    pub performers: Option<String>,
+    #[serde(default)]
+    pub isrc: Option<String>,
+    #[serde(default)]
+    pub copyright: Option<String>,
+    #[serde(default)]
+    pub media_number: Option<u32>,
+    #[serde(default)]
+    pub maximum_bit_depth: Option<u32>,
+    /// In kHz
+    #[serde(default)]
+    pub maximum_sampling_rate: Option<f64>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
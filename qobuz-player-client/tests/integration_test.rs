@@ -1,28 +1,33 @@
+use std::io::Read;
+
+use futures::{StreamExt, TryStreamExt};
 use qobuz_player_client::client::{
-    Client, FeaturedAlbumType, FeaturedGenreAlbumType, FeaturedPlaylistType, ReleaseType,
+    AudioQuality, Client, FavoriteType, FeaturedAlbumType, FeaturedGenreAlbumType,
+    FeaturedPlaylistType, ReleaseType, exchange_oauth_code_with_endpoints,
+    get_app_id_with_endpoints,
 };
-use qobuz_player_controls::database::{Credentials, Database};
-
-async fn get_token() -> Option<Credentials> {
-    let database = Database::new().await.ok()?;
-    database.get_credentials().await.ok()?
-}
+use qobuz_player_client::qobuz_models::{UrlType, parse_url};
+use qobuz_player_mock::{APP_ID, Fixtures, MockServer, USER_AUTH_TOKEN, USER_ID};
 
-async fn get_client() -> Option<Client> {
-    let credentials = get_token().await?;
+/// The client is only usable while the returned server runs
+async fn get_client() -> Option<(Client, MockServer)> {
+    let server = MockServer::start(Fixtures::bundled()).await.ok()?;
 
-    qobuz_player_client::client::Client::new(
-        &credentials.user_auth_token,
-        credentials.user_id,
-        qobuz_player_client::client::AudioQuality::Mp3,
+    let client = Client::new_with_endpoints(
+        USER_AUTH_TOKEN,
+        USER_ID,
+        AudioQuality::CD,
+        server.endpoints(),
     )
     .await
-    .ok()
+    .ok()?;
+
+    Some((client, server))
 }
 
 #[tokio::test]
 async fn featured_albums() {
-    let client = get_client().await.unwrap();
+    let (client, _server) = get_client().await.unwrap();
 
     client
         .featured_albums(FeaturedAlbumType::PressAwards)
@@ -52,7 +57,7 @@ async fn featured_albums() {
 
 #[tokio::test]
 async fn featured_playlists() {
-    let client = get_client().await.unwrap();
+    let (client, _server) = get_client().await.unwrap();
 
     client
         .featured_playlists(FeaturedPlaylistType::EditorsPick)
@@ -62,7 +67,7 @@ async fn featured_playlists() {
 
 #[tokio::test]
 async fn genres() {
-    let client = get_client().await.unwrap();
+    let (client, _server) = get_client().await.unwrap();
 
     let genres = client.genres().await.unwrap().genres.items;
 
@@ -93,86 +98,229 @@ async fn genres() {
 
 #[tokio::test]
 async fn user_playlists() {
-    let client = get_client().await.unwrap();
-    client.user_playlists().await.unwrap();
+    let (client, _server) = get_client().await.unwrap();
+    let playlists: Vec<_> = client.user_playlists().try_concat().await.unwrap();
+    assert_eq!(playlists.len(), 1);
 }
 
 #[tokio::test]
 async fn favorites() {
-    let client = get_client().await.unwrap();
-    client.favorites(3).await.unwrap();
+    let (client, _server) = get_client().await.unwrap();
+    client
+        .favorites_page(FavoriteType::Albums, 0, 3)
+        .await
+        .unwrap();
+
+    let albums: Vec<_> = client.favorite_albums().try_concat().await.unwrap();
+    let tracks: Vec<_> = client.favorite_tracks().try_concat().await.unwrap();
+    let artists: Vec<_> = client.favorite_artists().try_concat().await.unwrap();
+    assert_eq!((albums.len(), tracks.len(), artists.len()), (1, 1, 1));
 }
 
 #[tokio::test]
 async fn playlist() {
-    let client = get_client().await.unwrap();
-    client.playlist(28869445).await.unwrap();
+    let (client, _server) = get_client().await.unwrap();
+    let playlist = client.playlist(7001).await.unwrap();
+    assert_eq!(playlist.tracks.unwrap().items.len(), 2);
+
+    let page = client.playlist_page(7001, 1, 1).await.unwrap();
+    let tracks = page.tracks.unwrap();
+    assert_eq!((tracks.items.len(), tracks.total), (1, 2));
 }
 
 #[tokio::test]
 async fn search() {
-    let client = get_client().await.unwrap();
-    client
-        .search_all("a light for attracting attention", 3)
+    let (client, _server) = get_client().await.unwrap();
+    client.search_all("mock", 0, 3).await.unwrap();
+
+    // Five tracks take two pages of three
+    let pages: Vec<_> = client
+        .search_all_pages("mock", 3)
+        .try_collect()
         .await
         .unwrap();
+    assert_eq!(pages.len(), 2);
+    assert_eq!(pages[1].tracks.items.len(), 2);
 }
 
 #[tokio::test]
 async fn album() {
-    let client = get_client().await.unwrap();
-    client.album("mwytv5nahdbga").await.unwrap();
+    let (client, _server) = get_client().await.unwrap();
+    let album = client.album("mockalbum001").await.unwrap();
+
+    assert_eq!(album.label.unwrap().name, "Mock Records");
+    assert_eq!(album.genre.unwrap().name, "Pop/Rock");
+    assert_eq!(album.maximum_bit_depth, Some(24));
+
+    let track = &album.tracks.unwrap().items[0];
+    assert_eq!(track.composer.as_ref().unwrap().name, "The Mock Ensemble");
+    assert_eq!(track.isrc.as_deref(), Some("QZMCK1900001"));
+    assert_eq!(track.media_number, Some(1));
+    assert_eq!(track.work.as_deref(), Some("Mock Suite No. 1"));
+}
+
+#[tokio::test]
+async fn missing_album_keeps_the_status() {
+    let (client, _server) = get_client().await.unwrap();
+    let error = client.album("missingalbum").await.unwrap_err();
+
+    assert!(matches!(
+        error,
+        qobuz_player_client::Error::Api {
+            status: Some(404),
+            ..
+        }
+    ));
 }
 
 #[tokio::test]
 async fn album_2() {
-    let client = get_client().await.unwrap();
-    client.album("dpognys4zadzb").await.unwrap();
+    let (client, _server) = get_client().await.unwrap();
+    let album = client.album("mockalbum002").await.unwrap();
+
+    assert_eq!(album.media_count, Some(2));
+    let media_numbers: Vec<_> = album
+        .tracks
+        .unwrap()
+        .items
+        .iter()
+        .map(|t| (t.media_number, t.track_number))
+        .collect();
+    assert_eq!(media_numbers, vec![(Some(1), 1), (Some(2), 1)]);
+}
+
+#[tokio::test]
+async fn label() {
+    let (client, _server) = get_client().await.unwrap();
+
+    let label = client.label(8001).await.unwrap();
+    assert_eq!(label.name, "Mock Records");
+
+    let page = client.label_albums_page(8001, 1, 1).await.unwrap();
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.items[0].id, "mockalbum002");
+
+    let albums: Vec<_> = client.label_albums(8001).try_concat().await.unwrap();
+    assert_eq!(albums.len(), 2);
 }
 
 #[tokio::test]
 async fn track() {
-    let client = get_client().await.unwrap();
-    client.track(64868955).await.unwrap();
+    let (client, _server) = get_client().await.unwrap();
+    client.track(1001).await.unwrap();
 }
 
 #[tokio::test]
 async fn suggested_albums() {
-    let client = get_client().await.unwrap();
-    client.suggested_albums("mwytv5nahdbga").await.unwrap();
+    let (client, _server) = get_client().await.unwrap();
+    client.suggested_albums("mockalbum001").await.unwrap();
 }
 
 #[tokio::test]
 async fn artist() {
-    let client = get_client().await.unwrap();
-    client.artist(9316383).await.unwrap();
+    let (client, _server) = get_client().await.unwrap();
+    client.artist(9001).await.unwrap();
 }
 
 #[tokio::test]
 async fn similar_artist() {
-    let client = get_client().await.unwrap();
-    client.similar_artists(9316383, Some(3)).await.unwrap();
+    let (client, _server) = get_client().await.unwrap();
+    client.similar_artists(9001, Some(3)).await.unwrap();
 }
 
 #[tokio::test]
 async fn artist_releases() {
-    let client = get_client().await.unwrap();
+    let (client, _server) = get_client().await.unwrap();
     client
-        .artist_releases(9316383, ReleaseType::Albums, Some(3))
+        .artist_releases_page(9001, ReleaseType::Albums, 0, 3)
         .await
         .unwrap();
-    client
-        .artist_releases(9316383, ReleaseType::EPsAndSingles, Some(3))
-        .await
-        .unwrap();
-    client
-        .artist_releases(9316383, ReleaseType::Live, Some(3))
+
+    for release_type in [
+        ReleaseType::Albums,
+        ReleaseType::EPsAndSingles,
+        ReleaseType::Live,
+        ReleaseType::Compilations,
+    ] {
+        let pages = client.artist_releases(9001, release_type).count().await;
+        assert_eq!(pages, 1);
+    }
+}
+
+#[tokio::test]
+async fn missing_recording_is_an_error() {
+    let (client, _server) = get_client().await.unwrap();
+    assert!(client.album("unknown").await.is_err());
+}
+
+#[tokio::test]
+async fn app_id_and_oauth() {
+    let server = MockServer::start(Fixtures::bundled()).await.unwrap();
+    let endpoints = server.endpoints();
+
+    let app_id = get_app_id_with_endpoints(&endpoints).await.unwrap();
+    assert_eq!(app_id, APP_ID);
+
+    let login = exchange_oauth_code_with_endpoints("code", &app_id, &endpoints)
         .await
         .unwrap();
-    client
-        .artist_releases(9316383, ReleaseType::Compilations, Some(3))
+    assert_eq!(login.user_auth_token, USER_AUTH_TOKEN);
+    assert_eq!(login.user_id, USER_ID);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn stream_track_decrypts_segments() {
+    let (mut client, server) = get_client().await.unwrap();
+
+    let track_info = client.track_url(1001).await.unwrap();
+    assert!(track_info.url_template.starts_with(&server.url()));
+
+    let cache_dir =
+        std::env::temp_dir().join(format!("qobuz-player-client-test-{}", std::process::id()));
+    let reader = client
+        .stream_track(track_info, cache_dir.join("1001.flac"))
         .await
         .unwrap();
+
+    let streamed = tokio::task::spawn_blocking(move || {
+        let mut reader = reader;
+        let mut streamed = Vec::new();
+        reader.read_to_end(&mut streamed).map(|_| streamed)
+    })
+    .await
+    .unwrap()
+    .unwrap();
+
+    let original = Fixtures::bundled().audio(1001).unwrap();
+    assert_eq!(streamed, original);
+
+    _ = std::fs::remove_dir_all(cache_dir);
+}
+
+#[test]
+fn parse_share_urls() {
+    assert!(matches!(
+        parse_url("https://open.qobuz.com/album/mockalbum001"),
+        Ok(UrlType::Album { id }) if id == "mockalbum001"
+    ));
+    assert!(matches!(
+        parse_url("https://play.qobuz.com/playlist/42"),
+        Ok(UrlType::Playlist { id: 42 })
+    ));
+    assert!(matches!(
+        parse_url("https://www.qobuz.com/us-en/interpreter/mock-artist/9001"),
+        Ok(UrlType::Artist { id: 9001 })
+    ));
+    assert!(matches!(
+        parse_url("https://open.qobuz.com/label/8001"),
+        Ok(UrlType::Label { id: 8001 })
+    ));
+    assert!(matches!(
+        parse_url("qobuz:track:1001"),
+        Ok(UrlType::Track { id: 1001 })
+    ));
+    assert!(parse_url("https://example.com/album/1").is_err());
+    assert!(parse_url("https://open.qobuz.com/track/not-a-number").is_err());
 }
 
 // TODO: Add remaining tests
@@ -1,13 +1,15 @@
 use std::time::{Duration, SystemTime};
 
 use qobuz_player_controls::{
-    AppResult, AudioQuality, PositionReceiver, Status, StatusReceiver, TracklistReceiver,
-    VolumeReceiver,
+    AppResult, AudioQuality, PositionReceiver, Status, StatusReceiver, TracklistChangeReceiver,
+    TracklistReceiver, VolumeReceiver,
     controls::{Controls, NewQueueItem},
     error::Error,
     tracklist::Tracklist,
 };
 
+use tokio::sync::broadcast;
+
 use qonductor::{
     ActivationState, BufferState, Command, DeviceConfig, DeviceSession, Notification, PlayingState,
     SessionEvent, SessionManager,
@@ -18,10 +20,12 @@ struct ConnectState {
     controls: Controls,
     position_receiver: PositionReceiver,
     tracklist_receiver: TracklistReceiver,
+    tracklist_changes: TracklistChangeReceiver,
     status_receiver: StatusReceiver,
     volume_receiver: VolumeReceiver,
     audio_quality: i32,
     connected: bool,
+    reported_queue_ids: (Option<u64>, Option<u64>),
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -31,6 +35,7 @@ pub async fn init(
     controls: Controls,
     position_receiver: PositionReceiver,
     tracklist_receiver: TracklistReceiver,
+    tracklist_changes: TracklistChangeReceiver,
     status_receiver: StatusReceiver,
     volume_receiver: VolumeReceiver,
     max_audio_quality: AudioQuality,
@@ -41,10 +46,12 @@ pub async fn init(
         controls,
         position_receiver,
         tracklist_receiver,
+        tracklist_changes,
         status_receiver,
         volume_receiver: volume_receiver.clone(),
         audio_quality,
         connected: false,
+        reported_queue_ids: (None, None),
     };
 
     connect_state
@@ -126,11 +133,21 @@ impl ConnectState {
         Ok(())
     }
 
+    /// Only the current and next item are reported, so edits elsewhere in
+    /// the queue are skipped.
     async fn handle_tracklist_changed(&mut self, session: &DeviceSession) -> qonductor::Result<()> {
         if !self.connected {
             return Ok(());
         }
-        let tracklist = self.tracklist_receiver.borrow_and_update().clone();
+        let tracklist = self.tracklist_receiver.borrow().clone();
+        let queue_ids = (
+            tracklist.current_queue_id(),
+            tracklist.next_track_queue_id(),
+        );
+        if queue_ids == self.reported_queue_ids {
+            return Ok(());
+        }
+
         let position = {
             let position = self.position_receiver.borrow();
             *position
@@ -140,6 +157,7 @@ impl ConnectState {
 
         tracing::info!("Updating current state after tracklist change");
         session.report_state(new_state).await?;
+        self.reported_queue_ids = queue_ids;
         Ok(())
     }
 
@@ -183,8 +201,11 @@ impl ConnectState {
                 Ok(_) = self.position_receiver.changed() => {
                     self.handle_position_changed(&session).await?;
                 },
-                Ok(_) = self.tracklist_receiver.changed() => {
-                    self.handle_tracklist_changed(&session).await?;
+                changes = self.tracklist_changes.recv() => match changes {
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {
+                        self.handle_tracklist_changed(&session).await?;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return Ok(()),
                 },
                 Ok(_) = self.volume_receiver.changed() => {
                     self.handle_volume_changed(&session).await?;
@@ -224,16 +245,15 @@ impl ConnectState {
                         self.controls.seek(position);
                     }
 
-                    let current_position = self.tracklist_receiver.borrow().current_position();
-                    let tracklist_position = cmd
+                    let current_queue_id = self.tracklist_receiver.borrow().current_queue_id();
+                    let queue_id = cmd
                         .current_queue_item
-                        .map(|x| x.queue_item_id)
-                        .map(|x| x as usize);
+                        .map(|x| x.queue_item_id);
 
-                    if let Some(tracklist_position) = tracklist_position
-                        && current_position != tracklist_position
+                    if let Some(queue_id) = queue_id
+                        && current_queue_id != Some(queue_id)
                     {
-                        self.controls.skip_to_position(tracklist_position, true);
+                        self.controls.skip_to_queue_item(queue_id);
                     }
 
                     respond.send(response);
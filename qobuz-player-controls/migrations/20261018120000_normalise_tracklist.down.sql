@@ -0,0 +1,23 @@
+CREATE TABLE IF NOT EXISTS "tracklist" (
+	"tracklist"	string not null
+);
+
+INSERT INTO tracklist (tracklist)
+SELECT json_object(
+    'queue', (
+        SELECT json_group_array(
+            json_object(
+                'track', json_set(track, '$.status', status),
+                'queue_id', queue_id,
+                'index', item_index
+            )
+        )
+        FROM (SELECT * FROM queue_items ORDER BY position)
+    ),
+    'list_type', json(list_type),
+    'next_queue_id', next_queue_id
+)
+FROM tracklist_meta;
+
+DROP TABLE queue_items;
+DROP TABLE tracklist_meta;
@@ -0,0 +1,36 @@
+CREATE TABLE IF NOT EXISTS "tracklist_meta" (
+    "list_type" text not null default '"Tracks"',
+    "next_queue_id" integer not null default 0
+);
+
+CREATE TABLE IF NOT EXISTS "queue_items" (
+    "queue_id" integer primary key not null,
+    "position" integer not null,
+    "item_index" integer not null,
+    "status" text not null,
+    "track" text not null
+);
+
+INSERT INTO tracklist_meta (rowid, list_type, next_queue_id)
+SELECT 1, tracklist -> '$.list_type', coalesce(tracklist ->> '$.next_queue_id', 0)
+FROM tracklist
+LIMIT 1;
+
+-- Tracklists persisted before queue ids were unique get ids by position
+INSERT INTO queue_items (queue_id, position, item_index, status, track)
+SELECT
+    CASE
+        WHEN (
+            SELECT count(DISTINCT item.value ->> '$.queue_id') = count(*)
+            FROM tracklist, json_each(tracklist.tracklist, '$.queue') AS item
+        ) THEN queue_item.value ->> '$.queue_id'
+        ELSE queue_item.key
+    END,
+    queue_item.key,
+    queue_item.value ->> '$.index',
+    queue_item.value ->> '$.track.status',
+    queue_item.value -> '$.track'
+FROM (SELECT tracklist FROM tracklist LIMIT 1) AS tracklist,
+    json_each(tracklist.tracklist, '$.queue') AS queue_item;
+
+DROP TABLE tracklist;
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS "webhooks" (
+    "id" integer primary key not null,
+    "url" text not null,
+    "events" text not null default '[]',
+    "secret" text not null
+);
@@ -0,0 +1,20 @@
+CREATE TABLE IF NOT EXISTS "web_users" (
+    "id" integer primary key not null,
+    "username" text not null unique,
+    "password_hash" text not null,
+    "role" text not null
+);
+
+CREATE TABLE IF NOT EXISTS "web_sessions" (
+    "token_hash" text primary key not null,
+    "user_id" integer not null references web_users(id) on delete cascade,
+    "created_at" text not null
+);
+
+CREATE TABLE IF NOT EXISTS "api_tokens" (
+    "id" integer primary key not null,
+    "name" text not null,
+    "token_hash" text not null unique,
+    "role" text not null,
+    "created_at" text not null
+);
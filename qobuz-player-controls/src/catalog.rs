@@ -0,0 +1,101 @@
+use std::path::PathBuf;
+
+use futures::future::BoxFuture;
+use qobuz_player_client::stream::flac_source_stream::SeekableStreamReader;
+
+use crate::{
+    AppResult,
+    client::Client,
+    models::{Album, ArtistPage, Favorites, LabelPage, Playlist, SearchResults, Track},
+};
+
+pub use qobuz_player_client::qobuz_models::TrackInfo;
+
+pub mod memory;
+mod tone;
+
+/// Audio for a track, either a complete file or a stream that is written to
+/// the cache path while it plays.
+pub enum TrackSource {
+    Cached(PathBuf),
+    Streaming(SeekableStreamReader),
+}
+
+/// The catalog and streaming operations the player needs. Implemented by
+/// [`Client`] for Qobuz and by [`memory::MemoryCatalog`] for offline use.
+pub trait Catalog: Send + Sync {
+    fn album<'a>(&'a self, id: &'a str) -> BoxFuture<'a, AppResult<Album>>;
+
+    fn track(&self, id: u32) -> BoxFuture<'_, AppResult<Track>>;
+
+    fn tracks(&self, ids: Vec<u32>) -> BoxFuture<'_, AppResult<Vec<Track>>>;
+
+    fn playlist(&self, id: u32) -> BoxFuture<'_, AppResult<Playlist>>;
+
+    fn artist_page(&self, id: u32) -> BoxFuture<'_, AppResult<ArtistPage>>;
+
+    fn label(&self, id: u32) -> BoxFuture<'_, AppResult<LabelPage>>;
+
+    fn favorites(&self) -> BoxFuture<'_, AppResult<Favorites>>;
+
+    fn search(&self, query: String) -> BoxFuture<'_, AppResult<SearchResults>>;
+
+    fn track_url(&self, track_id: u32) -> BoxFuture<'_, AppResult<TrackInfo>>;
+
+    /// Start playback of a track not yet in the cache. The audio ends up at
+    /// `cache_path`.
+    fn stream_track(
+        &self,
+        cache_path: PathBuf,
+        track_info: TrackInfo,
+    ) -> BoxFuture<'_, AppResult<TrackSource>>;
+}
+
+impl Catalog for Client {
+    fn album<'a>(&'a self, id: &'a str) -> BoxFuture<'a, AppResult<Album>> {
+        Box::pin(Client::album(self, id))
+    }
+
+    fn track(&self, id: u32) -> BoxFuture<'_, AppResult<Track>> {
+        Box::pin(Client::track(self, id))
+    }
+
+    fn tracks(&self, ids: Vec<u32>) -> BoxFuture<'_, AppResult<Vec<Track>>> {
+        Box::pin(Client::tracks(self, ids))
+    }
+
+    fn playlist(&self, id: u32) -> BoxFuture<'_, AppResult<Playlist>> {
+        Box::pin(Client::playlist(self, id))
+    }
+
+    fn artist_page(&self, id: u32) -> BoxFuture<'_, AppResult<ArtistPage>> {
+        Box::pin(Client::artist_page(self, id))
+    }
+
+    fn label(&self, id: u32) -> BoxFuture<'_, AppResult<LabelPage>> {
+        Box::pin(Client::label(self, id))
+    }
+
+    fn favorites(&self) -> BoxFuture<'_, AppResult<Favorites>> {
+        Box::pin(Client::favorites(self))
+    }
+
+    fn search(&self, query: String) -> BoxFuture<'_, AppResult<SearchResults>> {
+        Box::pin(Client::search(self, query))
+    }
+
+    fn track_url(&self, track_id: u32) -> BoxFuture<'_, AppResult<TrackInfo>> {
+        Box::pin(Client::track_url(self, track_id))
+    }
+
+    fn stream_track(
+        &self,
+        cache_path: PathBuf,
+        track_info: TrackInfo,
+    ) -> BoxFuture<'_, AppResult<TrackSource>> {
+        Box::pin(async move {
+            let stream = Client::stream_track(self, cache_path, track_info).await?;
+            Ok(TrackSource::Streaming(stream))
+        })
+    }
+}
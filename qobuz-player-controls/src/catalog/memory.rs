@@ -0,0 +1,399 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use futures::future::BoxFuture;
+
+use crate::{
+    AppResult,
+    catalog::{Catalog, TrackInfo, TrackSource, tone::sine_flac},
+    error::Error,
+    models::{Album, Artist, ArtistPage, Favorites, LabelPage, Playlist, SearchResults, Track},
+};
+
+const SAMPLE_RATE: u32 = 44100;
+
+/// A catalog kept in memory. Every track plays a sine tone for its duration,
+/// generated into the audio cache the first time it is played.
+#[derive(Default, Clone)]
+pub struct MemoryCatalog {
+    albums: HashMap<String, Album>,
+    tracks: HashMap<u32, Track>,
+    playlists: HashMap<u32, Playlist>,
+    artists: HashMap<u32, ArtistPage>,
+    labels: HashMap<u32, LabelPage>,
+    favorites: Favorites,
+}
+
+impl MemoryCatalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A small catalog with two albums by one artist and label, and a
+    /// playlist mixing both.
+    pub fn fixture() -> Self {
+        let artist = Artist {
+            id: 1,
+            name: "Test Tones".into(),
+            image: None,
+        };
+
+        let first = fixture_album(
+            "1",
+            "Low Tones",
+            &artist,
+            2021,
+            &[(101, "A", 20), (102, "B", 25), (103, "C", 30)],
+        );
+        let second = fixture_album(
+            "2",
+            "High Tones",
+            &artist,
+            2023,
+            &[(201, "E", 15), (202, "F", 20)],
+        );
+
+        let playlist_tracks = vec![first.tracks[0].clone(), second.tracks[1].clone()];
+        let playlist = Playlist {
+            is_owned: true,
+            title: "Mixed Tones".into(),
+            duration_seconds: playlist_tracks.iter().map(|t| t.duration_seconds).sum(),
+            tracks_count: playlist_tracks.len() as u32,
+            id: 1,
+            image: None,
+            tracks: playlist_tracks,
+        };
+
+        let artist_page = ArtistPage {
+            id: artist.id,
+            name: artist.name.clone(),
+            top_tracks: vec![first.tracks[0].clone(), second.tracks[0].clone()],
+            albums: vec![first.clone().into(), second.clone().into()],
+            ..Default::default()
+        };
+
+        let label = LabelPage {
+            id: 1,
+            name: "Tone Records".into(),
+            description: None,
+            albums: vec![second.clone().into(), first.clone().into()],
+        };
+
+        let favorites = Favorites {
+            albums: vec![first.clone().into()],
+            artists: vec![artist],
+            playlists: vec![playlist.clone()],
+            tracks: vec![second.tracks[0].clone()],
+        };
+
+        Self::new()
+            .with_album(first)
+            .with_album(second)
+            .with_playlist(playlist)
+            .with_artist_page(artist_page)
+            .with_label(label)
+            .with_favorites(favorites)
+    }
+
+    /// Add an album together with its tracks
+    pub fn with_album(mut self, album: Album) -> Self {
+        for track in &album.tracks {
+            self.tracks.insert(track.id, track.clone());
+        }
+        self.albums.insert(album.id.clone(), album);
+        self
+    }
+
+    pub fn with_track(mut self, track: Track) -> Self {
+        self.tracks.insert(track.id, track);
+        self
+    }
+
+    /// Add a playlist together with its tracks
+    pub fn with_playlist(mut self, playlist: Playlist) -> Self {
+        for track in &playlist.tracks {
+            self.tracks.entry(track.id).or_insert_with(|| track.clone());
+        }
+        self.playlists.insert(playlist.id, playlist);
+        self
+    }
+
+    pub fn with_artist_page(mut self, artist_page: ArtistPage) -> Self {
+        self.artists.insert(artist_page.id, artist_page);
+        self
+    }
+
+    pub fn with_label(mut self, label: LabelPage) -> Self {
+        self.labels.insert(label.id, label);
+        self
+    }
+
+    pub fn with_favorites(mut self, favorites: Favorites) -> Self {
+        self.favorites = favorites;
+        self
+    }
+
+    fn find_track(&self, id: u32) -> AppResult<Track> {
+        self.tracks
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| not_found("Track", id))
+    }
+
+    fn find_search_results(&self, query: String) -> SearchResults {
+        let query_lower = query.to_lowercase();
+        let matches = |value: &str| value.to_lowercase().contains(&query_lower);
+
+        let mut albums: Vec<Album> = self
+            .albums
+            .values()
+            .filter(|album| matches(&album.title) || matches(&album.artist.name))
+            .cloned()
+            .collect();
+        albums.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let mut artists: Vec<Artist> = self
+            .artists
+            .values()
+            .filter(|artist| matches(&artist.name))
+            .map(|artist| Artist {
+                id: artist.id,
+                name: artist.name.clone(),
+                image: artist.image.clone(),
+            })
+            .collect();
+        artists.sort_by_key(|artist| artist.id);
+
+        let mut playlists: Vec<Playlist> = self
+            .playlists
+            .values()
+            .filter(|playlist| matches(&playlist.title))
+            .cloned()
+            .collect();
+        playlists.sort_by_key(|playlist| playlist.id);
+
+        let mut tracks: Vec<Track> = self
+            .tracks
+            .values()
+            .filter(|track| matches(&track.title))
+            .cloned()
+            .collect();
+        tracks.sort_by_key(|track| track.id);
+
+        SearchResults {
+            query,
+            albums,
+            artists,
+            playlists,
+            tracks,
+        }
+    }
+}
+
+impl Catalog for MemoryCatalog {
+    fn album<'a>(&'a self, id: &'a str) -> BoxFuture<'a, AppResult<Album>> {
+        let album = self
+            .albums
+            .get(id)
+            .cloned()
+            .ok_or_else(|| not_found("Album", id));
+        Box::pin(async move { album })
+    }
+
+    fn track(&self, id: u32) -> BoxFuture<'_, AppResult<Track>> {
+        Box::pin(async move { self.find_track(id) })
+    }
+
+    fn tracks(&self, ids: Vec<u32>) -> BoxFuture<'_, AppResult<Vec<Track>>> {
+        Box::pin(async move { ids.into_iter().map(|id| self.find_track(id)).collect() })
+    }
+
+    fn playlist(&self, id: u32) -> BoxFuture<'_, AppResult<Playlist>> {
+        let playlist = self
+            .playlists
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| not_found("Playlist", id));
+        Box::pin(async move { playlist })
+    }
+
+    fn artist_page(&self, id: u32) -> BoxFuture<'_, AppResult<ArtistPage>> {
+        let artist = self
+            .artists
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| not_found("Artist", id));
+        Box::pin(async move { artist })
+    }
+
+    fn label(&self, id: u32) -> BoxFuture<'_, AppResult<LabelPage>> {
+        let label = self
+            .labels
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| not_found("Label", id));
+        Box::pin(async move { label })
+    }
+
+    fn favorites(&self) -> BoxFuture<'_, AppResult<Favorites>> {
+        let favorites = self.favorites.clone();
+        Box::pin(async move { Ok(favorites) })
+    }
+
+    fn search(&self, query: String) -> BoxFuture<'_, AppResult<SearchResults>> {
+        Box::pin(async move { Ok(self.find_search_results(query)) })
+    }
+
+    fn track_url(&self, track_id: u32) -> BoxFuture<'_, AppResult<TrackInfo>> {
+        Box::pin(async move {
+            let track = self.find_track(track_id)?;
+            let n_samples = SAMPLE_RATE as u64 * track.duration_seconds as u64;
+
+            Ok(TrackInfo {
+                url_template: format!("memory://track/{track_id}"),
+                mime_type: "audio/flac".into(),
+                n_segments: 1,
+                sampling_rate: Some(SAMPLE_RATE),
+                duration: Some(track.duration_seconds as f64),
+                n_samples: Some(n_samples),
+                ..Default::default()
+            })
+        })
+    }
+
+    fn stream_track(
+        &self,
+        cache_path: PathBuf,
+        track_info: TrackInfo,
+    ) -> BoxFuture<'_, AppResult<TrackSource>> {
+        Box::pin(async move {
+            let track_id = track_info
+                .url_template
+                .rsplit('/')
+                .next()
+                .and_then(|id| id.parse::<u32>().ok())
+                .ok_or_else(|| Error::StreamError {
+                    message: format!("Not a memory track: {}", track_info.url_template),
+                })?;
+            let track = self.find_track(track_id)?;
+
+            let flac = sine_flac(
+                tone_frequency(track_id),
+                SAMPLE_RATE,
+                track.duration_seconds,
+            );
+
+            if let Some(parent) = cache_path.parent() {
+                tokio::fs::create_dir_all(parent)
+                    .await
+                    .map_err(|err| write_error(&cache_path, err))?;
+            }
+            tokio::fs::write(&cache_path, flac)
+                .await
+                .map_err(|err| write_error(&cache_path, err))?;
+
+            Ok(TrackSource::Cached(cache_path))
+        })
+    }
+}
+
+fn fixture_album(
+    id: &str,
+    title: &str,
+    artist: &Artist,
+    release_year: u32,
+    tracks: &[(u32, &str, u32)],
+) -> Album {
+    let tracks: Vec<Track> = tracks
+        .iter()
+        .enumerate()
+        .map(|(index, (track_id, track_title, duration_seconds))| Track {
+            id: *track_id,
+            title: track_title.to_string(),
+            number: index as u32 + 1,
+            available: true,
+            duration_seconds: *duration_seconds,
+            artist_name: Some(artist.name.clone()),
+            artist_id: Some(artist.id),
+            album_title: Some(title.to_string()),
+            album_id: Some(id.to_string()),
+            ..Default::default()
+        })
+        .collect();
+
+    Album {
+        id: id.to_string(),
+        title: title.to_string(),
+        artist: artist.clone(),
+        release_year,
+        total_tracks: tracks.len() as u32,
+        duration_seconds: tracks.iter().map(|t| t.duration_seconds).sum(),
+        available: true,
+        tracks,
+        ..Default::default()
+    }
+}
+
+/// A different pitch per track, so tracks can be told apart by ear
+fn tone_frequency(track_id: u32) -> f32 {
+    220.0 * 2f32.powf((track_id % 24) as f32 / 12.0)
+}
+
+fn not_found(kind: &str, id: impl std::fmt::Display) -> Error {
+    Error::Client {
+        message: format!("{kind} {id} not found"),
+        status: Some(404),
+    }
+}
+
+fn write_error(path: &std::path::Path, err: std::io::Error) -> Error {
+    Error::StreamError {
+        message: format!("Failed to write {}: {err}", path.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn fixture_lookups() {
+        let catalog = MemoryCatalog::fixture();
+
+        let album = catalog.album("1").await.unwrap();
+        assert_eq!(album.tracks.len(), 3);
+
+        let tracks = catalog.tracks(vec![202, 101]).await.unwrap();
+        assert_eq!(
+            tracks.iter().map(|t| t.id).collect::<Vec<_>>(),
+            vec![202, 101]
+        );
+
+        assert!(catalog.track(999).await.is_err());
+
+        let results = catalog.search("high".into()).await.unwrap();
+        assert_eq!(results.albums.len(), 1);
+        assert_eq!(results.albums[0].id, "2");
+    }
+
+    #[tokio::test]
+    async fn streams_tone_into_cache() {
+        let catalog = MemoryCatalog::fixture();
+        let cache_path = std::env::temp_dir()
+            .join(format!(
+                "qobuz-player-memory-catalog-{}",
+                std::process::id()
+            ))
+            .join("201.flac");
+
+        let track_info = catalog.track_url(201).await.unwrap();
+        let source = catalog
+            .stream_track(cache_path.clone(), track_info)
+            .await
+            .unwrap();
+
+        assert!(matches!(source, TrackSource::Cached(ref path) if path == &cache_path));
+        assert!(std::fs::metadata(&cache_path).unwrap().len() > 15 * SAMPLE_RATE as u64 * 2);
+
+        _ = std::fs::remove_dir_all(cache_path.parent().unwrap());
+    }
+}
@@ -0,0 +1,152 @@
+//! Generates mono 16 bit FLAC files with a sine tone, stored uncompressed in
+//! verbatim subframes. Enough for the decoder, without an encoder dependency.
+
+const BLOCK_SIZE: usize = 4096;
+const BITS_PER_SAMPLE: u32 = 16;
+
+pub(crate) fn sine_flac(frequency: f32, sample_rate: u32, duration_seconds: u32) -> Vec<u8> {
+    let total_samples = sample_rate as usize * duration_seconds as usize;
+    let samples: Vec<i16> = (0..total_samples)
+        .map(|n| {
+            let t = n as f32 / sample_rate as f32;
+            let value = (t * frequency * std::f32::consts::TAU).sin() * 0.2;
+            (value * i16::MAX as f32) as i16
+        })
+        .collect();
+
+    let mut out = Vec::with_capacity(42 + total_samples * 2 + total_samples / BLOCK_SIZE * 16);
+    out.extend_from_slice(b"fLaC");
+    write_stream_info(&mut out, sample_rate, total_samples as u64);
+
+    for (frame_number, block) in samples.chunks(BLOCK_SIZE).enumerate() {
+        write_frame(&mut out, frame_number as u64, block);
+    }
+
+    out
+}
+
+fn write_stream_info(out: &mut Vec<u8>, sample_rate: u32, total_samples: u64) {
+    // Last metadata block, type STREAMINFO, 34 bytes long
+    out.extend_from_slice(&[0x80, 0x00, 0x00, 34]);
+
+    out.extend_from_slice(&(BLOCK_SIZE as u16).to_be_bytes());
+    out.extend_from_slice(&(BLOCK_SIZE as u16).to_be_bytes());
+    // Unknown minimum and maximum frame size
+    out.extend_from_slice(&[0; 6]);
+
+    // 20 bits sample rate, 3 bits channels - 1, 5 bits bits per sample - 1,
+    // 36 bits total samples
+    let channels = 1u64;
+    let packed = (sample_rate as u64) << 44
+        | (channels - 1) << 41
+        | (BITS_PER_SAMPLE as u64 - 1) << 36
+        | (total_samples & 0xF_FFFF_FFFF);
+    out.extend_from_slice(&packed.to_be_bytes());
+
+    // MD5 of the audio is optional
+    out.extend_from_slice(&[0; 16]);
+}
+
+fn write_frame(out: &mut Vec<u8>, frame_number: u64, samples: &[i16]) {
+    let start = out.len();
+
+    // Sync code and fixed block size
+    out.extend_from_slice(&[0xFF, 0xF8]);
+    // Block size as 16 bit value at the end of the header, sample rate from
+    // STREAMINFO
+    out.push(0x70);
+    // Mono, 16 bits per sample
+    out.push(0x08);
+    write_coded_number(out, frame_number);
+    out.extend_from_slice(&(samples.len() as u16 - 1).to_be_bytes());
+    out.push(crc8(&out[start..]));
+
+    // Verbatim subframe without wasted bits
+    out.push(0x02);
+    for sample in samples {
+        out.extend_from_slice(&sample.to_be_bytes());
+    }
+
+    let crc = crc16(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Frame numbers use the same variable length coding as UTF-8, extended to 36
+/// bits.
+fn write_coded_number(out: &mut Vec<u8>, value: u64) {
+    if value < 0x80 {
+        out.push(value as u8);
+        return;
+    }
+
+    let continuation_bytes = match value {
+        v if v < 0x800 => 1,
+        v if v < 0x1_0000 => 2,
+        v if v < 0x20_0000 => 3,
+        v if v < 0x400_0000 => 4,
+        v if v < 0x8000_0000 => 5,
+        _ => 6,
+    };
+
+    let marker = !(0xFFu8 >> (continuation_bytes + 1));
+    let first = (value >> (6 * continuation_bytes)) as u8;
+    out.push(marker | first);
+
+    for i in (0..continuation_bytes).rev() {
+        out.push(0x80 | ((value >> (6 * i)) & 0x3F) as u8);
+    }
+}
+
+fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0, |crc, byte| {
+        (0..8).fold(crc ^ byte, |crc, _| {
+            if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0, |crc, byte| {
+        (0..8).fold(crc ^ ((*byte as u16) << 8), |crc, _| {
+            if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rodio::{Decoder, Source};
+
+    use super::*;
+
+    #[test]
+    fn coded_numbers() {
+        let coded = |value| {
+            let mut out = vec![];
+            write_coded_number(&mut out, value);
+            out
+        };
+
+        assert_eq!(coded(0x7F), vec![0x7F]);
+        assert_eq!(coded(0x80), vec![0xC2, 0x80]);
+        assert_eq!(coded(0x20AC), vec![0xE2, 0x82, 0xAC]);
+    }
+
+    #[test]
+    fn generated_flac_decodes() {
+        let flac = sine_flac(440.0, 8000, 2);
+        let decoder = Decoder::new(std::io::Cursor::new(flac)).expect("valid flac");
+
+        assert_eq!(decoder.sample_rate().get(), 8000);
+        assert_eq!(decoder.channels().get(), 1);
+        assert_eq!(decoder.count(), 16000);
+    }
+}
@@ -3,15 +3,17 @@ use std::{path::PathBuf, sync::Mutex};
 use crate::{
     database::Credentials,
     models::{
-        Album, AlbumSimple, ArtistPage, Favorites, Genre, Playlist, PlaylistSimple, SearchResults,
-        Track,
+        Album, AlbumSimple, ArtistPage, ComposerPage, Favorites, Genre, LabelPage, Playlist,
+        PlaylistSimple, SearchResults, Track, group_by_work,
         mapper::{
             parse_album, parse_album_simple, parse_artist, parse_artist_page, parse_featured_album,
-            parse_genre, parse_playlist, parse_playlist_simple, parse_search_results, parse_track,
+            parse_genre, parse_label_page, parse_playlist, parse_playlist_simple,
+            parse_search_results, parse_track,
         },
+        sort_by_work,
     },
 };
-use futures::future::join_all;
+use futures::{TryStreamExt, future::join_all};
 use moka::future::Cache;
 use qobuz_player_client::{
     client::{
@@ -23,7 +25,7 @@ use qobuz_player_client::{
 };
 use time::Duration;
 use tokio::{
-    sync::{OnceCell, RwLock},
+    sync::{RwLock, RwLockMappedWriteGuard, RwLockReadGuard, RwLockWriteGuard},
     try_join,
 };
 
@@ -31,12 +33,20 @@ use crate::{AppResult, error::Error, simple_cache::SimpleCache};
 
 pub use qobuz_player_client::client::exchange_oauth_code;
 pub use qobuz_player_client::client::get_app_id;
+pub use qobuz_player_client::client::oauth_url;
 
 type QobuzClient = qobuz_player_client::client::Client;
 type Result<T, E = Error> = std::result::Result<T, E>;
 
+/// Results per kind in one page of search results
+pub const SEARCH_PAGE_SIZE: u32 = 20;
+
+/// Albums in one page of a label's releases
+pub const LABEL_PAGE_SIZE: u32 = 50;
+
 pub struct Client {
-    qobuz_client: OnceCell<RwLock<QobuzClient>>,
+    /// Made on first use from the credentials, and dropped when they change
+    qobuz_client: RwLock<Option<QobuzClient>>,
     credentials: Mutex<Option<Credentials>>,
     max_audio_quality: AudioQuality,
     favorites_cache: SimpleCache<Favorites>,
@@ -47,6 +57,7 @@ pub struct Client {
     genre_playlists_cache: Cache<u32, Vec<PlaylistSimple>>,
     album_cache: Cache<String, Album>,
     artist_cache: Cache<u32, ArtistPage>,
+    label_cache: Cache<u32, LabelPage>,
     playlist_cache: Cache<u32, Playlist>,
     suggested_albums_cache: Cache<String, Vec<AlbumSimple>>,
     search_cache: Cache<String, SearchResults>,
@@ -63,6 +74,28 @@ impl Client {
         Ok(())
     }
 
+    /// Signs in as `credentials`, replacing any account that was signed in
+    pub async fn sign_in(&self, credentials: Credentials) -> AppResult<()> {
+        self.set_credentials(credentials)?;
+        self.reset_account().await;
+        Ok(())
+    }
+
+    /// Requests fail with [`Error::MissingCredentials`] until the next
+    /// [`Client::sign_in`]
+    pub async fn sign_out(&self) -> AppResult<()> {
+        *self.credentials.lock()? = None;
+        self.reset_account().await;
+        Ok(())
+    }
+
+    /// Favorites and playlist ownership belong to the account
+    async fn reset_account(&self) {
+        *self.qobuz_client.write().await = None;
+        self.favorites_cache.clear().await;
+        self.playlist_cache.invalidate_all();
+    }
+
     pub async fn app_id(&self) -> AppResult<String> {
         let client = self.get_client().await?;
         Ok(client.app_id().to_string())
@@ -94,6 +127,10 @@ impl Client {
             .time_to_live(std::time::Duration::from_secs(60 * 60 * 24))
             .build();
 
+        let label_cache = moka::future::CacheBuilder::new(1000)
+            .time_to_live(std::time::Duration::from_secs(60 * 60 * 24))
+            .build();
+
         let playlist_cache = moka::future::CacheBuilder::new(1000)
             .time_to_live(std::time::Duration::from_secs(60 * 60 * 24))
             .build();
@@ -128,6 +165,7 @@ impl Client {
             genre_playlists_cache,
             album_cache,
             artist_cache,
+            label_cache,
             playlist_cache,
             suggested_albums_cache,
             search_cache,
@@ -138,9 +176,7 @@ impl Client {
         let credentials = self.credentials.lock()?.clone();
 
         let Some(credentials) = credentials else {
-            return Err(Error::Login {
-                message: "Login credentials not set before logging in".to_string(),
-            });
+            return Err(Error::MissingCredentials);
         };
 
         let client = QobuzClient::new(
@@ -153,28 +189,31 @@ impl Client {
         Ok(client)
     }
 
-    async fn get_client(&self) -> Result<tokio::sync::RwLockReadGuard<'_, QobuzClient>> {
-        let cell = self
-            .qobuz_client
-            .get_or_try_init(|| async {
-                let client = self.init_client().await?;
-                Ok::<_, Error>(RwLock::new(client))
-            })
-            .await?;
+    async fn get_client(&self) -> Result<RwLockReadGuard<'_, QobuzClient>> {
+        let client = self.qobuz_client.read().await;
+        if let Ok(client) = RwLockReadGuard::try_map(client, Option::as_ref) {
+            return Ok(client);
+        }
 
-        Ok(cell.read().await)
+        let client = self.initialized_client().await?;
+        Ok(RwLockWriteGuard::downgrade_map(client, |client| {
+            client.as_ref().expect("initialized")
+        }))
     }
 
-    async fn get_client_mut(&self) -> Result<tokio::sync::RwLockWriteGuard<'_, QobuzClient>> {
-        let cell = self
-            .qobuz_client
-            .get_or_try_init(|| async {
-                let client = self.init_client().await?;
-                Ok::<_, Error>(RwLock::new(client))
-            })
-            .await?;
+    async fn get_client_mut(&self) -> Result<RwLockMappedWriteGuard<'_, QobuzClient>> {
+        let client = self.initialized_client().await?;
+        Ok(RwLockWriteGuard::map(client, |client| {
+            client.as_mut().expect("initialized")
+        }))
+    }
 
-        Ok(cell.write().await)
+    async fn initialized_client(&self) -> Result<RwLockWriteGuard<'_, Option<QobuzClient>>> {
+        let mut client = self.qobuz_client.write().await;
+        if client.is_none() {
+            *client = Some(self.init_client().await?);
+        }
+        Ok(client)
     }
 
     pub async fn track_url(&self, track_id: u32) -> Result<TrackInfo> {
@@ -212,16 +251,25 @@ impl Client {
             return Ok(cache);
         }
 
-        let client = self.get_client().await?;
-        let results = client.search_all(&query, 20).await?;
-        let user_id = self.get_client().await?.user_id();
-
-        let out = parse_search_results(results, user_id, &self.max_audio_quality);
+        let out = self.search_page(query.clone(), 0).await?;
 
         self.search_cache.insert(query, out.clone()).await;
         Ok(out)
     }
 
+    /// Search results starting at `offset` within each kind of result. A
+    /// kind with no more results comes back empty.
+    pub async fn search_page(&self, query: String, offset: u32) -> Result<SearchResults> {
+        let client = self.get_client().await?;
+        let results = client.search_all(&query, offset, SEARCH_PAGE_SIZE).await?;
+
+        Ok(parse_search_results(
+            results,
+            client.user_id(),
+            &self.max_audio_quality,
+        ))
+    }
+
     pub async fn artist_page(&self, id: u32) -> Result<ArtistPage> {
         if let Some(cache) = self.artist_cache.get(&id).await {
             return Ok(cache);
@@ -231,31 +279,31 @@ impl Client {
 
         let (artist, albums, singles, live, compilations, similar_artists) = try_join!(
             client.artist(id),
-            client.artist_releases(id, ReleaseType::Albums, None),
-            client.artist_releases(id, ReleaseType::EPsAndSingles, None),
-            client.artist_releases(id, ReleaseType::Live, None),
-            client.artist_releases(id, ReleaseType::Compilations, None),
+            client.artist_releases(id, ReleaseType::Albums).try_concat(),
+            client
+                .artist_releases(id, ReleaseType::EPsAndSingles)
+                .try_concat(),
+            client.artist_releases(id, ReleaseType::Live).try_concat(),
+            client
+                .artist_releases(id, ReleaseType::Compilations)
+                .try_concat(),
             client.similar_artists(id, None),
         )?;
 
         let artist = parse_artist_page(
             artist,
             albums
-                .items
                 .into_iter()
                 .map(|x| parse_album_simple(x, &self.max_audio_quality))
                 .collect(),
             singles
-                .items
                 .into_iter()
                 .map(|x| parse_album_simple(x, &self.max_audio_quality))
                 .collect(),
-            live.items
-                .into_iter()
+            live.into_iter()
                 .map(|x| parse_album_simple(x, &self.max_audio_quality))
                 .collect(),
             compilations
-                .items
                 .into_iter()
                 .map(|x| parse_album_simple(x, &self.max_audio_quality))
                 .collect(),
@@ -271,6 +319,66 @@ impl Client {
         Ok(artist)
     }
 
+    pub async fn label(&self, id: u32) -> Result<LabelPage> {
+        if let Some(cache) = self.label_cache.get(&id).await {
+            return Ok(cache);
+        }
+
+        let client = self.get_client().await?;
+
+        let (label, albums) = try_join!(
+            client.label(id),
+            client.label_albums_page(id, 0, LABEL_PAGE_SIZE)
+        )?;
+
+        let label = parse_label_page(
+            label,
+            albums
+                .items
+                .into_iter()
+                .map(|x| parse_album(x, &self.max_audio_quality).into())
+                .collect(),
+        );
+
+        self.label_cache.insert(id, label.clone()).await;
+        Ok(label)
+    }
+
+    /// A label's albums starting at `offset`. Past the last album the page
+    /// comes back empty.
+    pub async fn label_albums(&self, id: u32, offset: u32) -> Result<Vec<AlbumSimple>> {
+        let client = self.get_client().await?;
+        let albums = client
+            .label_albums_page(id, offset, LABEL_PAGE_SIZE)
+            .await?;
+
+        Ok(albums
+            .items
+            .into_iter()
+            .map(|x| parse_album(x, &self.max_audio_quality).into())
+            .collect())
+    }
+
+    /// A composer's releases, and their works among the favorite tracks
+    pub async fn composer_page(&self, id: u32) -> Result<ComposerPage> {
+        let (artist, favorites) = try_join!(self.artist_page(id), self.favorites())?;
+
+        let mut tracks: Vec<Track> = favorites
+            .tracks
+            .into_iter()
+            .filter(|t| t.composer.as_ref().is_some_and(|c| c.id == id))
+            .collect();
+        sort_by_work(&mut tracks);
+
+        Ok(ComposerPage {
+            id: artist.id,
+            name: artist.name,
+            image: artist.image,
+            albums: artist.albums,
+            works: group_by_work(&tracks),
+        })
+    }
+
     pub async fn tracks(&self, ids: Vec<u32>) -> Result<Vec<Track>> {
         let futures = ids.into_iter().map(|id| self.track(id));
         let results = join_all(futures).await;
@@ -478,12 +586,14 @@ impl Client {
 
         let client = self.get_client().await?;
 
-        let favorites_result = client.favorites(1000).await?;
-        let user_playlists = client.user_playlists().await?;
+        let (favorite_albums, favorite_tracks, favorite_artists, user_playlists) = try_join!(
+            client.favorite_albums().try_concat(),
+            client.favorite_tracks().try_concat(),
+            client.favorite_artists().try_concat(),
+            client.user_playlists().try_concat(),
+        )?;
 
-        let mut albums: Vec<_> = favorites_result
-            .albums
-            .items
+        let mut albums: Vec<_> = favorite_albums
             .into_iter()
             .map(|x| parse_album(x, &self.max_audio_quality).into())
             .collect();
@@ -495,31 +605,22 @@ impl Client {
                 .cmp(&b.artist.name.to_lowercase())
         });
 
-        let mut artists: Vec<_> = favorites_result
-            .artists
-            .items
-            .into_iter()
-            .map(parse_artist)
-            .collect();
-        artists.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+        let mut artists: Vec<_> = favorite_artists.into_iter().map(parse_artist).collect();
+        artists.sort_by_key(|a| a.name.to_lowercase());
 
         let mut playlists: Vec<_> = user_playlists
-            .playlists
-            .items
             .into_iter()
             .map(|x| parse_playlist(x, client.user_id(), &self.max_audio_quality))
             .collect();
 
-        playlists.sort_by(|a, b| a.title.to_lowercase().cmp(&b.title.to_lowercase()));
+        playlists.sort_by_key(|a| a.title.to_lowercase());
 
-        let mut tracks: Vec<_> = favorites_result
-            .tracks
-            .items
+        let mut tracks: Vec<_> = favorite_tracks
             .into_iter()
             .map(|x| parse_track(x, &self.max_audio_quality))
             .collect();
 
-        tracks.sort_by(|a, b| a.title.to_lowercase().cmp(&b.title.to_lowercase()));
+        tracks.sort_by_key(|a| a.title.to_lowercase());
 
         let favorites = Favorites {
             albums,
@@ -1,5 +1,7 @@
 use std::time::Duration;
 
+use crate::models::Link;
+
 #[derive(Debug)]
 pub enum ControlCommand {
     Album {
@@ -22,6 +24,16 @@ pub enum ControlCommand {
     Track {
         id: u32,
     },
+    PlayLink {
+        link: Link,
+    },
+    QueueLink {
+        link: Link,
+    },
+    InsertLinkAfter {
+        link: Link,
+        queue_id: Option<u64>,
+    },
     SkipToPosition {
         new_position: usize,
         force: bool,
@@ -45,6 +57,16 @@ pub enum ControlCommand {
     RemoveIndexFromQueue {
         index: usize,
     },
+    RemoveQueueItem {
+        queue_id: u64,
+    },
+    MoveQueueItem {
+        queue_id: u64,
+        new_position: usize,
+    },
+    SkipToQueueItem {
+        queue_id: u64,
+    },
     PlayTracksNext {
         ids: Vec<u32>,
     },
@@ -56,6 +78,8 @@ pub enum ControlCommand {
         play: bool,
     },
     ClearQueue,
+    UndoQueue,
+    RedoQueue,
 }
 
 #[derive(Debug, Clone)]
@@ -115,6 +139,28 @@ impl Controls {
             .expect("infallible");
     }
 
+    /// Replace the queue with whatever the link points to
+    pub fn play_link(&self, link: Link) {
+        self.tx
+            .send(ControlCommand::PlayLink { link })
+            .expect("infallible");
+    }
+
+    /// Append the tracks the link points to to the queue
+    pub fn queue_link(&self, link: Link) {
+        self.tx
+            .send(ControlCommand::QueueLink { link })
+            .expect("infallible");
+    }
+
+    /// Insert the tracks the link points to after the queue item, or first in
+    /// the queue when there is none
+    pub fn insert_link_after(&self, link: Link, queue_id: Option<u64>) {
+        self.tx
+            .send(ControlCommand::InsertLinkAfter { link, queue_id })
+            .expect("infallible");
+    }
+
     pub fn add_tracks_to_queue(&self, ids: Vec<u32>) {
         self.tx
             .send(ControlCommand::AddTracksToQueue { ids })
@@ -127,6 +173,27 @@ impl Controls {
             .expect("infallible");
     }
 
+    pub fn remove_queue_item(&self, queue_id: u64) {
+        self.tx
+            .send(ControlCommand::RemoveQueueItem { queue_id })
+            .expect("infallible");
+    }
+
+    pub fn move_queue_item(&self, queue_id: u64, new_position: usize) {
+        self.tx
+            .send(ControlCommand::MoveQueueItem {
+                queue_id,
+                new_position,
+            })
+            .expect("infallible");
+    }
+
+    pub fn skip_to_queue_item(&self, queue_id: u64) {
+        self.tx
+            .send(ControlCommand::SkipToQueueItem { queue_id })
+            .expect("infallible");
+    }
+
     pub fn play_tracks_next(&self, ids: Vec<u32>) {
         self.tx
             .send(ControlCommand::PlayTracksNext { ids })
@@ -189,6 +256,14 @@ impl Controls {
             .send(ControlCommand::ClearQueue)
             .expect("infallible");
     }
+
+    pub fn undo_queue(&self) {
+        self.tx.send(ControlCommand::UndoQueue).expect("infallible");
+    }
+
+    pub fn redo_queue(&self) {
+        self.tx.send(ControlCommand::RedoQueue).expect("infallible");
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -1,8 +1,13 @@
-use crate::{AppResult, AudioQuality, Error, Tracklist};
+use crate::{
+    AppResult, AudioQuality, Error, Tracklist,
+    models::{Track, TrackStatus},
+    tracklist::{QueueItem, TracklistType},
+};
 use qobuz_player_client::client::OAuthResult;
-use serde_json::to_string;
+use serde_json::{Value, to_string};
 use sqlx::types::Json;
 use sqlx::{Pool, Sqlite, SqlitePool, sqlite::SqliteConnectOptions};
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
 pub struct Database {
@@ -30,9 +35,14 @@ impl Database {
             url
         };
 
+        Database::open(&database_url).await
+    }
+
+    /// Open the database file at `path`, creating it when it is missing
+    pub async fn open(path: &Path) -> AppResult<Self> {
         let options = SqliteConnectOptions::new()
             .journal_mode(sqlx::sqlite::SqliteJournalMode::Wal)
-            .filename(database_url)
+            .filename(path)
             .create_if_missing(true);
 
         let pool = SqlitePool::connect_with(options).await?;
@@ -40,11 +50,12 @@ impl Database {
         Database::init(pool).await
     }
 
-    async fn init(pool: sqlx::Pool<sqlx::Sqlite>) -> AppResult<Self> {
+    pub(crate) async fn init(pool: sqlx::Pool<sqlx::Sqlite>) -> AppResult<Self> {
         sqlx::migrate!("./migrations").run(&pool).await?;
 
         create_credentials_row(&pool).await?;
         create_configuration(&pool).await?;
+        create_tracklist_meta_row(&pool).await?;
 
         Ok(Self { pool })
     }
@@ -70,29 +81,137 @@ impl Database {
         Ok(())
     }
 
+    /// Store the tracklist, writing only the queue items that changed since
+    /// the last call. A stored item usually only changes position and status,
+    /// but is written again when its queue id now holds another track, as
+    /// when a Connect controller reuses ids.
     pub async fn set_tracklist(&self, tracklist: &Tracklist) -> AppResult<()> {
-        let serialized = to_string(&tracklist)?;
+        let mut transaction = self.pool.begin().await?;
 
-        sqlx::query!("delete from tracklist")
-            .execute(&self.pool)
-            .await?;
+        let stored: HashMap<i64, QueueItemStateDb> = sqlx::query_as!(
+            QueueItemStateDb,
+            r#"select queue_id, position, status, track ->> '$.id' as "track_id!: i64" from queue_items"#
+        )
+        .fetch_all(&mut *transaction)
+        .await?
+        .into_iter()
+        .map(|row| (row.queue_id, row))
+        .collect();
+
+        let queue = tracklist.queue();
+
+        let mut kept = HashSet::new();
+        for (position, item) in queue.iter().enumerate() {
+            let queue_id = item.queue_id as i64;
+            let position = position as i64;
+            let status = status_to_string(&item.track.status);
+            kept.insert(queue_id);
+
+            match stored.get(&queue_id) {
+                Some(row) if row.track_id != i64::from(item.track.id) => {
+                    let item_index = item.index as i64;
+                    let track = to_string(&item.track)?;
+
+                    sqlx::query!(
+                        "update queue_items set position = ?2, item_index = ?3, status = ?4, track = ?5 where queue_id = ?1",
+                        queue_id,
+                        position,
+                        item_index,
+                        status,
+                        track
+                    )
+                    .execute(&mut *transaction)
+                    .await?;
+                }
+                Some(row) if row.position == position && row.status == status => (),
+                Some(_) => {
+                    sqlx::query!(
+                        "update queue_items set position = ?2, status = ?3 where queue_id = ?1",
+                        queue_id,
+                        position,
+                        status
+                    )
+                    .execute(&mut *transaction)
+                    .await?;
+                }
+                None => {
+                    let item_index = item.index as i64;
+                    let track = to_string(&item.track)?;
+
+                    sqlx::query!(
+                        "insert into queue_items (queue_id, position, item_index, status, track) values (?1, ?2, ?3, ?4, ?5)",
+                        queue_id,
+                        position,
+                        item_index,
+                        status,
+                        track
+                    )
+                    .execute(&mut *transaction)
+                    .await?;
+                }
+            }
+        }
 
-        sqlx::query!("insert into tracklist (tracklist) values (?1)", serialized)
-            .execute(&self.pool)
-            .await?;
+        for queue_id in stored.keys().filter(|queue_id| !kept.contains(queue_id)) {
+            sqlx::query!("delete from queue_items where queue_id = ?1", queue_id)
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        let list_type = to_string(tracklist.list_type())?;
+        let next_queue_id = tracklist.next_queue_id() as i64;
+
+        sqlx::query!(
+            r#"
+            update tracklist_meta
+            set list_type = ?1, next_queue_id = ?2
+            where rowid = 1 and (list_type != ?1 or next_queue_id != ?2)
+            "#,
+            list_type,
+            next_queue_id
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        transaction.commit().await?;
 
         Ok(())
     }
 
     pub async fn get_tracklist(&self) -> Option<Tracklist> {
-        let row = sqlx::query_as!(
-            TracklistDb,
-            r#"SELECT tracklist as "tracklist: Json<Tracklist>" FROM tracklist"#
+        let meta = sqlx::query_as!(
+            TracklistMetaDb,
+            r#"select list_type as "list_type: Json<TracklistType>", next_queue_id from tracklist_meta where rowid = 1"#
         )
         .fetch_one(&self.pool)
-        .await;
+        .await
+        .ok()?;
+
+        let rows = sqlx::query_as!(
+            QueueItemDb,
+            r#"select queue_id, item_index, status, track as "track: Json<Track>" from queue_items order by position"#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .ok()?;
 
-        row.ok().map(|x| x.tracklist.0)
+        let queue = rows
+            .into_iter()
+            .map(|row| {
+                let mut track = row.track.0;
+                track.status = status_from_string(&row.status);
+
+                QueueItem {
+                    track,
+                    queue_id: row.queue_id as u64,
+                    index: row.item_index as usize,
+                }
+            })
+            .collect();
+
+        let mut tracklist = Tracklist::new(meta.list_type.0, queue);
+        tracklist.reserve_queue_ids(meta.next_queue_id as u64);
+        Some(tracklist)
     }
 
     pub async fn set_volume(&self, volume: f32) -> AppResult<()> {
@@ -244,6 +363,212 @@ impl Database {
         Ok(paths)
     }
 
+    pub async fn get_webhooks(&self) -> AppResult<Vec<Webhook>> {
+        let rows = sqlx::query_as!(
+            WebhookDb,
+            r#"select id, url, events as "events: Json<Vec<String>>", secret from webhooks order by id"#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| Webhook {
+                id: row.id,
+                url: row.url,
+                events: row.events.0,
+                secret: row.secret,
+            })
+            .collect())
+    }
+
+    pub async fn add_webhook(&self, url: &str, events: &[String], secret: &str) -> AppResult<i64> {
+        let events = to_string(events)?;
+
+        let result = sqlx::query!(
+            "insert into webhooks (url, events, secret) values (?1, ?2, ?3)",
+            url,
+            events,
+            secret
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    pub async fn remove_webhook(&self, id: i64) -> AppResult<()> {
+        sqlx::query!("delete from webhooks where id = ?1", id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn has_web_users(&self) -> AppResult<bool> {
+        let row = sqlx::query!(r#"select exists(select 1 from web_users) as "exists!: bool""#)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(row.exists)
+    }
+
+    pub async fn get_web_users(&self) -> AppResult<Vec<WebUser>> {
+        let rows = sqlx::query_as!(
+            WebUserDb,
+            "select id, username, password_hash, role from web_users order by username"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    pub async fn get_web_user(&self, username: &str) -> AppResult<Option<WebUser>> {
+        let row = sqlx::query_as!(
+            WebUserDb,
+            "select id, username, password_hash, role from web_users where username = ?1",
+            username
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(Into::into))
+    }
+
+    pub async fn add_web_user(
+        &self,
+        username: &str,
+        password_hash: &str,
+        role: Role,
+    ) -> AppResult<i64> {
+        let role = role.as_str();
+
+        let result = sqlx::query!(
+            "insert into web_users (username, password_hash, role) values (?1, ?2, ?3)",
+            username,
+            password_hash,
+            role
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Remove the user and log them out everywhere
+    pub async fn remove_web_user(&self, id: i64) -> AppResult<()> {
+        sqlx::query!("delete from web_sessions where user_id = ?1", id)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query!("delete from web_users where id = ?1", id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn add_web_session(&self, token_hash: &str, user_id: i64) -> AppResult<()> {
+        let now = time::OffsetDateTime::now_utc()
+            .format(&time::format_description::well_known::Rfc3339)
+            .expect("infallible");
+
+        sqlx::query!(
+            "insert into web_sessions (token_hash, user_id, created_at) values (?1, ?2, ?3)",
+            token_hash,
+            user_id,
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// The user of a session started less than `max_age` ago
+    pub async fn get_web_session_user(
+        &self,
+        token_hash: &str,
+        max_age: time::Duration,
+    ) -> AppResult<Option<WebUser>> {
+        let cutoff = (time::OffsetDateTime::now_utc() - max_age)
+            .format(&time::format_description::well_known::Rfc3339)
+            .expect("infallible");
+
+        let row = sqlx::query_as!(
+            WebUserDb,
+            "select web_users.id, username, password_hash, role from web_sessions join web_users on web_users.id = web_sessions.user_id where token_hash = ?1 and created_at > ?2",
+            token_hash,
+            cutoff
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(Into::into))
+    }
+
+    pub async fn remove_web_session(&self, token_hash: &str) -> AppResult<()> {
+        sqlx::query!("delete from web_sessions where token_hash = ?1", token_hash)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_api_tokens(&self) -> AppResult<Vec<ApiToken>> {
+        let rows = sqlx::query!("select id, name, role, created_at from api_tokens order by id")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| ApiToken {
+                id: row.id,
+                name: row.name,
+                role: Role::parse(&row.role),
+                created_at: row.created_at,
+            })
+            .collect())
+    }
+
+    pub async fn add_api_token(&self, name: &str, token_hash: &str, role: Role) -> AppResult<i64> {
+        let role = role.as_str();
+        let now = time::OffsetDateTime::now_utc()
+            .format(&time::format_description::well_known::Rfc3339)
+            .expect("infallible");
+
+        let result = sqlx::query!(
+            "insert into api_tokens (name, token_hash, role, created_at) values (?1, ?2, ?3, ?4)",
+            name,
+            token_hash,
+            role,
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    pub async fn get_api_token_role(&self, token_hash: &str) -> AppResult<Option<Role>> {
+        let row = sqlx::query!(
+            "select role from api_tokens where token_hash = ?1",
+            token_hash
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|row| Role::parse(&row.role)))
+    }
+
+    pub async fn remove_api_token(&self, id: i64) -> AppResult<()> {
+        sqlx::query!("delete from api_tokens where id = ?1", id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
     pub async fn set_cache_entry(&self, path: &Path) {
         let now = time::OffsetDateTime::now_utc()
             .format(&time::format_description::well_known::Rfc3339)
@@ -268,6 +593,98 @@ impl Database {
     }
 }
 
+/// An HTTP endpoint player events are posted to
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct Webhook {
+    pub id: i64,
+    pub url: String,
+    /// Names of the events the webhook is sent for. Empty for every event.
+    pub events: Vec<String>,
+    /// Shared secret the deliveries are signed with
+    pub secret: String,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct WebhookDb {
+    id: i64,
+    url: String,
+    events: Json<Vec<String>>,
+    secret: String,
+}
+
+/// What a web user or API token may do. Ordered from least to most access.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize, serde::Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Browse and add to the queue
+    Guest,
+    /// Control playback and edit the queue
+    Controller,
+    /// Everything, including favorites, playlists and user management
+    Admin,
+}
+
+impl Role {
+    pub const ALL: [Self; 3] = [Self::Admin, Self::Controller, Self::Guest];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Guest => "guest",
+            Self::Controller => "controller",
+            Self::Admin => "admin",
+        }
+    }
+
+    /// Unknown roles get the least access
+    pub fn parse(role: &str) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|x| x.as_str() == role)
+            .unwrap_or(Self::Guest)
+    }
+}
+
+/// A person who can log in to the web UI
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct WebUser {
+    pub id: i64,
+    pub username: String,
+    /// Argon2 in PHC string format
+    pub password_hash: String,
+    pub role: Role,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct WebUserDb {
+    id: i64,
+    username: String,
+    password_hash: String,
+    role: String,
+}
+
+impl From<WebUserDb> for WebUser {
+    fn from(row: WebUserDb) -> Self {
+        Self {
+            id: row.id,
+            username: row.username,
+            password_hash: row.password_hash,
+            role: Role::parse(&row.role),
+        }
+    }
+}
+
+/// A bearer token for scripts and RFID clients. Only its hash is stored.
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct ApiToken {
+    pub id: i64,
+    pub name: String,
+    pub role: Role,
+    /// RFC 3339
+    pub created_at: String,
+}
+
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub enum ReferenceType {
     Album(String),
@@ -322,9 +739,37 @@ pub struct DatabaseConfiguration {
     pub max_audio_quality: Option<i64>,
 }
 
-#[derive(Debug, sqlx::FromRow, serde::Deserialize)]
-struct TracklistDb {
-    tracklist: Json<Tracklist>,
+#[derive(Debug, sqlx::FromRow)]
+struct TracklistMetaDb {
+    list_type: Json<TracklistType>,
+    next_queue_id: i64,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct QueueItemDb {
+    queue_id: i64,
+    item_index: i64,
+    status: String,
+    track: Json<Track>,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct QueueItemStateDb {
+    queue_id: i64,
+    position: i64,
+    status: String,
+    track_id: i64,
+}
+
+fn status_to_string(status: &TrackStatus) -> String {
+    match serde_json::to_value(status) {
+        Ok(Value::String(status)) => status,
+        _ => "Unplayed".into(),
+    }
+}
+
+fn status_from_string(status: &str) -> TrackStatus {
+    serde_json::from_value(Value::String(status.into())).unwrap_or_default()
 }
 
 #[derive(Debug, sqlx::FromRow, serde::Deserialize)]
@@ -344,6 +789,17 @@ async fn create_credentials_row(pool: &Pool<Sqlite>) -> AppResult<()> {
     Ok(())
 }
 
+async fn create_tracklist_meta_row(pool: &Pool<Sqlite>) -> AppResult<()> {
+    let rowid = 1;
+    sqlx::query!(
+        "insert or ignore into tracklist_meta (rowid) values (?1)",
+        rowid
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
 async fn create_configuration(pool: &Pool<Sqlite>) -> AppResult<()> {
     let rowid = 1;
     sqlx::query!(
@@ -393,4 +849,150 @@ mod tests {
         assert_eq!(remaining, vec![new_path_str]);
         assert_eq!(deleted, vec![old_path]);
     }
+
+    #[sqlx::test]
+    async fn web_users_sessions_and_tokens(pool: sqlx::Pool<sqlx::Sqlite>) {
+        let db = Database::init(pool).await.unwrap();
+        assert!(!db.has_web_users().await.unwrap());
+
+        let id = db
+            .add_web_user("alice", "$argon2id$hash", Role::Controller)
+            .await
+            .unwrap();
+        assert!(db.has_web_users().await.unwrap());
+        assert_eq!(
+            db.get_web_user("alice").await.unwrap().unwrap().role,
+            Role::Controller
+        );
+
+        db.add_web_session("session", id).await.unwrap();
+        let user = db
+            .get_web_session_user("session", Duration::weeks(1))
+            .await
+            .unwrap();
+        assert_eq!(user.unwrap().username, "alice");
+        assert!(
+            db.get_web_session_user("session", Duration::seconds(-1))
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        db.remove_web_user(id).await.unwrap();
+        assert!(
+            db.get_web_session_user("session", Duration::weeks(1))
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        let token_id = db
+            .add_api_token("rfid", "token hash", Role::Guest)
+            .await
+            .unwrap();
+        assert_eq!(
+            db.get_api_token_role("token hash").await.unwrap(),
+            Some(Role::Guest)
+        );
+        db.remove_api_token(token_id).await.unwrap();
+        assert_eq!(db.get_api_token_role("token hash").await.unwrap(), None);
+    }
+
+    #[sqlx::test]
+    async fn tracklist_round_trips_with_incremental_writes(pool: sqlx::Pool<sqlx::Sqlite>) {
+        let db = Database::init(pool).await.unwrap();
+
+        let mut tracklist = Tracklist::new(TracklistType::Tracks, vec![]);
+        for id in 1..=3 {
+            tracklist.push_track(Track {
+                id,
+                ..Default::default()
+            });
+        }
+        tracklist.skip_to_track(1);
+        db.set_tracklist(&tracklist).await.unwrap();
+
+        assert!(tracklist.remove_queue_item(0));
+        assert!(tracklist.move_queue_item(2, 0));
+        db.set_tracklist(&tracklist).await.unwrap();
+
+        let stored = db.get_tracklist().await.unwrap();
+        assert_eq!(stored, tracklist);
+
+        let rows: Vec<(i64, i64)> =
+            sqlx::query_as("select queue_id, position from queue_items order by position")
+                .fetch_all(&db.pool)
+                .await
+                .unwrap();
+        assert_eq!(rows, vec![(2, 0), (1, 1)]);
+    }
+
+    #[sqlx::test]
+    async fn tracklist_rewrites_item_with_reused_queue_id(pool: sqlx::Pool<sqlx::Sqlite>) {
+        let db = Database::init(pool).await.unwrap();
+
+        let item = |track_id| QueueItem {
+            track: Track {
+                id: track_id,
+                ..Default::default()
+            },
+            queue_id: 0,
+            index: 0,
+        };
+
+        db.set_tracklist(&Tracklist::new(TracklistType::Tracks, vec![item(1)]))
+            .await
+            .unwrap();
+
+        let replaced = Tracklist::new(TracklistType::Tracks, vec![item(2)]);
+        db.set_tracklist(&replaced).await.unwrap();
+
+        assert_eq!(db.get_tracklist().await.unwrap(), replaced);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn legacy_tracklist_migrates_to_tables(pool: sqlx::Pool<sqlx::Sqlite>) {
+        let mut legacy = sqlx::migrate!("./migrations");
+        legacy.migrations = legacy
+            .migrations
+            .iter()
+            .filter(|migration| migration.version < 20261018120000)
+            .cloned()
+            .collect();
+        legacy.run(&pool).await.unwrap();
+
+        let track = |id, status| Track {
+            id,
+            status,
+            ..Default::default()
+        };
+        // Written before queue ids were unique, so they are given by position
+        let legacy_tracklist = serde_json::json!({
+            "queue": [
+                {"track": track(1, TrackStatus::Played), "queue_id": 0, "index": 0},
+                {"track": track(2, TrackStatus::Playing), "queue_id": 0, "index": 1},
+            ],
+            "list_type": "Tracks",
+        });
+        sqlx::query("insert into tracklist (tracklist) values (?1)")
+            .bind(legacy_tracklist.to_string())
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let db = Database::init(pool).await.unwrap();
+        let tracklist = db.get_tracklist().await.unwrap();
+
+        let items: Vec<_> = tracklist
+            .queue()
+            .iter()
+            .map(|item| (item.queue_id, item.track.id, item.track.status.clone()))
+            .collect();
+        assert_eq!(
+            items,
+            vec![(0, 1, TrackStatus::Played), (1, 2, TrackStatus::Playing)]
+        );
+        assert_eq!(tracklist.list_type(), &TracklistType::Tracks);
+        assert_eq!(tracklist.next_queue_id(), 2);
+    }
 }
@@ -3,32 +3,34 @@ use std::{
     sync::Arc,
 };
 
-use qobuz_player_client::stream::flac_source_stream::SeekableStreamReader;
-
-use crate::{AppResult, client::Client, database::Database, models::Track};
-
-pub enum DownloadResult {
-    Cached(PathBuf),
-    Streaming(SeekableStreamReader),
-}
+use crate::{
+    AppResult,
+    catalog::{Catalog, TrackSource},
+    database::Database,
+    models::Track,
+};
 
 pub struct Downloader {
     audio_cache_dir: PathBuf,
     database: Arc<Database>,
-    client: Arc<Client>,
+    catalog: Arc<dyn Catalog>,
 }
 
 impl Downloader {
-    pub fn new(audio_cache_dir: PathBuf, database: Arc<Database>, client: Arc<Client>) -> Self {
+    pub fn new(
+        audio_cache_dir: PathBuf,
+        database: Arc<Database>,
+        catalog: Arc<dyn Catalog>,
+    ) -> Self {
         Self {
             audio_cache_dir,
             database,
-            client,
+            catalog,
         }
     }
 
-    pub async fn ensure_track_is_downloaded(&mut self, track: &Track) -> AppResult<DownloadResult> {
-        let track_info = self.client.track_url(track.id).await?;
+    pub async fn ensure_track_is_downloaded(&mut self, track: &Track) -> AppResult<TrackSource> {
+        let track_info = self.catalog.track_url(track.id).await?;
 
         let cache_path = cache_path(
             track,
@@ -40,12 +42,10 @@ impl Downloader {
 
         if cache_path.exists() {
             tracing::info!("Playing from cache: {}", cache_path.display());
-            return Ok(DownloadResult::Cached(cache_path));
+            return Ok(TrackSource::Cached(cache_path));
         }
 
-        let stream = self.client.stream_track(cache_path, track_info).await?;
-
-        Ok(DownloadResult::Streaming(stream))
+        self.catalog.stream_track(cache_path, track_info).await
     }
 }
 
@@ -78,8 +78,15 @@ fn cache_path(
 
     let sample_rate_suffix = sample_rate.map(|sr| format!("_{sr}")).unwrap_or_default();
 
+    // Track numbers restart on every disc, so later discs get a prefix
+    let disc_prefix = match track.disc_number {
+        0 | 1 => String::new(),
+        disc => format!("{disc}-"),
+    };
+
     let track_file = format!(
-        "{}_{}{}.{}",
+        "{}{}_{}{}.{}",
+        disc_prefix,
         track.number,
         sanitize_name(track_title),
         sample_rate_suffix,
@@ -135,3 +142,22 @@ fn guess_extension(mime: &str) -> String {
         _ => "unknown".to_string(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_on_later_discs_do_not_collide() {
+        let track = |disc_number| Track {
+            number: 1,
+            disc_number,
+            title: "Intro".into(),
+            ..Default::default()
+        };
+        let path = |track: &Track| cache_path(track, "audio/flac", None, Path::new("/cache"));
+
+        assert_eq!(path(&track(1)).file_name().unwrap(), "1_Intro.flac");
+        assert_eq!(path(&track(2)).file_name().unwrap(), "2-1_Intro.flac");
+    }
+}
@@ -13,9 +13,11 @@ pub enum Error {
     },
     #[snafu(display("Failed to seek"))]
     Seek,
+    /// `status` is the HTTP status Qobuz answered with, if it answered
     #[snafu(display("{message}"))]
     Client {
         message: String,
+        status: Option<u16>,
     },
     #[snafu(display("Unable to broadcast notification"))]
     Notification,
@@ -61,12 +63,45 @@ pub enum Error {
     PortInUse {
         port: u16,
     },
+    #[snafu(display("Not logged in to Qobuz. Log in first"))]
+    MissingCredentials,
+    #[snafu(display("Player stopped unexpectedly"))]
+    PlayerStopped,
     #[snafu(display("Unable to reorder playlist"))]
     PlaylistReorderError,
     #[snafu(display("{error}"))]
     ConnectError {
         error: String,
     },
+    #[snafu(display("Control socket {path} is already in use"))]
+    SocketInUse {
+        path: String,
+    },
+    #[snafu(display("Control socket error: {message}"))]
+    SocketError {
+        message: String,
+    },
+    #[snafu(display("Not a Qobuz link: {message}"))]
+    InvalidLink {
+        message: String,
+    },
+    #[snafu(display("UPnP renderer error: {message}"))]
+    UpnpError {
+        message: String,
+    },
+    #[snafu(display("MQTT error: {message}"))]
+    MqttError {
+        message: String,
+    },
+    #[snafu(display("Unable to listen on {address}: {message}"))]
+    ListenError {
+        address: String,
+        message: String,
+    },
+    #[snafu(display("TLS error: {message}"))]
+    TlsError {
+        message: String,
+    },
 }
 
 impl From<sqlx::migrate::MigrateError> for Error {
@@ -137,10 +172,24 @@ impl From<reqwest::Error> for Error {
     }
 }
 
+impl From<qobuz_player_client::qobuz_models::UrlTypeError> for Error {
+    fn from(value: qobuz_player_client::qobuz_models::UrlTypeError) -> Self {
+        Self::InvalidLink {
+            message: value.to_string(),
+        }
+    }
+}
+
 impl From<qobuz_player_client::Error> for Error {
     fn from(value: qobuz_player_client::Error) -> Self {
+        let status = match &value {
+            qobuz_player_client::Error::Api { status, .. } => *status,
+            _ => None,
+        };
+
         Error::Client {
             message: value.to_string(),
+            status,
         }
     }
 }
@@ -1,10 +1,14 @@
-use crate::{error::Error, tracklist::Tracklist};
+use crate::{
+    error::Error, player_event::PlayerEvent, tracklist::Tracklist,
+    tracklist_change::TracklistChange,
+};
 
 use std::time::Duration;
 use tokio::sync::{broadcast, watch};
 
 pub use qobuz_player_client::client::AudioQuality;
 
+pub mod catalog;
 pub mod client;
 pub mod controls;
 pub mod database;
@@ -13,10 +17,14 @@ pub mod error;
 pub mod models;
 pub mod notification;
 pub mod player;
+pub mod player_builder;
+pub mod player_event;
+mod queue_history;
 mod simple_cache;
 mod sink;
 mod stderr_redirect;
 pub mod tracklist;
+pub mod tracklist_change;
 
 pub type AppResult<T, E = Error> = std::result::Result<T, E>;
 
@@ -24,6 +32,20 @@ pub type PositionReceiver = watch::Receiver<Duration>;
 pub type VolumeReceiver = watch::Receiver<f32>;
 pub type StatusReceiver = watch::Receiver<Status>;
 pub type TracklistReceiver = watch::Receiver<Tracklist>;
+/// Changes are sent while the tracklist watch value is locked for the update.
+/// Use [`tracklist_snapshot`] to read the tracklist and subscribe to the
+/// changes after it without seeing a change twice.
+pub type TracklistChangeReceiver = broadcast::Receiver<Vec<TracklistChange>>;
+
+/// Read the tracklist and subscribe to its changes in one step. Every change is
+/// either part of the returned tracklist or arrives on the returned receiver.
+pub fn tracklist_snapshot(
+    tracklist: &TracklistReceiver,
+    changes: &TracklistChangeReceiver,
+) -> (Tracklist, TracklistChangeReceiver) {
+    let tracklist = tracklist.borrow();
+    (tracklist.clone(), changes.resubscribe())
+}
 
 #[derive(Default, Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum Status {
@@ -41,5 +63,7 @@ pub enum Notification {
     Info(String),
 }
 
+pub type PlayerEventReceiver = broadcast::Receiver<PlayerEvent>;
+
 pub type ExitReceiver = broadcast::Receiver<bool>;
 pub type ExitSender = broadcast::Sender<bool>;
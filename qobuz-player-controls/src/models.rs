@@ -1,5 +1,37 @@
+use std::collections::HashMap;
+
+use qobuz_player_client::qobuz_models::{UrlType, parse_url};
+
+use crate::{AppResult, error::Error};
+
 pub mod mapper;
 
+/// Something a Qobuz share link or `qobuz:` uri points to
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum Link {
+    Album(String),
+    Playlist(u32),
+    Track(u32),
+    Artist(u32),
+    Label(u32),
+}
+
+impl Link {
+    pub fn parse(url: &str) -> AppResult<Self> {
+        let invalid_id = || Error::InvalidLink {
+            message: url.to_string(),
+        };
+
+        Ok(match parse_url(url)? {
+            UrlType::Album { id } => Link::Album(id),
+            UrlType::Playlist { id } => Link::Playlist(id.try_into().map_err(|_| invalid_id())?),
+            UrlType::Track { id } => Link::Track(id.try_into().map_err(|_| invalid_id())?),
+            UrlType::Artist { id } => Link::Artist(id.try_into().map_err(|_| invalid_id())?),
+            UrlType::Label { id } => Link::Label(id.try_into().map_err(|_| invalid_id())?),
+        })
+    }
+}
+
 #[derive(Debug, Default, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
 pub enum TrackStatus {
     Played,
@@ -26,6 +58,41 @@ pub struct Track {
     pub album_title: Option<String>,
     pub album_id: Option<String>,
     pub playlist_track_id: Option<u64>,
+    /// Disc of the album the track is on, counting from 1. Qobuz leaves it
+    /// out for albums with a single disc, which makes it 1. Tracks stored
+    /// before disc numbers were kept have 0.
+    #[serde(default)]
+    pub disc_number: u32,
+    #[serde(default)]
+    pub composer: Option<Artist>,
+    /// The classical work the track is a part of
+    #[serde(default)]
+    pub work: Option<String>,
+    #[serde(default)]
+    pub credits: Vec<Credit>,
+    #[serde(default)]
+    pub isrc: Option<String>,
+    #[serde(default)]
+    pub copyright: Option<String>,
+    #[serde(default)]
+    pub bit_depth: Option<u32>,
+    /// In kHz
+    #[serde(default)]
+    pub sample_rate: Option<f64>,
+}
+
+impl Track {
+    /// Bit depth and sample rate, like `24-bit / 96 kHz`
+    pub fn quality(&self) -> Option<String> {
+        quality(self.bit_depth, self.sample_rate)
+    }
+}
+
+/// Someone credited on a track, with what they did on it
+#[derive(Default, Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Credit {
+    pub name: String,
+    pub roles: Vec<String>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
@@ -43,6 +110,145 @@ pub struct Album {
     pub image_thumbnail: String,
     pub duration_seconds: u32,
     pub description: Option<String>,
+    pub label: Option<Label>,
+    pub genre: Option<Genre>,
+    pub composer: Option<Artist>,
+    pub copyright: Option<String>,
+    pub upc: Option<String>,
+    pub disc_count: u32,
+    pub bit_depth: Option<u32>,
+    /// In kHz
+    pub sample_rate: Option<f64>,
+}
+
+impl Album {
+    /// Highest bit depth and sample rate on the album, like `24-bit / 96 kHz`
+    pub fn quality(&self) -> Option<String> {
+        quality(self.bit_depth, self.sample_rate)
+    }
+
+    /// Tracks grouped by disc, in album order
+    pub fn discs(&self) -> Vec<Disc> {
+        let mut discs: Vec<Disc> = Vec::new();
+
+        for (index, track) in self.tracks.iter().enumerate() {
+            match discs.last_mut() {
+                Some(disc) if disc.number == track.disc_number => disc.tracks.push(track.clone()),
+                _ => discs.push(Disc {
+                    number: track.disc_number,
+                    first_index: index,
+                    tracks: vec![track.clone()],
+                }),
+            }
+        }
+
+        discs
+    }
+
+    /// Tracks on the given disc
+    pub fn disc(&self, number: u32) -> Option<Disc> {
+        self.discs().into_iter().find(|disc| disc.number == number)
+    }
+
+    /// The classical works on the album, in album order
+    pub fn works(&self) -> Vec<Work> {
+        group_by_work(&self.tracks)
+    }
+}
+
+/// The movements of a classical work
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Work {
+    pub title: String,
+    pub composer: Option<Artist>,
+    pub tracks: Vec<Track>,
+}
+
+/// Groups runs of tracks from the same work. Tracks without a work are left
+/// out.
+pub fn group_by_work(tracks: &[Track]) -> Vec<Work> {
+    let mut works: Vec<Work> = Vec::new();
+
+    for track in tracks {
+        let Some(title) = &track.work else {
+            continue;
+        };
+
+        match works.last_mut() {
+            Some(work) if &work.title == title && work.composer == track.composer => {
+                work.tracks.push(track.clone())
+            }
+            _ => works.push(Work {
+                title: title.clone(),
+                composer: track.composer.clone(),
+                tracks: vec![track.clone()],
+            }),
+        }
+    }
+
+    works
+}
+
+/// Moves the movements of a work together, in track order, to where the
+/// first of them is. Other tracks keep their order.
+pub fn sort_by_work(tracks: &mut Vec<Track>) {
+    let mut work_starts: HashMap<(Option<String>, u32, String), usize> = HashMap::new();
+
+    let mut keyed: Vec<((usize, u32), Track)> = std::mem::take(tracks)
+        .into_iter()
+        .enumerate()
+        .map(|(index, track)| {
+            let key = match &track.work {
+                Some(work) => {
+                    let start = *work_starts
+                        .entry((track.album_id.clone(), track.disc_number, work.clone()))
+                        .or_insert(index);
+                    (start, track.number)
+                }
+                None => (index, 0),
+            };
+            (key, track)
+        })
+        .collect();
+
+    keyed.sort_by_key(|(key, _)| *key);
+    *tracks = keyed.into_iter().map(|(_, track)| track).collect();
+}
+
+/// One disc of an album
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Disc {
+    pub number: u32,
+    /// Position of the first track of the disc in the album
+    pub first_index: usize,
+    pub tracks: Vec<Track>,
+}
+
+fn quality(bit_depth: Option<u32>, sample_rate: Option<f64>) -> Option<String> {
+    match (bit_depth, sample_rate) {
+        (Some(bit_depth), Some(sample_rate)) => {
+            Some(format!("{bit_depth}-bit / {sample_rate} kHz"))
+        }
+        (Some(bit_depth), None) => Some(format!("{bit_depth}-bit")),
+        (None, Some(sample_rate)) => Some(format!("{sample_rate} kHz")),
+        (None, None) => None,
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Label {
+    pub id: u32,
+    pub name: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct LabelPage {
+    pub id: u32,
+    pub name: String,
+    pub description: Option<String>,
+    /// The first [`crate::client::LABEL_PAGE_SIZE`] albums when the label
+    /// comes from Qobuz. [`crate::client::Client::label_albums`] has the rest.
+    pub albums: Vec<AlbumSimple>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
@@ -91,6 +297,22 @@ pub struct Favorites {
     pub tracks: Vec<Track>,
 }
 
+impl Favorites {
+    /// Composers of the favorite tracks, by name
+    pub fn composers(&self) -> Vec<Artist> {
+        let mut composers: Vec<Artist> = Vec::new();
+
+        for composer in self.tracks.iter().filter_map(|t| t.composer.as_ref()) {
+            if !composers.iter().any(|c| c.id == composer.id) {
+                composers.push(composer.clone());
+            }
+        }
+
+        composers.sort_by_key(|c| c.name.to_lowercase());
+        composers
+    }
+}
+
 #[derive(Default, Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
 pub struct Artist {
     pub id: u32,
@@ -112,6 +334,16 @@ pub struct ArtistPage {
     pub compilations: Vec<AlbumSimple>,
 }
 
+/// A composer with their releases, and their works among the favorite tracks
+#[derive(Debug, Default, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct ComposerPage {
+    pub id: u32,
+    pub name: String,
+    pub image: Option<String>,
+    pub albums: Vec<AlbumSimple>,
+    pub works: Vec<Work>,
+}
+
 #[derive(Debug, Default, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
 pub struct Playlist {
     pub is_owned: bool,
@@ -164,3 +396,43 @@ pub struct Genre {
     pub name: String,
     pub id: u32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(id: u32, number: u32, work: Option<&str>) -> Track {
+        Track {
+            id,
+            number,
+            work: work.map(String::from),
+            album_id: Some("album".into()),
+            disc_number: 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn movements_are_moved_together_in_track_order() {
+        let mut tracks = vec![
+            track(1, 2, Some("Sonata")),
+            track(2, 5, None),
+            track(3, 1, Some("Sonata")),
+            track(4, 4, Some("Partita")),
+            track(5, 3, Some("Sonata")),
+        ];
+        sort_by_work(&mut tracks);
+
+        assert_eq!(
+            tracks.iter().map(|t| t.id).collect::<Vec<_>>(),
+            vec![3, 1, 5, 2, 4]
+        );
+
+        let works = group_by_work(&tracks);
+        assert_eq!(
+            works.iter().map(|w| w.title.as_str()).collect::<Vec<_>>(),
+            vec!["Sonata", "Partita"]
+        );
+        assert_eq!(works[0].tracks.len(), 3);
+    }
+}
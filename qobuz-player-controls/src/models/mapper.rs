@@ -5,7 +5,8 @@ use qobuz_player_client::{
 use time::macros::format_description;
 
 use crate::models::{
-    Album, AlbumSimple, Artist, ArtistPage, Genre, Playlist, PlaylistSimple, SearchResults, Track,
+    Album, AlbumSimple, Artist, ArtistPage, Credit, Genre, Label, LabelPage, Playlist,
+    PlaylistSimple, SearchResults, Track, sort_by_work,
 };
 
 pub fn parse_featured_album(value: qobuz_models::featured::FeaturedAlbum) -> AlbumSimple {
@@ -91,7 +92,7 @@ fn extract_year(date_str: &str) -> u32 {
 pub fn parse_album(value: qobuz_models::album::Album, max_audio_quality: &AudioQuality) -> Album {
     let year = extract_year(&value.release_date_original);
 
-    let tracks = value.tracks.map_or(Default::default(), |tracks| {
+    let mut tracks: Vec<Track> = value.tracks.map_or(Default::default(), |tracks| {
         tracks
             .items
             .into_iter()
@@ -111,9 +112,22 @@ pub fn parse_album(value: qobuz_models::album::Album, max_audio_quality: &AudioQ
                 album_title: Some(value.title.clone()),
                 album_id: Some(value.id.clone()),
                 playlist_track_id: None,
+                disc_number: t.media_number.unwrap_or(1),
+                composer: t.composer.map(parse_composer),
+                work: t.work,
+                credits: t
+                    .performers
+                    .as_deref()
+                    .map(parse_credits)
+                    .unwrap_or_default(),
+                isrc: t.isrc,
+                copyright: t.copyright,
+                bit_depth: t.maximum_bit_depth,
+                sample_rate: t.maximum_sampling_rate,
             })
             .collect()
     });
+    sort_by_work(&mut tracks);
 
     Album {
         id: value.id,
@@ -132,6 +146,20 @@ pub fn parse_album(value: qobuz_models::album::Album, max_audio_quality: &AudioQ
         image_thumbnail: value.image.small,
         duration_seconds: value.duration.map_or(0, |duration| duration as u32),
         description: sanitize_html(value.description),
+        label: value.label.map(|label| Label {
+            id: label.id as u32,
+            name: label.name,
+        }),
+        genre: value.genre.map(|genre| Genre {
+            name: genre.name,
+            id: genre.id as u32,
+        }),
+        composer: value.composer.map(parse_composer),
+        copyright: value.copyright,
+        upc: value.upc,
+        disc_count: value.media_count.unwrap_or(1),
+        bit_depth: value.maximum_bit_depth,
+        sample_rate: value.maximum_sampling_rate,
     }
 }
 
@@ -169,6 +197,18 @@ fn image_to_string(value: qobuz_models::artist_page::Image) -> String {
     )
 }
 
+pub fn parse_label_page(
+    label: qobuz_models::label::LabelPage,
+    albums: Vec<AlbumSimple>,
+) -> LabelPage {
+    LabelPage {
+        id: label.id as u32,
+        name: label.name,
+        description: label.description,
+        albums,
+    }
+}
+
 pub fn parse_artist_page(
     artist: qobuz_models::artist_page::ArtistPage,
     albums: Vec<AlbumSimple>,
@@ -210,6 +250,8 @@ pub fn parse_artist_page(
                     album_title: Some(t.album.title),
                     album_id: Some(t.album.id),
                     playlist_track_id: None,
+                    disc_number: t.physical_support.media_number,
+                    ..Default::default()
                 }
             })
             .collect(),
@@ -308,9 +350,48 @@ pub fn parse_track(value: qobuz_models::track::Track, max_audio_quality: &AudioQ
         album_title: value.album.as_ref().map(|a| a.title.clone()),
         album_id: value.album.as_ref().map(|a| a.id.clone()),
         playlist_track_id: value.playlist_track_id,
+        disc_number: value.media_number.unwrap_or(1),
+        composer: value.composer.map(parse_composer),
+        work: value.work,
+        credits: value
+            .performers
+            .as_deref()
+            .map(parse_credits)
+            .unwrap_or_default(),
+        isrc: value.isrc,
+        copyright: value.copyright,
+        bit_depth: value.maximum_bit_depth,
+        sample_rate: value.maximum_sampling_rate,
+    }
+}
+
+pub fn parse_composer(value: qobuz_models::Composer) -> Artist {
+    Artist {
+        id: value.id as u32,
+        name: value.name,
+        image: value.image.map(|i| i.large),
     }
 }
 
+/// Qobuz lists credits as `Name, Role, Role - Name, Role`
+fn parse_credits(performers: &str) -> Vec<Credit> {
+    performers
+        .split(" - ")
+        .filter_map(|performer| {
+            let mut parts = performer
+                .split(',')
+                .map(str::trim)
+                .filter(|part| !part.is_empty());
+            let name = parts.next()?.to_string();
+
+            Some(Credit {
+                name,
+                roles: parts.map(String::from).collect(),
+            })
+        })
+        .collect()
+}
+
 fn hifi_available(track_has_hires_available: bool, max_audio_quality: &AudioQuality) -> bool {
     if !track_has_hires_available {
         return false;
@@ -323,3 +404,27 @@ fn hifi_available(track_has_hires_available: bool, max_audio_quality: &AudioQual
         AudioQuality::HIFI192 => true,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn credits_are_split_into_names_and_roles() {
+        let credits = parse_credits("Miles Davis, MainArtist, Trumpet - Teo Macero, Producer");
+
+        assert_eq!(
+            credits,
+            vec![
+                Credit {
+                    name: "Miles Davis".into(),
+                    roles: vec!["MainArtist".into(), "Trumpet".into()],
+                },
+                Credit {
+                    name: "Teo Macero".into(),
+                    roles: vec!["Producer".into()],
+                },
+            ]
+        );
+    }
+}
@@ -6,6 +6,8 @@ pub enum Notification {
     Warning(String),
     Success(String),
     Info(String),
+    /// Informational message for a queue edit that can be reverted with undo
+    Undoable(String),
 }
 
 #[derive(Debug)]
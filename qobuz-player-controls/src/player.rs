@@ -1,43 +1,51 @@
 use crate::{
     controls::NewQueueItem,
-    models::{Album, Track, TrackStatus},
+    models::{Album, Link, Track, TrackStatus},
 };
 use rand::seq::SliceRandom;
 use tokio::{
     select,
     sync::{
-        mpsc,
+        broadcast, mpsc,
         watch::{self, Receiver, Sender},
     },
     time::sleep,
 };
 
 use crate::{
-    AppResult, ExitReceiver, PositionReceiver, Status, StatusReceiver, TracklistReceiver,
-    VolumeReceiver,
+    AppResult, ExitReceiver, PlayerEventReceiver, PositionReceiver, Status, StatusReceiver,
+    TracklistChangeReceiver, TracklistReceiver, VolumeReceiver,
+    catalog::{Catalog, TrackSource},
     controls::{ControlCommand, Controls},
     database::Database,
-    downloader::{DownloadResult, Downloader},
+    downloader::Downloader,
+    error::Error,
     notification::{Notification, NotificationBroadcast},
+    player_event::{PlayerEvent, PlayerEventKind},
+    queue_history::QueueHistory,
     sink::QueryTrackResult,
     tracklist::{QueueItem, TracklistType},
+    tracklist_change::TracklistChange,
 };
 use std::{sync::Arc, time::Duration};
 
 use crate::{
-    client::Client,
     sink::Sink,
     tracklist::{self, Tracklist},
 };
 
 const INTERVAL_MS: u64 = 500;
+/// How many of a label's albums a label link plays
+const LABEL_LINK_ALBUMS: usize = 10;
 
 pub struct Player {
     broadcast: Arc<NotificationBroadcast>,
     tracklist_tx: Sender<Tracklist>,
     tracklist_rx: Receiver<Tracklist>,
+    tracklist_changes: broadcast::Sender<Vec<TracklistChange>>,
+    events: broadcast::Sender<PlayerEvent>,
     target_status: Sender<Status>,
-    client: Arc<Client>,
+    catalog: Arc<dyn Catalog>,
     sink: Sink,
     volume: Sender<f32>,
     position: Sender<Duration>,
@@ -50,13 +58,14 @@ pub struct Player {
     downloader: Downloader,
     state_change_delay: Option<Duration>,
     sample_rate_change_delay: Option<Duration>,
+    queue_history: QueueHistory,
 }
 
 impl Player {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         tracklist: Tracklist,
-        client: Arc<Client>,
+        catalog: Arc<dyn Catalog>,
         volume: f32,
         broadcast: Arc<NotificationBroadcast>,
         audio_cache_dir: std::path::PathBuf,
@@ -68,13 +77,15 @@ impl Player {
         let (volume, volume_receiver) = watch::channel(volume);
         let sink = Sink::new(volume_receiver, preferred_device_id)?;
 
-        let downloader = Downloader::new(audio_cache_dir, database.clone(), client.clone());
+        let downloader = Downloader::new(audio_cache_dir, database.clone(), catalog.clone());
 
         let track_finished = sink.track_finished();
 
         let (position, _) = watch::channel(Default::default());
         let (target_status, _) = watch::channel(Default::default());
         let (tracklist_tx, tracklist_rx) = watch::channel(tracklist);
+        let (tracklist_changes, _) = broadcast::channel(20);
+        let (events, _) = broadcast::channel(100);
 
         let (controls_tx, controls_rx) = tokio::sync::mpsc::unbounded_channel();
         let controls = Controls::new(controls_tx);
@@ -83,10 +94,12 @@ impl Player {
             broadcast,
             tracklist_tx,
             tracklist_rx,
+            tracklist_changes,
+            events,
             controls_rx,
             controls,
             target_status,
-            client,
+            catalog,
             sink,
             volume,
             position,
@@ -97,6 +110,7 @@ impl Player {
             downloader,
             state_change_delay,
             sample_rate_change_delay,
+            queue_history: Default::default(),
         })
     }
 
@@ -120,6 +134,34 @@ impl Player {
         self.tracklist_tx.subscribe()
     }
 
+    /// Changes published alongside every tracklist update. Receivers that lag
+    /// behind should read the full tracklist again.
+    pub fn tracklist_changes(&self) -> TracklistChangeReceiver {
+        self.tracklist_changes.subscribe()
+    }
+
+    /// Typed events for integrations that need to know what happened rather
+    /// than the resulting state.
+    pub fn events(&self) -> PlayerEventReceiver {
+        self.events.subscribe()
+    }
+
+    fn publish(&self, kind: PlayerEventKind) {
+        _ = self.events.send(PlayerEvent::new(kind));
+    }
+
+    fn current_track(&self) -> Option<Track> {
+        self.tracklist_rx.borrow().current_track().cloned()
+    }
+
+    fn report_error(&self, error: Error) {
+        let message = error.to_string();
+        self.publish(PlayerEventKind::ErrorOccurred {
+            message: message.clone(),
+        });
+        self.broadcast.send_error(message);
+    }
+
     async fn play_pause(&mut self) -> AppResult<()> {
         let target_status = *self.target_status.borrow();
 
@@ -141,9 +183,18 @@ impl Player {
             tracing::info!("Sink is empty. Query track from play");
             self.set_target_status(Status::Buffering);
             self.query_track(&current_track, false).await?;
+            self.publish(PlayerEventKind::TrackStarted {
+                track: current_track,
+            });
         } else {
+            let resumed = *self.target_status.borrow() != Status::Playing;
             self.set_target_status(Status::Playing);
             self.sink.play();
+            if resumed {
+                self.publish(PlayerEventKind::Resumed {
+                    track: self.current_track(),
+                });
+            }
         }
 
         Ok(())
@@ -160,12 +211,30 @@ impl Player {
     }
 
     fn pause(&mut self) {
+        let paused = *self.target_status.borrow() != Status::Paused;
         self.set_target_status(Status::Paused);
         self.sink.pause();
+        if paused {
+            self.publish(PlayerEventKind::Paused {
+                track: self.current_track(),
+            });
+        }
     }
 
     fn set_target_status(&self, status: Status) {
+        let previous = *self.target_status.borrow();
         self.target_status.send(status).expect("infallible");
+
+        match (previous, status) {
+            (Status::Buffering, Status::Buffering) => (),
+            (_, Status::Buffering) => self.publish(PlayerEventKind::BufferingStarted {
+                track: self.current_track(),
+            }),
+            (Status::Buffering, _) => self.publish(PlayerEventKind::BufferingEnded {
+                track: self.current_track(),
+            }),
+            _ => (),
+        }
     }
 
     async fn query_track(&mut self, track: &Track, next_track: bool) -> AppResult<()> {
@@ -184,8 +253,8 @@ impl Player {
         self.wait_for_state_change_delay().await;
 
         let query_result = match download_result {
-            DownloadResult::Cached(track_path) => self.sink.query_track(&track_path)?,
-            DownloadResult::Streaming(reader) => self.sink.query_track_stream(reader)?,
+            TrackSource::Cached(track_path) => self.sink.query_track(&track_path)?,
+            TrackSource::Streaming(reader) => self.sink.query_track_stream(reader)?,
         };
 
         if next_track {
@@ -210,12 +279,42 @@ impl Player {
         self.volume.send(volume)?;
         self.sink.sync_volume();
         self.database.set_volume(volume).await?;
+        self.publish(PlayerEventKind::VolumeChanged { volume });
         Ok(())
     }
 
     async fn broadcast_tracklist(&self, tracklist: Tracklist) -> AppResult<()> {
         self.database.set_tracklist(&tracklist).await?;
-        self.tracklist_tx.send(tracklist)?;
+        let (changes, previous_queue_id) = {
+            let previous = self.tracklist_rx.borrow();
+            (
+                TracklistChange::diff(&previous, &tracklist),
+                previous.current_queue_id(),
+            )
+        };
+
+        let track_started = tracklist.current_queue_id() != previous_queue_id
+            && *self.target_status.borrow() != Status::Paused
+            && !self.sink.is_empty();
+        let started_track = match track_started {
+            true => tracklist.current_track().cloned(),
+            false => None,
+        };
+
+        // Send the changes while the watch is locked, see `tracklist_snapshot`
+        self.tracklist_tx.send_modify(|current| {
+            if !changes.is_empty() {
+                _ = self.tracklist_changes.send(changes.clone());
+            }
+            *current = tracklist;
+        });
+
+        if !changes.is_empty() {
+            self.publish(PlayerEventKind::QueueChanged { changes });
+        }
+        if let Some(track) = started_track {
+            self.publish(PlayerEventKind::TrackStarted { track });
+        }
         Ok(())
     }
 
@@ -224,6 +323,10 @@ impl Player {
             Ok(()) => {
                 self.position.send(self.sink.position())?;
                 self.set_target_status(Status::Playing);
+                self.publish(PlayerEventKind::Seeked {
+                    track: self.current_track(),
+                    position: duration,
+                });
             }
             Err(e) => {
                 tracing::warn!("Seek to {:?} failed: {e:?}", duration);
@@ -285,7 +388,10 @@ impl Player {
 
         self.position.send(Default::default())?;
 
-        if tracklist.skip_to_track(new_position).is_some() {
+        let from = tracklist.current_track().cloned();
+        let to = tracklist.skip_to_track(new_position).cloned();
+
+        if to.is_some() {
             self.new_queue(tracklist).await?;
         } else {
             tracklist.reset();
@@ -296,6 +402,7 @@ impl Player {
             self.broadcast_tracklist(tracklist).await?;
         }
 
+        self.publish(PlayerEventKind::Skipped { from, to });
         Ok(())
     }
 
@@ -326,14 +433,20 @@ impl Player {
         Ok(())
     }
 
+    async fn replace_queue(&mut self, tracklist: Tracklist) -> AppResult<()> {
+        self.queue_history.clear();
+        self.new_queue(tracklist).await
+    }
+
     async fn new_track_queue(&mut self, items: Vec<NewQueueItem>, play: bool) -> AppResult<()> {
+        self.queue_history.clear();
         self.sink.clear()?;
         self.next_track_is_queried = false;
         self.next_track_in_sink_queue = false;
 
         let mut queue_items = vec![];
         for (index, item) in items.iter().enumerate() {
-            let track = self.client.track(item.track_id).await?;
+            let track = self.catalog.track(item.track_id).await?;
             let queue_item = QueueItem {
                 track,
                 queue_id: item.queue_id,
@@ -346,7 +459,10 @@ impl Player {
             item.track.status = TrackStatus::Playing;
         }
 
-        let tracklist = Tracklist::new_with_id(TracklistType::Tracks, queue_items);
+        // The controller picks the ids of the new items. Ids handed out for
+        // items added here continue after both those and the old tracklist's.
+        let mut tracklist = Tracklist::new_with_id(TracklistType::Tracks, queue_items);
+        tracklist.reserve_queue_ids(self.tracklist_rx.borrow().next_queue_id());
 
         if play && let Some(first_track) = tracklist.current_track() {
             tracing::info!("New queue starting with: {}", first_track.title);
@@ -359,13 +475,18 @@ impl Player {
     }
 
     async fn clear_queue(&mut self) -> AppResult<()> {
+        self.queue_history
+            .record(self.tracklist_rx.borrow().clone());
         self.pause();
         self.sink.clear()?;
         self.next_track_is_queried = false;
         self.next_track_in_sink_queue = false;
 
-        let tracklist = Tracklist::default();
+        let mut tracklist = Tracklist::default();
+        tracklist.reserve_queue_ids(self.tracklist_rx.borrow().next_queue_id());
         self.broadcast_tracklist(tracklist).await?;
+        let notification = Notification::Undoable("Queue cleared".into());
+        self.broadcast.send(notification);
         Ok(())
     }
 
@@ -376,17 +497,24 @@ impl Player {
         Ok(())
     }
 
+    /// Queue items for a new tracklist, with ids continuing after the current
+    /// tracklist so clients never mistake a new item for an old one.
+    fn new_queue_items(&self, tracks: Vec<Track>) -> Vec<QueueItem> {
+        let first_queue_id = self.tracklist_rx.borrow().next_queue_id();
+        tracks_to_queue_items(tracks, first_queue_id)
+    }
+
     async fn play_track(&mut self, track_id: u32) -> AppResult<()> {
-        let mut track: Track = self.client.track(track_id).await?;
+        let mut track: Track = self.catalog.track(track_id).await?;
         track.status = TrackStatus::Playing;
 
-        let tracklist = Tracklist::new(TracklistType::Tracks, tracks_to_queue_items(vec![track]));
+        let tracklist = Tracklist::new(TracklistType::Tracks, self.new_queue_items(vec![track]));
 
-        self.new_queue(tracklist).await
+        self.replace_queue(tracklist).await
     }
 
     async fn play_album(&mut self, album_id: &str, index: usize) -> AppResult<()> {
-        let album: Album = self.client.album(album_id).await?;
+        let album: Album = self.catalog.album(album_id).await?;
 
         let unstreamable_tracks_to_index = album
             .tracks
@@ -401,15 +529,15 @@ impl Player {
                 id: album.id,
                 image: Some(album.image),
             }),
-            tracks_to_queue_items(album.tracks.into_iter().filter(|t| t.available).collect()),
+            self.new_queue_items(album.tracks.into_iter().filter(|t| t.available).collect()),
         );
 
         tracklist.skip_to_track(index as i32 - unstreamable_tracks_to_index);
-        self.new_queue(tracklist).await
+        self.replace_queue(tracklist).await
     }
 
     async fn play_top_tracks(&mut self, artist_id: u32, index: usize) -> AppResult<()> {
-        let artist = self.client.artist_page(artist_id).await?;
+        let artist = self.catalog.artist_page(artist_id).await?;
         let tracks = artist.top_tracks;
         let unstreamable_tracks_to_index =
             tracks.iter().take(index).filter(|t| !t.available).count() as i32;
@@ -420,16 +548,16 @@ impl Player {
                 id: artist_id,
                 image: artist.image,
             }),
-            tracks_to_queue_items(tracks.into_iter().filter(|t| t.available).collect()),
+            self.new_queue_items(tracks.into_iter().filter(|t| t.available).collect()),
         );
 
         tracklist.skip_to_track(index as i32 - unstreamable_tracks_to_index);
-        self.new_queue(tracklist).await
+        self.replace_queue(tracklist).await
     }
 
     async fn play_tracks(&mut self, ids: Vec<u32>, shuffle: bool) -> AppResult<()> {
         let mut tracks: Vec<_> = self
-            .client
+            .catalog
             .tracks(ids)
             .await?
             .into_iter()
@@ -440,10 +568,69 @@ impl Player {
             tracks.shuffle(&mut rand::rng());
         }
 
-        let mut tracklist = Tracklist::new(TracklistType::Tracks, tracks_to_queue_items(tracks));
+        let mut tracklist = Tracklist::new(TracklistType::Tracks, self.new_queue_items(tracks));
 
         tracklist.skip_to_track(0);
-        self.new_queue(tracklist).await
+        self.replace_queue(tracklist).await
+    }
+
+    async fn play_link(&mut self, link: Link) -> AppResult<()> {
+        match link {
+            Link::Album(id) => self.play_album(&id, 0).await,
+            Link::Playlist(id) => self.play_playlist(id, 0, false).await,
+            Link::Track(id) => self.play_track(id).await,
+            Link::Artist(id) => self.play_top_tracks(id, 0).await,
+            Link::Label(_) => {
+                let ids = self.link_track_ids(link).await?;
+                self.play_tracks(ids, false).await
+            }
+        }
+    }
+
+    async fn queue_link(&mut self, link: Link) -> AppResult<()> {
+        let ids = self.link_track_ids(link).await?;
+        self.add_tracks_to_queue(ids).await
+    }
+
+    async fn insert_link_after(&mut self, link: Link, queue_id: Option<u64>) -> AppResult<()> {
+        let position = match queue_id {
+            Some(queue_id) => {
+                let position = self.tracklist_rx.borrow().position_of_queue_id(queue_id);
+                let Some(position) = position else {
+                    self.broadcast_missing_queue_item();
+                    return Ok(());
+                };
+                position + 1
+            }
+            None => 0,
+        };
+
+        let ids = self.link_track_ids(link).await?;
+        self.insert_tracks(ids, position, "added to queue").await
+    }
+
+    /// The streamable tracks a link points to, in order
+    async fn link_track_ids(&self, link: Link) -> AppResult<Vec<u32>> {
+        let tracks = match link {
+            Link::Album(id) => self.catalog.album(&id).await?.tracks,
+            Link::Playlist(id) => self.catalog.playlist(id).await?.tracks,
+            Link::Track(id) => vec![self.catalog.track(id).await?],
+            Link::Artist(id) => self.catalog.artist_page(id).await?.top_tracks,
+            Link::Label(id) => {
+                let label = self.catalog.label(id).await?;
+                let mut tracks = vec![];
+                for album in label.albums.iter().take(LABEL_LINK_ALBUMS) {
+                    tracks.extend(self.catalog.album(&album.id).await?.tracks);
+                }
+                tracks
+            }
+        };
+
+        Ok(tracks
+            .into_iter()
+            .filter(|t| t.available)
+            .map(|t| t.id)
+            .collect())
     }
 
     async fn play_playlist(
@@ -452,7 +639,7 @@ impl Player {
         index: usize,
         shuffle: bool,
     ) -> AppResult<()> {
-        let playlist = self.client.playlist(playlist_id).await?;
+        let playlist = self.catalog.playlist(playlist_id).await?;
 
         let unstreamable_tracks_to_index = match shuffle {
             true => 0,
@@ -464,7 +651,7 @@ impl Player {
                 .count() as i32,
         };
 
-        let mut queue: Vec<QueueItem> = tracks_to_queue_items(
+        let mut queue: Vec<QueueItem> = self.new_queue_items(
             playlist
                 .tracks
                 .into_iter()
@@ -487,29 +674,82 @@ impl Player {
 
         tracklist.skip_to_track(index as i32 - unstreamable_tracks_to_index);
 
-        self.new_queue(tracklist).await
+        self.replace_queue(tracklist).await
     }
 
     async fn remove_index_from_queue(&mut self, index: usize) -> AppResult<()> {
         let mut tracklist = self.tracklist_rx.borrow().clone();
+        self.queue_history.record(tracklist.clone());
 
         tracklist.remove_track(index);
         self.update_queue(tracklist).await?;
-        let notification = Notification::Info("Queue updated".into());
+        let notification = Notification::Undoable("Queue updated".into());
+        self.broadcast.send(notification);
+        Ok(())
+    }
+
+    async fn remove_queue_item(&mut self, queue_id: u64) -> AppResult<()> {
+        let mut tracklist = self.tracklist_rx.borrow().clone();
+        let previous = tracklist.clone();
+
+        if !tracklist.remove_queue_item(queue_id) {
+            self.broadcast_missing_queue_item();
+            return Ok(());
+        }
+
+        self.queue_history.record(previous);
+        self.update_queue(tracklist).await?;
+        let notification = Notification::Undoable("Queue updated".into());
+        self.broadcast.send(notification);
+        Ok(())
+    }
+
+    async fn move_queue_item(&mut self, queue_id: u64, new_position: usize) -> AppResult<()> {
+        let mut tracklist = self.tracklist_rx.borrow().clone();
+        let previous = tracklist.clone();
+
+        if !tracklist.move_queue_item(queue_id, new_position) {
+            self.broadcast_missing_queue_item();
+            return Ok(());
+        }
+
+        self.queue_history.record(previous);
+        self.update_queue(tracklist).await?;
+        let notification = Notification::Undoable("Queue updated".into());
         self.broadcast.send(notification);
         Ok(())
     }
 
+    async fn skip_to_queue_item(&mut self, queue_id: u64) -> AppResult<()> {
+        let position = self.tracklist_rx.borrow().position_of_queue_id(queue_id);
+
+        match position {
+            Some(position) => self.skip_to_position(position as i32, true).await,
+            None => {
+                self.broadcast_missing_queue_item();
+                Ok(())
+            }
+        }
+    }
+
+    fn broadcast_missing_queue_item(&self) {
+        self.broadcast.send(Notification::Warning(
+            "Track is no longer in the queue".into(),
+        ));
+    }
+
     async fn add_tracks_to_queue(&mut self, ids: Vec<u32>) -> AppResult<()> {
         let mut tracklist = self.tracklist_rx.borrow().clone();
         tracklist.set_list_type(TracklistType::Tracks);
 
-        let tracks = self.client.tracks(ids).await?;
+        let tracks = self.catalog.tracks(ids).await?;
         let track_titles: Vec<_> = tracks.iter().map(|x| x.title.clone()).collect();
         let track_titles = track_titles.join(", ");
 
-        let notification = Notification::Info(format!("{} added to queue", track_titles));
+        let notification = Notification::Undoable(format!("{} added to queue", track_titles));
 
+        self.queue_history
+            .record(self.tracklist_rx.borrow().clone());
         for track in tracks {
             tracklist.push_track(track);
         }
@@ -520,20 +760,35 @@ impl Player {
     }
 
     async fn play_tracks_next(&mut self, ids: Vec<u32>) -> AppResult<()> {
+        // Nothing is playing in an empty queue, so the tracks go first
+        let position = {
+            let tracklist = self.tracklist_rx.borrow();
+            (tracklist.current_position() + 1).min(tracklist.total())
+        };
+        self.insert_tracks(ids, position, "playing next").await
+    }
+
+    async fn insert_tracks(
+        &mut self,
+        ids: Vec<u32>,
+        position: usize,
+        action: &str,
+    ) -> AppResult<()> {
         let mut tracklist = self.tracklist_rx.borrow().clone();
         tracklist.set_list_type(TracklistType::Tracks);
 
-        let mut tracks = self.client.tracks(ids).await?;
+        let mut tracks = self.catalog.tracks(ids).await?;
         let track_titles: Vec<_> = tracks.iter().map(|x| x.title.clone()).collect();
         let track_titles = track_titles.join(", ");
 
-        let notification = Notification::Info(format!("{} playing next", track_titles));
+        let notification = Notification::Undoable(format!("{} {action}", track_titles));
 
-        let current_index = tracklist.current_position();
+        self.queue_history
+            .record(self.tracklist_rx.borrow().clone());
 
         tracks.reverse();
         for track in tracks {
-            tracklist.insert_track(current_index + 1, track);
+            tracklist.insert_track(position, track);
         }
 
         self.update_queue(tracklist).await?;
@@ -543,15 +798,81 @@ impl Player {
 
     async fn reorder_queue(&mut self, new_order: Vec<usize>) -> AppResult<()> {
         let mut tracklist = self.tracklist_rx.borrow().clone();
+        self.queue_history.record(tracklist.clone());
 
         tracklist.reorder_queue(new_order);
 
         self.update_queue(tracklist).await?;
-        let notification = Notification::Info("Queue updated".into());
+        let notification = Notification::Undoable("Queue updated".into());
         self.broadcast.send(notification);
         Ok(())
     }
 
+    async fn undo_queue(&mut self) -> AppResult<()> {
+        let current = self.tracklist_rx.borrow().clone();
+
+        match self.queue_history.undo(current) {
+            Some(tracklist) => {
+                self.restore_queue(tracklist).await?;
+                self.broadcast
+                    .send(Notification::Info("Queue edit undone".into()));
+            }
+            None => self
+                .broadcast
+                .send(Notification::Warning("Nothing to undo".into())),
+        }
+
+        Ok(())
+    }
+
+    async fn redo_queue(&mut self) -> AppResult<()> {
+        let current = self.tracklist_rx.borrow().clone();
+
+        match self.queue_history.redo(current) {
+            Some(tracklist) => {
+                self.restore_queue(tracklist).await?;
+                self.broadcast
+                    .send(Notification::Info("Queue edit redone".into()));
+            }
+            None => self
+                .broadcast
+                .send(Notification::Warning("Nothing to redo".into())),
+        }
+
+        Ok(())
+    }
+
+    /// Replace the queue with a tracklist from the history, keeping the
+    /// currently playing track playing if it is part of the restored queue.
+    async fn restore_queue(&mut self, mut tracklist: Tracklist) -> AppResult<()> {
+        let (current_queue_id, next_queue_id) = {
+            let current = self.tracklist_rx.borrow();
+            (current.current_queue_id(), current.next_queue_id())
+        };
+        tracklist.reserve_queue_ids(next_queue_id);
+
+        let Some(current_queue_id) = current_queue_id else {
+            return self.update_queue(tracklist).await;
+        };
+
+        let restored_position = tracklist.position_of_queue_id(current_queue_id);
+
+        match restored_position {
+            Some(position) => {
+                tracklist.skip_to_track(position as i32);
+                self.update_queue(tracklist).await
+            }
+            None => {
+                self.pause();
+                self.sink.clear()?;
+                self.next_track_is_queried = false;
+                self.next_track_in_sink_queue = false;
+                self.position.send(Default::default())?;
+                self.broadcast_tracklist(tracklist).await
+            }
+        }
+    }
+
     async fn tick(&mut self) -> AppResult<()> {
         if *self.target_status.borrow() != Status::Playing {
             return Ok(());
@@ -603,6 +924,11 @@ impl Player {
             ControlCommand::Tracks { ids, shuffle } => {
                 self.play_tracks(ids, shuffle).await?;
             }
+            ControlCommand::PlayLink { link } => self.play_link(link).await?,
+            ControlCommand::QueueLink { link } => self.queue_link(link).await?,
+            ControlCommand::InsertLinkAfter { link, queue_id } => {
+                self.insert_link_after(link, queue_id).await?
+            }
             ControlCommand::Next => {
                 self.next().await?;
             }
@@ -640,10 +966,22 @@ impl Player {
             ControlCommand::RemoveIndexFromQueue { index } => {
                 self.remove_index_from_queue(index).await?
             }
+            ControlCommand::RemoveQueueItem { queue_id } => {
+                self.remove_queue_item(queue_id).await?
+            }
+            ControlCommand::MoveQueueItem {
+                queue_id,
+                new_position,
+            } => self.move_queue_item(queue_id, new_position).await?,
+            ControlCommand::SkipToQueueItem { queue_id } => {
+                self.skip_to_queue_item(queue_id).await?
+            }
             ControlCommand::PlayTracksNext { ids } => self.play_tracks_next(ids).await?,
             ControlCommand::ReorderQueue { new_order } => self.reorder_queue(new_order).await?,
             ControlCommand::NewQueue { items, play } => self.new_track_queue(items, play).await?,
             ControlCommand::ClearQueue => self.clear_queue().await?,
+            ControlCommand::UndoQueue => self.undo_queue().await?,
+            ControlCommand::RedoQueue => self.redo_queue().await?,
         }
         Ok(())
     }
@@ -651,6 +989,12 @@ impl Player {
     async fn track_finished(&mut self) -> AppResult<()> {
         let mut tracklist = self.tracklist_rx.borrow().clone();
 
+        if let Some(track) = tracklist.current_track() {
+            self.publish(PlayerEventKind::TrackFinished {
+                track: track.clone(),
+            });
+        }
+
         let current_position = tracklist.current_position();
         let new_position = current_position + 1;
 
@@ -676,6 +1020,7 @@ impl Player {
                 self.sink.pause();
                 self.sink.clear()?;
                 self.position.send(Default::default())?;
+                self.publish(PlayerEventKind::QueueEnded);
             }
         }
         self.next_track_is_queried = false;
@@ -690,19 +1035,19 @@ impl Player {
             select! {
                 _ = interval.tick() => {
                     if let Err(err) = self.tick().await {
-                        self.broadcast.send_error(err.to_string());
+                        self.report_error(err);
                     };
                 }
 
                 Some(notification) = self.controls_rx.recv() => {
                     if let Err(err) = self.handle_message(notification).await {
-                        self.broadcast.send_error(err.to_string());
+                        self.report_error(err);
                     };
                 }
 
                 Ok(_) = self.track_finished.changed() => {
                     if let Err(err) = self.track_finished().await {
-                        self.broadcast.send_error(err.to_string());
+                        self.report_error(err);
                     };
                 }
 
@@ -716,14 +1061,395 @@ impl Player {
     }
 }
 
-fn tracks_to_queue_items(tracks: Vec<Track>) -> Vec<QueueItem> {
+fn tracks_to_queue_items(tracks: Vec<Track>, first_queue_id: u64) -> Vec<QueueItem> {
     tracks
         .into_iter()
         .enumerate()
         .map(|(i, track)| QueueItem {
             track,
-            queue_id: i as u64,
+            queue_id: first_queue_id + i as u64,
             index: i,
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::catalog::memory::MemoryCatalog;
+
+    use super::*;
+
+    fn queued_track_ids(player: &Player) -> Vec<u32> {
+        player
+            .tracklist_rx
+            .borrow()
+            .queue()
+            .iter()
+            .map(|item| item.track.id)
+            .collect()
+    }
+
+    #[sqlx::test]
+    async fn queue_edits_against_memory_catalog(pool: sqlx::Pool<sqlx::Sqlite>) {
+        let database = Arc::new(Database::init(pool).await.unwrap());
+        let mut player = Player::new(
+            Tracklist::default(),
+            Arc::new(MemoryCatalog::fixture()),
+            1.0,
+            Arc::new(NotificationBroadcast::new()),
+            std::env::temp_dir().join("qobuz-player-test-cache"),
+            database.clone(),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        player
+            .handle_message(ControlCommand::AddTracksToQueue {
+                ids: vec![101, 102, 201],
+            })
+            .await
+            .unwrap();
+        assert_eq!(queued_track_ids(&player), vec![101, 102, 201]);
+
+        player
+            .handle_message(ControlCommand::MoveQueueItem {
+                queue_id: 2,
+                new_position: 0,
+            })
+            .await
+            .unwrap();
+        player
+            .handle_message(ControlCommand::RemoveQueueItem { queue_id: 1 })
+            .await
+            .unwrap();
+        assert_eq!(queued_track_ids(&player), vec![201, 101]);
+
+        let stored = database.get_tracklist().await.unwrap();
+        assert_eq!(&stored, &*player.tracklist_rx.borrow());
+
+        assert!(
+            player
+                .handle_message(ControlCommand::AddTracksToQueue { ids: vec![999] })
+                .await
+                .is_err()
+        );
+    }
+
+    #[sqlx::test]
+    async fn controller_queue_keeps_ids_unique(pool: sqlx::Pool<sqlx::Sqlite>) {
+        let database = Arc::new(Database::init(pool).await.unwrap());
+        let mut player = Player::new(
+            Tracklist::default(),
+            Arc::new(MemoryCatalog::fixture()),
+            1.0,
+            Arc::new(NotificationBroadcast::new()),
+            std::env::temp_dir().join("qobuz-player-test-cache"),
+            database,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        player
+            .handle_message(ControlCommand::AddTracksToQueue {
+                ids: vec![101, 102, 103],
+            })
+            .await
+            .unwrap();
+
+        player
+            .handle_message(ControlCommand::NewQueue {
+                items: vec![
+                    NewQueueItem {
+                        track_id: 201,
+                        queue_id: 0,
+                    },
+                    NewQueueItem {
+                        track_id: 202,
+                        queue_id: 1,
+                    },
+                ],
+                play: false,
+            })
+            .await
+            .unwrap();
+        player
+            .handle_message(ControlCommand::AddTracksToQueue { ids: vec![101] })
+            .await
+            .unwrap();
+
+        let queue_ids: Vec<_> = player
+            .tracklist_rx
+            .borrow()
+            .queue()
+            .iter()
+            .map(|item| item.queue_id)
+            .collect();
+        assert_eq!(queue_ids, vec![0, 1, 3]);
+    }
+
+    #[sqlx::test]
+    async fn events_for_play_pause_skip_and_queue(pool: sqlx::Pool<sqlx::Sqlite>) {
+        let database = Arc::new(Database::init(pool).await.unwrap());
+        // Not in the catalog, so skipping to it fails
+        let missing = QueueItem {
+            track: Track {
+                id: 999,
+                ..Default::default()
+            },
+            queue_id: 0,
+            index: 0,
+        };
+        let mut player = Player::new(
+            Tracklist::new(TracklistType::Tracks, vec![missing]),
+            Arc::new(MemoryCatalog::fixture()),
+            1.0,
+            Arc::new(NotificationBroadcast::new()),
+            std::env::temp_dir().join("qobuz-player-test-cache"),
+            database,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        let mut events = player.events();
+        let _status = player.status();
+        let _position = player.position();
+
+        player.handle_message(ControlCommand::Play).await.unwrap();
+        player.handle_message(ControlCommand::Pause).await.unwrap();
+        player
+            .handle_message(ControlCommand::AddTracksToQueue { ids: vec![101] })
+            .await
+            .unwrap();
+        assert!(
+            player
+                .handle_message(ControlCommand::SkipToPosition {
+                    new_position: 0,
+                    force: true,
+                })
+                .await
+                .is_err()
+        );
+        player
+            .handle_message(ControlCommand::SkipToPosition {
+                new_position: 5,
+                force: true,
+            })
+            .await
+            .unwrap();
+
+        let mut kinds = vec![];
+        while let Ok(event) = events.try_recv() {
+            kinds.push(event.kind);
+        }
+
+        assert!(matches!(
+            kinds.as_slice(),
+            [
+                PlayerEventKind::Resumed { .. },
+                PlayerEventKind::Paused { .. },
+                PlayerEventKind::QueueChanged { .. },
+                PlayerEventKind::QueueChanged { .. },
+                PlayerEventKind::Skipped { to: None, .. },
+            ]
+        ));
+    }
+
+    #[sqlx::test]
+    async fn links_resolve_against_memory_catalog(pool: sqlx::Pool<sqlx::Sqlite>) {
+        let database = Arc::new(Database::init(pool).await.unwrap());
+        let mut player = Player::new(
+            Tracklist::default(),
+            Arc::new(MemoryCatalog::fixture()),
+            1.0,
+            Arc::new(NotificationBroadcast::new()),
+            std::env::temp_dir().join("qobuz-player-test-cache"),
+            database,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        player
+            .handle_message(ControlCommand::QueueLink {
+                link: Link::parse("qobuz:label:1").unwrap(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(queued_track_ids(&player), vec![201, 202, 101, 102, 103]);
+
+        player
+            .handle_message(ControlCommand::QueueLink {
+                link: Link::parse("https://open.qobuz.com/track/101").unwrap(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            queued_track_ids(&player),
+            vec![201, 202, 101, 102, 103, 101]
+        );
+
+        assert!(
+            player
+                .handle_message(ControlCommand::QueueLink {
+                    link: Link::Album("missing".into()),
+                })
+                .await
+                .is_err()
+        );
+    }
+
+    #[sqlx::test]
+    async fn links_insert_after_queue_item(pool: sqlx::Pool<sqlx::Sqlite>) {
+        let database = Arc::new(Database::init(pool).await.unwrap());
+        let mut player = Player::new(
+            Tracklist::default(),
+            Arc::new(MemoryCatalog::fixture()),
+            1.0,
+            Arc::new(NotificationBroadcast::new()),
+            std::env::temp_dir().join("qobuz-player-test-cache"),
+            database,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        player
+            .handle_message(ControlCommand::QueueLink {
+                link: Link::Album("1".into()),
+            })
+            .await
+            .unwrap();
+        let queue_ids: Vec<_> = player
+            .tracklist_rx
+            .borrow()
+            .queue()
+            .iter()
+            .map(|item| item.queue_id)
+            .collect();
+
+        player
+            .handle_message(ControlCommand::InsertLinkAfter {
+                link: Link::Track(201),
+                queue_id: Some(queue_ids[1]),
+            })
+            .await
+            .unwrap();
+        assert_eq!(queued_track_ids(&player), vec![101, 102, 201, 103]);
+
+        player
+            .handle_message(ControlCommand::InsertLinkAfter {
+                link: Link::Track(202),
+                queue_id: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(queued_track_ids(&player), vec![202, 101, 102, 201, 103]);
+
+        player
+            .handle_message(ControlCommand::InsertLinkAfter {
+                link: Link::Track(202),
+                queue_id: Some(u64::MAX),
+            })
+            .await
+            .unwrap();
+        assert_eq!(queued_track_ids(&player), vec![202, 101, 102, 201, 103]);
+    }
+
+    #[sqlx::test]
+    async fn play_next_on_an_empty_queue(pool: sqlx::Pool<sqlx::Sqlite>) {
+        let database = Arc::new(Database::init(pool).await.unwrap());
+        let mut player = Player::new(
+            Tracklist::default(),
+            Arc::new(MemoryCatalog::fixture()),
+            1.0,
+            Arc::new(NotificationBroadcast::new()),
+            std::env::temp_dir().join("qobuz-player-test-cache"),
+            database,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        player
+            .handle_message(ControlCommand::PlayTracksNext { ids: vec![101] })
+            .await
+            .unwrap();
+        assert_eq!(queued_track_ids(&player), vec![101]);
+    }
+
+    #[sqlx::test]
+    async fn snapshots_see_each_queue_change_once(pool: sqlx::Pool<sqlx::Sqlite>) {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let database = Arc::new(Database::init(pool).await.unwrap());
+        let mut player = Player::new(
+            Tracklist::default(),
+            Arc::new(MemoryCatalog::fixture()),
+            1.0,
+            Arc::new(NotificationBroadcast::new()),
+            std::env::temp_dir().join("qobuz-player-test-cache"),
+            database,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        player
+            .handle_message(ControlCommand::AddTracksToQueue {
+                ids: vec![101, 102, 103],
+            })
+            .await
+            .unwrap();
+
+        // Take snapshots on another thread while the queue changes
+        let done = Arc::new(AtomicBool::new(false));
+        let snapshots = std::thread::spawn({
+            let tracklist = player.tracklist();
+            let changes = player.tracklist_changes();
+            let done = done.clone();
+            move || {
+                let mut snapshots = vec![];
+                while !done.load(Ordering::Relaxed) && snapshots.len() < 100_000 {
+                    snapshots.push(crate::tracklist_snapshot(&tracklist, &changes));
+                    std::thread::yield_now();
+                }
+                snapshots
+            }
+        });
+
+        for _ in 0..15 {
+            player
+                .handle_message(ControlCommand::AddTracksToQueue { ids: vec![101] })
+                .await
+                .unwrap();
+        }
+        done.store(true, Ordering::Relaxed);
+        let snapshots = snapshots.join().unwrap();
+
+        let expected: Vec<_> = player
+            .tracklist_rx
+            .borrow()
+            .queue()
+            .iter()
+            .map(|item| item.queue_id)
+            .collect();
+        for (tracklist, mut changes) in snapshots {
+            let mut queue: Vec<_> = tracklist.queue().into_iter().cloned().collect();
+            while let Ok(changes) = changes.try_recv() {
+                for change in changes {
+                    assert!(change.apply(&mut queue), "{change:?} did not apply");
+                }
+            }
+            let queue_ids: Vec<_> = queue.iter().map(|item| item.queue_id).collect();
+            assert_eq!(queue_ids, expected);
+        }
+    }
+}
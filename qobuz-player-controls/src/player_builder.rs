@@ -0,0 +1,422 @@
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use tokio::sync::{broadcast, oneshot};
+
+use crate::{
+    AppResult, AudioQuality, ExitSender, PlayerEventReceiver, PositionReceiver, StatusReceiver,
+    TracklistChangeReceiver, TracklistReceiver, VolumeReceiver, catalog::Catalog, client::Client,
+    controls::Controls, database::Database, error::Error, notification::NotificationBroadcast,
+    player::Player, tracklist::Tracklist,
+};
+
+/// Builds a [`Player`] with defaults for everything that is not provided.
+///
+/// Without a database the default database location is used, and without a
+/// client or catalog a client is created from the credentials stored in the
+/// database.
+pub struct PlayerBuilder {
+    client: Option<Arc<Client>>,
+    catalog: Option<Arc<dyn Catalog>>,
+    database: Option<Arc<Database>>,
+    broadcast: Option<Arc<NotificationBroadcast>>,
+    max_audio_quality: Option<AudioQuality>,
+    audio_cache: Option<PathBuf>,
+    audio_cache_time_to_live: Duration,
+    state_change_delay: Option<Duration>,
+    sample_rate_change_delay: Option<Duration>,
+    output_device_id: Option<String>,
+}
+
+impl Default for PlayerBuilder {
+    fn default() -> Self {
+        Self {
+            client: None,
+            catalog: None,
+            database: None,
+            broadcast: None,
+            max_audio_quality: None,
+            audio_cache: None,
+            audio_cache_time_to_live: Duration::from_secs(60 * 60),
+            state_change_delay: None,
+            sample_rate_change_delay: None,
+            output_device_id: None,
+        }
+    }
+}
+
+impl PlayerBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn client(mut self, client: Arc<Client>) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Play from this catalog instead of the client, for example a
+    /// [`crate::catalog::memory::MemoryCatalog`]
+    pub fn catalog(mut self, catalog: Arc<dyn Catalog>) -> Self {
+        self.catalog = Some(catalog);
+        self
+    }
+
+    pub fn database(mut self, database: Arc<Database>) -> Self {
+        self.database = Some(database);
+        self
+    }
+
+    pub fn broadcast(mut self, broadcast: Arc<NotificationBroadcast>) -> Self {
+        self.broadcast = Some(broadcast);
+        self
+    }
+
+    /// Only used when the client is created by the builder. Defaults to the
+    /// configured max audio quality.
+    pub fn max_audio_quality(mut self, quality: AudioQuality) -> Self {
+        self.max_audio_quality = Some(quality);
+        self
+    }
+
+    /// Defaults to `qobuz-player-cache` in the temporary directory
+    pub fn audio_cache(mut self, path: PathBuf) -> Self {
+        self.audio_cache = Some(path);
+        self
+    }
+
+    /// How long cached audio is kept after it was last played. Zero keeps it
+    /// forever. Defaults to one hour.
+    pub fn audio_cache_time_to_live(mut self, time_to_live: Duration) -> Self {
+        self.audio_cache_time_to_live = time_to_live;
+        self
+    }
+
+    pub fn state_change_delay(mut self, delay: Duration) -> Self {
+        self.state_change_delay = Some(delay);
+        self
+    }
+
+    pub fn sample_rate_change_delay(mut self, delay: Duration) -> Self {
+        self.sample_rate_change_delay = Some(delay);
+        self
+    }
+
+    /// Use the output device instead of the default device
+    pub fn output_device_id(mut self, device_id: String) -> Self {
+        self.output_device_id = Some(device_id);
+        self
+    }
+
+    /// Build the player without starting it. The caller runs
+    /// [`Player::player_loop`] and cleans up the audio cache.
+    pub async fn build(self) -> AppResult<Player> {
+        self.resolve().await?.into_player()
+    }
+
+    /// Start the player loop on its own thread, together with the audio
+    /// cache clean up.
+    pub async fn spawn(self) -> AppResult<PlayerHandle> {
+        let parts = self.resolve().await?;
+        let catalog = parts.catalog.clone();
+        let client = parts.client.clone();
+        let database = parts.database.clone();
+        let broadcast = parts.broadcast.clone();
+        let audio_cache_time_to_live = parts.audio_cache_time_to_live;
+
+        let (exit_sender, exit_receiver) = broadcast::channel(5);
+        let (started_tx, started_rx) = oneshot::channel();
+        let (finished_tx, finished_rx) = oneshot::channel();
+
+        std::thread::spawn(move || {
+            let runtime = match tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+            {
+                Ok(runtime) => runtime,
+                Err(err) => {
+                    _ = started_tx.send(Err(Error::FailedToPlay {
+                        message: err.to_string(),
+                    }));
+                    return;
+                }
+            };
+
+            let _guard = runtime.enter();
+            let mut player = match parts.into_player() {
+                Ok(player) => player,
+                Err(err) => {
+                    _ = started_tx.send(Err(err));
+                    return;
+                }
+            };
+
+            let receivers = PlayerReceivers {
+                controls: player.controls(),
+                position: player.position(),
+                tracklist: player.tracklist(),
+                tracklist_changes: player.tracklist_changes(),
+                status: player.status(),
+                volume: player.volume(),
+                events: player.events(),
+            };
+            _ = started_tx.send(Ok(receivers));
+
+            let result = runtime.block_on(player.player_loop(exit_receiver));
+            _ = finished_tx.send(result);
+        });
+
+        let receivers = started_rx.await.map_err(|_| Error::PlayerStopped)??;
+
+        spawn_cache_clean_up(database.clone(), audio_cache_time_to_live);
+
+        Ok(PlayerHandle {
+            receivers,
+            catalog,
+            client,
+            database,
+            broadcast,
+            exit_sender,
+            finished: finished_rx,
+        })
+    }
+
+    async fn resolve(self) -> AppResult<PlayerParts> {
+        let database = match self.database {
+            Some(database) => database,
+            None => Arc::new(Database::new().await?),
+        };
+
+        let client = match (self.client, &self.catalog) {
+            (Some(client), _) => Some(client),
+            (None, Some(_)) => None,
+            (None, None) => {
+                let credentials = database
+                    .get_credentials()
+                    .await?
+                    .ok_or(Error::MissingCredentials)?;
+                let max_audio_quality = match self.max_audio_quality {
+                    Some(quality) => quality,
+                    None => database.get_configuration().await?.max_audio_quality.into(),
+                };
+
+                Some(Arc::new(Client::new(Some(credentials), max_audio_quality)))
+            }
+        };
+
+        let catalog = match (self.catalog, &client) {
+            (Some(catalog), _) => catalog,
+            (None, Some(client)) => client.clone(),
+            (None, None) => return Err(Error::MissingCredentials),
+        };
+
+        let tracklist = database.get_tracklist().await.unwrap_or_default();
+        let volume = database.get_volume().await.unwrap_or(1.0);
+
+        Ok(PlayerParts {
+            tracklist,
+            volume,
+            catalog,
+            client,
+            database,
+            broadcast: self
+                .broadcast
+                .unwrap_or_else(|| Arc::new(NotificationBroadcast::new())),
+            audio_cache: self.audio_cache.unwrap_or_else(default_audio_cache),
+            audio_cache_time_to_live: self.audio_cache_time_to_live,
+            state_change_delay: self.state_change_delay,
+            sample_rate_change_delay: self.sample_rate_change_delay,
+            output_device_id: self.output_device_id,
+        })
+    }
+}
+
+/// Everything needed to create the player, so it can be created on the
+/// thread that runs it.
+struct PlayerParts {
+    tracklist: Tracklist,
+    volume: f32,
+    catalog: Arc<dyn Catalog>,
+    client: Option<Arc<Client>>,
+    database: Arc<Database>,
+    broadcast: Arc<NotificationBroadcast>,
+    audio_cache: PathBuf,
+    audio_cache_time_to_live: Duration,
+    state_change_delay: Option<Duration>,
+    sample_rate_change_delay: Option<Duration>,
+    output_device_id: Option<String>,
+}
+
+impl PlayerParts {
+    fn into_player(self) -> AppResult<Player> {
+        Player::new(
+            self.tracklist,
+            self.catalog,
+            self.volume,
+            self.broadcast,
+            self.audio_cache,
+            self.database,
+            self.state_change_delay,
+            self.sample_rate_change_delay,
+            self.output_device_id,
+        )
+    }
+}
+
+struct PlayerReceivers {
+    controls: Controls,
+    position: PositionReceiver,
+    tracklist: TracklistReceiver,
+    tracklist_changes: TracklistChangeReceiver,
+    status: StatusReceiver,
+    volume: VolumeReceiver,
+    events: PlayerEventReceiver,
+}
+
+/// A running player. Dropping the handle leaves the player running; use
+/// [`PlayerHandle::shutdown`] to stop it.
+pub struct PlayerHandle {
+    receivers: PlayerReceivers,
+    catalog: Arc<dyn Catalog>,
+    client: Option<Arc<Client>>,
+    database: Arc<Database>,
+    broadcast: Arc<NotificationBroadcast>,
+    exit_sender: ExitSender,
+    finished: oneshot::Receiver<AppResult<()>>,
+}
+
+impl PlayerHandle {
+    pub fn controls(&self) -> Controls {
+        self.receivers.controls.clone()
+    }
+
+    pub fn position(&self) -> PositionReceiver {
+        self.receivers.position.clone()
+    }
+
+    pub fn tracklist(&self) -> TracklistReceiver {
+        self.receivers.tracklist.clone()
+    }
+
+    pub fn tracklist_changes(&self) -> TracklistChangeReceiver {
+        self.receivers.tracklist_changes.resubscribe()
+    }
+
+    pub fn status(&self) -> StatusReceiver {
+        self.receivers.status.clone()
+    }
+
+    pub fn volume(&self) -> VolumeReceiver {
+        self.receivers.volume.clone()
+    }
+
+    pub fn events(&self) -> PlayerEventReceiver {
+        self.receivers.events.resubscribe()
+    }
+
+    pub fn catalog(&self) -> Arc<dyn Catalog> {
+        self.catalog.clone()
+    }
+
+    /// `None` when the player was built with only a catalog
+    pub fn client(&self) -> Option<Arc<Client>> {
+        self.client.clone()
+    }
+
+    pub fn database(&self) -> Arc<Database> {
+        self.database.clone()
+    }
+
+    pub fn broadcast(&self) -> Arc<NotificationBroadcast> {
+        self.broadcast.clone()
+    }
+
+    /// Sending `true` stops the player loop
+    pub fn exit_sender(&self) -> ExitSender {
+        self.exit_sender.clone()
+    }
+
+    /// Wait for the player loop to stop
+    pub async fn wait(self) -> AppResult<()> {
+        self.finished.await.map_err(|_| Error::PlayerStopped)?
+    }
+
+    pub async fn shutdown(self) -> AppResult<()> {
+        _ = self.exit_sender.send(true);
+        self.wait().await
+    }
+}
+
+/// Remove cached audio that has not been played within `time_to_live`,
+/// once every hour. Zero keeps the cache forever.
+pub fn spawn_cache_clean_up(database: Arc<Database>, time_to_live: Duration) {
+    if time_to_live.is_zero() {
+        return;
+    }
+
+    let Ok(max_age) = time::Duration::try_from(time_to_live) else {
+        return;
+    };
+
+    tokio::spawn(async move {
+        let hour = Duration::from_secs(60 * 60);
+        let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + hour, hour);
+
+        loop {
+            interval.tick().await;
+
+            if let Ok(deleted_paths) = database.clean_up_cache_entries(max_age).await {
+                for path in deleted_paths {
+                    _ = tokio::fs::remove_file(path.as_path()).await;
+                }
+            }
+        }
+    });
+}
+
+fn default_audio_cache() -> PathBuf {
+    let mut cache_dir = std::env::temp_dir();
+    cache_dir.push("qobuz-player-cache");
+    cache_dir
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::catalog::memory::MemoryCatalog;
+
+    use super::*;
+
+    #[sqlx::test]
+    async fn spawned_player_runs_until_shutdown(pool: sqlx::Pool<sqlx::Sqlite>) {
+        let database = Arc::new(Database::init(pool).await.unwrap());
+        let player = PlayerBuilder::new()
+            .catalog(Arc::new(MemoryCatalog::fixture()))
+            .database(database.clone())
+            .audio_cache(std::env::temp_dir().join("qobuz-player-test-cache"))
+            .audio_cache_time_to_live(Duration::ZERO)
+            .spawn()
+            .await
+            .unwrap();
+        assert!(player.client().is_none());
+
+        let mut tracklist = player.tracklist();
+        player.controls().add_tracks_to_queue(vec![101, 102]);
+
+        tokio::time::timeout(Duration::from_secs(5), tracklist.changed())
+            .await
+            .unwrap()
+            .unwrap();
+        let track_ids: Vec<_> = tracklist
+            .borrow()
+            .queue()
+            .iter()
+            .map(|item| item.track.id)
+            .collect();
+        assert_eq!(track_ids, vec![101, 102]);
+
+        player.shutdown().await.unwrap();
+        assert_eq!(
+            database.get_tracklist().await.unwrap().total(),
+            track_ids.len()
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use std::time::{Duration, SystemTime};
+
+use crate::{models::Track, tracklist_change::TracklistChange};
+
+/// Something that happened in the player, published as it happens.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct PlayerEvent {
+    pub timestamp: SystemTime,
+    pub kind: PlayerEventKind,
+}
+
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum PlayerEventKind {
+    /// A track became the current track while playing, or playback of the
+    /// current track started from an empty sink
+    TrackStarted {
+        track: Track,
+    },
+    /// Playback was paused
+    Paused {
+        track: Option<Track>,
+    },
+    /// Paused playback went on again
+    Resumed {
+        track: Option<Track>,
+    },
+    /// The track played to the end
+    TrackFinished {
+        track: Track,
+    },
+    /// The last track of the queue played to the end
+    QueueEnded,
+    /// The user skipped away from `from`. `to` is `None` when skipping past
+    /// the end of the queue.
+    Skipped {
+        from: Option<Track>,
+        to: Option<Track>,
+    },
+    Seeked {
+        track: Option<Track>,
+        position: Duration,
+    },
+    VolumeChanged {
+        volume: f32,
+    },
+    QueueChanged {
+        changes: Vec<TracklistChange>,
+    },
+    ErrorOccurred {
+        message: String,
+    },
+    BufferingStarted {
+        track: Option<Track>,
+    },
+    BufferingEnded {
+        track: Option<Track>,
+    },
+}
+
+impl PlayerEvent {
+    pub fn new(kind: PlayerEventKind) -> Self {
+        Self {
+            timestamp: SystemTime::now(),
+            kind,
+        }
+    }
+}
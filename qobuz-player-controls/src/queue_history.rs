@@ -0,0 +1,103 @@
+use std::collections::VecDeque;
+
+use crate::tracklist::Tracklist;
+
+const MAX_HISTORY: usize = 20;
+
+#[derive(Debug, Default)]
+pub struct QueueHistory {
+    undo: VecDeque<Tracklist>,
+    redo: Vec<Tracklist>,
+}
+
+impl QueueHistory {
+    /// Record the tracklist as it was before an edit. Clears the redo stack.
+    pub fn record(&mut self, tracklist: Tracklist) {
+        self.push_undo(tracklist);
+        self.redo.clear();
+    }
+
+    pub fn undo(&mut self, current: Tracklist) -> Option<Tracklist> {
+        let previous = self.undo.pop_back()?;
+        self.redo.push(current);
+        Some(previous)
+    }
+
+    pub fn redo(&mut self, current: Tracklist) -> Option<Tracklist> {
+        let next = self.redo.pop()?;
+        self.push_undo(current);
+        Some(next)
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    fn push_undo(&mut self, tracklist: Tracklist) {
+        if self.undo.len() == MAX_HISTORY {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(tracklist);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{models::Track, tracklist::TracklistType};
+
+    fn tracklist(ids: &[u32]) -> Tracklist {
+        let mut tracklist = Tracklist::new(TracklistType::Tracks, vec![]);
+        for id in ids {
+            tracklist.push_track(Track {
+                id: *id,
+                ..Default::default()
+            });
+        }
+        tracklist
+    }
+
+    #[test]
+    fn undo_then_redo_round_trips() {
+        let mut history = QueueHistory::default();
+        let before = tracklist(&[1, 2, 3]);
+        let after = tracklist(&[1, 3]);
+
+        history.record(before.clone());
+
+        let undone = history.undo(after.clone()).unwrap();
+        assert_eq!(undone, before);
+
+        let redone = history.redo(undone).unwrap();
+        assert_eq!(redone, after);
+        assert!(history.redo(redone).is_none());
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let mut history = QueueHistory::default();
+
+        history.record(tracklist(&[1]));
+        history.undo(tracklist(&[1, 2])).unwrap();
+        history.record(tracklist(&[1]));
+
+        assert!(history.redo(tracklist(&[])).is_none());
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let mut history = QueueHistory::default();
+
+        for id in 0..(MAX_HISTORY as u32 + 5) {
+            history.record(tracklist(&[id]));
+        }
+
+        let mut undone = 0;
+        while history.undo(tracklist(&[])).is_some() {
+            undone += 1;
+        }
+
+        assert_eq!(undone, MAX_HISTORY);
+    }
+}
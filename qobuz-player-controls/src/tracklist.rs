@@ -36,8 +36,11 @@ pub enum TracklistType {
 pub struct Tracklist {
     queue: Vec<QueueItem>,
     list_type: TracklistType,
+    #[serde(default)]
+    next_queue_id: u64,
 }
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
 pub enum PlayingEntity {
     Track(Track),
@@ -55,23 +58,23 @@ pub struct PlayingPlaylist {
 #[derive(Default, Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
 pub struct QueueItem {
     pub track: Track,
+    /// Unique within the tracklist and never reused, also across edits
     pub queue_id: u64,
+    /// Position of the track in the album or playlist the queue was created
+    /// from. Tracks added to the queue later get their queue position at the
+    /// time they were added.
     pub index: usize,
 }
 
 impl Tracklist {
     pub fn new(list_type: TracklistType, queue: Vec<QueueItem>) -> Self {
-        // let queue = tracks
-        //     .into_iter()
-        //     .enumerate()
-        //     .map(|(i, track)| QueueItem {
-        //         track,
-        //         queue_id: i as u64,
-        //         index: i,
-        //     })
-        //     .collect();
+        let next_queue_id = next_free_queue_id(&queue);
 
-        Self { queue, list_type }
+        Self {
+            queue,
+            list_type,
+            next_queue_id,
+        }
     }
 
     pub fn set_list_type(&mut self, list_type: TracklistType) {
@@ -79,10 +82,7 @@ impl Tracklist {
     }
 
     pub fn new_with_id(list_type: TracklistType, items: Vec<QueueItem>) -> Self {
-        Self {
-            queue: items,
-            list_type,
-        }
+        Self::new(list_type, items)
     }
 
     pub fn queue(&self) -> Vec<&QueueItem> {
@@ -128,25 +128,67 @@ impl Tracklist {
     }
 
     pub fn push_track(&mut self, track: Track) {
-        let id = self.total() + 1;
         let item = QueueItem {
             track,
-            queue_id: id as u64,
-            index: id,
+            queue_id: self.allocate_queue_id(),
+            index: self.total(),
         };
         self.queue.push(item);
     }
 
     pub fn insert_track(&mut self, index: usize, track: Track) {
-        let id = self.total() + 1;
         let item = QueueItem {
             track,
-            queue_id: id as u64,
-            index: id,
+            queue_id: self.allocate_queue_id(),
+            index,
         };
         self.queue.insert(index, item);
     }
 
+    pub fn position_of_queue_id(&self, queue_id: u64) -> Option<usize> {
+        self.queue.iter().position(|item| item.queue_id == queue_id)
+    }
+
+    /// Remove the item with the given queue id. Returns false if it is not in the queue.
+    pub fn remove_queue_item(&mut self, queue_id: u64) -> bool {
+        match self.position_of_queue_id(queue_id) {
+            Some(position) => {
+                self.queue.remove(position);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move the item with the given queue id to a new position, clamped to the
+    /// end of the queue. Returns false if it is not in the queue.
+    pub fn move_queue_item(&mut self, queue_id: u64, new_position: usize) -> bool {
+        let Some(position) = self.position_of_queue_id(queue_id) else {
+            return false;
+        };
+
+        let item = self.queue.remove(position);
+        let new_position = new_position.min(self.queue.len());
+        self.queue.insert(new_position, item);
+        true
+    }
+
+    pub fn next_queue_id(&self) -> u64 {
+        self.next_queue_id
+    }
+
+    /// Make sure ids handed out from now on are at least `next_queue_id`, so an
+    /// id is not reused when this tracklist replaces another one.
+    pub fn reserve_queue_ids(&mut self, next_queue_id: u64) {
+        self.next_queue_id = self.next_queue_id.max(next_queue_id);
+    }
+
+    fn allocate_queue_id(&mut self) -> u64 {
+        let queue_id = self.next_queue_id;
+        self.next_queue_id += 1;
+        queue_id
+    }
+
     pub fn reorder_queue(&mut self, new_order: Vec<usize>) {
         if new_order.iter().enumerate().all(|(i, &v)| i == v) {
             return;
@@ -251,3 +293,58 @@ impl Tracklist {
         new_track
     }
 }
+
+fn next_free_queue_id(queue: &[QueueItem]) -> u64 {
+    queue
+        .iter()
+        .map(|item| item.queue_id + 1)
+        .max()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(id: u32) -> Track {
+        Track {
+            id,
+            ..Default::default()
+        }
+    }
+
+    fn queue_ids(tracklist: &Tracklist) -> Vec<u64> {
+        tracklist.queue().iter().map(|item| item.queue_id).collect()
+    }
+
+    #[test]
+    fn queue_ids_are_not_reused_after_removal() {
+        let mut tracklist = Tracklist::default();
+        tracklist.push_track(track(1));
+        tracklist.push_track(track(2));
+        tracklist.push_track(track(3));
+
+        assert!(tracklist.remove_queue_item(1));
+        tracklist.push_track(track(4));
+        tracklist.insert_track(0, track(5));
+
+        assert_eq!(queue_ids(&tracklist), vec![4, 0, 2, 3]);
+    }
+
+    #[test]
+    fn move_queue_item_by_id() {
+        let mut tracklist = Tracklist::default();
+        for id in 1..=4 {
+            tracklist.push_track(track(id));
+        }
+
+        assert!(tracklist.move_queue_item(0, 2));
+        assert_eq!(queue_ids(&tracklist), vec![1, 2, 0, 3]);
+
+        assert!(tracklist.move_queue_item(1, 100));
+        assert_eq!(queue_ids(&tracklist), vec![2, 0, 3, 1]);
+
+        assert!(!tracklist.move_queue_item(42, 0));
+        assert!(!tracklist.remove_queue_item(42));
+    }
+}
@@ -0,0 +1,365 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    models::TrackStatus,
+    tracklist::{QueueItem, Tracklist, TracklistType},
+};
+
+/// A single change between two versions of the tracklist.
+///
+/// Changes are applied in order. Positions refer to the queue as it is after
+/// the previous changes have been applied.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum TracklistChange {
+    /// Too much changed to describe; read the full tracklist instead
+    Replaced,
+    ItemsAdded {
+        position: usize,
+        items: Vec<QueueItem>,
+    },
+    ItemRemoved {
+        queue_id: u64,
+    },
+    /// Remove the item and insert it again at `new_position`
+    ItemMoved {
+        queue_id: u64,
+        new_position: usize,
+    },
+    CurrentChanged {
+        queue_id: Option<u64>,
+    },
+    TypeChanged {
+        list_type: TracklistType,
+    },
+}
+
+impl TracklistChange {
+    /// Changes that turn `old` into `new`.
+    pub fn diff(old: &Tracklist, new: &Tracklist) -> Vec<TracklistChange> {
+        let old_queue = old.queue();
+        let new_queue = new.queue();
+
+        // Items in both versions. An id that now holds another track, like
+        // after a controller reused it, is a removal and an addition.
+        let old_tracks: HashMap<u64, u32> = old_queue
+            .iter()
+            .map(|item| (item.queue_id, item.track.id))
+            .collect();
+        let kept: HashSet<u64> = new_queue
+            .iter()
+            .filter(|item| old_tracks.get(&item.queue_id) == Some(&item.track.id))
+            .map(|item| item.queue_id)
+            .collect();
+
+        if !new_queue.is_empty() && kept.is_empty() {
+            return vec![TracklistChange::Replaced];
+        }
+
+        let mut changes = vec![];
+
+        if old.list_type() != new.list_type() {
+            changes.push(TracklistChange::TypeChanged {
+                list_type: new.list_type().clone(),
+            });
+        }
+
+        let mut working: Vec<u64> = vec![];
+        for item in &old_queue {
+            if kept.contains(&item.queue_id) {
+                working.push(item.queue_id);
+            } else {
+                changes.push(TracklistChange::ItemRemoved {
+                    queue_id: item.queue_id,
+                });
+            }
+        }
+
+        let target: Vec<u64> = new_queue
+            .iter()
+            .map(|item| item.queue_id)
+            .filter(|queue_id| kept.contains(queue_id))
+            .collect();
+        changes.extend(moves(working, &target));
+
+        let mut position = 0;
+        while position < new_queue.len() {
+            if kept.contains(&new_queue[position].queue_id) {
+                position += 1;
+                continue;
+            }
+
+            let start = position;
+            while position < new_queue.len() && !kept.contains(&new_queue[position].queue_id) {
+                position += 1;
+            }
+
+            changes.push(TracklistChange::ItemsAdded {
+                position: start,
+                items: new_queue[start..position]
+                    .iter()
+                    .copied()
+                    .cloned()
+                    .collect(),
+            });
+        }
+
+        if old.current_queue_id() != new.current_queue_id() {
+            changes.push(TracklistChange::CurrentChanged {
+                queue_id: new.current_queue_id(),
+            });
+        }
+
+        if changes.len() > new_queue.len().max(1) {
+            return vec![TracklistChange::Replaced];
+        }
+
+        changes
+    }
+
+    /// Apply the change to a copy of the queue. Returns false for
+    /// [`TracklistChange::Replaced`] or a change that does not fit the queue,
+    /// in which case the caller should read the full tracklist again.
+    pub fn apply(&self, queue: &mut Vec<QueueItem>) -> bool {
+        match self {
+            TracklistChange::Replaced => false,
+            TracklistChange::ItemsAdded { position, items } => {
+                let already_queued = items
+                    .iter()
+                    .any(|new| queue.iter().any(|item| item.queue_id == new.queue_id));
+                if *position > queue.len() || already_queued {
+                    return false;
+                }
+                queue.splice(*position..*position, items.iter().cloned());
+                true
+            }
+            TracklistChange::ItemRemoved { queue_id } => {
+                match queue.iter().position(|item| item.queue_id == *queue_id) {
+                    Some(position) => {
+                        queue.remove(position);
+                        true
+                    }
+                    None => false,
+                }
+            }
+            TracklistChange::ItemMoved {
+                queue_id,
+                new_position,
+            } => match queue.iter().position(|item| item.queue_id == *queue_id) {
+                Some(position) => {
+                    let item = queue.remove(position);
+                    let new_position = (*new_position).min(queue.len());
+                    queue.insert(new_position, item);
+                    true
+                }
+                None => false,
+            },
+            TracklistChange::CurrentChanged { queue_id } => {
+                let current_position = queue_id
+                    .and_then(|queue_id| queue.iter().position(|item| item.queue_id == queue_id));
+
+                for (position, item) in queue.iter_mut().enumerate() {
+                    if item.track.status == TrackStatus::Unplayable {
+                        continue;
+                    }
+
+                    item.track.status = match current_position {
+                        Some(current) if position < current => TrackStatus::Played,
+                        Some(current) if position == current => TrackStatus::Playing,
+                        _ => TrackStatus::Unplayed,
+                    };
+                }
+                true
+            }
+            TracklistChange::TypeChanged { .. } => true,
+        }
+    }
+}
+
+/// Moves that put the items of `working` in the order of `target`. Both
+/// contain the same ids. Items on the longest run already in order stay put.
+fn moves(mut working: Vec<u64>, target: &[u64]) -> Vec<TracklistChange> {
+    let target_positions: HashMap<u64, usize> = target
+        .iter()
+        .enumerate()
+        .map(|(position, queue_id)| (*queue_id, position))
+        .collect();
+
+    let ranks: Vec<usize> = working
+        .iter()
+        .map(|queue_id| target_positions[queue_id])
+        .collect();
+    let stationary: HashSet<u64> = longest_increasing_subsequence(&ranks)
+        .into_iter()
+        .map(|index| working[index])
+        .collect();
+
+    let mut changes = vec![];
+
+    for (target_position, queue_id) in target.iter().enumerate() {
+        if stationary.contains(queue_id) {
+            continue;
+        }
+
+        let position = working
+            .iter()
+            .position(|x| x == queue_id)
+            .expect("infallible");
+        working.remove(position);
+
+        let new_position = match target_position {
+            0 => 0,
+            _ => {
+                let predecessor = target[target_position - 1];
+                working
+                    .iter()
+                    .position(|x| *x == predecessor)
+                    .expect("infallible")
+                    + 1
+            }
+        };
+        working.insert(new_position, *queue_id);
+
+        changes.push(TracklistChange::ItemMoved {
+            queue_id: *queue_id,
+            new_position,
+        });
+    }
+
+    changes
+}
+
+/// Indices into `values` of one longest strictly increasing subsequence.
+fn longest_increasing_subsequence(values: &[usize]) -> Vec<usize> {
+    let mut tails: Vec<usize> = vec![];
+    let mut previous: Vec<Option<usize>> = vec![None; values.len()];
+
+    for (index, value) in values.iter().enumerate() {
+        let length = tails.partition_point(|tail| values[*tail] < *value);
+
+        if length > 0 {
+            previous[index] = Some(tails[length - 1]);
+        }
+
+        if length == tails.len() {
+            tails.push(index);
+        } else {
+            tails[length] = index;
+        }
+    }
+
+    let mut result = vec![];
+    let mut current = tails.last().copied();
+    while let Some(index) = current {
+        result.push(index);
+        current = previous[index];
+    }
+    result.reverse();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Track;
+
+    fn tracklist(queue_ids: &[u64], current: Option<u64>) -> Tracklist {
+        let items = queue_ids
+            .iter()
+            .map(|queue_id| QueueItem {
+                track: Track {
+                    id: *queue_id as u32,
+                    ..Default::default()
+                },
+                queue_id: *queue_id,
+                index: 0,
+            })
+            .collect();
+
+        let mut tracklist = Tracklist::new(TracklistType::Tracks, items);
+        if let Some(position) = current.and_then(|id| tracklist.position_of_queue_id(id)) {
+            tracklist.skip_to_track(position as i32);
+        }
+        tracklist
+    }
+
+    fn assert_diff_applies(old: &Tracklist, new: &Tracklist) -> Vec<TracklistChange> {
+        let changes = TracklistChange::diff(old, new);
+        let mut queue: Vec<QueueItem> = old.queue().into_iter().cloned().collect();
+
+        for change in &changes {
+            assert!(change.apply(&mut queue), "{change:?} did not apply");
+        }
+
+        let expected: Vec<QueueItem> = new.queue().into_iter().cloned().collect();
+        assert_eq!(queue, expected);
+        changes
+    }
+
+    #[test]
+    fn single_move_is_one_change() {
+        let changes = assert_diff_applies(
+            &tracklist(&[1, 2, 3, 4, 5], None),
+            &tracklist(&[2, 3, 4, 5, 1], None),
+        );
+
+        assert_eq!(
+            changes,
+            vec![TracklistChange::ItemMoved {
+                queue_id: 1,
+                new_position: 4
+            }]
+        );
+    }
+
+    #[test]
+    fn removals_additions_and_moves() {
+        assert_diff_applies(
+            &tracklist(&[1, 2, 3, 4, 5, 6], Some(2)),
+            &tracklist(&[6, 1, 7, 8, 3, 5, 9], Some(3)),
+        );
+    }
+
+    #[test]
+    fn current_change_updates_status() {
+        let changes = assert_diff_applies(
+            &tracklist(&[1, 2, 3], Some(1)),
+            &tracklist(&[1, 2, 3], Some(2)),
+        );
+
+        assert_eq!(
+            changes,
+            vec![TracklistChange::CurrentChanged { queue_id: Some(2) }]
+        );
+    }
+
+    #[test]
+    fn reused_id_is_removed_and_added() {
+        let old = tracklist(&[1, 2, 3], None);
+        let mut items: Vec<QueueItem> = old.queue().into_iter().cloned().collect();
+        items[1].track.id = 20;
+        let new = Tracklist::new(TracklistType::Tracks, items);
+
+        let changes = assert_diff_applies(&old, &new);
+
+        assert_eq!(
+            changes,
+            vec![
+                TracklistChange::ItemRemoved { queue_id: 2 },
+                TracklistChange::ItemsAdded {
+                    position: 1,
+                    items: vec![new.queue()[1].clone()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn new_queue_is_replaced() {
+        let changes = TracklistChange::diff(
+            &tracklist(&[1, 2, 3], Some(1)),
+            &tracklist(&[4, 5], Some(4)),
+        );
+
+        assert_eq!(changes, vec![TracklistChange::Replaced]);
+    }
+}
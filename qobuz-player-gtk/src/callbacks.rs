@@ -12,6 +12,7 @@ use crate::{
         DetailPage,
         album_detail_page::{AlbumDetailPage, AlbumHeaderInfo},
         artist_detail_page::{ArtistDetailPage, ArtistHeaderInfo},
+        label_detail_page::{LabelDetailPage, LabelHeaderInfo},
         playlist_detail_page::{PlaylistDetailPage, PlaylistHeaderInfo},
     },
 };
@@ -19,17 +20,20 @@ use crate::{
 type OpenAlbumCb = Rc<dyn Fn(AlbumHeaderInfo) + 'static>;
 type OpenArtistCb = Rc<dyn Fn(ArtistHeaderInfo) + 'static>;
 type OpenPlaylistCb = Rc<dyn Fn(PlaylistHeaderInfo) + 'static>;
+type OpenLabelCb = Rc<dyn Fn(LabelHeaderInfo) + 'static>;
 
 struct Callbacks {
     open_album: OpenAlbumCb,
     open_artist: OpenArtistCb,
     open_playlist: OpenPlaylistCb,
+    open_label: OpenLabelCb,
 }
 
 pub struct CallbackHandles {
     pub open_album: OpenAlbumCb,
     pub open_artist: OpenArtistCb,
     pub open_playlist: OpenPlaylistCb,
+    pub open_label: OpenLabelCb,
     _keepalive: Rc<Callbacks>,
 }
 
@@ -81,6 +85,7 @@ pub fn build_callbacks(
                     }
 
                     let open_artist = callbacks.open_artist.clone();
+                    let open_label = callbacks.open_label.clone();
 
                     let detail = AlbumDetailPage::new(
                         info.id,
@@ -89,6 +94,7 @@ pub fn build_callbacks(
                         tracklist_receiver,
                         sender,
                         open_artist,
+                        open_label,
                     );
 
                     app_nav.push(detail.page());
@@ -190,10 +196,48 @@ pub fn build_callbacks(
             }
         });
 
+        // ---------- open_label ----------
+        let weak_for_label = weak_callbacks.clone();
+        let open_label: OpenLabelCb = Rc::new({
+            let main_ctx = main_ctx.clone();
+            let app_nav = app_nav.clone();
+            let client = client.clone();
+            let detail_pages = detail_pages.clone();
+
+            move |info: LabelHeaderInfo| {
+                let weak_for_label = weak_for_label.clone();
+                let app_nav = app_nav.clone();
+                let client = client.clone();
+                let detail_pages = detail_pages.clone();
+
+                main_ctx.invoke_local(move || {
+                    let Some(callbacks) = weak_for_label.upgrade() else {
+                        return;
+                    };
+
+                    let already_open = {
+                        let pages = detail_pages.borrow();
+                        pages.iter().any(|p| p.detail_type().is_label(info.id))
+                    };
+                    if already_open {
+                        return;
+                    }
+
+                    let open_album = callbacks.open_album.clone();
+
+                    let detail = LabelDetailPage::new(info.id, client, open_album);
+
+                    app_nav.push(detail.page());
+                    detail_pages.borrow_mut().push(Rc::new(detail));
+                });
+            }
+        });
+
         Callbacks {
             open_album,
             open_artist,
             open_playlist,
+            open_label,
         }
     });
 
@@ -201,6 +245,7 @@ pub fn build_callbacks(
         open_album: callbacks.open_album.clone(),
         open_artist: callbacks.open_artist.clone(),
         open_playlist: callbacks.open_playlist.clone(),
+        open_label: callbacks.open_label.clone(),
         _keepalive: callbacks, // <-- CRITICAL: keeps callbacks alive
     }
 }
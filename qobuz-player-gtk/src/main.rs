@@ -1,10 +1,7 @@
-use qobuz_player_cli::{
-    ConnectArgs, SharedArgs, create_player, default_audio_quality, spawn_clean_up,
-};
+use qobuz_player_cli::{ConnectArgs, SharedArgs, create_player, default_audio_quality, play_url};
 #[cfg(any(windows, target_os = "linux", target_os = "macos"))]
 use qobuz_player_controls::StatusReceiver;
 use std::sync::Arc;
-use tokio::sync::broadcast;
 
 use clap::Parser;
 use qobuz_player_controls::{
@@ -42,8 +39,6 @@ pub async fn run() -> AppResult<()> {
 
     let database = Arc::new(Database::new().await?);
 
-    let (exit_sender, exit_receiver) = broadcast::channel(5);
-
     let max_audio_quality = default_audio_quality(&database, args.shared.max_audio_quality).await?;
     let credentials = database.get_credentials().await?;
 
@@ -52,7 +47,7 @@ pub async fn run() -> AppResult<()> {
 
     let broadcast = Arc::new(NotificationBroadcast::new());
 
-    let mut player = create_player(
+    let player = create_player(
         args.shared.audio_cache,
         database.clone(),
         client.clone(),
@@ -60,19 +55,32 @@ pub async fn run() -> AppResult<()> {
         None,
         None,
         args.shared.output_device_id,
+        args.shared.audio_cache_time_to_live,
     )
     .await?;
 
+    let exit_sender = player.exit_sender();
+    play_url(&player, args.shared.play_url.as_deref())?;
+
+    #[cfg(unix)]
+    qobuz_player_cli::spawn_control_socket(&player, args.shared.control_socket);
+
+    qobuz_player_cli::spawn_mpd_server(&player, client.clone(), args.shared.mpd);
+    qobuz_player_cli::spawn_upnp_renderer(&player, args.shared.upnp_name, args.shared.upnp_port);
+    qobuz_player_cli::spawn_mqtt_client(&player, args.shared.mqtt);
+
     #[cfg(target_os = "linux")]
     {
         let position_receiver = player.position();
         let tracklist_receiver = player.tracklist();
         let volume_receiver = player.volume();
         let status_receiver = player.status();
+        let client = client.clone();
         let controls = player.controls();
         let exit_sender = exit_sender.clone();
         tokio::spawn(async move {
             if let Err(e) = qobuz_player_mpris::init(
+                client,
                 position_receiver,
                 tracklist_receiver,
                 volume_receiver,
@@ -98,6 +106,7 @@ pub async fn run() -> AppResult<()> {
     if args.connect.connect {
         let position_receiver = player.position();
         let tracklist_receiver = player.tracklist();
+        let tracklist_changes = player.tracklist_changes();
         let volume_receiver = player.volume();
         let status_receiver = player.status();
         let controls = player.controls();
@@ -110,6 +119,7 @@ pub async fn run() -> AppResult<()> {
                 controls,
                 position_receiver,
                 tracklist_receiver,
+                tracklist_changes,
                 status_receiver,
                 volume_receiver,
                 max_audio_quality,
@@ -141,8 +151,7 @@ pub async fn run() -> AppResult<()> {
         };
     });
 
-    spawn_clean_up(database, args.shared.audio_cache_time_to_live);
-    player.player_loop(exit_receiver).await?;
+    player.wait().await?;
 
     Ok(())
 }
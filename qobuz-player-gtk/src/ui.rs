@@ -11,6 +11,7 @@ pub mod artist_detail_page;
 pub mod artists_page;
 pub mod favorites_button;
 pub mod grid_page;
+pub mod label_detail_page;
 pub mod library_page;
 pub mod now_playing_bar;
 pub mod playlist_detail_page;
@@ -161,6 +162,7 @@ pub enum DetailPageType {
     Album(String),
     Artist(u32),
     Playlist(u32),
+    Label(u32),
 }
 
 impl DetailPageType {
@@ -182,6 +184,12 @@ impl DetailPageType {
             _ => false,
         }
     }
+    pub fn is_label(&self, id: u32) -> bool {
+        match self {
+            DetailPageType::Label(test_id) => test_id == &id,
+            _ => false,
+        }
+    }
 }
 
 pub trait DetailPage {
@@ -6,7 +6,11 @@ use gtk4::prelude::*;
 use libadwaita as adw;
 
 use qobuz_player_controls::{
-    TracklistReceiver, client::Client, controls::Controls, tracklist::PlayingEntity,
+    TracklistReceiver,
+    client::Client,
+    controls::Controls,
+    models::{Album, Disc, Track},
+    tracklist::PlayingEntity,
 };
 
 use crate::{
@@ -16,7 +20,9 @@ use crate::{
         artist_detail_page::ArtistHeaderInfo,
         build_track_row, clickable_tile,
         favorites_button::{FavoriteButtonType, new_favorite_button},
-        format_time, set_image_from_url,
+        format_time,
+        label_detail_page::LabelHeaderInfo,
+        set_image_from_url,
     },
 };
 
@@ -42,10 +48,12 @@ pub struct AlbumDetailPage {
     meta: gtk4::Label,
 
     tracks_list: gtk4::ListBox,
+    info_list: gtk4::ListBox,
 
     track_rows: Rc<RefCell<HashMap<u32, WeakRef<gtk4::ListBoxRow>>>>,
     current_selected_id: Rc<RefCell<Option<u32>>>,
     on_open_artist: Rc<dyn Fn(ArtistHeaderInfo)>,
+    on_open_label: Rc<dyn Fn(LabelHeaderInfo)>,
     loaded: RefCell<bool>,
 }
 
@@ -57,6 +65,7 @@ impl AlbumDetailPage {
         tracklist_receiver: TracklistReceiver,
         library_tx: Sender<UiEvent>,
         on_open_artist: Rc<dyn Fn(ArtistHeaderInfo)>,
+        on_open_label: Rc<dyn Fn(LabelHeaderInfo)>,
     ) -> Self {
         let empty_title = gtk4::Box::builder().hexpand(true).build();
 
@@ -152,6 +161,14 @@ impl AlbumDetailPage {
             .margin_bottom(18)
             .build();
 
+        let info_list = gtk4::ListBox::builder()
+            .selection_mode(gtk4::SelectionMode::None)
+            .css_classes(vec!["boxed-list"])
+            .margin_start(18)
+            .margin_end(18)
+            .margin_bottom(18)
+            .build();
+
         let content = gtk4::Box::builder()
             .orientation(gtk4::Orientation::Vertical)
             .vexpand(true)
@@ -160,6 +177,7 @@ impl AlbumDetailPage {
 
         content.append(&header_section);
         content.append(&tracks_list);
+        content.append(&info_list);
 
         let clamp = adw::Clamp::builder()
             .maximum_size(900)
@@ -201,10 +219,12 @@ impl AlbumDetailPage {
             artist_box,
             meta,
             tracks_list,
+            info_list,
             loaded: RefCell::new(false),
             track_rows: Rc::new(RefCell::new(HashMap::new())),
             current_selected_id: Rc::new(RefCell::new(None)),
             on_open_artist,
+            on_open_label,
         };
 
         s.load_album();
@@ -229,9 +249,11 @@ impl AlbumDetailPage {
         let artist_box = self.artist_box.clone();
         let meta = self.meta.clone();
         let tracks_list = self.tracks_list.clone();
+        let info_list = self.info_list.clone();
         let track_rows = self.track_rows.clone();
         let current_selected_id = self.current_selected_id.clone();
         let on_open_artist = self.on_open_artist.clone();
+        let on_open_label = self.on_open_label.clone();
 
         stack.set_visible_child_name("loading");
 
@@ -265,8 +287,45 @@ impl AlbumDetailPage {
 
                     clear_listbox(&tracks_list);
 
+                    let discs = album.discs();
+                    let disc_starts: HashMap<usize, &Disc> = match discs.len() {
+                        0 | 1 => HashMap::new(),
+                        _ => discs.iter().map(|disc| (disc.first_index, disc)).collect(),
+                    };
+
+                    let works = album.works();
+                    let mut previous_work = None;
+
                     for (idx, track) in album.tracks.iter().enumerate() {
+                        let disc_start = disc_starts.get(&idx);
+                        if let Some(disc) = disc_start {
+                            tracks_list.append(&section_header_row(
+                                format!("Disc {}", disc.number),
+                                None,
+                                format!("Play disc {}", disc.number),
+                                disc.tracks.iter().map(|t| t.id).collect(),
+                                &controls,
+                            ));
+                        }
+
+                        if track.work.is_some()
+                            && (disc_start.is_some() || previous_work != track.work.as_ref())
+                            && let Some(work) = works
+                                .iter()
+                                .find(|work| work.tracks.iter().any(|t| t.id == track.id))
+                        {
+                            tracks_list.append(&section_header_row(
+                                work.title.clone(),
+                                work.composer.as_ref().map(|c| c.name.clone()),
+                                "Play work".to_string(),
+                                work.tracks.iter().map(|t| t.id).collect(),
+                                &controls,
+                            ));
+                        }
+                        previous_work = track.work.as_ref();
+
                         let row = build_track_row(track, false, false, false);
+                        row.set_tooltip_text(track_details(track).as_deref());
 
                         let weak = glib::WeakRef::new();
                         weak.set(Some(&row));
@@ -287,6 +346,32 @@ impl AlbumDetailPage {
                         tracks_list.append(&row);
                     }
 
+                    clear_listbox(&info_list);
+                    if let Some(label) = &album.label {
+                        let row = adw::ActionRow::builder()
+                            .title("Label")
+                            .subtitle(&label.name)
+                            .activatable(true)
+                            .css_classes(vec!["property"])
+                            .build();
+                        row.add_suffix(&gtk4::Image::from_icon_name("go-next-symbolic"));
+
+                        let label_id = label.id;
+                        let on_open_label = on_open_label.clone();
+                        row.connect_activated(move |_| {
+                            on_open_label(LabelHeaderInfo { id: label_id });
+                        });
+                        info_list.append(&row);
+                    }
+                    for (title, value) in album_details(&album) {
+                        let row = adw::ActionRow::builder()
+                            .title(title)
+                            .subtitle(value)
+                            .css_classes(vec!["property"])
+                            .build();
+                        info_list.append(&row);
+                    }
+
                     let playing_entity = tracklist_receiver.borrow().current_playing_entity();
                     if let Some(playing_entity) = playing_entity {
                         update_current_playing(
@@ -362,3 +447,106 @@ fn clear_listbox(list: &gtk4::ListBox) {
         list.remove(&child);
     }
 }
+
+/// A heading row above a disc or a work, with a button playing its tracks
+fn section_header_row(
+    title: String,
+    subtitle: Option<String>,
+    play_tooltip: String,
+    track_ids: Vec<u32>,
+    controls: &Controls,
+) -> gtk4::ListBoxRow {
+    let labels = gtk4::Box::builder()
+        .orientation(gtk4::Orientation::Vertical)
+        .hexpand(true)
+        .build();
+
+    let title_label = gtk4::Label::builder()
+        .label(title)
+        .xalign(0.0)
+        .wrap(true)
+        .css_classes(vec!["heading"])
+        .build();
+    labels.append(&title_label);
+
+    if let Some(subtitle) = subtitle {
+        let subtitle_label = gtk4::Label::builder()
+            .label(subtitle)
+            .xalign(0.0)
+            .css_classes(vec!["dim-label"])
+            .build();
+        labels.append(&subtitle_label);
+    }
+
+    let play_button = gtk4::Button::builder()
+        .icon_name("media-playback-start-symbolic")
+        .tooltip_text(play_tooltip)
+        .valign(gtk4::Align::Center)
+        .css_classes(vec!["flat", "circular"])
+        .build();
+
+    {
+        let controls = controls.clone();
+        play_button.connect_clicked(move |_| {
+            controls.play_tracks(track_ids.clone(), false);
+        });
+    }
+
+    let header = gtk4::Box::builder()
+        .orientation(gtk4::Orientation::Horizontal)
+        .spacing(12)
+        .margin_top(6)
+        .margin_bottom(6)
+        .margin_start(12)
+        .margin_end(12)
+        .build();
+    header.append(&labels);
+    header.append(&play_button);
+
+    gtk4::ListBoxRow::builder()
+        .selectable(false)
+        .activatable(false)
+        .child(&header)
+        .build()
+}
+
+fn album_details(album: &Album) -> Vec<(&'static str, String)> {
+    let mut details = vec![];
+
+    if let Some(genre) = &album.genre {
+        details.push(("Genre", genre.name.clone()));
+    }
+    if let Some(composer) = &album.composer {
+        details.push(("Composer", composer.name.clone()));
+    }
+    if let Some(quality) = album.quality() {
+        details.push(("Quality", quality));
+    }
+    if album.disc_count > 1 {
+        details.push(("Discs", album.disc_count.to_string()));
+    }
+    if let Some(upc) = &album.upc {
+        details.push(("UPC", upc.clone()));
+    }
+    if let Some(copyright) = &album.copyright {
+        details.push(("Copyright", copyright.clone()));
+    }
+
+    details
+}
+
+fn track_details(track: &Track) -> Option<String> {
+    let mut lines = vec![];
+
+    if let Some(composer) = &track.composer {
+        lines.push(format!("Composer: {}", composer.name));
+    }
+    for credit in &track.credits {
+        lines.push(format!("{}: {}", credit.name, credit.roles.join(", ")));
+    }
+    if let Some(isrc) = &track.isrc {
+        lines.push(format!("ISRC: {isrc}"));
+    }
+
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
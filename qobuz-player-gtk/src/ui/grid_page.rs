@@ -161,6 +161,22 @@ impl<T: 'static> GridPage<T> {
         self.filter.changed(gtk::FilterChange::Different);
     }
 
+    /// Add items after the loaded ones, keeping the filter
+    pub fn append(&self, items: Vec<T>) {
+        for item in items {
+            self.store.append(&BoxedAnyObject::new(item));
+        }
+    }
+
+    /// Call `f` whenever the grid is scrolled to the bottom
+    pub fn connect_end_reached(&self, f: impl Fn() + 'static) {
+        self.widget.connect_edge_reached(move |_, position| {
+            if position == gtk::PositionType::Bottom {
+                f();
+            }
+        });
+    }
+
     pub fn filter(&self, query: &str) {
         *self.query.borrow_mut() = query.trim().to_string();
         self.filter.changed(gtk::FilterChange::Different);
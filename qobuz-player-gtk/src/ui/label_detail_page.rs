@@ -0,0 +1,222 @@
+use std::{cell::RefCell, rc::Rc, sync::Arc};
+
+use gtk4::prelude::*;
+use libadwaita as adw;
+
+use qobuz_player_controls::{
+    client::{Client, LABEL_PAGE_SIZE},
+    tracklist::PlayingEntity,
+};
+
+use crate::ui::{
+    DetailPage, DetailPageType,
+    album_detail_page::AlbumHeaderInfo,
+    albums_page::{AlbumsPage, new_albums_page},
+};
+
+#[derive(Clone, Debug)]
+pub struct LabelHeaderInfo {
+    pub id: u32,
+}
+
+pub struct LabelDetailPage {
+    page: adw::NavigationPage,
+
+    client: Arc<Client>,
+    label_id: u32,
+
+    stack: gtk4::Stack,
+
+    name: gtk4::Label,
+    description: gtk4::Label,
+    albums: Rc<RefCell<AlbumsPage>>,
+    /// Offset of the next page of albums, `None` once a page came back short
+    /// or while a page is loading
+    next_offset: Rc<RefCell<Option<u32>>>,
+
+    loaded: RefCell<bool>,
+}
+
+impl LabelDetailPage {
+    pub fn new(
+        label_id: u32,
+        client: Arc<Client>,
+        on_open_album: Rc<dyn Fn(AlbumHeaderInfo)>,
+    ) -> Self {
+        let empty_title = gtk4::Box::builder().hexpand(true).build();
+
+        let nav_bar = adw::HeaderBar::builder().title_widget(&empty_title).build();
+
+        let spinner = gtk4::Spinner::new();
+        spinner.start();
+        let spinner_box = gtk4::Box::builder()
+            .vexpand(true)
+            .hexpand(true)
+            .halign(gtk4::Align::Center)
+            .valign(gtk4::Align::Center)
+            .build();
+        spinner_box.append(&spinner);
+
+        let name = gtk4::Label::builder()
+            .xalign(0.0)
+            .css_classes(["title-1"])
+            .wrap(true)
+            .build();
+
+        let description = gtk4::Label::builder()
+            .xalign(0.0)
+            .css_classes(["dim-label"])
+            .wrap(true)
+            .visible(false)
+            .build();
+
+        let header_section = gtk4::Box::builder()
+            .orientation(gtk4::Orientation::Vertical)
+            .spacing(12)
+            .margin_top(18)
+            .margin_bottom(18)
+            .margin_start(18)
+            .margin_end(18)
+            .build();
+
+        header_section.append(&name);
+        header_section.append(&description);
+
+        let albums = new_albums_page(on_open_album);
+        albums.widget().set_vexpand(true);
+
+        let next_offset = Rc::new(RefCell::new(None));
+        connect_load_more(&albums, label_id, client.clone(), next_offset.clone());
+
+        let content = gtk4::Box::builder()
+            .orientation(gtk4::Orientation::Vertical)
+            .vexpand(true)
+            .hexpand(true)
+            .build();
+
+        content.append(&header_section);
+        content.append(albums.widget());
+
+        let stack = gtk4::Stack::builder()
+            .transition_type(gtk4::StackTransitionType::Crossfade)
+            .build();
+
+        stack.add_named(&spinner_box, Some("loading"));
+        stack.add_named(&content, Some("content"));
+        stack.set_visible_child_name("loading");
+
+        let toolbar = adw::ToolbarView::new();
+        toolbar.add_top_bar(&nav_bar);
+        toolbar.set_content(Some(&stack));
+
+        let page = adw::NavigationPage::builder()
+            .title("Label")
+            .child(&toolbar)
+            .build();
+
+        let s = Self {
+            page,
+            client,
+            label_id,
+            stack,
+            name,
+            description,
+            albums: Rc::new(RefCell::new(albums)),
+            next_offset,
+            loaded: RefCell::new(false),
+        };
+
+        s.load_label();
+
+        s
+    }
+
+    fn load_label(&self) {
+        if *self.loaded.borrow() {
+            return;
+        }
+        *self.loaded.borrow_mut() = true;
+
+        let client = self.client.clone();
+        let label_id = self.label_id;
+
+        let stack = self.stack.clone();
+        let name = self.name.clone();
+        let description = self.description.clone();
+        let albums = self.albums.clone();
+        let next_offset = self.next_offset.clone();
+
+        stack.set_visible_child_name("loading");
+
+        glib::MainContext::default().spawn_local(async move {
+            match client.label(label_id).await {
+                Ok(label) => {
+                    name.set_label(&label.name);
+
+                    if let Some(text) = &label.description {
+                        description.set_label(text);
+                        description.set_visible(true);
+                    }
+
+                    *next_offset.borrow_mut() = next_page_offset(0, label.albums.len());
+                    albums.borrow_mut().load(label.albums);
+
+                    stack.set_visible_child_name("content");
+                }
+                Err(err) => {
+                    tracing::error!("Failed to load label {label_id}: {err}");
+                    stack.set_visible_child_name("content");
+                }
+            }
+        });
+    }
+}
+
+/// Fetch the next page of albums when the grid is scrolled to the bottom
+fn connect_load_more(
+    albums: &AlbumsPage,
+    label_id: u32,
+    client: Arc<Client>,
+    next_offset: Rc<RefCell<Option<u32>>>,
+) {
+    let page = albums.clone();
+
+    albums.connect_end_reached(move || {
+        let Some(offset) = next_offset.borrow_mut().take() else {
+            return;
+        };
+
+        let page = page.clone();
+        let client = client.clone();
+        let next_offset = next_offset.clone();
+
+        glib::MainContext::default().spawn_local(async move {
+            match client.label_albums(label_id, offset).await {
+                Ok(items) => {
+                    *next_offset.borrow_mut() = next_page_offset(offset, items.len());
+                    page.append(items);
+                }
+                Err(err) => {
+                    tracing::error!("Loading more albums of label {label_id} failed: {err}");
+                    *next_offset.borrow_mut() = Some(offset);
+                }
+            }
+        });
+    });
+}
+
+fn next_page_offset(offset: u32, count: usize) -> Option<u32> {
+    (count >= LABEL_PAGE_SIZE as usize).then_some(offset + LABEL_PAGE_SIZE)
+}
+
+impl DetailPage for LabelDetailPage {
+    fn page(&self) -> &adw::NavigationPage {
+        &self.page
+    }
+
+    fn update_current_playing(&self, _playing_entity: PlayingEntity) {}
+
+    fn detail_type(&self) -> DetailPageType {
+        DetailPageType::Label(self.label_id)
+    }
+}
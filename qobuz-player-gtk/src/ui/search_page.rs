@@ -1,3 +1,4 @@
+use std::cell::RefCell;
 use std::rc::Rc;
 use std::sync::Arc;
 
@@ -5,16 +6,31 @@ use gtk4::glib;
 use gtk4::prelude::*;
 use libadwaita as adw;
 
-use qobuz_player_controls::client::Client;
+use qobuz_player_controls::client::{Client, SEARCH_PAGE_SIZE};
+use qobuz_player_controls::models::SearchResults;
 
 use crate::ui::albums_page::new_albums_page;
 use crate::ui::artists_page::new_artists_page;
+use crate::ui::grid_page::GridPage;
 use crate::ui::playlists_page::new_playlists_page;
 use crate::ui::{
     album_detail_page::AlbumHeaderInfo, artist_detail_page::ArtistHeaderInfo,
     playlist_detail_page::PlaylistHeaderInfo,
 };
 
+const ALBUMS: usize = 0;
+const ARTISTS: usize = 1;
+const PLAYLISTS: usize = 2;
+
+/// The query behind the shown results, and the offset of the next page of
+/// albums, artists and playlists. `None` once a page came back short.
+#[derive(Default)]
+struct Paging {
+    query: String,
+    next_offsets: [Option<u32>; 3],
+    loading: bool,
+}
+
 pub struct SearchPage {
     root: gtk4::Box,
 }
@@ -32,6 +48,30 @@ impl SearchPage {
         let artists_page = new_artists_page(on_open_artist.clone());
         let playlists_page = new_playlists_page(on_open_playlist.clone());
 
+        let paging = Rc::new(RefCell::new(Paging::default()));
+
+        connect_load_more(
+            &albums_page,
+            ALBUMS,
+            client.clone(),
+            paging.clone(),
+            |results| results.albums.into_iter().map(|x| x.into()).collect(),
+        );
+        connect_load_more(
+            &artists_page,
+            ARTISTS,
+            client.clone(),
+            paging.clone(),
+            |results| results.artists,
+        );
+        connect_load_more(
+            &playlists_page,
+            PLAYLISTS,
+            client.clone(),
+            paging.clone(),
+            |results| results.playlists.into_iter().map(|x| x.into()).collect(),
+        );
+
         stack.add_titled(albums_page.widget(), Some("albums"), "Albums");
         stack.add_titled(artists_page.widget(), Some("artists"), "Artists");
         stack.add_titled(playlists_page.widget(), Some("playlists"), "Playlists");
@@ -83,6 +123,7 @@ impl SearchPage {
         search_entry.connect_activate({
             let client = client.clone();
             let spinner = spinner.clone();
+            let paging = paging.clone();
 
             move |entry| {
                 let mut albums_page = albums_page.clone();
@@ -103,10 +144,24 @@ impl SearchPage {
 
                 let client = client.clone();
                 let spinner = spinner.clone();
+                let paging = paging.clone();
+
+                *paging.borrow_mut() = Paging {
+                    query: query.clone(),
+                    ..Default::default()
+                };
 
                 glib::MainContext::default().spawn_local(async move {
-                    match client.search(query).await {
+                    match client.search(query.clone()).await {
                         Ok(search) => {
+                            if paging.borrow().query == query {
+                                paging.borrow_mut().next_offsets = [
+                                    next_offset(0, search.albums.len()),
+                                    next_offset(0, search.artists.len()),
+                                    next_offset(0, search.playlists.len()),
+                                ];
+                            }
+
                             let albums: Vec<_> =
                                 search.albums.into_iter().map(|x| x.into()).collect();
                             albums_page.load(albums);
@@ -135,3 +190,66 @@ impl SearchPage {
         &self.root
     }
 }
+
+/// Fetch the next page of one kind of results when its grid is scrolled to
+/// the bottom
+fn connect_load_more<T: 'static>(
+    page: &GridPage<T>,
+    kind: usize,
+    client: Arc<Client>,
+    paging: Rc<RefCell<Paging>>,
+    take: fn(SearchResults) -> Vec<T>,
+) {
+    let page_for_signal = page.clone();
+
+    page.connect_end_reached(move || {
+        let (query, offset) = {
+            let mut paging = paging.borrow_mut();
+            if paging.loading {
+                return;
+            }
+            let Some(offset) = paging.next_offsets[kind] else {
+                return;
+            };
+
+            paging.loading = true;
+            (paging.query.clone(), offset)
+        };
+
+        let page = page_for_signal.clone();
+        let client = client.clone();
+        let paging = paging.clone();
+
+        glib::MainContext::default().spawn_local(async move {
+            let result = client.search_page(query.clone(), offset).await;
+
+            let items = {
+                let mut paging = paging.borrow_mut();
+                paging.loading = false;
+
+                // A new search replaced these results
+                if paging.query != query {
+                    return;
+                }
+
+                match result {
+                    Ok(results) => {
+                        let items = take(results);
+                        paging.next_offsets[kind] = next_offset(offset, items.len());
+                        items
+                    }
+                    Err(err) => {
+                        tracing::error!("Loading more search results failed: {err}");
+                        return;
+                    }
+                }
+            };
+
+            page.append(items);
+        });
+    });
+}
+
+fn next_offset(offset: u32, count: usize) -> Option<u32> {
+    (count >= SEARCH_PAGE_SIZE as usize).then_some(offset + SEARCH_PAGE_SIZE)
+}
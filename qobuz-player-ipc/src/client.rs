@@ -0,0 +1,81 @@
+use std::{io, path::Path};
+
+use qobuz_player_controls::{AppResult, error::Error};
+use serde_json::Value;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines},
+    net::{
+        UnixStream,
+        unix::{OwnedReadHalf, OwnedWriteHalf},
+    },
+};
+
+use crate::{Notification, Request, Response};
+
+/// A connection to the control socket of a running player
+pub struct Connection {
+    lines: Lines<BufReader<OwnedReadHalf>>,
+    writer: OwnedWriteHalf,
+    next_id: u64,
+}
+
+impl Connection {
+    pub async fn connect(socket_path: &Path) -> AppResult<Self> {
+        let stream = UnixStream::connect(socket_path)
+            .await
+            .map_err(|err| Error::SocketError {
+                message: format!(
+                    "Unable to connect to {}. Is qobuz-player running? {err}",
+                    socket_path.display()
+                ),
+            })?;
+        let (reader, writer) = stream.into_split();
+
+        Ok(Self {
+            lines: BufReader::new(reader).lines(),
+            writer,
+            next_id: 1,
+        })
+    }
+
+    /// Call `method` and wait for its result
+    pub async fn call(&mut self, method: &str, params: Value) -> AppResult<Value> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let mut line = serde_json::to_vec(&Request::new(id, method, params))?;
+        line.push(b'\n');
+        self.writer.write_all(&line).await.map_err(socket_error)?;
+
+        let line = self.next_line().await?.ok_or_else(|| Error::SocketError {
+            message: "Player closed the connection".into(),
+        })?;
+        let response: Response = serde_json::from_str(&line)?;
+
+        match (response.result, response.error) {
+            (_, Some(error)) => Err(Error::SocketError {
+                message: error.message,
+            }),
+            (result, None) => Ok(result.unwrap_or_default()),
+        }
+    }
+
+    /// The next notification after a `follow` call, or `None` when the
+    /// player exits
+    pub async fn next_notification(&mut self) -> AppResult<Option<Notification>> {
+        match self.next_line().await? {
+            Some(line) => Ok(Some(serde_json::from_str(&line)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn next_line(&mut self) -> AppResult<Option<String>> {
+        self.lines.next_line().await.map_err(socket_error)
+    }
+}
+
+fn socket_error(err: io::Error) -> Error {
+    Error::SocketError {
+        message: err.to_string(),
+    }
+}
@@ -0,0 +1,150 @@
+//! JSON-RPC 2.0 over a Unix domain socket, one request or notification per
+//! line.
+//!
+//! Methods: `play`, `pause`, `play_pause`, `next`, `previous`, `status`,
+//! `queue`, `volume` (`{"volume": 0.5}` sets it), `play_url`
+//! (`{"url": "...", "queue": false}`) and `follow`, after which the server
+//! sends `event` and `status` notifications until the connection closes.
+
+use std::path::PathBuf;
+
+use qobuz_player_controls::{Status, models::Track, tracklist::QueueItem};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub mod client;
+pub mod server;
+
+pub const PARSE_ERROR: i32 = -32700;
+pub const METHOD_NOT_FOUND: i32 = -32601;
+pub const INVALID_PARAMS: i32 = -32602;
+pub const INTERNAL_ERROR: i32 = -32603;
+
+/// `$XDG_RUNTIME_DIR/qobuz-player.sock`. Without a runtime dir the socket
+/// goes in a `qobuz-player` directory in the user's cache dir, which the
+/// server makes readable by the user only.
+pub fn default_socket_path() -> PathBuf {
+    dirs::runtime_dir()
+        .or_else(|| dirs::cache_dir().map(|dir| dir.join("qobuz-player")))
+        .unwrap_or_else(|| std::env::temp_dir().join("qobuz-player"))
+        .join("qobuz-player.sock")
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Request {
+    pub jsonrpc: String,
+    /// `None` for notifications, which get no response. A request may still
+    /// have a `null` id.
+    #[serde(
+        default,
+        deserialize_with = "present",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub id: Option<Value>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+impl Request {
+    pub fn new(id: u64, method: &str, params: Value) -> Self {
+        Self {
+            jsonrpc: "2.0".into(),
+            id: Some(id.into()),
+            method: method.into(),
+            params,
+        }
+    }
+}
+
+/// Keeps an `id` that is there but `null` apart from a missing one
+fn present<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<Value>, D::Error> {
+    Value::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Response {
+    pub jsonrpc: String,
+    pub id: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+impl Response {
+    pub fn new(id: Value, result: Result<Value, RpcError>) -> Self {
+        let (result, error) = match result {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+
+        Self {
+            jsonrpc: "2.0".into(),
+            id,
+            result,
+            error,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i32,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn new(code: i32, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// Sent to followers without an id
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Notification {
+    pub jsonrpc: String,
+    pub method: String,
+    pub params: Value,
+}
+
+impl Notification {
+    pub fn new(method: &str, params: Value) -> Self {
+        Self {
+            jsonrpc: "2.0".into(),
+            method: method.into(),
+            params,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatusInfo {
+    pub status: Status,
+    pub position_ms: u64,
+    pub volume: f32,
+    pub track: Option<Track>,
+    pub queue_position: usize,
+    pub queue_length: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QueueInfo {
+    pub current_queue_id: Option<u64>,
+    pub items: Vec<QueueItem>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VolumeParams {
+    pub volume: Option<f32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PlayUrlParams {
+    pub url: String,
+    #[serde(default)]
+    pub queue: bool,
+}
@@ -0,0 +1,437 @@
+use std::{
+    io,
+    os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use qobuz_player_controls::{
+    AppResult, PlayerEventReceiver, PositionReceiver, StatusReceiver, TracklistReceiver,
+    VolumeReceiver, controls::Controls, error::Error, models::Link,
+};
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream, unix::OwnedWriteHalf},
+    sync::broadcast::error::RecvError,
+};
+
+use crate::{
+    INTERNAL_ERROR, INVALID_PARAMS, METHOD_NOT_FOUND, Notification, PARSE_ERROR, PlayUrlParams,
+    QueueInfo, Request, Response, RpcError, StatusInfo, VolumeParams,
+};
+
+struct State {
+    controls: Controls,
+    position_receiver: PositionReceiver,
+    tracklist_receiver: TracklistReceiver,
+    volume_receiver: VolumeReceiver,
+    status_receiver: StatusReceiver,
+    events: PlayerEventReceiver,
+}
+
+/// Serve the control socket at `socket_path`. Fails if another player
+/// already listens there; a socket file left behind by a player that is gone
+/// is replaced.
+pub async fn init(
+    socket_path: PathBuf,
+    controls: Controls,
+    position_receiver: PositionReceiver,
+    tracklist_receiver: TracklistReceiver,
+    volume_receiver: VolumeReceiver,
+    status_receiver: StatusReceiver,
+    events: PlayerEventReceiver,
+) -> AppResult<()> {
+    let listener = bind(&socket_path).await?;
+    tracing::info!("Control socket listening on {}", socket_path.display());
+
+    let state = Arc::new(State {
+        controls,
+        position_receiver,
+        tracklist_receiver,
+        volume_receiver,
+        status_receiver,
+        events,
+    });
+
+    loop {
+        let (stream, _) = listener.accept().await.map_err(socket_error)?;
+        let state = state.clone();
+
+        tokio::spawn(async move {
+            if let Err(err) = handle_connection(stream, &state).await {
+                tracing::debug!("Control socket connection closed: {err}");
+            }
+        });
+    }
+}
+
+async fn bind(socket_path: &Path) -> AppResult<UnixListener> {
+    if let Some(directory) = socket_path.parent() {
+        private_directory(directory)?;
+    }
+
+    // A socket left behind by a player that is gone is replaced, any other
+    // file at the path is left alone
+    if let Ok(metadata) = std::fs::symlink_metadata(socket_path) {
+        if !metadata.file_type().is_socket() {
+            return Err(Error::SocketError {
+                message: format!("{} exists and is not a socket", socket_path.display()),
+            });
+        }
+        if UnixStream::connect(socket_path).await.is_ok() {
+            return Err(Error::SocketInUse {
+                path: socket_path.display().to_string(),
+            });
+        }
+        std::fs::remove_file(socket_path).map_err(socket_error)?;
+    }
+
+    let listener = UnixListener::bind(socket_path).map_err(socket_error)?;
+    std::fs::set_permissions(socket_path, std::fs::Permissions::from_mode(0o600))
+        .map_err(socket_error)?;
+
+    Ok(listener)
+}
+
+/// Makes the socket directory readable by the user only when it is missing,
+/// and refuses one that other users can write to, as they could swap the
+/// socket for their own
+fn private_directory(directory: &Path) -> AppResult<()> {
+    if !directory.exists() {
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(directory)
+            .map_err(socket_error)?;
+    }
+
+    let mode = std::fs::metadata(directory)
+        .map_err(socket_error)?
+        .permissions()
+        .mode();
+    let sticky = mode & 0o1000 != 0;
+    if mode & 0o022 != 0 && !sticky {
+        return Err(Error::SocketError {
+            message: format!("{} is writable by other users", directory.display()),
+        });
+    }
+
+    Ok(())
+}
+
+fn socket_error(err: io::Error) -> Error {
+    Error::SocketError {
+        message: err.to_string(),
+    }
+}
+
+async fn handle_connection(stream: UnixStream, state: &State) -> io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let request: Request = match serde_json::from_str(&line) {
+            Ok(request) => request,
+            Err(err) => {
+                let error = RpcError::new(PARSE_ERROR, err.to_string());
+                write_line(&mut writer, &Response::new(Value::Null, Err(error))).await?;
+                continue;
+            }
+        };
+
+        if request.method == "follow" {
+            if let Some(id) = request.id {
+                write_line(&mut writer, &Response::new(id, Ok(Value::Null))).await?;
+            }
+            return follow(writer, state).await;
+        }
+
+        let result = handle_request(state, &request.method, request.params);
+
+        // Notifications are carried out without an answer, also when they fail
+        if let Some(id) = request.id {
+            write_line(&mut writer, &Response::new(id, result)).await?;
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_request(state: &State, method: &str, params: Value) -> Result<Value, RpcError> {
+    match method {
+        "play" => state.controls.play(),
+        "pause" => state.controls.pause(),
+        "play_pause" => state.controls.play_pause(),
+        "next" => state.controls.next(),
+        "previous" => state.controls.previous(),
+        "status" => return to_value(status(state)),
+        "queue" => {
+            let tracklist = state.tracklist_receiver.borrow();
+            return to_value(QueueInfo {
+                current_queue_id: tracklist.current_queue_id(),
+                items: tracklist.queue().into_iter().cloned().collect(),
+            });
+        }
+        "volume" => {
+            let params: VolumeParams = parse_params(params)?;
+            return match params.volume {
+                Some(volume) => {
+                    let volume = volume.clamp(0.0, 1.0);
+                    state.controls.set_volume(volume);
+                    to_value(volume)
+                }
+                None => to_value(*state.volume_receiver.borrow()),
+            };
+        }
+        "play_url" => {
+            let params: PlayUrlParams = parse_params(params)?;
+            let link = Link::parse(&params.url)
+                .map_err(|err| RpcError::new(INVALID_PARAMS, err.to_string()))?;
+
+            match params.queue {
+                true => state.controls.queue_link(link),
+                false => state.controls.play_link(link),
+            }
+        }
+        _ => {
+            return Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Unknown method {method}"),
+            ));
+        }
+    }
+
+    Ok(Value::Null)
+}
+
+fn status(state: &State) -> StatusInfo {
+    let tracklist = state.tracklist_receiver.borrow();
+
+    StatusInfo {
+        status: *state.status_receiver.borrow(),
+        position_ms: state.position_receiver.borrow().as_millis() as u64,
+        volume: *state.volume_receiver.borrow(),
+        track: tracklist.current_track().cloned(),
+        queue_position: tracklist.current_position(),
+        queue_length: tracklist.total(),
+    }
+}
+
+/// Stream player events and status changes until the follower disconnects
+async fn follow(mut writer: OwnedWriteHalf, state: &State) -> io::Result<()> {
+    let mut events = state.events.resubscribe();
+    let mut status = state.status_receiver.clone();
+
+    loop {
+        let notification = tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => Notification::new("event", serde_json::to_value(event)?),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return Ok(()),
+            },
+            changed = status.changed() => {
+                if changed.is_err() {
+                    return Ok(());
+                }
+                let status = *status.borrow_and_update();
+                Notification::new("status", serde_json::to_value(status)?)
+            }
+        };
+
+        write_line(&mut writer, &notification).await?;
+    }
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    let params = match params {
+        Value::Null => Value::Object(Default::default()),
+        params => params,
+    };
+
+    serde_json::from_value(params).map_err(|err| RpcError::new(INVALID_PARAMS, err.to_string()))
+}
+
+fn to_value<T: Serialize>(value: T) -> Result<Value, RpcError> {
+    serde_json::to_value(value).map_err(|err| RpcError::new(INTERNAL_ERROR, err.to_string()))
+}
+
+async fn write_line<T: Serialize>(writer: &mut OwnedWriteHalf, value: &T) -> io::Result<()> {
+    let mut line = serde_json::to_vec(value)?;
+    line.push(b'\n');
+    writer.write_all(&line).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use qobuz_player_controls::{Status, controls::ControlCommand, tracklist::Tracklist};
+    use serde_json::json;
+    use tokio::sync::{broadcast, mpsc, watch};
+
+    use super::*;
+    use crate::client::Connection;
+
+    #[tokio::test]
+    async fn requests_over_the_socket() {
+        let socket_path =
+            std::env::temp_dir().join(format!("qobuz-player-ipc-test-{}.sock", std::process::id()));
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let (_position_tx, position_receiver) = watch::channel(Duration::from_secs(3));
+        let (_tracklist_tx, tracklist_receiver) = watch::channel(Tracklist::default());
+        let (_volume_tx, volume_receiver) = watch::channel(0.5);
+        let (_status_tx, status_receiver) = watch::channel(Status::Paused);
+        let (_events_tx, events) = broadcast::channel(1);
+
+        let listener = bind(&socket_path).await.unwrap();
+        let state = Arc::new(State {
+            controls: Controls::new(tx),
+            position_receiver,
+            tracklist_receiver,
+            volume_receiver,
+            status_receiver,
+            events,
+        });
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            handle_connection(stream, &state).await.unwrap();
+        });
+
+        let mut connection = Connection::connect(&socket_path).await.unwrap();
+
+        let status = connection.call("status", Value::Null).await.unwrap();
+        assert_eq!(status["position_ms"], 3000);
+        assert_eq!(status["status"], "Paused");
+
+        let volume = connection
+            .call("volume", json!({"volume": 2.0}))
+            .await
+            .unwrap();
+        assert_eq!(volume, 1.0);
+        assert!(matches!(
+            rx.recv().await,
+            Some(ControlCommand::SetVolume { volume }) if volume == 1.0
+        ));
+
+        connection
+            .call("play_url", json!({"url": "qobuz:album:42", "queue": true}))
+            .await
+            .unwrap();
+        assert!(matches!(
+            rx.recv().await,
+            Some(ControlCommand::QueueLink { link: Link::Album(id) }) if id == "42"
+        ));
+
+        assert!(connection.call("rewind", Value::Null).await.is_err());
+
+        _ = std::fs::remove_file(socket_path);
+    }
+
+    #[tokio::test]
+    async fn notifications_get_no_response() {
+        let socket_path = std::env::temp_dir().join(format!(
+            "qobuz-player-ipc-notification-test-{}.sock",
+            std::process::id()
+        ));
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let (_events_tx, events) = broadcast::channel(1);
+
+        let listener = bind(&socket_path).await.unwrap();
+        let state = Arc::new(State {
+            controls: Controls::new(tx),
+            position_receiver: watch::channel(Duration::default()).1,
+            tracklist_receiver: watch::channel(Tracklist::default()).1,
+            volume_receiver: watch::channel(0.5).1,
+            status_receiver: watch::channel(Status::Paused).1,
+            events,
+        });
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            handle_connection(stream, &state).await.unwrap();
+        });
+
+        let stream = UnixStream::connect(&socket_path).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+
+        writer
+            .write_all(
+                concat!(
+                    "{\"jsonrpc\": \"2.0\", \"method\": \"pause\"}\n",
+                    "{\"jsonrpc\": \"2.0\", \"method\": \"rewind\"}\n",
+                    "{\"jsonrpc\": \"2.0\", \"id\": null, \"method\": \"next\"}\n",
+                )
+                .as_bytes(),
+            )
+            .await
+            .unwrap();
+
+        let response: Response =
+            serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(response.id, Value::Null);
+        assert!(response.error.is_none());
+
+        assert!(matches!(rx.recv().await, Some(ControlCommand::Pause)));
+        assert!(matches!(rx.recv().await, Some(ControlCommand::Next)));
+
+        _ = std::fs::remove_file(socket_path);
+    }
+
+    #[tokio::test]
+    async fn unix_socket_replaces_only_stale_sockets() {
+        let socket_path = std::env::temp_dir().join(format!(
+            "qobuz-player-ipc-stale-test-{}.sock",
+            std::process::id()
+        ));
+
+        std::fs::write(&socket_path, "not a socket").unwrap();
+        assert!(bind(&socket_path).await.is_err());
+        assert_eq!(
+            std::fs::read_to_string(&socket_path).unwrap(),
+            "not a socket"
+        );
+        std::fs::remove_file(&socket_path).unwrap();
+
+        // Left behind by a listener that is gone
+        drop(std::os::unix::net::UnixListener::bind(&socket_path).unwrap());
+        let listener = bind(&socket_path).await.unwrap();
+        let mode = std::fs::metadata(&socket_path)
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        assert!(matches!(
+            bind(&socket_path).await,
+            Err(Error::SocketInUse { .. })
+        ));
+
+        drop(listener);
+        _ = std::fs::remove_file(socket_path);
+    }
+
+    #[test]
+    fn socket_directory_is_private() {
+        let directory = std::env::temp_dir().join(format!(
+            "qobuz-player-ipc-directory-test-{}",
+            std::process::id()
+        ));
+
+        private_directory(&directory).unwrap();
+        let mode = std::fs::metadata(&directory).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+
+        std::fs::set_permissions(&directory, std::fs::Permissions::from_mode(0o777)).unwrap();
+        assert!(private_directory(&directory).is_err());
+
+        _ = std::fs::remove_dir(directory);
+    }
+}
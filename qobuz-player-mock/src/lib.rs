@@ -0,0 +1,485 @@
+//! A local stand-in for the Qobuz api. It serves the web player bundle the
+//! app id is read from, starts sessions, checks request signatures, replays
+//! recorded json responses and serves encrypted CMAF segments, so the client
+//! can be exercised without an account or network.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use aes::cipher::{BlockModeEncrypt, KeyIvInit, block_padding::Pkcs7};
+use axum::{
+    Form, Json, Router,
+    body::Bytes,
+    extract::{Path as UrlPath, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
+    routing::get,
+};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use parking_lot::Mutex;
+use qobuz_player_client::{client::Endpoints, stream::crypto::derive_session_key};
+use serde_json::{Value, json};
+use tokio::{net::TcpListener, task::JoinHandle};
+
+use crate::segments::{EncryptedTrack, encrypt_track};
+
+mod segments;
+
+/// App id found in the mocked web player bundle
+pub const APP_ID: &str = "123456789";
+/// Token accepted by the oauth callback. Any token is accepted by the api.
+pub const USER_AUTH_TOKEN: &str = "mock-user-auth-token";
+pub const USER_ID: i64 = 1;
+
+const APP_SECRET: &str = "0123456789abcdef0123456789abcdef";
+const RNG_INIT: &str = "abb21364945c0583309667d13ca3d93a";
+const BUNDLE_PATH: &str = "/resources/1.0.0-b001/bundle.js";
+const API_PATH: &str = "/api.json/0.2/";
+/// Host of the segment urls handed out by `file/url`. Clients are pointed
+/// back at the mock with [`Endpoints::segment_host`].
+const SEGMENT_HOST: &str = "https://streaming-qobuz-mock.invalid";
+const SESSION_LIFETIME_SECONDS: u64 = 60 * 60;
+
+/// Recorded responses and audio served by the mock.
+///
+/// Responses live in `api/<endpoint>.json`, for example `api/album/get.json`.
+/// A response in `api/<endpoint>/<value>.json` is preferred when `<value>`
+/// is one of the request parameters, so `api/album/get/abc.json` answers
+/// `album_id=abc`. Audio is read from `audio/<track id>.flac`, falling back
+/// to `audio/default.flac`. The FLAC files must start with STREAMINFO.
+#[derive(Debug, Clone)]
+pub struct Fixtures {
+    directory: PathBuf,
+}
+
+impl Fixtures {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+
+    /// The fixtures shipped with this crate
+    pub fn bundled() -> Self {
+        Self::new(Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures"))
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    fn response(&self, endpoint: &str, params: &HashMap<String, String>) -> Option<Value> {
+        let api_dir = self.directory.join("api");
+
+        let by_param = params
+            .values()
+            .filter(|value| is_file_name(value))
+            .map(|value| api_dir.join(endpoint).join(format!("{value}.json")))
+            .find(|path| path.is_file());
+
+        let path = by_param.unwrap_or_else(|| api_dir.join(format!("{endpoint}.json")));
+        let content = std::fs::read_to_string(path).ok()?;
+        let mut response = serde_json::from_str(&content).ok()?;
+
+        let offset = params.get("offset").and_then(|offset| offset.parse().ok());
+        let limit = params.get("limit").and_then(|limit| limit.parse().ok());
+        if offset.is_some() || limit.is_some() {
+            paginate(
+                &mut response,
+                offset.unwrap_or(0),
+                limit.unwrap_or(usize::MAX),
+            );
+        }
+
+        Some(response)
+    }
+
+    /// The unencrypted FLAC file for a track
+    pub fn audio(&self, track_id: u32) -> io::Result<Vec<u8>> {
+        let audio_dir = self.directory.join("audio");
+        let track_path = audio_dir.join(format!("{track_id}.flac"));
+
+        match track_path.is_file() {
+            true => std::fs::read(track_path),
+            false => std::fs::read(audio_dir.join("default.flac")),
+        }
+    }
+}
+
+/// Cut every list in a recorded response down to the requested page, the way
+/// the api pages through long lists
+fn paginate(value: &mut Value, offset: usize, limit: usize) {
+    let Value::Object(object) = value else {
+        return;
+    };
+
+    if let Some(Value::Array(items)) = object.get_mut("items") {
+        let total = items.len();
+        *items = items.drain(..).skip(offset).take(limit).collect();
+        let has_more = offset + items.len() < total;
+
+        if object.contains_key("offset") {
+            object.insert("offset".into(), json!(offset));
+        }
+        if object.contains_key("limit") {
+            object.insert("limit".into(), json!(limit.min(total)));
+        }
+        if object.contains_key("has_more") {
+            object.insert("has_more".into(), json!(has_more));
+        }
+    }
+
+    for (key, child) in object.iter_mut() {
+        if key != "items" {
+            paginate(child, offset, limit);
+        }
+    }
+}
+
+fn is_file_name(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// A running mock server. Stops when dropped.
+pub struct MockServer {
+    address: SocketAddr,
+    server: Option<JoinHandle<()>>,
+}
+
+impl MockServer {
+    /// Start on a free port on localhost
+    pub async fn start(fixtures: Fixtures) -> io::Result<Self> {
+        Self::bind(SocketAddr::from(([127, 0, 0, 1], 0)), fixtures).await
+    }
+
+    pub async fn bind(address: SocketAddr, fixtures: Fixtures) -> io::Result<Self> {
+        let listener = TcpListener::bind(address).await?;
+        let address = listener.local_addr()?;
+
+        let app = router(Arc::new(MockState {
+            fixtures,
+            sessions: Default::default(),
+            next_session: Default::default(),
+            tracks: Default::default(),
+        }));
+
+        let server = tokio::spawn(async move {
+            if let Err(err) = axum::serve(listener, app).await {
+                tracing::error!("Mock server stopped: {err}");
+            }
+        });
+
+        Ok(Self {
+            address,
+            server: Some(server),
+        })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.address)
+    }
+
+    /// Endpoints that send every request of the client to this server
+    pub fn endpoints(&self) -> Endpoints {
+        Endpoints {
+            api_base_url: format!("{}{API_PATH}", self.url()),
+            web_player_url: self.url(),
+            segment_host: Some(self.url()),
+        }
+    }
+
+    /// Wait until the server stops
+    pub async fn wait(mut self) {
+        if let Some(server) = self.server.take() {
+            _ = server.await;
+        }
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        if let Some(server) = self.server.take() {
+            server.abort();
+        }
+    }
+}
+
+struct MockState {
+    fixtures: Fixtures,
+    /// Session id to session key
+    sessions: Mutex<HashMap<String, [u8; 16]>>,
+    next_session: AtomicU64,
+    tracks: Mutex<HashMap<u32, Arc<EncryptedTrack>>>,
+}
+
+impl MockState {
+    #[allow(clippy::result_large_err)]
+    fn track(&self, track_id: u32) -> Result<Arc<EncryptedTrack>, Response> {
+        if let Some(track) = self.tracks.lock().get(&track_id) {
+            return Ok(track.clone());
+        }
+
+        let flac = self
+            .fixtures
+            .audio(track_id)
+            .map_err(|err| error(StatusCode::NOT_FOUND, &format!("No audio: {err}")))?;
+        let track = encrypt_track(track_id, &flac, &content_key(track_id))
+            .map_err(|err| error(StatusCode::INTERNAL_SERVER_ERROR, &err))?;
+
+        let track = Arc::new(track);
+        self.tracks.lock().insert(track_id, track.clone());
+        Ok(track)
+    }
+}
+
+fn router(state: Arc<MockState>) -> Router {
+    Router::new()
+        .route("/login", get(login_page))
+        .route("/resources/{version}/bundle.js", get(bundle))
+        .route("/api.json/0.2/{*endpoint}", get(api_get).post(api_post))
+        .route("/segments/{track_id}/{segment}", get(segment))
+        .with_state(state)
+}
+
+async fn login_page() -> Html<String> {
+    Html(format!(
+        r#"<html><head><script src="{BUNDLE_PATH}"></script></head><body></body></html>"#
+    ))
+}
+
+async fn bundle() -> String {
+    format!(r#"var config={{production:{{api:{{appId:"{APP_ID}",appSecret:"{APP_SECRET}"}}}}}};"#)
+}
+
+async fn api_get(
+    State(state): State<Arc<MockState>>,
+    UrlPath(endpoint): UrlPath<String>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    api(&state, &endpoint, &headers, params)
+}
+
+async fn api_post(
+    State(state): State<Arc<MockState>>,
+    UrlPath(endpoint): UrlPath<String>,
+    headers: HeaderMap,
+    Form(params): Form<HashMap<String, String>>,
+) -> Response {
+    api(&state, &endpoint, &headers, params)
+}
+
+fn api(
+    state: &MockState,
+    endpoint: &str,
+    headers: &HeaderMap,
+    params: HashMap<String, String>,
+) -> Response {
+    if header(headers, "X-App-Id") != Some(APP_ID) {
+        return error(
+            StatusCode::BAD_REQUEST,
+            "Invalid or missing app_id parameter",
+        );
+    }
+
+    if endpoint == "oauth/callback" {
+        return Json(json!({ "token": USER_AUTH_TOKEN, "user_id": USER_ID })).into_response();
+    }
+
+    if header(headers, "X-User-Auth-Token").is_none() {
+        return error(StatusCode::UNAUTHORIZED, "User authentication is required");
+    }
+
+    match endpoint {
+        "session/start" => start_session(state, &params),
+        "file/url" => file_url(state, headers, &params),
+        _ => match state.fixtures.response(endpoint, &params) {
+            Some(response) => Json(response).into_response(),
+            None => error(
+                StatusCode::NOT_FOUND,
+                &format!("No recorded response for {endpoint}"),
+            ),
+        },
+    }
+}
+
+fn start_session(state: &MockState, params: &HashMap<String, String>) -> Response {
+    let Some(profile) = params.get("profile") else {
+        return error(StatusCode::BAD_REQUEST, "Missing profile");
+    };
+    if let Err(response) = check_signature("sessionstart", &[("profile", profile)], params) {
+        return response;
+    }
+
+    let session_number = state.next_session.fetch_add(1, Ordering::Relaxed);
+    let session_id = format!("mock-session-{session_number}");
+    let salt = md5::compute(format!("salt-{session_id}")).0;
+    let info = md5::compute(format!("info-{session_id}")).0;
+    let infos = format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(salt),
+        URL_SAFE_NO_PAD.encode(info)
+    );
+
+    let session_key = match derive_session_key(&infos) {
+        Ok(key) => key,
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string()),
+    };
+    state
+        .sessions
+        .lock()
+        .insert(session_id.clone(), session_key);
+
+    Json(json!({
+        "session_id": session_id,
+        "expires_at": unix_now() + SESSION_LIFETIME_SECONDS,
+        "infos": infos,
+    }))
+    .into_response()
+}
+
+fn file_url(state: &MockState, headers: &HeaderMap, params: &HashMap<String, String>) -> Response {
+    let session_key = header(headers, "X-Session-Id")
+        .and_then(|session_id| state.sessions.lock().get(session_id).copied());
+    let Some(session_key) = session_key else {
+        return error(StatusCode::UNAUTHORIZED, "Invalid or expired session");
+    };
+
+    let (Some(format_id), Some(intent), Some(track_id)) = (
+        params.get("format_id"),
+        params.get("intent"),
+        params.get("track_id"),
+    ) else {
+        return error(StatusCode::BAD_REQUEST, "Missing parameters");
+    };
+
+    let signed = [
+        ("format_id", format_id.as_str()),
+        ("intent", intent.as_str()),
+        ("track_id", track_id.as_str()),
+    ];
+    if let Err(response) = check_signature("fileurl", &signed, params) {
+        return response;
+    }
+
+    let Ok(track_id) = track_id.parse::<u32>() else {
+        return error(StatusCode::BAD_REQUEST, "Invalid track_id");
+    };
+    let track = match state.track(track_id) {
+        Ok(track) => track,
+        Err(response) => return response,
+    };
+
+    let key_iv = md5::compute(format!("iv-{track_id}")).0;
+    let mut buffer = [0u8; 32];
+    buffer[..16].copy_from_slice(&content_key(track_id));
+    let wrapped = cbc::Encryptor::<aes::Aes128>::new((&session_key).into(), (&key_iv).into())
+        .encrypt_padded::<Pkcs7>(&mut buffer, 16)
+        .expect("buffer fits a padded block");
+
+    Json(json!({
+        "track_id": track_id,
+        "format_id": format_id.parse::<i64>().unwrap_or_default(),
+        "url_template": format!("{SEGMENT_HOST}/segments/{track_id}/$SEGMENT$"),
+        "mime_type": "audio/mp4; codecs=\"flac\"",
+        "n_segments": track.segments.len(),
+        "key_id": format!("mock-key-{track_id}"),
+        "key": format!(
+            "qbz-1.{}.{}",
+            URL_SAFE_NO_PAD.encode(wrapped),
+            URL_SAFE_NO_PAD.encode(key_iv)
+        ),
+        "sampling_rate": track.sample_rate,
+        "duration": track.total_samples as f64 / track.sample_rate.max(1) as f64,
+        "n_samples": track.total_samples,
+    }))
+    .into_response()
+}
+
+async fn segment(
+    State(state): State<Arc<MockState>>,
+    UrlPath((track_id, segment)): UrlPath<(u32, usize)>,
+) -> Response {
+    let track = match state.track(track_id) {
+        Ok(track) => track,
+        Err(response) => return response,
+    };
+
+    match track.segments.get(segment) {
+        Some(data) => Bytes::copy_from_slice(data).into_response(),
+        None => error(StatusCode::NOT_FOUND, "No such segment"),
+    }
+}
+
+/// Check `request_sig` the way Qobuz signs requests: md5 of the method,
+/// the sorted arguments, the timestamp and a fixed secret.
+#[allow(clippy::result_large_err)]
+fn check_signature(
+    method: &str,
+    signed: &[(&str, &str)],
+    params: &HashMap<String, String>,
+) -> Result<(), Response> {
+    let (Some(timestamp), Some(signature)) = (params.get("request_ts"), params.get("request_sig"))
+    else {
+        return Err(error(StatusCode::BAD_REQUEST, "Missing request signature"));
+    };
+
+    let arguments: BTreeMap<&str, &str> = signed.iter().copied().collect();
+    let arguments: String = arguments
+        .iter()
+        .map(|(key, value)| format!("{key}{value}"))
+        .collect();
+    let expected = format!(
+        "{:x}",
+        md5::compute(format!("{method}{arguments}{timestamp}{RNG_INIT}"))
+    );
+
+    match *signature == expected {
+        true => Ok(()),
+        false => Err(error(StatusCode::BAD_REQUEST, "Invalid request signature")),
+    }
+}
+
+/// Each track has its own fixed content key
+fn content_key(track_id: u32) -> [u8; 16] {
+    md5::compute(format!("content-key-{track_id}")).0
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+fn error(status: StatusCode, message: &str) -> Response {
+    (
+        status,
+        Json(json!({
+            "status": "error",
+            "code": status.as_u16(),
+            "message": message,
+        })),
+    )
+        .into_response()
+}
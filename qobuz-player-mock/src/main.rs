@@ -0,0 +1,38 @@
+use std::{net::SocketAddr, path::PathBuf};
+
+use clap::Parser;
+use qobuz_player_mock::{Fixtures, MockServer};
+
+#[derive(Parser)]
+#[clap(author, version, about, long_about = None)]
+struct Arguments {
+    #[clap(long, default_value_t = 9890)]
+    /// Port to listen on
+    port: u16,
+
+    #[clap(long)]
+    /// Directory with recorded responses and audio. Defaults to the bundled
+    /// fixtures.
+    fixtures: Option<PathBuf>,
+}
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    tracing_subscriber::fmt().compact().init();
+
+    let args = Arguments::parse();
+    let fixtures = args
+        .fixtures
+        .map(Fixtures::new)
+        .unwrap_or_else(Fixtures::bundled);
+
+    let server = MockServer::bind(SocketAddr::from(([127, 0, 0, 1], args.port)), fixtures).await?;
+    let endpoints = server.endpoints();
+
+    println!("Mock Qobuz api listening on {}", server.url());
+    println!("  api base url:   {}", endpoints.api_base_url);
+    println!("  web player url: {}", endpoints.web_player_url);
+
+    server.wait().await;
+    Ok(())
+}
@@ -0,0 +1,163 @@
+use qobuz_player_client::stream::crypto::decrypt_frame;
+
+const QBZ_INIT_UUID: [u8; 16] = [
+    0xc7, 0xc7, 0x5d, 0xf0, 0xfd, 0xd9, 0x51, 0xe9, 0x8f, 0xc2, 0x29, 0x71, 0xe4, 0xac, 0xf8, 0xd2,
+];
+const QBZ_SEGMENT_UUID: [u8; 16] = [
+    0x3b, 0x42, 0x12, 0x92, 0x56, 0xf3, 0x5f, 0x75, 0x92, 0x36, 0x63, 0xb6, 0x9a, 0x1f, 0x52, 0xb2,
+];
+
+/// Segments are cut so a track never needs more than this many
+const MAX_AUDIO_SEGMENTS: usize = 200;
+const MIN_SEGMENT_LEN: usize = 4096;
+/// Encrypted chunks within a segment
+const FRAME_LEN: usize = 1024;
+
+/// A FLAC file cut into CMAF segments the way Qobuz serves them: an init
+/// segment with the FLAC header and segment table, followed by audio segments
+/// with AES-CTR encrypted frames.
+pub(crate) struct EncryptedTrack {
+    pub(crate) segments: Vec<Vec<u8>>,
+    pub(crate) sample_rate: u32,
+    pub(crate) total_samples: u64,
+}
+
+pub(crate) fn encrypt_track(
+    track_id: u32,
+    flac: &[u8],
+    content_key: &[u8; 16],
+) -> Result<EncryptedTrack, String> {
+    let (header, audio) = split_flac(flac)?;
+    let stream_info = &header[8..42];
+    let sample_rate =
+        u32::from_be_bytes([0, stream_info[10], stream_info[11], stream_info[12]]) >> 4;
+    let channels = ((stream_info[12] >> 1) & 0x07) + 1;
+    let bits_per_sample = (((stream_info[12] & 0x01) << 4) | (stream_info[13] >> 4)) + 1;
+    let total_samples = u64::from_be_bytes([
+        0,
+        0,
+        0,
+        stream_info[13] & 0x0F,
+        stream_info[14],
+        stream_info[15],
+        stream_info[16],
+        stream_info[17],
+    ]);
+
+    let segment_len = audio
+        .len()
+        .div_ceil(MAX_AUDIO_SEGMENTS)
+        .max(MIN_SEGMENT_LEN);
+    let audio_segments: Vec<&[u8]> = audio.chunks(segment_len).collect();
+
+    let mut payload = Vec::new();
+    payload.extend_from_slice(&0u32.to_be_bytes());
+    payload.extend_from_slice(&track_id.to_be_bytes());
+    payload.extend_from_slice(&track_id.to_be_bytes());
+    payload.extend_from_slice(&sample_rate.to_be_bytes());
+    payload.push(bits_per_sample);
+    payload.extend_from_slice(&[channels, 0, 0]);
+    payload.extend_from_slice(&total_samples.to_be_bytes()[2..]);
+    payload.extend_from_slice(&(header.len() as u16).to_be_bytes());
+    payload.extend_from_slice(header);
+    let key_id = format!("mock-key-{track_id}");
+    payload.push(key_id.len() as u8);
+    payload.extend_from_slice(key_id.as_bytes());
+    payload.extend_from_slice(&(audio_segments.len() as u16).to_be_bytes());
+    for segment in &audio_segments {
+        let sample_count = total_samples * segment.len() as u64 / audio.len().max(1) as u64;
+        payload.extend_from_slice(&(segment.len() as u32).to_be_bytes());
+        payload.extend_from_slice(&(sample_count as u32).to_be_bytes());
+    }
+
+    let mut segments = vec![uuid_box(&QBZ_INIT_UUID, &payload)];
+    segments.extend(
+        audio_segments
+            .iter()
+            .enumerate()
+            .map(|(index, data)| audio_segment(index, data, content_key)),
+    );
+
+    Ok(EncryptedTrack {
+        segments,
+        sample_rate,
+        total_samples,
+    })
+}
+
+/// FLAC header, which must start with STREAMINFO, and the frames after the
+/// metadata blocks
+fn split_flac(flac: &[u8]) -> Result<(&[u8], &[u8]), String> {
+    if flac.len() < 42 || &flac[..4] != b"fLaC" || flac[4] & 0x7F != 0 {
+        return Err("Not a FLAC file starting with STREAMINFO".into());
+    }
+
+    let mut pos = 4;
+    loop {
+        if pos + 4 > flac.len() {
+            return Err("FLAC metadata truncated".into());
+        }
+        let last = flac[pos] & 0x80 != 0;
+        let len = u32::from_be_bytes([0, flac[pos + 1], flac[pos + 2], flac[pos + 3]]) as usize;
+        pos += 4 + len;
+        if last {
+            break;
+        }
+    }
+
+    if pos > flac.len() {
+        return Err("FLAC metadata truncated".into());
+    }
+
+    Ok((&flac[..42], &flac[pos..]))
+}
+
+fn audio_segment(index: usize, data: &[u8], content_key: &[u8; 16]) -> Vec<u8> {
+    let frames: Vec<&[u8]> = data.chunks(FRAME_LEN).collect();
+
+    let mut entries = Vec::new();
+    let mut encrypted = Vec::with_capacity(data.len());
+    for (frame_index, frame) in frames.iter().enumerate() {
+        let iv = (((index as u64) << 32) | frame_index as u64).to_be_bytes();
+        // Frames with zero flags are not encrypted, so serve both kinds
+        let flags: u16 = if frame_index % 2 == 0 { 1 } else { 0 };
+
+        let mut frame = frame.to_vec();
+        if flags != 0 {
+            // CTR mode, so decrypting encrypts
+            decrypt_frame(content_key, &iv, &mut frame);
+        }
+        encrypted.extend_from_slice(&frame);
+
+        entries.extend_from_slice(&(frame.len() as u32).to_be_bytes());
+        entries.extend_from_slice(&[0, 0]);
+        entries.extend_from_slice(&flags.to_be_bytes());
+        entries.extend_from_slice(&iv);
+    }
+
+    // version, data offset, iv size, frame count, entries
+    let uuid_box_len = 8 + 16 + 4 + 4 + 1 + 3 + entries.len();
+    let data_offset = uuid_box_len + 8;
+
+    let mut payload = Vec::new();
+    payload.extend_from_slice(&0u32.to_be_bytes());
+    payload.extend_from_slice(&(data_offset as u32).to_be_bytes());
+    payload.push(8);
+    payload.extend_from_slice(&(frames.len() as u32).to_be_bytes()[1..]);
+    payload.extend_from_slice(&entries);
+
+    let mut segment = uuid_box(&QBZ_SEGMENT_UUID, &payload);
+    segment.extend_from_slice(&((encrypted.len() + 8) as u32).to_be_bytes());
+    segment.extend_from_slice(b"mdat");
+    segment.extend_from_slice(&encrypted);
+    segment
+}
+
+fn uuid_box(uuid: &[u8; 16], payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(24 + payload.len());
+    out.extend_from_slice(&((24 + payload.len()) as u32).to_be_bytes());
+    out.extend_from_slice(b"uuid");
+    out.extend_from_slice(uuid);
+    out.extend_from_slice(payload);
+    out
+}
@@ -0,0 +1,531 @@
+use std::{sync::atomic::Ordering, time::Duration};
+
+use qobuz_player_controls::{Status, models::Link, tracklist::Tracklist};
+
+use crate::{
+    Session,
+    library::Source,
+    protocol::{Ack, arg, field, number, range, song, track_uri},
+};
+
+/// How long a queue edit may take before its reply is sent anyway
+const QUEUE_CHANGE_TIMEOUT: Duration = Duration::from_secs(5);
+
+const COMMANDS: &[&str] = &[
+    "add",
+    "addid",
+    "clear",
+    "close",
+    "command_list_begin",
+    "command_list_end",
+    "command_list_ok_begin",
+    "commands",
+    "consume",
+    "crossfade",
+    "currentsong",
+    "decoders",
+    "delete",
+    "deleteid",
+    "find",
+    "findadd",
+    "getvol",
+    "idle",
+    "list",
+    "listplaylist",
+    "listplaylistinfo",
+    "listplaylists",
+    "load",
+    "lsinfo",
+    "move",
+    "moveid",
+    "next",
+    "noidle",
+    "notcommands",
+    "outputs",
+    "password",
+    "pause",
+    "ping",
+    "play",
+    "playid",
+    "playlistid",
+    "playlistinfo",
+    "plchanges",
+    "plchangesposid",
+    "previous",
+    "random",
+    "repeat",
+    "replay_gain_status",
+    "search",
+    "searchadd",
+    "seek",
+    "seekcur",
+    "seekid",
+    "setvol",
+    "single",
+    "stats",
+    "status",
+    "stop",
+    "tagtypes",
+    "urlhandlers",
+    "volume",
+];
+
+/// What a client may run before it has sent the password
+const COMMANDS_BEFORE_PASSWORD: &[&str] = &["close", "commands", "notcommands", "password", "ping"];
+
+const TAG_TYPES: &[&str] = &[
+    "Artist",
+    "AlbumArtist",
+    "Album",
+    "Title",
+    "Track",
+    "Disc",
+    "Composer",
+    "Work",
+];
+
+impl Session {
+    /// Run one command. The reply is returned without the final `OK`.
+    pub(crate) async fn execute(&mut self, command: &str, args: &[String]) -> Result<String, Ack> {
+        let mut out = String::new();
+        let controls = &self.state.controls;
+
+        if !self.authorized && !COMMANDS_BEFORE_PASSWORD.contains(&command) {
+            return Err(Ack::permission(format!(
+                "you don't have permission for \"{command}\""
+            )));
+        }
+
+        match command {
+            "ping" | "clearerror" | "binarylimit" => {}
+            "password" => {
+                let password = arg(args, 0)?;
+                match &self.state.password {
+                    Some(expected) if expected == password => self.authorized = true,
+                    _ => return Err(Ack::password("incorrect password")),
+                }
+            }
+            "commands" => {
+                for command in COMMANDS {
+                    field(&mut out, "command", command);
+                }
+            }
+            "notcommands" | "decoders" => {}
+            "tagtypes" => {
+                if args.is_empty() {
+                    for tag in TAG_TYPES {
+                        field(&mut out, "tagtype", tag);
+                    }
+                }
+            }
+            "urlhandlers" => {
+                for handler in ["qobuz:", "https://"] {
+                    field(&mut out, "handler", handler);
+                }
+            }
+            "outputs" => {
+                field(&mut out, "outputid", 0);
+                field(&mut out, "outputname", "qobuz-player");
+                field(&mut out, "plugin", "rodio");
+                field(&mut out, "outputenabled", 1);
+            }
+            "replay_gain_status" => field(&mut out, "replay_gain_mode", "off"),
+            "status" => self.write_status(&mut out),
+            "stats" => {
+                for key in [
+                    "artists",
+                    "albums",
+                    "songs",
+                    "uptime",
+                    "playtime",
+                    "db_playtime",
+                ] {
+                    field(&mut out, key, 0);
+                }
+            }
+            "currentsong" => {
+                let tracklist = self.tracklist.borrow();
+                if let (Some(track), Some(queue_id)) =
+                    (tracklist.current_track(), tracklist.current_queue_id())
+                {
+                    song(
+                        &mut out,
+                        track,
+                        Some((tracklist.current_position(), queue_id)),
+                    );
+                }
+            }
+
+            "play" => {
+                if let Some(position) = args.first() {
+                    let position: usize = number(position)?;
+                    if position >= self.tracklist.borrow().total() {
+                        return Err(Ack::arg("Bad song index"));
+                    }
+                    controls.skip_to_position(position, true);
+                }
+                controls.play();
+            }
+            "playid" => {
+                if let Some(queue_id) = args.first() {
+                    let queue_id = self.existing_queue_id(queue_id)?;
+                    controls.skip_to_queue_item(queue_id);
+                }
+                controls.play();
+            }
+            "pause" => match args.first().map(String::as_str) {
+                Some("1") => controls.pause(),
+                Some("0") => controls.play(),
+                Some(state) => return Err(Ack::arg(format!("Boolean expected: {state}"))),
+                None => controls.play_pause(),
+            },
+            "stop" => controls.pause(),
+            "next" => controls.next(),
+            "previous" => controls.previous(),
+            "seekcur" => {
+                let time = arg(args, 0)?;
+                let seconds: f64 = number(time.trim_start_matches(['+', '-']))?;
+                let position = self.state.position_receiver.borrow().as_secs_f64();
+
+                let target = match time.chars().next() {
+                    Some('+') => position + seconds,
+                    Some('-') => (position - seconds).max(0.0),
+                    _ => seconds,
+                };
+                controls.seek(Duration::from_secs_f64(target));
+            }
+            "seek" | "seekid" => {
+                let song = arg(args, 0)?;
+                let seconds: f64 = number(arg(args, 1)?)?;
+
+                let tracklist = self.tracklist.borrow();
+                let is_current = match command {
+                    "seek" => {
+                        tracklist.current_queue_id().is_some()
+                            && number::<usize>(song)? == tracklist.current_position()
+                    }
+                    _ => Some(number::<u64>(song)?) == tracklist.current_queue_id(),
+                };
+
+                if !is_current {
+                    return Err(Ack::arg("Only the current song can be seeked"));
+                }
+                controls.seek(Duration::from_secs_f64(seconds.max(0.0)));
+            }
+            "setvol" => {
+                let volume: u32 = number(arg(args, 0)?)?;
+                if volume > 100 {
+                    return Err(Ack::arg("Invalid volume value"));
+                }
+                controls.set_volume(volume as f32 / 100.0);
+            }
+            "volume" => {
+                let change: i32 = number(arg(args, 0)?)?;
+                let volume = (*self.volume.borrow() * 100.0).round() as i32 + change;
+                controls.set_volume(volume.clamp(0, 100) as f32 / 100.0);
+            }
+            "getvol" => field(
+                &mut out,
+                "volume",
+                (*self.volume.borrow() * 100.0).round() as u32,
+            ),
+            "repeat" | "random" | "single" | "consume" | "crossfade" => {
+                if arg(args, 0)? != "0" {
+                    return Err(Ack::arg(format!("{command} is not supported")));
+                }
+            }
+
+            "playlistinfo" | "playlistid" | "plchanges" | "plchangesposid" => {
+                let tracklist = self.tracklist.borrow();
+                let queue = tracklist.queue();
+
+                let positions = match command {
+                    "playlistinfo" => match args.first() {
+                        Some(positions) => range(positions, queue.len())?,
+                        None => 0..queue.len(),
+                    },
+                    "playlistid" => match args.first() {
+                        Some(queue_id) => {
+                            let queue_id: u64 = number(queue_id)?;
+                            let position = tracklist
+                                .position_of_queue_id(queue_id)
+                                .ok_or_else(|| Ack::no_exist("No such song"))?;
+                            position..position + 1
+                        }
+                        None => 0..queue.len(),
+                    },
+                    // Versions are not kept, so any older version gets the whole queue
+                    _ => {
+                        let version: u32 = number(arg(args, 0)?)?;
+                        match version == self.state.playlist_version.load(Ordering::Relaxed) {
+                            true => 0..0,
+                            false => 0..queue.len(),
+                        }
+                    }
+                };
+
+                for position in positions {
+                    let item = queue[position];
+                    match command {
+                        "plchangesposid" => {
+                            field(&mut out, "cpos", position);
+                            field(&mut out, "Id", item.queue_id);
+                        }
+                        _ => song(&mut out, &item.track, Some((position, item.queue_id))),
+                    }
+                }
+            }
+            "add" | "addid" => {
+                let uri = arg(args, 0)?;
+                let next = match args.get(1) {
+                    Some(position) => self.is_next_position(position)?,
+                    None => false,
+                };
+
+                let source = self.resolve(uri).await?;
+                if command == "addid" && !matches!(&source, Source::Tracks(ids) if ids.len() == 1) {
+                    return Err(Ack::arg(format!("Not a song: {uri}")));
+                }
+
+                let queue_id = self.add(source, next).await?;
+                if command == "addid" {
+                    field(&mut out, "Id", queue_id);
+                }
+            }
+            "searchadd" | "findadd" => {
+                let ids = self
+                    .find(args, command == "findadd")
+                    .await?
+                    .into_iter()
+                    .map(|track| track.id)
+                    .collect::<Vec<_>>();
+                if !ids.is_empty() {
+                    self.add(Source::Tracks(ids), false).await?;
+                }
+            }
+            "load" => {
+                let playlist = self.stored_playlist(arg(args, 0)?).await?;
+                self.add(Source::Link(Link::Playlist(playlist.id)), false)
+                    .await?;
+            }
+            "delete" | "deleteid" => {
+                let queue_ids: Vec<u64> = match command {
+                    "delete" => {
+                        let tracklist = self.tracklist.borrow();
+                        let queue = tracklist.queue();
+                        queue[range(arg(args, 0)?, queue.len())?]
+                            .iter()
+                            .map(|item| item.queue_id)
+                            .collect()
+                    }
+                    _ => vec![self.existing_queue_id(arg(args, 0)?)?],
+                };
+
+                for queue_id in &queue_ids {
+                    controls.remove_queue_item(*queue_id);
+                }
+                self.wait_for_queue(|tracklist| {
+                    queue_ids
+                        .iter()
+                        .all(|id| tracklist.position_of_queue_id(*id).is_none())
+                })
+                .await;
+            }
+            "move" | "moveid" => {
+                let (positions, total) = {
+                    let tracklist = self.tracklist.borrow();
+                    let total = tracklist.total();
+                    let from = arg(args, 0)?;
+                    let positions = match command {
+                        "move" => range(from, total)?,
+                        _ => {
+                            let queue_id: u64 = number(from)?;
+                            let position = tracklist
+                                .position_of_queue_id(queue_id)
+                                .ok_or_else(|| Ack::no_exist("No such song"))?;
+                            position..position + 1
+                        }
+                    };
+                    (positions, total)
+                };
+
+                let to: usize = number(arg(args, 1)?)?;
+                if to + positions.len() > total {
+                    return Err(Ack::arg("Bad song index"));
+                }
+
+                let mut new_order: Vec<usize> =
+                    (0..total).filter(|x| !positions.contains(x)).collect();
+                new_order.splice(to..to, positions);
+
+                let expected: Vec<u64> = {
+                    let tracklist = self.tracklist.borrow();
+                    let queue = tracklist.queue();
+                    new_order.iter().map(|&x| queue[x].queue_id).collect()
+                };
+
+                controls.reorder_queue(new_order);
+                self.wait_for_queue(|tracklist| crate::queue_ids(tracklist) == expected)
+                    .await;
+            }
+            "clear" => {
+                controls.clear_queue();
+                self.wait_for_queue(|tracklist| tracklist.total() == 0)
+                    .await;
+            }
+
+            "lsinfo" => {
+                let uri = args.first().map(String::as_str).unwrap_or_default();
+                self.lsinfo(uri, &mut out).await?;
+            }
+            "listplaylists" => self.list_playlists(&mut out).await?,
+            "listplaylist" | "listplaylistinfo" => {
+                let playlist = self.stored_playlist(arg(args, 0)?).await?;
+                for track in &playlist.tracks {
+                    match command {
+                        "listplaylist" => field(&mut out, "file", track_uri(track)),
+                        _ => song(&mut out, track, None),
+                    }
+                }
+            }
+            "find" | "search" => {
+                for track in self.find(args, command == "find").await? {
+                    song(&mut out, &track, None);
+                }
+            }
+            "list" => self.list(args, &mut out).await?,
+
+            _ => return Err(Ack::unknown(format!("unknown command \"{command}\""))),
+        }
+
+        Ok(out)
+    }
+
+    fn write_status(&self, out: &mut String) {
+        let tracklist = self.tracklist.borrow();
+        let status = *self.status.borrow();
+        let volume = (*self.volume.borrow() * 100.0).round() as u32;
+
+        field(out, "volume", volume);
+        for mode in ["repeat", "random", "single", "consume"] {
+            field(out, mode, 0);
+        }
+        field(
+            out,
+            "playlist",
+            self.state.playlist_version.load(Ordering::Relaxed),
+        );
+        field(out, "playlistlength", tracklist.total());
+
+        let (Some(track), Some(queue_id)) =
+            (tracklist.current_track(), tracklist.current_queue_id())
+        else {
+            field(out, "state", "stop");
+            return;
+        };
+
+        let state = match status {
+            Status::Paused => "pause",
+            Status::Playing | Status::Buffering => "play",
+        };
+        field(out, "state", state);
+
+        let position = tracklist.current_position();
+        let elapsed = self.state.position_receiver.borrow().as_secs_f64();
+        field(out, "song", position);
+        field(out, "songid", queue_id);
+        field(
+            out,
+            "time",
+            format!("{}:{}", elapsed.round(), track.duration_seconds),
+        );
+        field(out, "elapsed", format!("{elapsed:.3}"));
+        field(out, "duration", format!("{}.000", track.duration_seconds));
+
+        if let (Some(bit_depth), Some(sample_rate)) = (track.bit_depth, track.sample_rate) {
+            let sample_rate = (sample_rate * 1000.0).round() as u32;
+            field(out, "audio", format!("{sample_rate}:{bit_depth}:2"));
+        }
+
+        if let Some(next_queue_id) = tracklist.next_track_queue_id() {
+            field(out, "nextsong", position + 1);
+            field(out, "nextsongid", next_queue_id);
+        }
+    }
+
+    fn existing_queue_id(&self, queue_id: &str) -> Result<u64, Ack> {
+        let queue_id: u64 = number(queue_id)?;
+        match self.tracklist.borrow().position_of_queue_id(queue_id) {
+            Some(_) => Ok(queue_id),
+            None => Err(Ack::no_exist("No such song")),
+        }
+    }
+
+    /// Songs go at the end of the queue or right after the current song,
+    /// given as `+0` or as its absolute position. Without a current song
+    /// `+0` is the end of the queue.
+    fn is_next_position(&self, position: &str) -> Result<bool, Ack> {
+        let tracklist = self.tracklist.borrow();
+        if position == "+0" {
+            return Ok(tracklist.current_queue_id().is_some());
+        }
+
+        let position: usize = number(position)?;
+        if position == tracklist.total() {
+            Ok(false)
+        } else if tracklist.current_queue_id().is_some()
+            && position == tracklist.current_position() + 1
+        {
+            Ok(true)
+        } else {
+            Err(Ack::arg(
+                "Songs can only be added after the current song or at the end",
+            ))
+        }
+    }
+
+    /// Add to the queue and return the queue id of the first added song, as
+    /// found in the queue once the player has added it
+    async fn add(&mut self, source: Source, next: bool) -> Result<u64, Ack> {
+        let controls = &self.state.controls;
+        let before = crate::queue_ids(&self.tracklist.borrow());
+
+        match (source, next) {
+            (Source::Tracks(ids), _) if ids.is_empty() => {
+                return Err(Ack::no_exist("No songs to add"));
+            }
+            (Source::Tracks(ids), true) => controls.play_tracks_next(ids),
+            (Source::Tracks(ids), false) => controls.add_tracks_to_queue(ids),
+            (Source::Link(link), false) => controls.queue_link(link),
+            (Source::Link(_), true) => {
+                return Err(Ack::arg("Only songs can be added after the current song"));
+            }
+        }
+
+        let added = |tracklist: &Tracklist| {
+            tracklist
+                .queue()
+                .iter()
+                .map(|item| item.queue_id)
+                .find(|queue_id| !before.contains(queue_id))
+        };
+
+        match self
+            .wait_for_queue(|tracklist| added(tracklist).is_some())
+            .await
+        {
+            true => added(&self.tracklist.borrow()).ok_or_else(|| Ack::system("Nothing was added")),
+            false => Err(Ack::system("The player did not add the songs in time")),
+        }
+    }
+
+    /// Give the player a moment to apply a queue edit, so replies to the
+    /// following commands already include it. Returns false when it took
+    /// too long.
+    async fn wait_for_queue(&mut self, done: impl FnMut(&Tracklist) -> bool) -> bool {
+        matches!(
+            tokio::time::timeout(QUEUE_CHANGE_TIMEOUT, self.tracklist.wait_for(done)).await,
+            Ok(Ok(_))
+        )
+    }
+}
@@ -0,0 +1,589 @@
+//! A server for the MPD protocol, so MPD clients like mpc, ncmpcpp or MALP
+//! can drive the player.
+//!
+//! Songs are named by `qobuz:track:<id>` uris and the queue ids of the player
+//! are the song ids. The database is the Qobuz favorites, browsed as the
+//! `Albums`, `Playlists` and `Tracks` directories, and searches go to Qobuz.
+//! The favorite playlists are the stored playlists. Repeat, random, single
+//! and consume modes are not supported, and `stop` pauses.
+
+use std::{
+    io,
+    net::SocketAddr,
+    sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    },
+};
+
+use qobuz_player_controls::{
+    AppResult, PositionReceiver, Status, StatusReceiver, TracklistReceiver, VolumeReceiver,
+    client::Client, controls::Controls, error::Error, tracklist::Tracklist,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines},
+    net::{
+        TcpListener, TcpStream,
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+    },
+};
+
+use crate::protocol::tokenize;
+
+mod commands;
+mod library;
+mod protocol;
+
+const GREETING: &str = "OK MPD 0.23.5\n";
+
+pub struct MpdConfig {
+    /// Loopback by default, as MPD clients can play, queue and change
+    /// anything
+    pub address: SocketAddr,
+    /// Clients have to send it with `password` before any other command
+    pub password: Option<String>,
+}
+
+struct State {
+    password: Option<String>,
+    client: Arc<Client>,
+    controls: Controls,
+    position_receiver: PositionReceiver,
+    tracklist_receiver: TracklistReceiver,
+    volume_receiver: VolumeReceiver,
+    status_receiver: StatusReceiver,
+    /// Bumped on every queue change, reported as `playlist` in `status`
+    playlist_version: AtomicU32,
+}
+
+/// Serve the MPD protocol on the configured address
+pub async fn init(
+    config: MpdConfig,
+    client: Arc<Client>,
+    controls: Controls,
+    position_receiver: PositionReceiver,
+    tracklist_receiver: TracklistReceiver,
+    volume_receiver: VolumeReceiver,
+    status_receiver: StatusReceiver,
+) -> AppResult<()> {
+    let listener = TcpListener::bind(config.address)
+        .await
+        .or(Err(Error::PortInUse {
+            port: config.address.port(),
+        }))?;
+    tracing::info!("MPD server listening on {}", config.address);
+
+    let state = Arc::new(State {
+        password: config.password,
+        client,
+        controls,
+        position_receiver,
+        tracklist_receiver,
+        volume_receiver,
+        status_receiver,
+        playlist_version: AtomicU32::new(1),
+    });
+
+    tokio::spawn(count_playlist_versions(state.clone()));
+
+    loop {
+        let (stream, address) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(err) => {
+                tracing::warn!("Unable to accept MPD connection: {err}");
+                continue;
+            }
+        };
+        let state = state.clone();
+
+        tokio::spawn(async move {
+            if let Err(err) = handle_connection(stream, state).await {
+                tracing::debug!("MPD connection from {address} closed: {err}");
+            }
+        });
+    }
+}
+
+fn queue_ids(tracklist: &Tracklist) -> Vec<u64> {
+    tracklist.queue().iter().map(|item| item.queue_id).collect()
+}
+
+async fn count_playlist_versions(state: Arc<State>) {
+    let mut tracklist = state.tracklist_receiver.clone();
+    let mut current = queue_ids(&tracklist.borrow_and_update());
+
+    while tracklist.changed().await.is_ok() {
+        let queue = queue_ids(&tracklist.borrow_and_update());
+        if queue != current {
+            current = queue;
+            state.playlist_version.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// What an idling client has been told about
+struct Snapshot {
+    queue: Vec<u64>,
+    current: Option<u64>,
+    status: Status,
+    volume: f32,
+}
+
+impl Snapshot {
+    /// The MPD subsystems that differ from `other`
+    fn changed(&self, other: &Snapshot) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        if self.queue != other.queue {
+            changed.push("playlist");
+        }
+        if self.current != other.current || self.status != other.status {
+            changed.push("player");
+        }
+        if self.volume != other.volume {
+            changed.push("mixer");
+        }
+        changed
+    }
+
+    /// Take over the parts of `other` belonging to the reported subsystems
+    fn acknowledge(&mut self, other: Snapshot, subsystems: &[&str]) {
+        if subsystems.contains(&"playlist") {
+            self.queue = other.queue;
+        }
+        if subsystems.contains(&"player") {
+            self.current = other.current;
+            self.status = other.status;
+        }
+        if subsystems.contains(&"mixer") {
+            self.volume = other.volume;
+        }
+    }
+}
+
+/// One client connection
+struct Session {
+    state: Arc<State>,
+    tracklist: TracklistReceiver,
+    status: StatusReceiver,
+    volume: VolumeReceiver,
+    seen: Snapshot,
+    /// Whether the client may run more than the commands allowed before the
+    /// password
+    authorized: bool,
+}
+
+impl Session {
+    fn new(state: Arc<State>) -> Self {
+        let mut session = Self {
+            tracklist: state.tracklist_receiver.clone(),
+            status: state.status_receiver.clone(),
+            volume: state.volume_receiver.clone(),
+            authorized: state.password.is_none(),
+            state,
+            seen: Snapshot {
+                queue: Vec::new(),
+                current: None,
+                status: Status::Paused,
+                volume: 0.0,
+            },
+        };
+        session.seen = session.snapshot();
+        session
+    }
+
+    fn snapshot(&mut self) -> Snapshot {
+        let tracklist = self.tracklist.borrow_and_update();
+
+        Snapshot {
+            queue: queue_ids(&tracklist),
+            current: tracklist.current_queue_id(),
+            status: *self.status.borrow_and_update(),
+            volume: *self.volume.borrow_and_update(),
+        }
+    }
+
+    async fn run(&mut self, command: &str, args: &[String]) -> String {
+        match self.execute(command, args).await {
+            Ok(mut response) => {
+                response.push_str("OK\n");
+                response
+            }
+            Err(ack) => ack.line(0, command),
+        }
+    }
+
+    /// Run a command list, stopping at the first failing command
+    async fn run_list(&mut self, commands: Vec<Vec<String>>, list_ok: bool) -> String {
+        let mut response = String::new();
+
+        for (index, command) in commands.iter().enumerate() {
+            let Some((name, args)) = command.split_first() else {
+                continue;
+            };
+
+            match self.execute(name, args).await {
+                Ok(output) => response.push_str(&output),
+                Err(ack) => {
+                    response.push_str(&ack.line(index, name));
+                    return response;
+                }
+            }
+
+            if list_ok {
+                response.push_str("list_OK\n");
+            }
+        }
+
+        response.push_str("OK\n");
+        response
+    }
+
+    /// Wait for a change in one of `subsystems`, or any when empty, until the
+    /// client sends `noidle`. Returns false when the connection should close.
+    async fn idle(
+        &mut self,
+        subsystems: &[String],
+        lines: &mut Lines<BufReader<OwnedReadHalf>>,
+        writer: &mut OwnedWriteHalf,
+    ) -> io::Result<bool> {
+        loop {
+            let current = self.snapshot();
+            let changed: Vec<_> = self
+                .seen
+                .changed(&current)
+                .into_iter()
+                .filter(|name| subsystems.is_empty() || subsystems.iter().any(|x| x == name))
+                .collect();
+
+            if !changed.is_empty() {
+                let mut response = String::new();
+                for name in &changed {
+                    response.push_str(&format!("changed: {name}\n"));
+                }
+                response.push_str("OK\n");
+
+                self.seen.acknowledge(current, &changed);
+                writer.write_all(response.as_bytes()).await?;
+                return Ok(true);
+            }
+
+            tokio::select! {
+                line = lines.next_line() => {
+                    // Only noidle is allowed while idling, anything else ends the connection like in MPD
+                    return match line? {
+                        Some(line) if line.trim() == "noidle" => {
+                            writer.write_all(b"OK\n").await?;
+                            Ok(true)
+                        }
+                        _ => Ok(false),
+                    };
+                }
+                changed = self.tracklist.changed() => {
+                    if changed.is_err() {
+                        return Ok(false);
+                    }
+                }
+                changed = self.status.changed() => {
+                    if changed.is_err() {
+                        return Ok(false);
+                    }
+                }
+                changed = self.volume.changed() => {
+                    if changed.is_err() {
+                        return Ok(false);
+                    }
+                }
+            }
+        }
+    }
+}
+
+async fn handle_connection(stream: TcpStream, state: Arc<State>) -> io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    let mut session = Session::new(state);
+
+    writer.write_all(GREETING.as_bytes()).await?;
+
+    // Commands collected after command_list_begin, and whether each gets a list_OK
+    let mut command_list: Option<(Vec<Vec<String>>, bool)> = None;
+
+    while let Some(line) = lines.next_line().await? {
+        let args = match tokenize(&line) {
+            Ok(args) => args,
+            Err(ack) => {
+                writer.write_all(ack.line(0, "").as_bytes()).await?;
+                continue;
+            }
+        };
+        let Some((command, args)) = args.split_first() else {
+            continue;
+        };
+
+        if let Some((mut commands, list_ok)) = command_list.take() {
+            if command == "command_list_end" {
+                let response = session.run_list(commands, list_ok).await;
+                writer.write_all(response.as_bytes()).await?;
+            } else {
+                commands.push(
+                    std::iter::once(command.clone())
+                        .chain(args.to_vec())
+                        .collect(),
+                );
+                command_list = Some((commands, list_ok));
+            }
+            continue;
+        }
+
+        let response = match command.as_str() {
+            "command_list_begin" => {
+                command_list = Some((Vec::new(), false));
+                continue;
+            }
+            "command_list_ok_begin" => {
+                command_list = Some((Vec::new(), true));
+                continue;
+            }
+            "idle" if session.authorized => {
+                if !session.idle(args, &mut lines, &mut writer).await? {
+                    return Ok(());
+                }
+                continue;
+            }
+            "noidle" => continue,
+            "close" => return Ok(()),
+            _ => session.run(command, args).await,
+        };
+
+        writer.write_all(response.as_bytes()).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use qobuz_player_controls::{
+        AudioQuality,
+        controls::ControlCommand,
+        models::Track,
+        tracklist::{QueueItem, TracklistType},
+    };
+    use tokio::{
+        io::AsyncReadExt,
+        sync::{mpsc, watch},
+    };
+
+    use super::*;
+
+    async fn read_response(stream: &mut TcpStream) -> String {
+        let mut response = String::new();
+        while !(response.ends_with("OK\n")
+            || response.starts_with("OK MPD ") && response.ends_with('\n')
+            || response.contains("ACK ["))
+        {
+            let mut buffer = [0; 1024];
+            let read = stream.read(&mut buffer).await.unwrap();
+            assert!(read > 0, "connection closed");
+            response.push_str(std::str::from_utf8(&buffer[..read]).unwrap());
+        }
+        response
+    }
+
+    async fn send(stream: &mut TcpStream, command: &str) -> String {
+        stream
+            .write_all(format!("{command}\n").as_bytes())
+            .await
+            .unwrap();
+        read_response(stream).await
+    }
+
+    #[tokio::test]
+    async fn commands_over_tcp() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let (_position_tx, position_receiver) = watch::channel(Duration::default());
+        let (_tracklist_tx, tracklist_receiver) = watch::channel(Tracklist::default());
+        let (volume_tx, volume_receiver) = watch::channel(0.5);
+        let (_status_tx, status_receiver) = watch::channel(Status::Paused);
+
+        let state = Arc::new(State {
+            password: None,
+            client: Arc::new(Client::new(None, AudioQuality::Mp3)),
+            controls: Controls::new(tx),
+            position_receiver,
+            tracklist_receiver,
+            volume_receiver,
+            status_receiver,
+            playlist_version: AtomicU32::new(1),
+        });
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            handle_connection(stream, state).await.unwrap();
+        });
+
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        assert_eq!(read_response(&mut stream).await, GREETING);
+
+        let status = send(&mut stream, "status").await;
+        assert!(status.contains("volume: 50\n"));
+        assert!(status.contains("state: stop\n"));
+        assert!(status.ends_with("OK\n"));
+
+        assert_eq!(send(&mut stream, "setvol 20").await, "OK\n");
+        assert!(matches!(
+            rx.recv().await,
+            Some(ControlCommand::SetVolume { volume }) if volume == 0.2
+        ));
+
+        stream
+            .write_all(b"command_list_ok_begin\nping\npause 1\ncommand_list_end\n")
+            .await
+            .unwrap();
+        assert_eq!(read_response(&mut stream).await, "list_OK\nlist_OK\nOK\n");
+        assert!(matches!(rx.recv().await, Some(ControlCommand::Pause)));
+
+        assert_eq!(
+            send(&mut stream, "rewind").await,
+            "ACK [5@0] {rewind} unknown command \"rewind\"\n"
+        );
+        assert_eq!(
+            send(&mut stream, "play 3").await,
+            "ACK [2@0] {play} Bad song index\n"
+        );
+
+        stream.write_all(b"idle mixer\n").await.unwrap();
+        volume_tx.send(0.2).unwrap();
+        assert_eq!(read_response(&mut stream).await, "changed: mixer\nOK\n");
+
+        stream.write_all(b"idle\n").await.unwrap();
+        assert_eq!(send(&mut stream, "noidle").await, "OK\n");
+    }
+
+    fn new_session(
+        password: Option<&str>,
+        tracklist: Tracklist,
+    ) -> (
+        Session,
+        mpsc::UnboundedReceiver<ControlCommand>,
+        watch::Sender<Tracklist>,
+    ) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let (tracklist_tx, tracklist_receiver) = watch::channel(tracklist);
+
+        let session = Session::new(Arc::new(State {
+            password: password.map(str::to_string),
+            client: Arc::new(Client::new(None, AudioQuality::Mp3)),
+            controls: Controls::new(tx),
+            position_receiver: watch::channel(Duration::default()).1,
+            tracklist_receiver,
+            volume_receiver: watch::channel(0.5).1,
+            status_receiver: watch::channel(Status::Paused).1,
+            playlist_version: AtomicU32::new(1),
+        }));
+
+        (session, rx, tracklist_tx)
+    }
+
+    fn queue_item(queue_id: u64, track_id: u32) -> QueueItem {
+        QueueItem {
+            track: Track {
+                id: track_id,
+                ..Default::default()
+            },
+            queue_id,
+            index: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn password_guards_commands() {
+        let (mut session, _rx, _tracklist_tx) = new_session(Some("secret"), Tracklist::default());
+
+        assert_eq!(
+            session.run("status", &[]).await,
+            "ACK [4@0] {status} you don't have permission for \"status\"\n"
+        );
+        assert_eq!(session.run("ping", &[]).await, "OK\n");
+        assert_eq!(
+            session.run("password", &["wrong".into()]).await,
+            "ACK [3@0] {password} incorrect password\n"
+        );
+        assert_eq!(session.run("password", &["secret".into()]).await, "OK\n");
+        assert!(session.run("status", &[]).await.ends_with("OK\n"));
+
+        let (mut without_password, _rx, _tracklist_tx) = new_session(None, Tracklist::default());
+        assert!(without_password.run("status", &[]).await.ends_with("OK\n"));
+        assert_eq!(
+            without_password.run("password", &["secret".into()]).await,
+            "ACK [3@0] {password} incorrect password\n"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn addid_reports_the_id_the_player_gave() {
+        let (mut session, mut rx, tracklist_tx) = new_session(
+            None,
+            Tracklist::new(TracklistType::Tracks, vec![queue_item(3, 100)]),
+        );
+
+        let player = tokio::spawn(async move {
+            let Some(ControlCommand::AddTracksToQueue { ids }) = rx.recv().await else {
+                panic!("expected the songs to be queued");
+            };
+            assert_eq!(ids, vec![5]);
+            tracklist_tx
+                .send(Tracklist::new(
+                    TracklistType::Tracks,
+                    vec![queue_item(3, 100), queue_item(9, 5)],
+                ))
+                .unwrap();
+
+            // The second song never makes it to the queue
+            rx.recv().await;
+            tracklist_tx
+        });
+
+        assert_eq!(
+            session.run("addid", &["qobuz:track:5".into()]).await,
+            "Id: 9\nOK\n"
+        );
+        assert_eq!(
+            session.run("addid", &["qobuz:track:6".into()]).await,
+            "ACK [52@0] {addid} The player did not add the songs in time\n"
+        );
+
+        player.await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn add_next_without_a_current_song_appends() {
+        let (mut session, mut rx, tracklist_tx) = new_session(None, Tracklist::default());
+
+        let player = tokio::spawn(async move {
+            let Some(ControlCommand::AddTracksToQueue { ids }) = rx.recv().await else {
+                panic!("expected the songs to be appended");
+            };
+            assert_eq!(ids, vec![1]);
+            tracklist_tx
+                .send(Tracklist::new(
+                    TracklistType::Tracks,
+                    vec![queue_item(0, 1)],
+                ))
+                .unwrap();
+        });
+
+        assert_eq!(
+            session
+                .run("add", &["qobuz:track:1".into(), "+0".into()])
+                .await,
+            "OK\n"
+        );
+
+        player.await.unwrap();
+    }
+}
@@ -0,0 +1,292 @@
+use qobuz_player_controls::models::{AlbumSimple, Link, Playlist, Track};
+
+use crate::{
+    Session,
+    protocol::{Ack, field, filters, song, track_uri},
+};
+
+const ALBUMS: &str = "Albums";
+const PLAYLISTS: &str = "Playlists";
+const TRACKS: &str = "Tracks";
+
+/// What a uri or library path stands for when it is added to the queue
+pub(crate) enum Source {
+    Tracks(Vec<u32>),
+    Link(Link),
+}
+
+fn album_path(album: &AlbumSimple) -> String {
+    let name = format!("{} - {}", album.artist.name, album.title);
+    format!("{ALBUMS}/{}", name.replace('/', "-"))
+}
+
+fn playlist_path(playlist: &Playlist) -> String {
+    format!("{PLAYLISTS}/{}", playlist.title.replace('/', "-"))
+}
+
+fn is_uri(uri: &str) -> bool {
+    uri.starts_with("qobuz:") || uri.starts_with("https://") || uri.starts_with("http://")
+}
+
+impl Session {
+    /// Resolve a `qobuz:` uri, a share link or a path in the favorites
+    pub(crate) async fn resolve(&self, uri: &str) -> Result<Source, Ack> {
+        if is_uri(uri) {
+            return match Link::parse(uri)? {
+                Link::Track(id) => Ok(Source::Tracks(vec![id])),
+                link => Ok(Source::Link(link)),
+            };
+        }
+
+        let uri = uri.trim_matches('/');
+        let favorites = self.state.client.favorites().await?;
+
+        if uri == TRACKS {
+            let ids = favorites.tracks.iter().map(|track| track.id).collect();
+            return Ok(Source::Tracks(ids));
+        }
+
+        if let Some(album) = favorites.albums.iter().find(|x| album_path(x) == uri) {
+            return Ok(Source::Link(Link::Album(album.id.clone())));
+        }
+
+        if let Some(playlist) = favorites.playlists.iter().find(|x| playlist_path(x) == uri) {
+            return Ok(Source::Link(Link::Playlist(playlist.id)));
+        }
+
+        Err(Ack::no_exist(format!("No such directory: {uri}")))
+    }
+
+    /// List a directory of the favorites, or a single song by its uri
+    pub(crate) async fn lsinfo(&self, uri: &str, out: &mut String) -> Result<(), Ack> {
+        if is_uri(uri) {
+            let Link::Track(id) = Link::parse(uri)? else {
+                return Err(Ack::no_exist(format!("Not a song: {uri}")));
+            };
+            song(out, &self.state.client.track(id).await?, None);
+            return Ok(());
+        }
+
+        let uri = uri.trim_matches('/');
+        if uri.is_empty() {
+            for directory in [ALBUMS, PLAYLISTS, TRACKS] {
+                field(out, "directory", directory);
+            }
+            return Ok(());
+        }
+
+        let favorites = self.state.client.favorites().await?;
+        match uri {
+            ALBUMS => {
+                for album in &favorites.albums {
+                    field(out, "directory", album_path(album));
+                }
+            }
+            PLAYLISTS => {
+                for playlist in &favorites.playlists {
+                    field(out, "directory", playlist_path(playlist));
+                }
+            }
+            TRACKS => {
+                for track in &favorites.tracks {
+                    song(out, track, None);
+                }
+            }
+            _ => {
+                for track in self.directory_tracks(uri).await? {
+                    song(out, &track, None);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn directory_tracks(&self, uri: &str) -> Result<Vec<Track>, Ack> {
+        match self.resolve(uri).await? {
+            Source::Link(Link::Album(id)) => Ok(self.state.client.album(&id).await?.tracks),
+            Source::Link(Link::Playlist(id)) => Ok(self.state.client.playlist(id).await?.tracks),
+            _ => Err(Ack::no_exist(format!("No such directory: {uri}"))),
+        }
+    }
+
+    /// The favorite playlists are the stored playlists
+    pub(crate) async fn list_playlists(&self, out: &mut String) -> Result<(), Ack> {
+        for playlist in self.state.client.favorites().await?.playlists {
+            field(out, "playlist", playlist.title);
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn stored_playlist(&self, name: &str) -> Result<Playlist, Ack> {
+        let favorites = self.state.client.favorites().await?;
+        let playlist = favorites
+            .playlists
+            .into_iter()
+            .find(|playlist| playlist.title == name)
+            .ok_or_else(|| Ack::no_exist(format!("No such playlist: {name}")))?;
+
+        Ok(self.state.client.playlist(playlist.id).await?)
+    }
+
+    /// Songs matching the filters. `find` matches whole values and `search`
+    /// parts of them, both ignoring case. An album filter naming a favorite
+    /// album is answered from that album, everything else from Qobuz search.
+    pub(crate) async fn find(&self, args: &[String], exact: bool) -> Result<Vec<Track>, Ack> {
+        let filters = filters(args)?;
+        if filters.is_empty() {
+            return Err(Ack::arg("Too few arguments"));
+        }
+
+        let tracks = match filters.iter().find(|(tag, _)| tag == "file") {
+            Some((_, uri)) => match Link::parse(uri)? {
+                Link::Track(id) => vec![self.state.client.track(id).await?],
+                _ => return Err(Ack::no_exist(format!("Not a song: {uri}"))),
+            },
+            None => match self.favorite_album(&filters).await? {
+                Some(album) => self.state.client.album(&album.id).await?.tracks,
+                None => {
+                    let query: Vec<&str> =
+                        filters.iter().map(|(_, value)| value.as_str()).collect();
+                    self.state.client.search(query.join(" ")).await?.tracks
+                }
+            },
+        };
+
+        let mut found = Vec::new();
+        for track in tracks {
+            if matches(&track, &filters, exact)? {
+                found.push(track);
+            }
+        }
+        Ok(found)
+    }
+
+    async fn favorite_album(
+        &self,
+        filters: &[(String, String)],
+    ) -> Result<Option<AlbumSimple>, Ack> {
+        let Some((_, title)) = filters.iter().find(|(tag, _)| tag == "album") else {
+            return Ok(None);
+        };
+
+        let favorites = self.state.client.favorites().await?;
+        Ok(favorites
+            .albums
+            .into_iter()
+            .find(|album| album.title.eq_ignore_ascii_case(title)))
+    }
+
+    /// Values of a tag across the favorites, narrowed by filters
+    pub(crate) async fn list(&self, args: &[String], out: &mut String) -> Result<(), Ack> {
+        let Some((tag, args)) = args.split_first() else {
+            return Err(Ack::arg("Too few arguments"));
+        };
+        let filters = filters(args)?;
+        let favorites = self.state.client.favorites().await?;
+
+        let album_matches = |album: &AlbumSimple| {
+            filters.iter().all(|(tag, value)| match tag.as_str() {
+                "artist" | "albumartist" => album.artist.name.eq_ignore_ascii_case(value),
+                "album" => album.title.eq_ignore_ascii_case(value),
+                _ => true,
+            })
+        };
+
+        let mut values: Vec<String> = Vec::new();
+        let key = match tag.to_lowercase().as_str() {
+            "album" => {
+                values.extend(
+                    favorites
+                        .albums
+                        .iter()
+                        .filter(|album| album_matches(album))
+                        .map(|album| album.title.clone()),
+                );
+                "Album"
+            }
+            "albumartist" => {
+                values.extend(
+                    favorites
+                        .albums
+                        .iter()
+                        .map(|album| album.artist.name.clone()),
+                );
+                "AlbumArtist"
+            }
+            "artist" => {
+                values.extend(favorites.artists.iter().map(|artist| artist.name.clone()));
+                values.extend(
+                    favorites
+                        .albums
+                        .iter()
+                        .map(|album| album.artist.name.clone()),
+                );
+                "Artist"
+            }
+            "title" => {
+                for track in &favorites.tracks {
+                    if matches(track, &filters, true)? {
+                        values.push(track.title.clone());
+                    }
+                }
+                "Title"
+            }
+            "file" => {
+                for track in &favorites.tracks {
+                    if matches(track, &filters, true)? {
+                        values.push(track_uri(track));
+                    }
+                }
+                "file"
+            }
+            _ => return Ok(()),
+        };
+
+        let mut seen = std::collections::HashSet::new();
+        for value in values {
+            if seen.insert(value.to_lowercase()) {
+                field(out, key, value);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn matches(track: &Track, filters: &[(String, String)], exact: bool) -> Result<bool, Ack> {
+    for (tag, value) in filters {
+        let lowercase_value = value.to_lowercase();
+        let is_match = |candidate: Option<&str>| {
+            candidate.is_some_and(|candidate| {
+                let candidate = candidate.to_lowercase();
+                match exact {
+                    true => candidate == lowercase_value,
+                    false => candidate.contains(&lowercase_value),
+                }
+            })
+        };
+
+        let title = Some(track.title.as_str());
+        let artist = track.artist_name.as_deref();
+        let album = track.album_title.as_deref();
+        let composer = track.composer.as_ref().map(|x| x.name.as_str());
+
+        let matched = match tag.as_str() {
+            "any" => [title, artist, album, composer].into_iter().any(is_match),
+            "title" => is_match(title),
+            "artist" | "albumartist" | "performer" => is_match(artist),
+            "album" => is_match(album),
+            "composer" => is_match(composer),
+            "work" => is_match(track.work.as_deref()),
+            "file" => Link::parse(value)? == Link::Track(track.id),
+            _ => return Err(Ack::arg(format!("Unsupported tag: {tag}"))),
+        };
+
+        if !matched {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
@@ -0,0 +1,289 @@
+use std::{fmt::Display, fmt::Write, ops::Range, str::FromStr};
+
+use qobuz_player_controls::{error::Error, models::Track};
+
+pub(crate) const ACK_ERROR_ARG: u8 = 2;
+pub(crate) const ACK_ERROR_PASSWORD: u8 = 3;
+pub(crate) const ACK_ERROR_PERMISSION: u8 = 4;
+pub(crate) const ACK_ERROR_UNKNOWN: u8 = 5;
+pub(crate) const ACK_ERROR_NO_EXIST: u8 = 50;
+pub(crate) const ACK_ERROR_SYSTEM: u8 = 52;
+
+/// A failed command, answered with `ACK [code@index] {command} message`
+#[derive(Debug, PartialEq)]
+pub(crate) struct Ack {
+    pub code: u8,
+    pub message: String,
+}
+
+impl Ack {
+    pub fn arg(message: impl Into<String>) -> Self {
+        Self {
+            code: ACK_ERROR_ARG,
+            message: message.into(),
+        }
+    }
+
+    pub fn password(message: impl Into<String>) -> Self {
+        Self {
+            code: ACK_ERROR_PASSWORD,
+            message: message.into(),
+        }
+    }
+
+    pub fn permission(message: impl Into<String>) -> Self {
+        Self {
+            code: ACK_ERROR_PERMISSION,
+            message: message.into(),
+        }
+    }
+
+    pub fn unknown(message: impl Into<String>) -> Self {
+        Self {
+            code: ACK_ERROR_UNKNOWN,
+            message: message.into(),
+        }
+    }
+
+    pub fn no_exist(message: impl Into<String>) -> Self {
+        Self {
+            code: ACK_ERROR_NO_EXIST,
+            message: message.into(),
+        }
+    }
+
+    pub fn system(message: impl Into<String>) -> Self {
+        Self {
+            code: ACK_ERROR_SYSTEM,
+            message: message.into(),
+        }
+    }
+
+    /// `index` is the position of the command in a command list
+    pub fn line(&self, index: usize, command: &str) -> String {
+        format!(
+            "ACK [{}@{index}] {{{command}}} {}\n",
+            self.code, self.message
+        )
+    }
+}
+
+impl From<Error> for Ack {
+    fn from(error: Error) -> Self {
+        Self {
+            code: ACK_ERROR_SYSTEM,
+            message: error.to_string(),
+        }
+    }
+}
+
+/// Split a command line into the command and its arguments. Arguments with
+/// spaces are double quoted, with quotes and backslashes inside escaped by a
+/// backslash.
+pub(crate) fn tokenize(line: &str) -> Result<Vec<String>, Ack> {
+    let mut args = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        let Some(&first) = chars.peek() else {
+            return Ok(args);
+        };
+
+        let mut arg = String::new();
+        if first == '"' {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some(c) => arg.push(c),
+                        None => return Err(Ack::arg("Missing closing '\"'")),
+                    },
+                    Some(c) => arg.push(c),
+                    None => return Err(Ack::arg("Missing closing '\"'")),
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                arg.push(c);
+            }
+        }
+
+        args.push(arg);
+    }
+}
+
+pub(crate) fn arg(args: &[String], index: usize) -> Result<&str, Ack> {
+    args.get(index)
+        .map(String::as_str)
+        .ok_or_else(|| Ack::arg("Too few arguments"))
+}
+
+pub(crate) fn number<T: FromStr>(arg: &str) -> Result<T, Ack> {
+    arg.parse()
+        .map_err(|_| Ack::arg(format!("Number expected: {arg}")))
+}
+
+/// A song position `N` or a range `START:END` of a queue with `total` songs.
+/// A range without an end runs to the end of the queue.
+pub(crate) fn range(arg: &str, total: usize) -> Result<Range<usize>, Ack> {
+    let range = match arg.split_once(':') {
+        Some((start, "")) => number(start)?..total,
+        Some((start, end)) => number(start)?..number::<usize>(end)?.min(total),
+        None => {
+            let position: usize = number(arg)?;
+            position..position + 1
+        }
+    };
+
+    if range.start > range.end || range.end > total {
+        return Err(Ack::arg("Bad song index"));
+    }
+
+    Ok(range)
+}
+
+/// The tag and value pairs of a `find`, `search` or `list` filter, given
+/// either as `TAG VALUE ...` or as an expression like `(artist == "value")`.
+/// Operators are not told apart: `find` always matches exactly and `search`
+/// always matches parts, ignoring case.
+pub(crate) fn filters(args: &[String]) -> Result<Vec<(String, String)>, Ack> {
+    if let Some(expression) = args.first().filter(|arg| arg.starts_with('(')) {
+        let inner = strip_parens(expression);
+        let parts: Vec<&str> = match inner.contains(" AND ") {
+            true => inner.split(" AND ").map(strip_parens).collect(),
+            false => vec![inner],
+        };
+
+        return parts
+            .into_iter()
+            .map(|part| {
+                let (tag, rest) = part
+                    .split_once(' ')
+                    .ok_or_else(|| Ack::arg(format!("Bad filter: {part}")))?;
+                let (_operator, value) = rest
+                    .trim_start()
+                    .split_once(' ')
+                    .ok_or_else(|| Ack::arg(format!("Bad filter: {part}")))?;
+
+                Ok((tag.to_lowercase(), unquote(value.trim())))
+            })
+            .collect();
+    }
+
+    let mut filters = Vec::new();
+    let mut args = args.iter();
+    while let Some(tag) = args.next() {
+        let tag = tag.to_lowercase();
+        if matches!(tag.as_str(), "sort" | "window" | "group") {
+            break;
+        }
+
+        let value = args
+            .next()
+            .ok_or_else(|| Ack::arg(format!("Missing value for {tag}")))?;
+        filters.push((tag, value.clone()));
+    }
+
+    Ok(filters)
+}
+
+fn strip_parens(value: &str) -> &str {
+    let value = value.trim();
+    value
+        .strip_prefix('(')
+        .and_then(|value| value.strip_suffix(')'))
+        .unwrap_or(value)
+}
+
+fn unquote(value: &str) -> String {
+    let inner = ['"', '\'']
+        .into_iter()
+        .find_map(|quote| value.strip_prefix(quote)?.strip_suffix(quote))
+        .unwrap_or(value);
+
+    let mut unquoted = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unquoted.extend(chars.next()),
+            c => unquoted.push(c),
+        }
+    }
+    unquoted
+}
+
+pub(crate) fn field(out: &mut String, key: &str, value: impl Display) {
+    let value = value.to_string().replace('\n', " ");
+    _ = writeln!(out, "{key}: {value}");
+}
+
+pub(crate) fn track_uri(track: &Track) -> String {
+    format!("qobuz:track:{}", track.id)
+}
+
+/// Write a song entry, with its position and id when it is in the queue
+pub(crate) fn song(out: &mut String, track: &Track, queue: Option<(usize, u64)>) {
+    field(out, "file", track_uri(track));
+    field(out, "Title", &track.title);
+    if let Some(artist) = &track.artist_name {
+        field(out, "Artist", artist);
+    }
+    if let Some(album) = &track.album_title {
+        field(out, "Album", album);
+    }
+    if let Some(composer) = &track.composer {
+        field(out, "Composer", &composer.name);
+    }
+    if let Some(work) = &track.work {
+        field(out, "Work", work);
+    }
+    field(out, "Track", track.number);
+    if track.disc_number > 0 {
+        field(out, "Disc", track.disc_number);
+    }
+    field(out, "Time", track.duration_seconds);
+    field(out, "duration", format!("{}.000", track.duration_seconds));
+
+    if let Some((position, queue_id)) = queue {
+        field(out, "Pos", position);
+        field(out, "Id", queue_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_arguments_and_filters() {
+        assert_eq!(
+            tokenize(r#"find  artist "Miles \"the\" Davis" "#).unwrap(),
+            ["find", "artist", r#"Miles "the" Davis"#]
+        );
+        assert!(tokenize(r#"add "qobuz:track:1"#).is_err());
+
+        assert_eq!(range("3", 5).unwrap(), 3..4);
+        assert_eq!(range("1:", 5).unwrap(), 1..5);
+        assert_eq!(range("1:9", 5).unwrap(), 1..5);
+        assert!(range("5", 5).is_err());
+
+        let args = tokenize(r#"search album "Kind of Blue" window 0:10"#).unwrap();
+        assert_eq!(
+            filters(&args[1..]).unwrap(),
+            [("album".to_string(), "Kind of Blue".to_string())]
+        );
+
+        let args = tokenize(r#"find "((Artist == 'Miles Davis') AND (album == \"Kind (Live)\"))""#)
+            .unwrap();
+        assert_eq!(
+            filters(&args[1..]).unwrap(),
+            [
+                ("artist".to_string(), "Miles Davis".to_string()),
+                ("album".to_string(), "Kind (Live)".to_string())
+            ]
+        );
+    }
+}
@@ -1,16 +1,26 @@
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 
 use mpris_server::{
-    LoopStatus, Metadata, PlaybackRate, PlaybackStatus, PlayerInterface, Property, RootInterface,
-    Server, Time, TrackId, Volume,
+    LoopStatus, Metadata, PlaybackRate, PlaybackStatus, PlayerInterface, Playlist as MprisPlaylist,
+    PlaylistId, PlaylistOrdering, PlaylistsInterface, Property, RootInterface, Server, Time,
+    TrackId, TrackListInterface, TrackListSignal, Uri, Volume,
     zbus::{self, fdo},
 };
 use qobuz_player_controls::{
     AppResult, ExitSender, PositionReceiver, Status, StatusReceiver, TracklistReceiver,
-    VolumeReceiver, controls::Controls, error::Error, models::Track,
+    VolumeReceiver,
+    client::Client,
+    controls::Controls,
+    error::Error,
+    models::{Link, Playlist, Track},
+    tracklist::{Tracklist, TracklistType},
 };
 
+const TRACK_ID_PREFIX: &str = "/io/github/sofusa/QobuzPlayer/queue/";
+const PLAYLIST_ID_PREFIX: &str = "/io/github/sofusa/QobuzPlayer/playlist/";
+
 struct MprisPlayer {
+    client: Arc<Client>,
     controls: Controls,
     position_receiver: PositionReceiver,
     tracklist_receiver: TracklistReceiver,
@@ -48,13 +58,13 @@ impl RootInterface for MprisPlayer {
         Ok(false)
     }
     async fn has_track_list(&self) -> fdo::Result<bool> {
-        Ok(false)
+        Ok(true)
     }
     async fn desktop_entry(&self) -> fdo::Result<String> {
         Ok("qobuz-player".into())
     }
     async fn supported_uri_schemes(&self) -> fdo::Result<Vec<String>> {
-        Ok(vec![])
+        Ok(vec!["https".into(), "qobuz".into()])
     }
     async fn supported_mime_types(&self) -> fdo::Result<Vec<String>> {
         Ok(vec![])
@@ -109,12 +119,33 @@ impl PlayerInterface for MprisPlayer {
         Ok(())
     }
 
-    async fn set_position(&self, _track_id: TrackId, _position: Time) -> fdo::Result<()> {
-        Err(fdo::Error::NotSupported("Not supported".into()))
+    async fn set_position(&self, track_id: TrackId, position: Time) -> fdo::Result<()> {
+        let (current_queue_id, duration_seconds) = {
+            let tracklist = self.tracklist_receiver.borrow();
+            let duration_seconds = tracklist.current_track().map(|t| t.duration_seconds);
+            (tracklist.current_queue_id(), duration_seconds)
+        };
+
+        // Stale requests for a track that is no longer playing are ignored,
+        // as are positions outside the track
+        if current_queue_id.is_none() || queue_id(&track_id) != current_queue_id {
+            return Ok(());
+        }
+        let Ok(millis) = u64::try_from(position.as_millis()) else {
+            return Ok(());
+        };
+        if duration_seconds.is_some_and(|seconds| millis > u64::from(seconds) * 1000) {
+            return Ok(());
+        }
+
+        self.controls.seek(Duration::from_millis(millis));
+        Ok(())
     }
 
-    async fn open_uri(&self, _uri: String) -> fdo::Result<()> {
-        Err(fdo::Error::NotSupported("Not supported".into()))
+    async fn open_uri(&self, uri: String) -> fdo::Result<()> {
+        let link = Link::parse(&uri).map_err(|err| fdo::Error::InvalidArgs(err.to_string()))?;
+        self.controls.play_link(link);
+        Ok(())
     }
 
     async fn playback_status(&self) -> fdo::Result<PlaybackStatus> {
@@ -155,7 +186,10 @@ impl PlayerInterface for MprisPlayer {
         let current_track = tracklist.current_track();
 
         if let Some(current_track) = current_track {
-            return Ok(track_to_metadata(current_track));
+            return Ok(track_to_metadata(
+                current_track,
+                tracklist.current_queue_id(),
+            ));
         };
 
         Ok(Metadata::new())
@@ -210,7 +244,155 @@ impl PlayerInterface for MprisPlayer {
     }
 }
 
+impl TrackListInterface for MprisPlayer {
+    async fn get_tracks_metadata(&self, track_ids: Vec<TrackId>) -> fdo::Result<Vec<Metadata>> {
+        let tracklist = self.tracklist_receiver.borrow();
+        let queue = tracklist.queue();
+
+        let metadata = track_ids
+            .iter()
+            .filter_map(|track_id| {
+                let id = queue_id(track_id)?;
+                queue.iter().find(|item| item.queue_id == id)
+            })
+            .map(|item| track_to_metadata(&item.track, Some(item.queue_id)))
+            .collect();
+
+        Ok(metadata)
+    }
+
+    async fn add_track(
+        &self,
+        uri: Uri,
+        after_track: TrackId,
+        set_as_current: bool,
+    ) -> fdo::Result<()> {
+        let link = Link::parse(&uri).map_err(|err| fdo::Error::InvalidArgs(err.to_string()))?;
+
+        if set_as_current {
+            self.controls.play_link(link);
+            return Ok(());
+        }
+
+        // The no-track id places the tracks first in the queue
+        let after = match after_track == TrackId::NO_TRACK {
+            true => None,
+            false => {
+                let tracklist = self.tracklist_receiver.borrow();
+                let known = queue_id(&after_track)
+                    .filter(|id| tracklist.position_of_queue_id(*id).is_some());
+                let Some(after) = known else {
+                    return Err(fdo::Error::InvalidArgs(format!(
+                        "Unknown track {}",
+                        after_track.as_str()
+                    )));
+                };
+                Some(after)
+            }
+        };
+
+        self.controls.insert_link_after(link, after);
+        Ok(())
+    }
+
+    async fn remove_track(&self, track_id: TrackId) -> fdo::Result<()> {
+        let queue_id = queue_id(&track_id).ok_or_else(|| {
+            fdo::Error::InvalidArgs(format!("Unknown track {}", track_id.as_str()))
+        })?;
+        self.controls.remove_queue_item(queue_id);
+        Ok(())
+    }
+
+    async fn go_to(&self, track_id: TrackId) -> fdo::Result<()> {
+        let queue_id = queue_id(&track_id).ok_or_else(|| {
+            fdo::Error::InvalidArgs(format!("Unknown track {}", track_id.as_str()))
+        })?;
+        self.controls.skip_to_queue_item(queue_id);
+        Ok(())
+    }
+
+    async fn tracks(&self) -> fdo::Result<Vec<TrackId>> {
+        Ok(track_ids(&self.tracklist_receiver.borrow()))
+    }
+
+    async fn can_edit_tracks(&self) -> fdo::Result<bool> {
+        Ok(true)
+    }
+}
+
+impl PlaylistsInterface for MprisPlayer {
+    async fn activate_playlist(&self, playlist_id: PlaylistId) -> fdo::Result<()> {
+        let id = playlist_id
+            .as_str()
+            .strip_prefix(PLAYLIST_ID_PREFIX)
+            .and_then(|id| id.parse().ok())
+            .ok_or_else(|| {
+                fdo::Error::InvalidArgs(format!("Unknown playlist {}", playlist_id.as_str()))
+            })?;
+
+        self.controls.play_playlist(id, 0, false);
+        Ok(())
+    }
+
+    async fn get_playlists(
+        &self,
+        index: u32,
+        max_count: u32,
+        order: PlaylistOrdering,
+        reverse_order: bool,
+    ) -> fdo::Result<Vec<MprisPlaylist>> {
+        let playlists = self.playlists().await?;
+        Ok(page_playlists(
+            playlists,
+            index,
+            max_count,
+            order,
+            reverse_order,
+        ))
+    }
+
+    async fn playlist_count(&self) -> fdo::Result<u32> {
+        Ok(self.playlists().await?.len() as u32)
+    }
+
+    async fn orderings(&self) -> fdo::Result<Vec<PlaylistOrdering>> {
+        Ok(vec![
+            PlaylistOrdering::UserDefined,
+            PlaylistOrdering::Alphabetical,
+        ])
+    }
+
+    async fn active_playlist(&self) -> fdo::Result<Option<MprisPlaylist>> {
+        let tracklist = self.tracklist_receiver.borrow();
+        let playlist = match tracklist.list_type() {
+            TracklistType::Playlist(playlist) => Some(mpris_playlist(
+                playlist.id,
+                &playlist.title,
+                playlist.image.as_deref(),
+            )),
+            _ => None,
+        };
+
+        Ok(playlist)
+    }
+}
+
+impl MprisPlayer {
+    /// The user's favorite and own playlists. Fetched on its own task, as
+    /// the interface futures have to be `Sync`.
+    async fn playlists(&self) -> fdo::Result<Vec<Playlist>> {
+        let client = self.client.clone();
+        let favorites = tokio::spawn(async move { client.favorites().await })
+            .await
+            .map_err(|err| fdo::Error::Failed(err.to_string()))?
+            .map_err(|err| fdo::Error::Failed(err.to_string()))?;
+
+        Ok(favorites.playlists)
+    }
+}
+
 pub async fn init(
+    client: Arc<Client>,
     position_receiver: PositionReceiver,
     mut tracklist_receiver: TracklistReceiver,
     mut volume_receiver: VolumeReceiver,
@@ -220,9 +402,10 @@ pub async fn init(
 ) -> AppResult<()> {
     let mut exit_receiver = exit_sender.subscribe();
 
-    let Ok(server) = Server::new(
+    let Ok(server) = Server::new_with_all(
         "qobuz-player",
         MprisPlayer {
+            client,
             controls,
             position_receiver,
             tracklist_receiver: tracklist_receiver.clone(),
@@ -236,14 +419,32 @@ pub async fn init(
         return Err(Error::MprisInitError);
     };
 
+    let mut current_track_ids = vec![];
+
     loop {
         tokio::select! {
             Ok(_) = tracklist_receiver.changed() => {
                 let tracklist = tracklist_receiver.borrow_and_update().clone();
                 let current_track = tracklist.current_track();
 
+                let tracks = track_ids(&tracklist);
+                if tracks != current_track_ids {
+                    current_track_ids = tracks.clone();
+
+                    let current = tracklist
+                        .current_queue_id()
+                        .map(track_id)
+                        .unwrap_or(TrackId::NO_TRACK);
+
+                    let Ok(_) = server
+                        .track_list_emit(TrackListSignal::TrackListReplaced { tracks, current_track: current })
+                        .await else {
+                            return Err(Error::MprisPropertyError { property: "Tracks".into() });
+                        };
+                }
+
                 if let Some(current_track) = current_track {
-                    let metadata = track_to_metadata(current_track);
+                    let metadata = track_to_metadata(current_track, tracklist.current_queue_id());
 
                     let current_position = tracklist.current_position();
                     let total_tracks = tracklist.total();
@@ -302,8 +503,61 @@ pub async fn init(
     }
 }
 
-fn track_to_metadata(track: &Track) -> Metadata {
+fn track_id(queue_id: u64) -> TrackId {
+    TrackId::try_from(format!("{TRACK_ID_PREFIX}{queue_id}")).expect("valid object path")
+}
+
+/// The queue item a track id from [`track_id`] refers to
+fn queue_id(track_id: &TrackId) -> Option<u64> {
+    track_id
+        .as_str()
+        .strip_prefix(TRACK_ID_PREFIX)
+        .and_then(|id| id.parse().ok())
+}
+
+fn track_ids(tracklist: &Tracklist) -> Vec<TrackId> {
+    tracklist
+        .queue()
+        .iter()
+        .map(|item| track_id(item.queue_id))
+        .collect()
+}
+
+/// The page of playlists a `GetPlaylists` call asks for. User defined keeps
+/// the order Qobuz returns them in.
+fn page_playlists(
+    mut playlists: Vec<Playlist>,
+    index: u32,
+    max_count: u32,
+    order: PlaylistOrdering,
+    reverse_order: bool,
+) -> Vec<MprisPlaylist> {
+    if matches!(order, PlaylistOrdering::Alphabetical) {
+        playlists.sort_by_key(|playlist| playlist.title.to_lowercase());
+    }
+    if reverse_order {
+        playlists.reverse();
+    }
+
+    playlists
+        .iter()
+        .skip(index as usize)
+        .take(max_count as usize)
+        .map(|playlist| mpris_playlist(playlist.id, &playlist.title, playlist.image.as_deref()))
+        .collect()
+}
+
+fn mpris_playlist(id: u32, title: &str, image: Option<&str>) -> MprisPlaylist {
+    MprisPlaylist {
+        id: PlaylistId::try_from(format!("{PLAYLIST_ID_PREFIX}{id}")).expect("valid object path"),
+        name: title.to_string(),
+        icon: image.unwrap_or_default().to_string(),
+    }
+}
+
+fn track_to_metadata(track: &Track, queue_id: Option<u64>) -> Metadata {
     let mut metadata = Metadata::new();
+    metadata.set_trackid(queue_id.map(track_id));
     let duration = mpris_server::Time::from_secs(track.duration_seconds as i64);
     metadata.set_length(Some(duration));
 
@@ -322,3 +576,211 @@ fn track_to_metadata(track: &Track) -> Metadata {
 
     metadata
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qobuz_player_controls::{
+        AudioQuality,
+        controls::ControlCommand,
+        tracklist::{PlaylistTracklist, QueueItem},
+    };
+    use tokio::sync::{broadcast, mpsc, watch};
+
+    fn queue_item(queue_id: u64, track_id: u32) -> QueueItem {
+        QueueItem {
+            track: Track {
+                id: track_id,
+                title: format!("Track {track_id}"),
+                ..Default::default()
+            },
+            queue_id,
+            index: queue_id as usize,
+        }
+    }
+
+    fn mpris_player(
+        tracklist: Tracklist,
+    ) -> (MprisPlayer, mpsc::UnboundedReceiver<ControlCommand>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let (exit_sender, _) = broadcast::channel(1);
+
+        let player = MprisPlayer {
+            client: Arc::new(Client::new(None, AudioQuality::Mp3)),
+            controls: Controls::new(tx),
+            position_receiver: watch::channel(Duration::default()).1,
+            tracklist_receiver: watch::channel(tracklist).1,
+            volume_receiver: watch::channel(1.0).1,
+            status_receiver: watch::channel(Status::Paused).1,
+            exit_sender,
+        };
+
+        (player, rx)
+    }
+
+    fn playlist(id: u32, title: &str) -> Playlist {
+        Playlist {
+            id,
+            title: title.into(),
+            ..Default::default()
+        }
+    }
+
+    fn names(playlists: &[MprisPlaylist]) -> Vec<&str> {
+        playlists.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn track_list_lists_the_queue() {
+        let tracklist = Tracklist::new(
+            TracklistType::Tracks,
+            vec![queue_item(4, 101), queue_item(7, 102)],
+        );
+        let (player, _rx) = mpris_player(tracklist);
+
+        let tracks = player.tracks().await.unwrap();
+        assert_eq!(tracks, vec![track_id(4), track_id(7)]);
+
+        let metadata = player
+            .get_tracks_metadata(vec![track_id(7), track_id(99)])
+            .await
+            .unwrap();
+        assert_eq!(metadata.len(), 1);
+        assert_eq!(metadata[0].trackid(), Some(track_id(7)));
+        assert_eq!(metadata[0].title(), Some("Track 102"));
+    }
+
+    #[tokio::test]
+    async fn track_list_adds_after_the_given_track() {
+        let tracklist = Tracklist::new(
+            TracklistType::Tracks,
+            vec![queue_item(4, 101), queue_item(7, 102)],
+        );
+        let (player, mut rx) = mpris_player(tracklist);
+
+        player
+            .add_track("qobuz:track:201".into(), track_id(7), false)
+            .await
+            .unwrap();
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(ControlCommand::InsertLinkAfter {
+                link: Link::Track(201),
+                queue_id: Some(7),
+            })
+        ));
+
+        player
+            .add_track("qobuz:album:1".into(), TrackId::NO_TRACK, false)
+            .await
+            .unwrap();
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(ControlCommand::InsertLinkAfter {
+                link: Link::Album(id),
+                queue_id: None,
+            }) if id == "1"
+        ));
+
+        player
+            .add_track("qobuz:track:201".into(), track_id(4), true)
+            .await
+            .unwrap();
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(ControlCommand::PlayLink {
+                link: Link::Track(201)
+            })
+        ));
+
+        assert!(matches!(
+            player
+                .add_track("qobuz:track:201".into(), track_id(99), false)
+                .await,
+            Err(fdo::Error::InvalidArgs(_))
+        ));
+        assert!(matches!(
+            player
+                .add_track("spotify:track:1".into(), track_id(4), false)
+                .await,
+            Err(fdo::Error::InvalidArgs(_))
+        ));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn track_list_removes_and_goes_to_tracks() {
+        let tracklist = Tracklist::new(TracklistType::Tracks, vec![queue_item(4, 101)]);
+        let (player, mut rx) = mpris_player(tracklist);
+
+        player.remove_track(track_id(4)).await.unwrap();
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(ControlCommand::RemoveQueueItem { queue_id: 4 })
+        ));
+
+        player.go_to(track_id(4)).await.unwrap();
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(ControlCommand::SkipToQueueItem { queue_id: 4 })
+        ));
+
+        assert!(player.go_to(TrackId::NO_TRACK).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn playlists_activate_and_show_the_active_one() {
+        let mut tracklist = Tracklist::default();
+        tracklist.set_list_type(TracklistType::Playlist(PlaylistTracklist {
+            title: "Mixed Tones".into(),
+            id: 12,
+            image: None,
+        }));
+        let (player, mut rx) = mpris_player(tracklist);
+
+        let active = player.active_playlist().await.unwrap().unwrap();
+        assert_eq!(active.name, "Mixed Tones");
+
+        player.activate_playlist(active.id).await.unwrap();
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(ControlCommand::Playlist {
+                id: 12,
+                index: 0,
+                shuffle: false,
+            })
+        ));
+
+        let unknown = PlaylistId::try_from("/io/github/sofusa/QobuzPlayer/other/1").unwrap();
+        assert!(player.activate_playlist(unknown).await.is_err());
+    }
+
+    #[test]
+    fn playlists_are_ordered_and_paged() {
+        let playlists = || {
+            vec![
+                playlist(1, "jazz"),
+                playlist(2, "Ambient"),
+                playlist(3, "Baroque"),
+            ]
+        };
+
+        let user_defined = page_playlists(playlists(), 0, 10, PlaylistOrdering::UserDefined, false);
+        assert_eq!(names(&user_defined), vec!["jazz", "Ambient", "Baroque"]);
+        assert_eq!(
+            user_defined[0].id.as_str(),
+            "/io/github/sofusa/QobuzPlayer/playlist/1"
+        );
+
+        let alphabetical =
+            page_playlists(playlists(), 0, 10, PlaylistOrdering::Alphabetical, false);
+        assert_eq!(names(&alphabetical), vec!["Ambient", "Baroque", "jazz"]);
+
+        let page = page_playlists(playlists(), 1, 1, PlaylistOrdering::Alphabetical, true);
+        assert_eq!(names(&page), vec!["Baroque"]);
+
+        assert!(
+            page_playlists(playlists(), 3, 10, PlaylistOrdering::UserDefined, false).is_empty()
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use serde_json::{Value, json};
+
+use crate::{AVAILABILITY, POSITION, STATUS, TRACK, Topics, VOLUME};
+
+/// Home Assistant's MQTT integration has no media player, so the player is a
+/// device with sensors for its state, an image for the artwork, a number for
+/// the volume and buttons for the transport.
+pub(crate) fn configs(topics: &Topics, discovery_prefix: &str) -> Vec<(String, String)> {
+    let track_sensor = |name: &str, field: &str, icon: &str| {
+        json!({
+            "name": name,
+            "state_topic": topics.state(TRACK),
+            "value_template": format!("{{{{ value_json.{field} }}}}"),
+            "icon": icon,
+        })
+    };
+    let button = |name: &str, command: &str, icon: &str| {
+        json!({
+            "name": name,
+            "command_topic": topics.command(command),
+            "payload_press": "",
+            "icon": icon,
+        })
+    };
+
+    let entities = [
+        (
+            "sensor",
+            "status",
+            json!({
+                "name": "Status",
+                "state_topic": topics.state(STATUS),
+                "icon": "mdi:play-pause",
+            }),
+        ),
+        (
+            "sensor",
+            "title",
+            track_sensor("Title", "title", "mdi:music-note"),
+        ),
+        (
+            "sensor",
+            "artist",
+            track_sensor("Artist", "artist", "mdi:account-music"),
+        ),
+        (
+            "sensor",
+            "album",
+            track_sensor("Album", "album", "mdi:album"),
+        ),
+        (
+            "sensor",
+            "position",
+            json!({
+                "name": "Position",
+                "state_topic": topics.state(POSITION),
+                "device_class": "duration",
+                "unit_of_measurement": "s",
+            }),
+        ),
+        (
+            "image",
+            "artwork",
+            json!({
+                "name": "Artwork",
+                "url_topic": topics.state(TRACK),
+                "url_template": "{{ value_json.artwork_url }}",
+            }),
+        ),
+        (
+            "number",
+            "volume",
+            json!({
+                "name": "Volume",
+                "state_topic": topics.state(VOLUME),
+                "command_topic": topics.command("volume"),
+                "min": 0,
+                "max": 100,
+                "step": 1,
+                "unit_of_measurement": "%",
+                "icon": "mdi:volume-high",
+            }),
+        ),
+        ("button", "play", button("Play", "play", "mdi:play")),
+        ("button", "pause", button("Pause", "pause", "mdi:pause")),
+        (
+            "button",
+            "play_pause",
+            button("Play/pause", "play_pause", "mdi:play-pause"),
+        ),
+        ("button", "next", button("Next", "next", "mdi:skip-next")),
+        (
+            "button",
+            "previous",
+            button("Previous", "previous", "mdi:skip-previous"),
+        ),
+        (
+            "text",
+            "play_url",
+            json!({
+                "name": "Play link",
+                "command_topic": topics.command("play_url"),
+                "icon": "mdi:link",
+            }),
+        ),
+    ];
+
+    let device = json!({
+        "identifiers": [topics.node_id],
+        "name": topics.prefix,
+        "manufacturer": "qobuz-player",
+        "model": "qobuz-player",
+        "sw_version": env!("CARGO_PKG_VERSION"),
+    });
+
+    entities
+        .into_iter()
+        .map(|(component, object_id, mut config)| {
+            if let Value::Object(config) = &mut config {
+                config.insert(
+                    "unique_id".into(),
+                    format!("{}_{object_id}", topics.node_id).into(),
+                );
+                config.insert(
+                    "availability_topic".into(),
+                    topics.state(AVAILABILITY).into(),
+                );
+                config.insert("device".into(), device.clone());
+            }
+
+            (
+                format!(
+                    "{discovery_prefix}/{component}/{}/{object_id}/config",
+                    topics.node_id
+                ),
+                config.to_string(),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn volume_config() {
+        let topics = Topics::new("qobuz-player").unwrap();
+        let configs = configs(&topics, "homeassistant");
+
+        let (_, volume) = configs
+            .iter()
+            .find(|(topic, _)| topic == "homeassistant/number/qobuz_player/volume/config")
+            .unwrap();
+        let volume: Value = serde_json::from_str(volume).unwrap();
+
+        assert_eq!(volume["unique_id"], "qobuz_player_volume");
+        assert_eq!(volume["state_topic"], "qobuz-player/volume");
+        assert_eq!(volume["command_topic"], "qobuz-player/command/volume");
+        assert_eq!(volume["availability_topic"], "qobuz-player/availability");
+        assert_eq!(volume["device"]["identifiers"][0], "qobuz_player");
+
+        let (_, title) = configs
+            .iter()
+            .find(|(topic, _)| topic == "homeassistant/sensor/qobuz_player/title/config")
+            .unwrap();
+        let title: Value = serde_json::from_str(title).unwrap();
+        assert_eq!(title["value_template"], "{{ value_json.title }}");
+    }
+}
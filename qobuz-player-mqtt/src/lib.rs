@@ -0,0 +1,416 @@
+//! Publishes the player to an MQTT broker and takes commands from it, with
+//! Home Assistant discovery so the player shows up as a device there.
+//!
+//! State is published, retained, under the topic prefix:
+//!
+//! - `availability`: `online` or `offline`
+//! - `status`: `playing`, `paused` or `buffering`
+//! - `track`: JSON with the title, artist, album, artwork url and duration
+//! - `position`: seconds into the current track
+//! - `volume`: 0 to 100
+//!
+//! Commands are published to `command/<name>` under the prefix. `play`,
+//! `pause`, `play_pause`, `next` and `previous` ignore the payload, `volume`
+//! takes 0 to 100, `seek` takes seconds, and `play_url` and `queue_url` take
+//! a Qobuz share link or `qobuz:` uri.
+
+use std::{sync::Arc, time::Duration};
+
+use qobuz_player_controls::{
+    AppResult, PositionReceiver, Status, StatusReceiver, TracklistReceiver, VolumeReceiver,
+    controls::Controls, error::Error, models::Link,
+};
+use rumqttc::{AsyncClient, ClientError, Event, Incoming, LastWill, MqttOptions, Publish, QoS};
+
+mod discovery;
+
+pub const DEFAULT_PORT: u16 = 1883;
+pub const DEFAULT_TOPIC_PREFIX: &str = "qobuz-player";
+pub const DEFAULT_DISCOVERY_PREFIX: &str = "homeassistant";
+
+const AVAILABILITY: &str = "availability";
+const STATUS: &str = "status";
+const TRACK: &str = "track";
+const POSITION: &str = "position";
+const VOLUME: &str = "volume";
+const ONLINE: &str = "online";
+const OFFLINE: &str = "offline";
+
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+pub struct MqttConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Prefix of the state and command topics
+    pub topic_prefix: String,
+    /// Prefix Home Assistant reads discovery configs from
+    pub discovery_prefix: String,
+}
+
+impl MqttConfig {
+    pub fn new(host: String) -> Self {
+        Self {
+            host,
+            port: DEFAULT_PORT,
+            username: None,
+            password: None,
+            topic_prefix: DEFAULT_TOPIC_PREFIX.to_string(),
+            discovery_prefix: DEFAULT_DISCOVERY_PREFIX.to_string(),
+        }
+    }
+}
+
+/// Topic names under the prefix
+struct Topics {
+    prefix: String,
+    /// The prefix as a Home Assistant id and MQTT client id
+    node_id: String,
+}
+
+impl Topics {
+    fn new(prefix: &str) -> AppResult<Self> {
+        let prefix = prefix.trim_matches('/');
+        if prefix.is_empty() || prefix.contains(['+', '#']) {
+            return Err(Error::MqttError {
+                message: format!("Invalid topic prefix: {prefix:?}"),
+            });
+        }
+
+        let node_id = prefix
+            .chars()
+            .map(|c| match c.is_ascii_alphanumeric() {
+                true => c,
+                false => '_',
+            })
+            .collect();
+
+        Ok(Self {
+            prefix: prefix.to_string(),
+            node_id,
+        })
+    }
+
+    fn state(&self, name: &str) -> String {
+        format!("{}/{name}", self.prefix)
+    }
+
+    fn command(&self, name: &str) -> String {
+        format!("{}/command/{name}", self.prefix)
+    }
+
+    /// The command name of a topic under `command/`
+    fn command_name<'a>(&self, topic: &'a str) -> Option<&'a str> {
+        topic
+            .strip_prefix(self.prefix.as_str())?
+            .strip_prefix("/command/")
+    }
+}
+
+/// The state payloads, as published
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Payloads {
+    status: String,
+    track: String,
+    position: String,
+    volume: String,
+}
+
+impl Payloads {
+    fn messages(&self) -> [(&'static str, &str); 4] {
+        [
+            (STATUS, &self.status),
+            (TRACK, &self.track),
+            (POSITION, &self.position),
+            (VOLUME, &self.volume),
+        ]
+    }
+}
+
+struct Publisher {
+    client: AsyncClient,
+    topics: Topics,
+    discovery_prefix: String,
+    position_receiver: PositionReceiver,
+    tracklist_receiver: TracklistReceiver,
+    volume_receiver: VolumeReceiver,
+    status_receiver: StatusReceiver,
+}
+
+impl Publisher {
+    fn payloads(&self) -> Payloads {
+        let status = match *self.status_receiver.borrow() {
+            Status::Playing => "playing",
+            Status::Buffering => "buffering",
+            Status::Paused => "paused",
+        };
+
+        let tracklist = self.tracklist_receiver.borrow();
+        let track = match tracklist.current_track() {
+            Some(track) => serde_json::json!({
+                "id": track.id,
+                "title": track.title,
+                "artist": track.artist_name.clone().unwrap_or_default(),
+                "album": track.album_title.clone().unwrap_or_default(),
+                "artwork_url": track.image.clone().unwrap_or_default(),
+                "duration": track.duration_seconds,
+            }),
+            None => serde_json::json!({
+                "id": null,
+                "title": "",
+                "artist": "",
+                "album": "",
+                "artwork_url": "",
+                "duration": 0,
+            }),
+        };
+
+        let volume = (*self.volume_receiver.borrow() * 100.0).round() as u32;
+
+        Payloads {
+            status: status.to_string(),
+            track: track.to_string(),
+            position: self.position_receiver.borrow().as_secs().to_string(),
+            volume: volume.to_string(),
+        }
+    }
+
+    async fn publish(&self, name: &str, payload: &str) -> Result<(), ClientError> {
+        self.client
+            .publish(self.topics.state(name), QoS::AtLeastOnce, true, payload)
+            .await
+    }
+
+    /// Subscribe to the commands and publish the discovery configs and the
+    /// whole state. Runs on every connect, as the broker may have restarted.
+    async fn announce(&self) -> Result<(), ClientError> {
+        self.client
+            .subscribe(self.topics.command("+"), QoS::AtLeastOnce)
+            .await?;
+
+        for (topic, config) in discovery::configs(&self.topics, &self.discovery_prefix) {
+            self.client
+                .publish(topic, QoS::AtLeastOnce, true, config)
+                .await?;
+        }
+
+        self.publish(AVAILABILITY, ONLINE).await?;
+        for (name, payload) in self.payloads().messages() {
+            self.publish(name, payload).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Publish the state payloads that change
+    async fn publish_changes(&self) {
+        let mut position = self.position_receiver.clone();
+        let mut tracklist = self.tracklist_receiver.clone();
+        let mut volume = self.volume_receiver.clone();
+        let mut status = self.status_receiver.clone();
+
+        let mut published = self.payloads();
+
+        loop {
+            let changed = tokio::select! {
+                changed = position.changed() => changed,
+                changed = tracklist.changed() => changed,
+                changed = volume.changed() => changed,
+                changed = status.changed() => changed,
+            };
+            if changed.is_err() {
+                return;
+            }
+            position.mark_unchanged();
+            tracklist.mark_unchanged();
+            volume.mark_unchanged();
+            status.mark_unchanged();
+
+            let payloads = self.payloads();
+            for ((name, payload), (_, previous)) in
+                payloads.messages().into_iter().zip(published.messages())
+            {
+                if payload == previous {
+                    continue;
+                }
+                if let Err(err) = self.publish(name, payload).await {
+                    tracing::debug!("Unable to publish MQTT {name}: {err}");
+                    return;
+                }
+            }
+
+            published = payloads;
+        }
+    }
+}
+
+/// Map a command onto the controls
+fn handle_command(controls: &Controls, command: &str, payload: &str) -> Result<(), String> {
+    let payload = payload.trim();
+
+    match command {
+        "play" => controls.play(),
+        "pause" => controls.pause(),
+        "play_pause" => controls.play_pause(),
+        "next" => controls.next(),
+        "previous" => controls.previous(),
+        "volume" => {
+            let volume = payload
+                .parse::<f32>()
+                .ok()
+                .filter(|volume| (0.0..=100.0).contains(volume))
+                .ok_or_else(|| format!("Volume must be between 0 and 100, not {payload:?}"))?;
+            controls.set_volume(volume / 100.0);
+        }
+        "seek" => {
+            let seconds = payload
+                .parse::<f64>()
+                .ok()
+                .filter(|seconds| *seconds >= 0.0 && seconds.is_finite())
+                .ok_or_else(|| format!("Seek takes seconds, not {payload:?}"))?;
+            controls.seek(Duration::from_secs_f64(seconds));
+        }
+        "play_url" => controls.play_link(Link::parse(payload).map_err(|err| err.to_string())?),
+        "queue_url" => controls.queue_link(Link::parse(payload).map_err(|err| err.to_string())?),
+        _ => return Err(format!("Unknown command {command:?}")),
+    }
+
+    Ok(())
+}
+
+fn handle_publish(controls: &Controls, topics: &Topics, publish: &Publish) {
+    let Some(command) = topics.command_name(&publish.topic) else {
+        return;
+    };
+    let payload = String::from_utf8_lossy(&publish.payload);
+
+    if let Err(err) = handle_command(controls, command, &payload) {
+        tracing::warn!("Ignoring MQTT command {command}: {err}");
+    }
+}
+
+/// Stay connected to the broker until the player exits, reconnecting when
+/// the connection drops
+pub async fn init(
+    config: MqttConfig,
+    controls: Controls,
+    position_receiver: PositionReceiver,
+    tracklist_receiver: TracklistReceiver,
+    volume_receiver: VolumeReceiver,
+    status_receiver: StatusReceiver,
+) -> AppResult<()> {
+    let topics = Topics::new(&config.topic_prefix)?;
+
+    let mut options = MqttOptions::new(topics.node_id.clone(), &config.host, config.port);
+    options.set_keep_alive(KEEP_ALIVE);
+    options.set_last_will(LastWill::new(
+        topics.state(AVAILABILITY),
+        OFFLINE,
+        QoS::AtLeastOnce,
+        true,
+    ));
+    if let Some(username) = config.username {
+        options.set_credentials(username, config.password.unwrap_or_default());
+    }
+
+    let (client, mut eventloop) = AsyncClient::new(options, 64);
+    let publisher = Arc::new(Publisher {
+        client,
+        topics,
+        discovery_prefix: config.discovery_prefix.trim_matches('/').to_string(),
+        position_receiver,
+        tracklist_receiver,
+        volume_receiver,
+        status_receiver,
+    });
+
+    let changes = publisher.clone();
+    tokio::spawn(async move { changes.publish_changes().await });
+
+    loop {
+        match eventloop.poll().await {
+            Ok(Event::Incoming(Incoming::ConnAck(_))) => {
+                tracing::info!("Connected to MQTT broker {}:{}", config.host, config.port);
+                let publisher = publisher.clone();
+                tokio::spawn(async move {
+                    if let Err(err) = publisher.announce().await {
+                        tracing::warn!("Unable to announce the player over MQTT: {err}");
+                    }
+                });
+            }
+            Ok(Event::Incoming(Incoming::Publish(publish))) => {
+                handle_publish(&controls, &publisher.topics, &publish);
+            }
+            Ok(_) => {}
+            Err(err) => {
+                tracing::warn!(
+                    "MQTT connection to {}:{} failed: {err}",
+                    config.host,
+                    config.port
+                );
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use qobuz_player_controls::controls::ControlCommand;
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    #[test]
+    fn topics() {
+        let topics = Topics::new("/home/living room/").unwrap();
+        assert_eq!(topics.state(STATUS), "home/living room/status");
+        assert_eq!(topics.command("volume"), "home/living room/command/volume");
+        assert_eq!(topics.node_id, "home_living_room");
+        assert_eq!(
+            topics.command_name("home/living room/command/play"),
+            Some("play")
+        );
+        assert_eq!(topics.command_name("home/living room/status"), None);
+
+        assert!(Topics::new("").is_err());
+        assert!(Topics::new("players/#").is_err());
+    }
+
+    #[test]
+    fn commands_map_onto_controls() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let controls = Controls::new(tx);
+
+        handle_command(&controls, "play_pause", "").unwrap();
+        assert!(matches!(rx.try_recv(), Ok(ControlCommand::PlayPause)));
+
+        handle_command(&controls, "volume", " 30 ").unwrap();
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(ControlCommand::SetVolume { volume }) if (volume - 0.3).abs() < f32::EPSILON
+        ));
+
+        handle_command(&controls, "seek", "90.5").unwrap();
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(ControlCommand::Seek { time }) if time == Duration::from_millis(90_500)
+        ));
+
+        handle_command(&controls, "queue_url", "qobuz:track:42").unwrap();
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(ControlCommand::QueueLink {
+                link: Link::Track(42)
+            })
+        ));
+
+        assert!(handle_command(&controls, "volume", "150").is_err());
+        assert!(handle_command(&controls, "seek", "soon").is_err());
+        assert!(handle_command(&controls, "play_url", "https://example.com").is_err());
+        assert!(handle_command(&controls, "shuffle", "").is_err());
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,154 @@
+//! Runs against a local broker, for example `mosquitto -p 1883`:
+//!
+//! ```sh
+//! cargo test -p qobuz-player-mqtt -- --ignored
+//! ```
+
+use std::{collections::HashMap, time::Duration};
+
+use qobuz_player_controls::{
+    Status,
+    controls::{ControlCommand, Controls},
+    models::Link,
+    tracklist::Tracklist,
+};
+use qobuz_player_mqtt::MqttConfig;
+use rumqttc::{AsyncClient, Event, EventLoop, Incoming, MqttOptions, QoS};
+use tokio::{
+    sync::{mpsc, watch},
+    time::timeout,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+const PREFIX: &str = "qobuz-player-test";
+
+/// Poll until the retained messages include all of `topics`
+async fn wait_for(
+    eventloop: &mut EventLoop,
+    messages: &mut HashMap<String, String>,
+    topics: &[&str],
+) {
+    timeout(TIMEOUT, async {
+        while !topics.iter().all(|topic| messages.contains_key(*topic)) {
+            if let Event::Incoming(Incoming::Publish(publish)) = eventloop.poll().await.unwrap() {
+                messages.insert(
+                    publish.topic,
+                    String::from_utf8_lossy(&publish.payload).to_string(),
+                );
+            }
+        }
+    })
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+#[ignore = "needs an MQTT broker on localhost:1883"]
+async fn home_assistant_over_mosquitto() {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let (_position_tx, position_rx) = watch::channel(Duration::from_secs(12));
+    let (_tracklist_tx, tracklist_rx) = watch::channel(Tracklist::default());
+    let (volume_tx, volume_rx) = watch::channel(0.5);
+    let (_status_tx, status_rx) = watch::channel(Status::Paused);
+
+    let mut config = MqttConfig::new("localhost".into());
+    config.topic_prefix = PREFIX.into();
+    tokio::spawn(qobuz_player_mqtt::init(
+        config,
+        Controls::new(tx),
+        position_rx,
+        tracklist_rx,
+        volume_rx,
+        status_rx,
+    ));
+
+    // Home Assistant's side
+    let (client, mut eventloop) = AsyncClient::new(
+        MqttOptions::new("home-assistant-test", "localhost", 1883),
+        16,
+    );
+    client
+        .subscribe(format!("{PREFIX}/#"), QoS::AtLeastOnce)
+        .await
+        .unwrap();
+    client
+        .subscribe(
+            "homeassistant/+/qobuz_player_test/+/config",
+            QoS::AtLeastOnce,
+        )
+        .await
+        .unwrap();
+
+    let mut messages = HashMap::new();
+    let volume_config = "homeassistant/number/qobuz_player_test/volume/config";
+    wait_for(
+        &mut eventloop,
+        &mut messages,
+        &[
+            volume_config,
+            "qobuz-player-test/availability",
+            "qobuz-player-test/status",
+            "qobuz-player-test/position",
+            "qobuz-player-test/volume",
+        ],
+    )
+    .await;
+
+    assert!(
+        messages[volume_config].contains("\"command_topic\":\"qobuz-player-test/command/volume\"")
+    );
+    assert_eq!(messages["qobuz-player-test/availability"], "online");
+    assert_eq!(messages["qobuz-player-test/status"], "paused");
+    assert_eq!(messages["qobuz-player-test/position"], "12");
+    assert_eq!(messages["qobuz-player-test/volume"], "50");
+
+    // State changes are published
+    messages.clear();
+    volume_tx.send(0.8).unwrap();
+    wait_for(&mut eventloop, &mut messages, &["qobuz-player-test/volume"]).await;
+    assert_eq!(messages["qobuz-player-test/volume"], "80");
+
+    // Commands reach the controls
+    client
+        .publish(
+            "qobuz-player-test/command/volume",
+            QoS::AtLeastOnce,
+            false,
+            "30",
+        )
+        .await
+        .unwrap();
+    client
+        .publish(
+            "qobuz-player-test/command/play_url",
+            QoS::AtLeastOnce,
+            false,
+            "https://open.qobuz.com/track/42",
+        )
+        .await
+        .unwrap();
+
+    let commands = timeout(TIMEOUT, async {
+        let mut commands = vec![];
+        while commands.len() < 2 {
+            tokio::select! {
+                command = rx.recv() => commands.push(command.unwrap()),
+                polled = eventloop.poll() => { polled.unwrap(); }
+            }
+        }
+        commands
+    })
+    .await
+    .unwrap();
+
+    assert!(matches!(
+        commands[0],
+        ControlCommand::SetVolume { volume } if (volume - 0.3).abs() < f32::EPSILON
+    ));
+    assert!(matches!(
+        commands[1],
+        ControlCommand::PlayLink {
+            link: Link::Track(42)
+        }
+    ));
+}
@@ -23,7 +23,7 @@ pub async fn init(
     database: Arc<Database>,
     broadcast: Arc<NotificationBroadcast>,
     rfid_server_base_address: Option<String>,
-    rfid_server_secret: Option<String>,
+    rfid_server_token: Option<String>,
 ) -> AppResult<()> {
     let mut reader = tokio::io::BufReader::new(tokio::io::stdin());
     let mut out = tokio::io::stdout();
@@ -61,7 +61,7 @@ pub async fn init(
                     res,
                     &album_id,
                     rfid_server_base_address.as_deref(),
-                    rfid_server_secret.as_deref(),
+                    rfid_server_token.as_deref(),
                 )
                 .await
             }
@@ -73,7 +73,7 @@ pub async fn init(
                     res,
                     playlist_id,
                     rfid_server_base_address.as_deref(),
-                    rfid_server_secret.as_deref(),
+                    rfid_server_token.as_deref(),
                 )
                 .await
             }
@@ -84,7 +84,7 @@ pub async fn init(
                     &broadcast,
                     res,
                     rfid_server_base_address.as_deref(),
-                    rfid_server_secret.as_deref(),
+                    rfid_server_token.as_deref(),
                 )
                 .await;
             }
@@ -99,7 +99,7 @@ pub async fn handle_play_scan(
     broadcast: &NotificationBroadcast,
     reference_id: &str,
     rfid_server_base_address: Option<&str>,
-    rfid_server_secret: Option<&str>,
+    rfid_server_token: Option<&str>,
 ) {
     let reference = match rfid_server_base_address {
         Some(server) => {
@@ -107,7 +107,7 @@ pub async fn handle_play_scan(
             let url = format!("{}/api/rfid/reference/{}", server, reference_id);
 
             let mut request = client.get(&url);
-            request = set_secret_header(request, rfid_server_secret);
+            request = set_token_header(request, rfid_server_token);
 
             let response = match request.send().await.and_then(|x| x.error_for_status()) {
                 Ok(res) => res,
@@ -181,7 +181,7 @@ async fn submit_link_album(
     rfid_id: &str,
     id: &str,
     rfid_server_base_address: Option<&str>,
-    rfid_server_secret: Option<&str>,
+    rfid_server_token: Option<&str>,
 ) {
     let reference = ReferenceType::Album(id.to_owned());
     submit_link(
@@ -191,7 +191,7 @@ async fn submit_link_album(
         rfid_id,
         reference,
         rfid_server_base_address,
-        rfid_server_secret,
+        rfid_server_token,
     )
     .await;
 }
@@ -203,7 +203,7 @@ async fn submit_link_playlist(
     rfid_id: &str,
     id: u32,
     rfid_server_base_address: Option<&str>,
-    rfid_server_secret: Option<&str>,
+    rfid_server_token: Option<&str>,
 ) {
     let reference = ReferenceType::Playlist(id);
     submit_link(
@@ -213,7 +213,7 @@ async fn submit_link_playlist(
         rfid_id,
         reference,
         rfid_server_base_address,
-        rfid_server_secret,
+        rfid_server_token,
     )
     .await;
 }
@@ -225,7 +225,7 @@ async fn submit_link(
     rfid_id: &str,
     reference: ReferenceType,
     rfid_server_base_address: Option<&str>,
-    rfid_server_secret: Option<&str>,
+    rfid_server_token: Option<&str>,
 ) {
     if let Some(server) = rfid_server_base_address {
         let client = reqwest::Client::new();
@@ -270,7 +270,7 @@ async fn submit_link(
         };
 
         request =
-            set_secret_header(request, rfid_server_secret).header(CONTENT_TYPE, "application/json");
+            set_token_header(request, rfid_server_token).header(CONTENT_TYPE, "application/json");
 
         match request.send().await.and_then(|x| x.error_for_status()) {
             Ok(_) => {
@@ -317,9 +317,9 @@ pub struct LinkPlaylistRfid {
     pub id: u32,
 }
 
-fn set_secret_header(mut request: RequestBuilder, secret: Option<&str>) -> RequestBuilder {
-    if let Some(secret) = secret {
-        request = request.header("Cookie", &format!("secret={secret}"));
+fn set_token_header(mut request: RequestBuilder, token: Option<&str>) -> RequestBuilder {
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
     }
     request
 }
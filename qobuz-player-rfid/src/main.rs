@@ -2,11 +2,10 @@
 use qobuz_player_cli::GpioArgs;
 use qobuz_player_cli::{
     ConnectArgs, DelayArgs, RfidArgs, SharedArgs, SharedCommands, create_player,
-    default_audio_quality, get_client, handle_shared_commands, spawn_clean_up,
+    default_audio_quality, get_client, handle_shared_commands, play_url,
 };
 use qobuz_player_rfid::RfidState;
 use std::sync::Arc;
-use tokio::sync::broadcast;
 
 use clap::Parser;
 use qobuz_player_controls::{
@@ -56,15 +55,13 @@ pub async fn run() -> AppResult<()> {
         return Ok(());
     }
 
-    let (_, exit_receiver) = broadcast::channel(5);
-
     let max_audio_quality = default_audio_quality(&database, args.shared.max_audio_quality).await?;
     let client = get_client(&database, max_audio_quality, headless).await?;
     let client = Arc::new(client);
 
     let broadcast = Arc::new(NotificationBroadcast::new());
 
-    let mut player = create_player(
+    let player = create_player(
         args.shared.audio_cache,
         database.clone(),
         client.clone(),
@@ -72,9 +69,19 @@ pub async fn run() -> AppResult<()> {
         args.delay.state_change_delay_ms,
         args.delay.sample_rate_change_delay_ms,
         args.shared.output_device_id,
+        args.shared.audio_cache_time_to_live,
     )
     .await?;
 
+    play_url(&player, args.shared.play_url.as_deref())?;
+
+    #[cfg(unix)]
+    qobuz_player_cli::spawn_control_socket(&player, args.shared.control_socket);
+
+    qobuz_player_cli::spawn_mpd_server(&player, client.clone(), args.shared.mpd);
+    qobuz_player_cli::spawn_upnp_renderer(&player, args.shared.upnp_name, args.shared.upnp_port);
+    qobuz_player_cli::spawn_mqtt_client(&player, args.shared.mqtt);
+
     #[cfg(feature = "gpio")]
     if args.gpio.gpio {
         let status_receiver = player.status();
@@ -97,7 +104,7 @@ pub async fn run() -> AppResult<()> {
                 database,
                 broadcast,
                 args.rfid_config.rfid_server_base_address,
-                args.rfid_config.rfid_server_secret,
+                args.rfid_config.rfid_server_token,
             )
             .await
             {
@@ -110,6 +117,7 @@ pub async fn run() -> AppResult<()> {
         let app_id = client.app_id().await?;
         let position_receiver = player.position();
         let tracklist_receiver = player.tracklist();
+        let tracklist_changes = player.tracklist_changes();
         let volume_receiver = player.volume();
         let status_receiver = player.status();
         let controls = player.controls();
@@ -121,6 +129,7 @@ pub async fn run() -> AppResult<()> {
                 controls,
                 position_receiver,
                 tracklist_receiver,
+                tracklist_changes,
                 status_receiver,
                 volume_receiver,
                 max_audio_quality,
@@ -132,8 +141,7 @@ pub async fn run() -> AppResult<()> {
         });
     }
 
-    spawn_clean_up(database, args.shared.audio_cache_time_to_live);
-    player.player_loop(exit_receiver).await?;
+    player.wait().await?;
 
     Ok(())
 }
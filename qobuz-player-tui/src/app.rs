@@ -3,7 +3,7 @@ use crate::{
     favorites::FavoritesState,
     genres::GenresState,
     now_playing::NowPlayingState,
-    popup::{Popup, TrackPopupState},
+    popup::{OpenLinkPopupState, Popup, TrackPopupState},
     queue::QueueState,
     search::SearchState,
 };
@@ -12,7 +12,8 @@ use crossterm::event::{Event, EventStream, KeyCode, KeyEventKind};
 use futures::StreamExt;
 use image::load_from_memory;
 use qobuz_player_controls::{
-    AppResult, PositionReceiver, Status, StatusReceiver, TracklistReceiver,
+    AppResult, PositionReceiver, Status, StatusReceiver, TracklistChangeReceiver,
+    TracklistReceiver,
     client::Client,
     controls::Controls,
     models::{Album, Track},
@@ -22,7 +23,10 @@ use qobuz_player_controls::{
 use ratatui::{DefaultTerminal, widgets::*};
 use ratatui_image::{picker::Picker, protocol::StatefulProtocol};
 use std::{io, sync::Arc, time::Instant};
-use tokio::time::{self, Duration};
+use tokio::{
+    sync::broadcast,
+    time::{self, Duration},
+};
 
 #[derive(Default)]
 pub struct NotificationList {
@@ -53,6 +57,7 @@ pub struct App {
     pub controls: Controls,
     pub position: PositionReceiver,
     pub tracklist: TracklistReceiver,
+    pub tracklist_changes: TracklistChangeReceiver,
     pub status: StatusReceiver,
     pub current_screen: Tab,
     pub exit: bool,
@@ -71,6 +76,7 @@ pub struct App {
     pub current_image_url: Option<String>,
 }
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Default)]
 pub enum AppState {
     #[default]
@@ -86,6 +92,7 @@ pub enum Output {
     NotConsumed,
     UpdateFavorites,
     Popup(Popup),
+    PopPopup,
     PopPopupUpdateFavorites,
     AddTrackToPlaylistPopup(Track),
     AddTrackToPlaylistAndPopPopup((u32, u32)), // TODO: Add a type
@@ -158,6 +165,18 @@ where
     pub fn set_filter(&mut self, items: Vec<T>) {
         self.filter = items;
     }
+
+    /// Append items, keeping the selection
+    pub fn extend(&mut self, items: Vec<T>) {
+        self.all_items.extend(items.clone());
+        self.filter.extend(items);
+    }
+
+    pub fn is_last_selected(&self) -> bool {
+        self.state
+            .selected()
+            .is_some_and(|index| index + 1 >= self.filter.len())
+    }
 }
 
 impl App {
@@ -193,11 +212,6 @@ impl App {
 
                 Ok(_) = self.tracklist.changed() => {
                     let tracklist = self.tracklist.borrow_and_update().clone();
-                    self.queue.set_items(tracklist
-                        .queue()
-                        .into_iter()
-                        .map(|x| x.track.clone())
-                        .collect());
                     let status = self.now_playing.status;
                     let (mut new_state, image_url) = get_current_state_without_image(&tracklist, status);
 
@@ -218,6 +232,24 @@ impl App {
                     self.should_draw = true;
                 },
 
+                changes = self.tracklist_changes.recv() => {
+                    let applied = match &changes {
+                        Ok(changes) => self.queue.apply_changes(changes),
+                        Err(broadcast::error::RecvError::Lagged(_)) => false,
+                        Err(broadcast::error::RecvError::Closed) => continue,
+                    };
+
+                    if !applied {
+                        self.queue.set_items(self.tracklist
+                            .borrow()
+                            .queue()
+                            .into_iter()
+                            .cloned()
+                            .collect());
+                    }
+                    self.should_draw = true;
+                },
+
                 Some(image) = image_rx.recv() => {
                     self.now_playing.image = image;
                     self.should_draw = true;
@@ -352,6 +384,16 @@ impl App {
                     self.full_screen = !self.full_screen;
                     self.should_draw = true;
                 }
+                KeyCode::Char('o') => {
+                    self.app_state =
+                        AppState::Popup(vec![Popup::OpenLink(OpenLinkPopupState::new(false))]);
+                    self.should_draw = true;
+                }
+                KeyCode::Char('O') => {
+                    self.app_state =
+                        AppState::Popup(vec![Popup::OpenLink(OpenLinkPopupState::new(true))]);
+                    self.should_draw = true;
+                }
                 _ => {}
             },
             Output::Popup(popup) => {
@@ -365,6 +407,15 @@ impl App {
                 self.app_state = AppState::Popup(popups);
                 self.should_draw = true;
             }
+            Output::PopPopup => {
+                if let AppState::Popup(popups) = &mut self.app_state {
+                    popups.pop();
+                    if popups.is_empty() {
+                        self.app_state = AppState::Normal;
+                    }
+                    self.should_draw = true;
+                }
+            }
             Output::PopPopupUpdateFavorites => {
                 if let AppState::Popup(popups) = &mut self.app_state {
                     popups.pop();
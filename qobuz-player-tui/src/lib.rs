@@ -3,8 +3,9 @@ use std::sync::Arc;
 use app::{App, get_current_state_without_image};
 use favorites::FavoritesState;
 use qobuz_player_controls::{
-    AppResult, ExitSender, PositionReceiver, StatusReceiver, TracklistReceiver, client::Client,
-    controls::Controls, error::Error, notification::NotificationBroadcast,
+    AppResult, ExitSender, PositionReceiver, StatusReceiver, TracklistChangeReceiver,
+    TracklistReceiver, client::Client, controls::Controls, error::Error,
+    notification::NotificationBroadcast,
 };
 use queue::QueueState;
 use ratatui::{prelude::*, widgets::*};
@@ -29,6 +30,7 @@ pub async fn init(
     controls: Controls,
     position_receiver: PositionReceiver,
     tracklist_receiver: TracklistReceiver,
+    tracklist_changes: TracklistChangeReceiver,
     status_receiver: StatusReceiver,
     exit_sender: ExitSender,
     disable_tui_album_cover: bool,
@@ -39,11 +41,7 @@ pub async fn init(
 
     let tracklist_value = tracklist_receiver.borrow().clone();
     let status_value = *status_receiver.borrow();
-    let queue_tracks = tracklist_value
-        .queue()
-        .into_iter()
-        .map(|x| x.track.clone())
-        .collect();
+    let queue_items = tracklist_value.queue().into_iter().cloned().collect();
     let (now_playing, current_image_url) =
         get_current_state_without_image(&tracklist_value, status_value);
 
@@ -55,6 +53,7 @@ pub async fn init(
         full_screen: false,
         position: position_receiver,
         tracklist: tracklist_receiver,
+        tracklist_changes,
         status: status_receiver,
         current_screen: Default::default(),
         exit: Default::default(),
@@ -64,7 +63,7 @@ pub async fn init(
         current_image_url,
         favorites: FavoritesState::new(&client).await?,
         search: Default::default(),
-        queue: QueueState::new(queue_tracks),
+        queue: QueueState::new(queue_items),
         discover: discover::DiscoverState::new(&client).await?,
         genres: genres::GenresState::new(&client).await?,
         client,
@@ -1,10 +1,9 @@
 use futures::executor::block_on;
 use qobuz_player_cli::{
     ConnectArgs, SharedArgs, SharedCommands, create_player, default_audio_quality, get_client,
-    handle_shared_commands, spawn_clean_up,
+    handle_shared_commands, play_url,
 };
 use std::sync::Arc;
-use tokio::sync::broadcast;
 
 use clap::Parser;
 #[cfg(any(windows, target_os = "linux", target_os = "macos"))]
@@ -50,15 +49,13 @@ pub async fn run() -> AppResult<()> {
         return Ok(());
     }
 
-    let (exit_sender, exit_receiver) = broadcast::channel(5);
-
     let max_audio_quality = default_audio_quality(&database, args.shared.max_audio_quality).await?;
     let client = get_client(&database, max_audio_quality, headless).await?;
     let client = Arc::new(client);
 
     let broadcast = Arc::new(NotificationBroadcast::new());
 
-    let mut player = create_player(
+    let player = create_player(
         args.shared.audio_cache,
         database.clone(),
         client.clone(),
@@ -66,19 +63,32 @@ pub async fn run() -> AppResult<()> {
         None,
         None,
         args.shared.output_device_id,
+        args.shared.audio_cache_time_to_live,
     )
     .await?;
 
+    let exit_sender = player.exit_sender();
+    play_url(&player, args.shared.play_url.as_deref())?;
+
+    #[cfg(unix)]
+    qobuz_player_cli::spawn_control_socket(&player, args.shared.control_socket);
+
+    qobuz_player_cli::spawn_mpd_server(&player, client.clone(), args.shared.mpd);
+    qobuz_player_cli::spawn_upnp_renderer(&player, args.shared.upnp_name, args.shared.upnp_port);
+    qobuz_player_cli::spawn_mqtt_client(&player, args.shared.mqtt);
+
     #[cfg(target_os = "linux")]
     {
         let position_receiver = player.position();
         let tracklist_receiver = player.tracklist();
         let volume_receiver = player.volume();
         let status_receiver = player.status();
+        let client = client.clone();
         let controls = player.controls();
         let exit_sender = exit_sender.clone();
         tokio::spawn(async move {
             if let Err(e) = qobuz_player_mpris::init(
+                client,
                 position_receiver,
                 tracklist_receiver,
                 volume_receiver,
@@ -101,6 +111,7 @@ pub async fn run() -> AppResult<()> {
 
     let position_receiver = player.position();
     let tracklist_receiver = player.tracklist();
+    let tracklist_changes = player.tracklist_changes();
     let status_receiver = player.status();
     let controls = player.controls();
     let client = client.clone();
@@ -110,6 +121,7 @@ pub async fn run() -> AppResult<()> {
         let app_id = client.app_id().await?;
         let position_receiver = player.position();
         let tracklist_receiver = player.tracklist();
+        let tracklist_changes = player.tracklist_changes();
         let volume_receiver = player.volume();
         let status_receiver = player.status();
         let controls = player.controls();
@@ -121,6 +133,7 @@ pub async fn run() -> AppResult<()> {
                 controls,
                 position_receiver,
                 tracklist_receiver,
+                tracklist_changes,
                 status_receiver,
                 volume_receiver,
                 max_audio_quality,
@@ -139,6 +152,7 @@ pub async fn run() -> AppResult<()> {
             controls,
             position_receiver,
             tracklist_receiver,
+            tracklist_changes,
             status_receiver,
             exit_sender,
             args.disable_album_cover,
@@ -149,8 +163,7 @@ pub async fn run() -> AppResult<()> {
         };
     });
 
-    spawn_clean_up(database, args.shared.audio_cache_time_to_live);
-    player.player_loop(exit_receiver).await?;
+    player.wait().await?;
 
     Ok(())
 }
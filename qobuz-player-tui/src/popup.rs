@@ -1,8 +1,8 @@
 use qobuz_player_controls::{
     AppResult,
-    client::Client,
+    client::{Client, LABEL_PAGE_SIZE},
     controls::Controls,
-    models::{Album, Artist, Playlist, PlaylistSimple, Track},
+    models::{Album, Artist, Label, LabelPage, Link, Playlist, PlaylistSimple, Track, Work},
 };
 use ratatui::{
     crossterm::event::{Event, KeyCode, KeyEventKind},
@@ -189,24 +189,109 @@ impl ArtistPopupState {
 
 pub struct AlbumPopupState {
     title: String,
-    tracks: TrackList,
+    discs: Vec<DiscTrackList>,
+    works: Vec<Work>,
+    label: Option<Label>,
+    selected_disc: usize,
     id: String,
 }
 
+struct DiscTrackList {
+    number: u32,
+    first_index: usize,
+    tracks: TrackList,
+}
+
 impl AlbumPopupState {
     pub fn new(album: Album) -> Self {
-        let is_empty = album.tracks.is_empty();
-        let mut state = Self {
+        let mut discs: Vec<DiscTrackList> = album
+            .discs()
+            .into_iter()
+            .map(|disc| {
+                let mut tracks = TrackList::new(disc.tracks);
+                tracks.select_first();
+                DiscTrackList {
+                    number: disc.number,
+                    first_index: disc.first_index,
+                    tracks,
+                }
+            })
+            .collect();
+
+        if discs.is_empty() {
+            discs.push(DiscTrackList {
+                number: 1,
+                first_index: 0,
+                tracks: TrackList::new(vec![]),
+            });
+        }
+
+        Self {
+            works: album.works(),
+            label: album.label,
             title: album.title,
-            tracks: TrackList::new(album.tracks),
+            discs,
+            selected_disc: 0,
             id: album.id,
+        }
+    }
+
+    fn current_disc(&self) -> &DiscTrackList {
+        &self.discs[self.selected_disc]
+    }
+
+    fn current_disc_mut(&mut self) -> &mut DiscTrackList {
+        &mut self.discs[self.selected_disc]
+    }
+
+    fn cycle_disc(&mut self) {
+        self.selected_disc = (self.selected_disc + 1) % self.discs.len();
+    }
+
+    fn cycle_disc_backwards(&mut self) {
+        self.selected_disc = (self.selected_disc + self.discs.len() - 1) % self.discs.len();
+    }
+}
+
+pub struct LabelPopupState {
+    id: u32,
+    name: String,
+    albums: AlbumList,
+    next_offset: Option<u32>,
+}
+
+impl LabelPopupState {
+    pub fn new(label: LabelPage) -> Self {
+        let is_empty = label.albums.is_empty();
+        let mut state = Self {
+            id: label.id,
+            name: label.name,
+            next_offset: next_label_offset(0, label.albums.len()),
+            albums: AlbumList::new(label.albums),
         };
 
         if !is_empty {
-            state.tracks.select_first();
+            state.albums.select_first();
         }
         state
     }
+
+    /// Fetch the next page of albums once the last album is selected
+    async fn load_more(&mut self, client: &Client) -> AppResult<()> {
+        let Some(offset) = self.next_offset.filter(|_| self.albums.is_last_selected()) else {
+            return Ok(());
+        };
+
+        let albums = client.label_albums(self.id, offset).await?;
+        self.next_offset = next_label_offset(offset, albums.len());
+        self.albums.extend(albums);
+
+        Ok(())
+    }
+}
+
+fn next_label_offset(offset: u32, count: usize) -> Option<u32> {
+    (count >= LABEL_PAGE_SIZE as usize).then_some(offset + LABEL_PAGE_SIZE)
 }
 
 pub struct PlaylistPopupState {
@@ -283,33 +368,73 @@ impl NewPlaylistPopupState {
     }
 }
 
+pub struct OpenLinkPopupState {
+    url: Input,
+    queue: bool,
+}
+
+impl OpenLinkPopupState {
+    /// Play the pasted link, or append it to the queue if `queue` is set
+    pub fn new(queue: bool) -> Self {
+        Self {
+            url: Default::default(),
+            queue,
+        }
+    }
+}
+
 #[allow(clippy::large_enum_variant)]
 pub enum Popup {
     Artist(ArtistPopupState),
     Album(AlbumPopupState),
+    Label(LabelPopupState),
     Playlist(PlaylistPopupState),
     Track(TrackPopupState),
     NewPlaylist(NewPlaylistPopupState),
     DeletePlaylist(DeletePlaylistPopupstate),
+    OpenLink(OpenLinkPopupState),
 }
 
 impl Popup {
     pub fn render(&mut self, frame: &mut Frame) {
         match self {
             Popup::Album(state) => {
+                let tabs_height: u16 = if state.discs.len() > 1 { 2 } else { 0 };
                 let area = center(
                     frame.area(),
                     Constraint::Percentage(50),
-                    Constraint::Length(state.tracks.filter().len() as u16 + 2),
+                    Constraint::Length(
+                        state.current_disc().tracks.filter().len() as u16 + 2 + tabs_height,
+                    ),
                 );
 
                 let block = block(Some(&state.title));
 
                 frame.render_widget(Clear, area);
                 frame.render_widget(&block, area);
+
+                let chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Length(tabs_height), Constraint::Min(1)])
+                    .split(block.inner(area));
+
+                if state.discs.len() > 1 {
+                    let labels: Vec<String> = state
+                        .discs
+                        .iter()
+                        .map(|disc| format!("Disc {}", disc.number))
+                        .collect();
+                    let tabs = tab_bar(
+                        labels.iter().map(String::as_str).collect(),
+                        state.selected_disc,
+                    );
+                    frame.render_widget(tabs, chunks[0]);
+                }
+
                 state
+                    .current_disc_mut()
                     .tracks
-                    .render(block.inner(area), frame.buffer_mut(), false);
+                    .render(chunks[1], frame.buffer_mut(), false);
             }
             Popup::Artist(artist) => {
                 let visible_rows = (artist.current_row_count() + 1).min(15) as u16;
@@ -352,6 +477,19 @@ impl Popup {
                     }
                 }
             }
+            Popup::Label(state) => {
+                let visible_rows = state.albums.filter().len().min(15) as u16;
+                let popup_height =
+                    (visible_rows + 3).clamp(4, frame.area().height.saturating_sub(2));
+                let popup_width = (frame.area().width * 75 / 100).max(30);
+
+                let area = centered_rect_fixed(popup_width, popup_height, frame.area());
+                let block = block(Some(&state.name));
+
+                frame.render_widget(Clear, area);
+                frame.render_widget(&block, area);
+                state.albums.render(block.inner(area), frame.buffer_mut());
+            }
             Popup::Playlist(playlist_state) => {
                 let visible_rows = playlist_state.tracks.filter().len().min(15) as u16;
 
@@ -417,6 +555,21 @@ impl Popup {
                 frame.render_widget(Clear, area);
                 render_input(&state.name, false, area, frame, "Create playlist");
             }
+            Popup::OpenLink(state) => {
+                let area = center(
+                    frame.area(),
+                    Constraint::Percentage(75),
+                    Constraint::Length(3),
+                );
+
+                let title = match state.queue {
+                    true => "Queue Qobuz link",
+                    false => "Play Qobuz link",
+                };
+
+                frame.render_widget(Clear, area);
+                render_input(&state.url, false, area, frame, title);
+            }
             Popup::DeletePlaylist(state) => {
                 let block_title = format!("Delete {}?", state.title);
                 let area = center(
@@ -446,18 +599,63 @@ impl Popup {
     ) -> AppResult<Output> {
         match event {
             Event::Key(key_event) if key_event.kind == KeyEventKind::Press => match self {
-                Popup::Album(album_state) => {
-                    album_state
-                        .tracks
-                        .handle_events(
-                            key_event.code,
-                            client,
-                            controls,
-                            notifications,
-                            TrackListEvent::Album(album_state.id.clone()),
-                        )
-                        .await
-                }
+                Popup::Album(album_state) => match key_event.code {
+                    KeyCode::Left | KeyCode::Char('h') if album_state.discs.len() > 1 => {
+                        album_state.cycle_disc_backwards();
+                        Ok(Output::Consumed)
+                    }
+                    KeyCode::Right | KeyCode::Char('l') if album_state.discs.len() > 1 => {
+                        album_state.cycle_disc();
+                        Ok(Output::Consumed)
+                    }
+                    KeyCode::Char('P') => {
+                        let ids = album_state
+                            .current_disc()
+                            .tracks
+                            .all_items()
+                            .iter()
+                            .map(|x| x.id)
+                            .collect();
+                        controls.play_tracks(ids, false);
+                        Ok(Output::Consumed)
+                    }
+                    KeyCode::Char('L') => {
+                        let Some(label) = &album_state.label else {
+                            return Ok(Output::Consumed);
+                        };
+
+                        let label = client.label(label.id).await?;
+                        Ok(Output::Popup(Popup::Label(LabelPopupState::new(label))))
+                    }
+                    KeyCode::Char('W') => {
+                        let selected = album_state.current_disc().tracks.selected();
+                        let work = selected.and_then(|track| {
+                            album_state
+                                .works
+                                .iter()
+                                .find(|work| work.tracks.iter().any(|t| t.id == track.id))
+                        });
+
+                        if let Some(work) = work {
+                            let ids = work.tracks.iter().map(|x| x.id).collect();
+                            controls.play_tracks(ids, false);
+                        }
+                        Ok(Output::Consumed)
+                    }
+                    _ => {
+                        let id = album_state.id.clone();
+                        let disc = album_state.current_disc_mut();
+                        disc.tracks
+                            .handle_events(
+                                key_event.code,
+                                client,
+                                controls,
+                                notifications,
+                                TrackListEvent::Album(id, disc.first_index),
+                            )
+                            .await
+                    }
+                },
                 Popup::Artist(artist_popup_state) => match key_event.code {
                     KeyCode::Left | KeyCode::Char('h') => {
                         artist_popup_state.cycle_subtab_backwards();
@@ -546,6 +744,18 @@ impl Popup {
                     }
                     _ => Ok(Output::NotConsumed),
                 },
+                Popup::Label(label_state) => {
+                    let output = label_state
+                        .albums
+                        .handle_events(key_event.code, client, controls, notifications)
+                        .await?;
+
+                    if matches!(key_event.code, KeyCode::Down | KeyCode::Char('j')) {
+                        label_state.load_more(client).await?;
+                    }
+
+                    Ok(output)
+                }
                 Popup::NewPlaylist(state) => match key_event.code {
                     KeyCode::Enter => {
                         let input = state.name.value();
@@ -559,6 +769,20 @@ impl Popup {
                         Ok(Output::Consumed)
                     }
                 },
+                Popup::OpenLink(state) => match key_event.code {
+                    KeyCode::Enter => {
+                        let link = Link::parse(state.url.value())?;
+                        match state.queue {
+                            true => controls.queue_link(link),
+                            false => controls.play_link(link),
+                        }
+                        Ok(Output::PopPopup)
+                    }
+                    _ => {
+                        state.url.handle_event(&event);
+                        Ok(Output::Consumed)
+                    }
+                },
                 Popup::DeletePlaylist(state) => match key_event.code {
                     KeyCode::Enter => {
                         if state.confirm {
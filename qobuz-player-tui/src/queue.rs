@@ -1,6 +1,6 @@
 use qobuz_player_controls::{
-    controls::Controls,
-    models::{Track, TrackStatus},
+    controls::Controls, models::TrackStatus, tracklist::QueueItem,
+    tracklist_change::TracklistChange,
 };
 use ratatui::{
     crossterm::event::{Event, KeyCode, KeyEventKind},
@@ -15,14 +15,14 @@ use crate::{
 };
 
 pub struct QueueState {
-    items: Vec<Track>,
+    items: Vec<QueueItem>,
     state: TableState,
 }
 
 impl QueueState {
-    pub fn new(tracks: Vec<Track>) -> Self {
+    pub fn new(items: Vec<QueueItem>) -> Self {
         Self {
-            items: tracks,
+            items,
             state: Default::default(),
         }
     }
@@ -31,7 +31,8 @@ impl QueueState {
             self.items
                 .iter()
                 .enumerate()
-                .map(|(index, track)| {
+                .map(|(index, item)| {
+                    let track = &item.track;
                     let style = match track.status {
                         TrackStatus::Played => Style::default().add_modifier(Modifier::CROSSED_OUT),
                         TrackStatus::Playing => Style::default().add_modifier(Modifier::BOLD),
@@ -60,14 +61,36 @@ impl QueueState {
         frame.render_stateful_widget(table, area, &mut self.state);
     }
 
-    pub fn items(&self) -> &Vec<Track> {
+    pub fn items(&self) -> &Vec<QueueItem> {
         &self.items
     }
 
-    pub fn set_items(&mut self, items: Vec<Track>) {
+    pub fn set_items(&mut self, items: Vec<QueueItem>) {
         self.items = items
     }
 
+    /// Apply the changes and keep the selection on the same item. Returns
+    /// false if the items should be read from the tracklist again.
+    pub fn apply_changes(&mut self, changes: &[TracklistChange]) -> bool {
+        let selected_queue_id = self.selected_item().map(|(_, item)| item.queue_id);
+
+        if !changes.iter().all(|change| change.apply(&mut self.items)) {
+            return false;
+        }
+
+        if let Some(queue_id) = selected_queue_id {
+            let index = self.items.iter().position(|item| item.queue_id == queue_id);
+            self.state.select(index);
+        }
+
+        true
+    }
+
+    fn selected_item(&self) -> Option<(usize, &QueueItem)> {
+        let index = self.state.selected()?;
+        self.items.get(index).map(|item| (index, item))
+    }
+
     pub async fn handle_events(&mut self, event: Event, controls: &Controls) -> Output {
         match event {
             Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
@@ -81,49 +104,42 @@ impl QueueState {
                         Output::Consumed
                     }
                     KeyCode::Char('d') => {
-                        let index = self.state.selected();
-
-                        if let Some(index) = index {
+                        if let Some((index, item)) = self.selected_item() {
                             if index == self.items().len() - 1 {
                                 return Output::Consumed;
                             }
 
-                            let mut order: Vec<_> =
-                                self.items().iter().enumerate().map(|x| x.0).collect();
-
-                            order.swap(index, index + 1);
-                            controls.reorder_queue(order);
+                            controls.move_queue_item(item.queue_id, index + 1);
                         }
                         Output::Consumed
                     }
                     KeyCode::Char('u') => {
-                        let index = self.state.selected();
-
-                        if let Some(index) = index {
+                        if let Some((index, item)) = self.selected_item() {
                             if index == 0 {
                                 return Output::Consumed;
                             }
-                            let mut order: Vec<_> =
-                                self.items().iter().enumerate().map(|x| x.0).collect();
 
-                            order.swap(index, index - 1);
-                            controls.reorder_queue(order);
+                            controls.move_queue_item(item.queue_id, index - 1);
                         }
                         Output::Consumed
                     }
                     KeyCode::Char('D') => {
-                        let index = self.state.selected();
-
-                        if let Some(index) = index {
-                            controls.remove_index_from_queue(index);
+                        if let Some((_, item)) = self.selected_item() {
+                            controls.remove_queue_item(item.queue_id);
                         }
                         Output::Consumed
                     }
+                    KeyCode::Char('z') => {
+                        controls.undo_queue();
+                        Output::Consumed
+                    }
+                    KeyCode::Char('Z') => {
+                        controls.redo_queue();
+                        Output::Consumed
+                    }
                     KeyCode::Enter => {
-                        let index = self.state.selected();
-
-                        if let Some(index) = index {
-                            controls.skip_to_position(index, true);
+                        if let Some((_, item)) = self.selected_item() {
+                            controls.skip_to_queue_item(item.queue_id);
                         }
                         Output::Consumed
                     }
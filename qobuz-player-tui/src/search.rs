@@ -1,4 +1,8 @@
-use qobuz_player_controls::{AppResult, client::Client, controls::Controls};
+use qobuz_player_controls::{
+    AppResult,
+    client::{Client, SEARCH_PAGE_SIZE},
+    controls::Controls,
+};
 use ratatui::{
     crossterm::event::{Event, KeyCode, KeyEventKind},
     prelude::*,
@@ -26,6 +30,10 @@ pub struct SearchState {
     pub playlists: PlaylistList,
     pub tracks: TrackList,
     pub sub_tab: SubTab,
+    query: String,
+    /// Offset of the next page of results per sub tab, `None` once a page
+    /// came back short
+    next_offsets: [Option<u32>; SubTab::COUNT as usize],
 }
 
 impl SearchState {
@@ -86,34 +94,52 @@ impl SearchState {
                             self.cycle_subtab();
                             Ok(Output::Consumed)
                         }
-                        _ => match self.sub_tab {
-                            SubTab::Albums => {
-                                self.albums
-                                    .handle_events(key_event.code, client, controls, notifications)
-                                    .await
-                            }
-                            SubTab::Artists => {
-                                self.artists
-                                    .handle_events(key_event.code, client, notifications)
-                                    .await
-                            }
-                            SubTab::Playlists => {
-                                self.playlists
-                                    .handle_events(key_event.code, client, controls, notifications)
-                                    .await
-                            }
-                            SubTab::Tracks => {
-                                self.tracks
-                                    .handle_events(
-                                        key_event.code,
-                                        client,
-                                        controls,
-                                        notifications,
-                                        TrackListEvent::Track,
-                                    )
-                                    .await
+                        _ => {
+                            let output = match self.sub_tab {
+                                SubTab::Albums => {
+                                    self.albums
+                                        .handle_events(
+                                            key_event.code,
+                                            client,
+                                            controls,
+                                            notifications,
+                                        )
+                                        .await
+                                }
+                                SubTab::Artists => {
+                                    self.artists
+                                        .handle_events(key_event.code, client, notifications)
+                                        .await
+                                }
+                                SubTab::Playlists => {
+                                    self.playlists
+                                        .handle_events(
+                                            key_event.code,
+                                            client,
+                                            controls,
+                                            notifications,
+                                        )
+                                        .await
+                                }
+                                SubTab::Tracks => {
+                                    self.tracks
+                                        .handle_events(
+                                            key_event.code,
+                                            client,
+                                            controls,
+                                            notifications,
+                                            TrackListEvent::Track,
+                                        )
+                                        .await
+                                }
+                            }?;
+
+                            if matches!(key_event.code, KeyCode::Down | KeyCode::Char('j')) {
+                                self.load_more(client).await?;
                             }
-                        },
+
+                            Ok(output)
+                        }
                     },
                     true => match key_event.code {
                         KeyCode::Esc | KeyCode::Enter => {
@@ -134,7 +160,15 @@ impl SearchState {
 
     async fn update_search(&mut self, client: &Client) -> AppResult<()> {
         if !self.filter.value().trim().is_empty() {
-            let search_results = client.search(self.filter.value().to_string()).await?;
+            self.query = self.filter.value().to_string();
+            let search_results = client.search(self.query.clone()).await?;
+
+            self.next_offsets = [
+                next_offset(0, search_results.albums.len()),
+                next_offset(0, search_results.artists.len()),
+                next_offset(0, search_results.playlists.len()),
+                next_offset(0, search_results.tracks.len()),
+            ];
 
             self.albums.set_all_items(
                 search_results
@@ -157,6 +191,62 @@ impl SearchState {
         Ok(())
     }
 
+    /// Fetch the next page of the current sub tab once its last result is
+    /// selected
+    async fn load_more(&mut self, client: &Client) -> AppResult<()> {
+        let tab = self.sub_tab.selected() as usize;
+        let last_selected = match self.sub_tab {
+            SubTab::Albums => self.albums.is_last_selected(),
+            SubTab::Artists => self.artists.is_last_selected(),
+            SubTab::Playlists => self.playlists.is_last_selected(),
+            SubTab::Tracks => self.tracks.is_last_selected(),
+        };
+
+        let Some(offset) = self.next_offsets[tab].filter(|_| last_selected) else {
+            return Ok(());
+        };
+
+        let search_results = client.search_page(self.query.clone(), offset).await?;
+
+        let count = match self.sub_tab {
+            SubTab::Albums => {
+                let count = search_results.albums.len();
+                self.albums.extend(
+                    search_results
+                        .albums
+                        .into_iter()
+                        .map(|x| x.into())
+                        .collect(),
+                );
+                count
+            }
+            SubTab::Artists => {
+                let count = search_results.artists.len();
+                self.artists.extend(search_results.artists);
+                count
+            }
+            SubTab::Playlists => {
+                let count = search_results.playlists.len();
+                self.playlists.extend(
+                    search_results
+                        .playlists
+                        .into_iter()
+                        .map(|x| x.into())
+                        .collect(),
+                );
+                count
+            }
+            SubTab::Tracks => {
+                let count = search_results.tracks.len();
+                self.tracks.extend(search_results.tracks);
+                count
+            }
+        };
+
+        self.next_offsets[tab] = next_offset(offset, count);
+        Ok(())
+    }
+
     fn start_editing(&mut self) {
         self.editing = true;
     }
@@ -173,3 +263,7 @@ impl SearchState {
         self.sub_tab = self.sub_tab.next();
     }
 }
+
+fn next_offset(offset: u32, count: usize) -> Option<u32> {
+    (count >= SEARCH_PAGE_SIZE as usize).then_some(offset + SEARCH_PAGE_SIZE)
+}
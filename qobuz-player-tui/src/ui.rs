@@ -1,4 +1,7 @@
-use qobuz_player_controls::{models::Album, notification::Notification};
+use qobuz_player_controls::{
+    models::{Album, Track},
+    notification::Notification,
+};
 use ratatui::{layout::Flex, prelude::*, widgets::*};
 use ratatui_image::{StatefulImage, protocol::StatefulProtocol};
 use tui_input::Input;
@@ -24,7 +27,9 @@ impl App {
 
         if let AppState::AlbumInfo(album) = &self.app_state {
             let album = album.clone();
-            render_album_info(frame, &album, &mut self.now_playing.image);
+            let playing_id = self.now_playing.playing_track.as_ref().map(|t| t.id);
+            let track = album.tracks.iter().find(|t| Some(t.id) == playing_id);
+            render_album_info(frame, &album, track, &mut self.now_playing.image);
         }
 
         self.render_notifications(frame, area);
@@ -119,7 +124,7 @@ impl App {
                 Notification::Error(msg) => ("Error", msg, Color::Red),
                 Notification::Warning(msg) => ("Warning", msg, Color::Yellow),
                 Notification::Success(msg) => ("Success", msg, Color::Green),
-                Notification::Info(msg) => ("Info", msg, Color::Blue),
+                Notification::Info(msg) | Notification::Undoable(msg) => ("Info", msg, Color::Blue),
             });
 
         let inner_width = 60;
@@ -213,6 +218,8 @@ fn render_help(frame: &mut Frame) {
         ["Delete from queue", "D"],
         ["Move up in queue", "u"],
         ["Move down in queue", "d"],
+        ["Undo queue edit", "z (queue page)"],
+        ["Redo queue edit", "Z (queue page)"],
         ["Remove from favorites", "D"],
         ["Add to favorites", "A"],
         ["Create playlist", "C (playlist page)"],
@@ -221,6 +228,11 @@ fn render_help(frame: &mut Frame) {
         ["Move playlist track up", "u"],
         ["Move playlist track down", "d"],
         ["Album info", "i"],
+        ["Play disc", "P (album popup)"],
+        ["Play work", "W (album popup)"],
+        ["Show label", "L (album popup)"],
+        ["Play Qobuz link", "o"],
+        ["Queue Qobuz link", "O"],
         ["Exit", "q"],
     ];
 
@@ -248,6 +260,7 @@ fn render_help(frame: &mut Frame) {
 fn render_album_info(
     frame: &mut Frame,
     album: &Album,
+    track: Option<&Track>,
     image: &mut Option<(StatefulProtocol, f32)>,
 ) {
     let mut info_lines: Vec<Line> = Vec::new();
@@ -266,7 +279,9 @@ fn render_album_info(
         format_seconds(album.duration_seconds)
     )));
 
-    if album.hires_available {
+    if let Some(quality) = album.quality() {
+        info_lines.push(Line::from(format!("Quality:  {quality}")));
+    } else if album.hires_available {
         info_lines.push(Line::from("Quality:  Hi-Res"));
     }
 
@@ -274,6 +289,51 @@ fn render_album_info(
         info_lines.push(Line::from("Explicit: Yes"));
     }
 
+    if album.disc_count > 1 {
+        info_lines.push(Line::from(format!("Discs:    {}", album.disc_count)));
+    }
+
+    if let Some(label) = &album.label {
+        info_lines.push(Line::from(format!("Label:    {}", label.name)));
+    }
+
+    if let Some(genre) = &album.genre {
+        info_lines.push(Line::from(format!("Genre:    {}", genre.name)));
+    }
+
+    if let Some(composer) = &album.composer {
+        info_lines.push(Line::from(format!("Composer: {}", composer.name)));
+    }
+
+    if let Some(upc) = &album.upc {
+        info_lines.push(Line::from(format!("UPC:      {upc}")));
+    }
+
+    if let Some(copyright) = &album.copyright {
+        info_lines.push(Line::from(copyright.clone()));
+    }
+
+    if let Some(track) = track {
+        info_lines.push(Line::from(""));
+        info_lines.push(Line::from(track.title.clone()).style(Style::new().bold()));
+
+        if let Some(composer) = &track.composer {
+            info_lines.push(Line::from(format!("Composer: {}", composer.name)));
+        }
+
+        if let Some(isrc) = &track.isrc {
+            info_lines.push(Line::from(format!("ISRC:     {isrc}")));
+        }
+
+        for credit in &track.credits {
+            info_lines.push(Line::from(format!(
+                "{}: {}",
+                credit.name,
+                credit.roles.join(", ")
+            )));
+        }
+    }
+
     let info_height = info_lines.len() as u16;
 
     let box_width = frame.area().width / 2;
@@ -50,6 +50,14 @@ impl ArtistList {
         self.items.set_all_items(items);
     }
 
+    pub fn extend(&mut self, items: Vec<Artist>) {
+        self.items.extend(items);
+    }
+
+    pub fn is_last_selected(&self) -> bool {
+        self.items.is_last_selected()
+    }
+
     pub async fn handle_events(
         &mut self,
         event: KeyCode,
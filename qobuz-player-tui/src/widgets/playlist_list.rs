@@ -45,6 +45,14 @@ impl PlaylistList {
         self.items.set_all_items(items);
     }
 
+    pub fn extend(&mut self, items: Vec<PlaylistSimple>) {
+        self.items.extend(items);
+    }
+
+    pub fn is_last_selected(&self) -> bool {
+        self.items.is_last_selected()
+    }
+
     pub fn selected(&self) -> Option<usize> {
         self.items.state.selected()
     }
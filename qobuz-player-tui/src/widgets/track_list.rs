@@ -22,7 +22,8 @@ pub struct TrackList {
 
 pub enum TrackListEvent {
     Track,
-    Album(String),
+    /// Album id and the album position of the first track in the list
+    Album(String, usize),
     Playlist(u32, bool),
     Artist(u32),
 }
@@ -54,6 +55,21 @@ impl TrackList {
         self.items.set_all_items(items);
     }
 
+    pub fn extend(&mut self, items: Vec<Track>) {
+        self.items.extend(items);
+    }
+
+    pub fn is_last_selected(&self) -> bool {
+        self.items.is_last_selected()
+    }
+
+    pub fn selected(&self) -> Option<&Track> {
+        self.items
+            .state
+            .selected()
+            .and_then(|index| self.items.filter().get(index))
+    }
+
     pub fn filter(&self) -> &Vec<Track> {
         self.items.filter()
     }
@@ -159,7 +175,9 @@ impl TrackList {
                             controls.play_track(selected.id);
                         }
                     }
-                    TrackListEvent::Album(id) => controls.play_album(&id, index),
+                    TrackListEvent::Album(id, first_index) => {
+                        controls.play_album(&id, first_index + index)
+                    }
                     TrackListEvent::Playlist(id, shuffle) => {
                         controls.play_playlist(id, index, shuffle)
                     }
@@ -180,8 +198,13 @@ fn track_table<'a>(rows: &[Track], show_album: bool) -> Table<'a> {
         .map(|track| {
             let mut cols: Vec<Line<'a>> = Vec::with_capacity(if show_album { 4 } else { 3 });
 
+            let title = match &track.work {
+                Some(work) => format!("{work}: {}", track.title),
+                None => track.title.clone(),
+            };
+
             cols.push(mark_explicit_and_hifi(
-                track.title.clone(),
+                title,
                 track.explicit,
                 track.hires_available,
             ));
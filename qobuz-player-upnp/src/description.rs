@@ -0,0 +1,441 @@
+use crate::soap::escape;
+
+pub(crate) const DEVICE_TYPE: &str = "urn:schemas-upnp-org:device:MediaRenderer:1";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Service {
+    AVTransport,
+    RenderingControl,
+    ConnectionManager,
+}
+
+impl Service {
+    pub const ALL: [Service; 3] = [
+        Service::AVTransport,
+        Service::RenderingControl,
+        Service::ConnectionManager,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Service::AVTransport => "AVTransport",
+            Service::RenderingControl => "RenderingControl",
+            Service::ConnectionManager => "ConnectionManager",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|service| service.name() == name)
+    }
+
+    pub fn service_type(self) -> String {
+        format!("urn:schemas-upnp-org:service:{}:1", self.name())
+    }
+
+    fn actions(self) -> &'static [Action] {
+        match self {
+            Service::AVTransport => AV_TRANSPORT_ACTIONS,
+            Service::RenderingControl => RENDERING_CONTROL_ACTIONS,
+            Service::ConnectionManager => CONNECTION_MANAGER_ACTIONS,
+        }
+    }
+
+    fn variables(self) -> &'static [Variable] {
+        match self {
+            Service::AVTransport => AV_TRANSPORT_VARIABLES,
+            Service::RenderingControl => RENDERING_CONTROL_VARIABLES,
+            Service::ConnectionManager => CONNECTION_MANAGER_VARIABLES,
+        }
+    }
+}
+
+struct Action {
+    name: &'static str,
+    /// Name and related state variable
+    inputs: &'static [(&'static str, &'static str)],
+    outputs: &'static [(&'static str, &'static str)],
+}
+
+struct Variable {
+    name: &'static str,
+    data_type: &'static str,
+    evented: bool,
+    allowed: &'static [&'static str],
+    range: Option<(u32, u32)>,
+}
+
+const fn variable(name: &'static str, data_type: &'static str) -> Variable {
+    Variable {
+        name,
+        data_type,
+        evented: false,
+        allowed: &[],
+        range: None,
+    }
+}
+
+const fn allowed(name: &'static str, allowed: &'static [&'static str]) -> Variable {
+    Variable {
+        name,
+        data_type: "string",
+        evented: false,
+        allowed,
+        range: None,
+    }
+}
+
+const fn evented(name: &'static str) -> Variable {
+    Variable {
+        name,
+        data_type: "string",
+        evented: true,
+        allowed: &[],
+        range: None,
+    }
+}
+
+const INSTANCE_ID: (&str, &str) = ("InstanceID", "A_ARG_TYPE_InstanceID");
+const CHANNEL: (&str, &str) = ("Channel", "A_ARG_TYPE_Channel");
+
+const AV_TRANSPORT_ACTIONS: &[Action] = &[
+    Action {
+        name: "SetAVTransportURI",
+        inputs: &[
+            INSTANCE_ID,
+            ("CurrentURI", "AVTransportURI"),
+            ("CurrentURIMetaData", "AVTransportURIMetaData"),
+        ],
+        outputs: &[],
+    },
+    Action {
+        name: "SetNextAVTransportURI",
+        inputs: &[
+            INSTANCE_ID,
+            ("NextURI", "NextAVTransportURI"),
+            ("NextURIMetaData", "NextAVTransportURIMetaData"),
+        ],
+        outputs: &[],
+    },
+    Action {
+        name: "GetMediaInfo",
+        inputs: &[INSTANCE_ID],
+        outputs: &[
+            ("NrTracks", "NumberOfTracks"),
+            ("MediaDuration", "CurrentMediaDuration"),
+            ("CurrentURI", "AVTransportURI"),
+            ("CurrentURIMetaData", "AVTransportURIMetaData"),
+            ("NextURI", "NextAVTransportURI"),
+            ("NextURIMetaData", "NextAVTransportURIMetaData"),
+            ("PlayMedium", "PlaybackStorageMedium"),
+            ("RecordMedium", "RecordStorageMedium"),
+            ("WriteStatus", "RecordMediumWriteStatus"),
+        ],
+    },
+    Action {
+        name: "GetTransportInfo",
+        inputs: &[INSTANCE_ID],
+        outputs: &[
+            ("CurrentTransportState", "TransportState"),
+            ("CurrentTransportStatus", "TransportStatus"),
+            ("CurrentSpeed", "TransportPlaySpeed"),
+        ],
+    },
+    Action {
+        name: "GetPositionInfo",
+        inputs: &[INSTANCE_ID],
+        outputs: &[
+            ("Track", "CurrentTrack"),
+            ("TrackDuration", "CurrentTrackDuration"),
+            ("TrackMetaData", "CurrentTrackMetaData"),
+            ("TrackURI", "CurrentTrackURI"),
+            ("RelTime", "RelativeTimePosition"),
+            ("AbsTime", "AbsoluteTimePosition"),
+            ("RelCount", "RelativeCounterPosition"),
+            ("AbsCount", "AbsoluteCounterPosition"),
+        ],
+    },
+    Action {
+        name: "GetDeviceCapabilities",
+        inputs: &[INSTANCE_ID],
+        outputs: &[
+            ("PlayMedia", "PossiblePlaybackStorageMedia"),
+            ("RecMedia", "PossibleRecordStorageMedia"),
+            ("RecQualityModes", "PossibleRecordQualityModes"),
+        ],
+    },
+    Action {
+        name: "GetTransportSettings",
+        inputs: &[INSTANCE_ID],
+        outputs: &[
+            ("PlayMode", "CurrentPlayMode"),
+            ("RecQualityMode", "CurrentRecordQualityMode"),
+        ],
+    },
+    Action {
+        name: "GetCurrentTransportActions",
+        inputs: &[INSTANCE_ID],
+        outputs: &[("Actions", "CurrentTransportActions")],
+    },
+    Action {
+        name: "Stop",
+        inputs: &[INSTANCE_ID],
+        outputs: &[],
+    },
+    Action {
+        name: "Play",
+        inputs: &[INSTANCE_ID, ("Speed", "TransportPlaySpeed")],
+        outputs: &[],
+    },
+    Action {
+        name: "Pause",
+        inputs: &[INSTANCE_ID],
+        outputs: &[],
+    },
+    Action {
+        name: "Seek",
+        inputs: &[
+            INSTANCE_ID,
+            ("Unit", "A_ARG_TYPE_SeekMode"),
+            ("Target", "A_ARG_TYPE_SeekTarget"),
+        ],
+        outputs: &[],
+    },
+    Action {
+        name: "Next",
+        inputs: &[INSTANCE_ID],
+        outputs: &[],
+    },
+    Action {
+        name: "Previous",
+        inputs: &[INSTANCE_ID],
+        outputs: &[],
+    },
+];
+
+const AV_TRANSPORT_VARIABLES: &[Variable] = &[
+    allowed(
+        "TransportState",
+        &[
+            "STOPPED",
+            "PLAYING",
+            "PAUSED_PLAYBACK",
+            "TRANSITIONING",
+            "NO_MEDIA_PRESENT",
+        ],
+    ),
+    allowed("TransportStatus", &["OK", "ERROR_OCCURRED"]),
+    allowed("PlaybackStorageMedium", &["NETWORK", "NONE"]),
+    allowed("RecordStorageMedium", &["NOT_IMPLEMENTED"]),
+    variable("PossiblePlaybackStorageMedia", "string"),
+    variable("PossibleRecordStorageMedia", "string"),
+    allowed("CurrentPlayMode", &["NORMAL"]),
+    allowed("TransportPlaySpeed", &["1"]),
+    allowed("RecordMediumWriteStatus", &["NOT_IMPLEMENTED"]),
+    allowed("CurrentRecordQualityMode", &["NOT_IMPLEMENTED"]),
+    variable("PossibleRecordQualityModes", "string"),
+    variable("NumberOfTracks", "ui4"),
+    variable("CurrentTrack", "ui4"),
+    variable("CurrentTrackDuration", "string"),
+    variable("CurrentMediaDuration", "string"),
+    variable("CurrentTrackMetaData", "string"),
+    variable("CurrentTrackURI", "string"),
+    variable("AVTransportURI", "string"),
+    variable("AVTransportURIMetaData", "string"),
+    variable("NextAVTransportURI", "string"),
+    variable("NextAVTransportURIMetaData", "string"),
+    variable("RelativeTimePosition", "string"),
+    variable("AbsoluteTimePosition", "string"),
+    variable("RelativeCounterPosition", "i4"),
+    variable("AbsoluteCounterPosition", "i4"),
+    variable("CurrentTransportActions", "string"),
+    evented("LastChange"),
+    allowed("A_ARG_TYPE_SeekMode", &["TRACK_NR", "REL_TIME", "ABS_TIME"]),
+    variable("A_ARG_TYPE_SeekTarget", "string"),
+    variable("A_ARG_TYPE_InstanceID", "ui4"),
+];
+
+const RENDERING_CONTROL_ACTIONS: &[Action] = &[
+    Action {
+        name: "ListPresets",
+        inputs: &[INSTANCE_ID],
+        outputs: &[("CurrentPresetNameList", "PresetNameList")],
+    },
+    Action {
+        name: "SelectPreset",
+        inputs: &[INSTANCE_ID, ("PresetName", "A_ARG_TYPE_PresetName")],
+        outputs: &[],
+    },
+    Action {
+        name: "GetMute",
+        inputs: &[INSTANCE_ID, CHANNEL],
+        outputs: &[("CurrentMute", "Mute")],
+    },
+    Action {
+        name: "SetMute",
+        inputs: &[INSTANCE_ID, CHANNEL, ("DesiredMute", "Mute")],
+        outputs: &[],
+    },
+    Action {
+        name: "GetVolume",
+        inputs: &[INSTANCE_ID, CHANNEL],
+        outputs: &[("CurrentVolume", "Volume")],
+    },
+    Action {
+        name: "SetVolume",
+        inputs: &[INSTANCE_ID, CHANNEL, ("DesiredVolume", "Volume")],
+        outputs: &[],
+    },
+];
+
+const RENDERING_CONTROL_VARIABLES: &[Variable] = &[
+    evented("LastChange"),
+    variable("PresetNameList", "string"),
+    allowed("A_ARG_TYPE_PresetName", &["FactoryDefaults"]),
+    variable("Mute", "boolean"),
+    Variable {
+        name: "Volume",
+        data_type: "ui2",
+        evented: false,
+        allowed: &[],
+        range: Some((0, 100)),
+    },
+    allowed("A_ARG_TYPE_Channel", &["Master"]),
+    variable("A_ARG_TYPE_InstanceID", "ui4"),
+];
+
+const CONNECTION_MANAGER_ACTIONS: &[Action] = &[
+    Action {
+        name: "GetProtocolInfo",
+        inputs: &[],
+        outputs: &[
+            ("Source", "SourceProtocolInfo"),
+            ("Sink", "SinkProtocolInfo"),
+        ],
+    },
+    Action {
+        name: "GetCurrentConnectionIDs",
+        inputs: &[],
+        outputs: &[("ConnectionIDs", "CurrentConnectionIDs")],
+    },
+    Action {
+        name: "GetCurrentConnectionInfo",
+        inputs: &[("ConnectionID", "A_ARG_TYPE_ConnectionID")],
+        outputs: &[
+            ("RcsID", "A_ARG_TYPE_RcsID"),
+            ("AVTransportID", "A_ARG_TYPE_AVTransportID"),
+            ("ProtocolInfo", "A_ARG_TYPE_ProtocolInfo"),
+            ("PeerConnectionManager", "A_ARG_TYPE_ConnectionManager"),
+            ("PeerConnectionID", "A_ARG_TYPE_ConnectionID"),
+            ("Direction", "A_ARG_TYPE_Direction"),
+            ("Status", "A_ARG_TYPE_ConnectionStatus"),
+        ],
+    },
+];
+
+const CONNECTION_MANAGER_VARIABLES: &[Variable] = &[
+    evented("SourceProtocolInfo"),
+    evented("SinkProtocolInfo"),
+    evented("CurrentConnectionIDs"),
+    allowed(
+        "A_ARG_TYPE_ConnectionStatus",
+        &[
+            "OK",
+            "ContentFormatMismatch",
+            "InsufficientBandwidth",
+            "UnreliableChannel",
+            "Unknown",
+        ],
+    ),
+    variable("A_ARG_TYPE_ConnectionManager", "string"),
+    allowed("A_ARG_TYPE_Direction", &["Input", "Output"]),
+    variable("A_ARG_TYPE_ProtocolInfo", "string"),
+    variable("A_ARG_TYPE_ConnectionID", "i4"),
+    variable("A_ARG_TYPE_AVTransportID", "i4"),
+    variable("A_ARG_TYPE_RcsID", "i4"),
+];
+
+pub(crate) fn device_description(uuid: &str, friendly_name: &str) -> String {
+    let services: String = Service::ALL
+        .into_iter()
+        .map(|service| {
+            let name = service.name();
+            format!(
+                "<service><serviceType>{}</serviceType>\
+                 <serviceId>urn:upnp-org:serviceId:{name}</serviceId>\
+                 <SCPDURL>/{name}/scpd.xml</SCPDURL>\
+                 <controlURL>/{name}/control</controlURL>\
+                 <eventSubURL>/{name}/event</eventSubURL></service>",
+                service.service_type()
+            )
+        })
+        .collect();
+
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<root xmlns="urn:schemas-upnp-org:device-1-0"><specVersion><major>1</major><minor>0</minor></specVersion><device><deviceType>{DEVICE_TYPE}</deviceType><friendlyName>{}</friendlyName><manufacturer>qobuz-player</manufacturer><manufacturerURL>https://github.com/sofusa/qobuz-player</manufacturerURL><modelName>qobuz-player</modelName><modelNumber>{}</modelNumber><UDN>{uuid}</UDN><serviceList>{services}</serviceList></device></root>"#,
+        escape(friendly_name),
+        env!("CARGO_PKG_VERSION"),
+    )
+}
+
+/// The SCPD document describing the actions and state variables of a service
+pub(crate) fn scpd(service: Service) -> String {
+    let actions: String = service
+        .actions()
+        .iter()
+        .map(|action| {
+            let inputs = action.inputs.iter().map(|x| (x, "in"));
+            let outputs = action.outputs.iter().map(|x| (x, "out"));
+            let arguments: String = inputs
+                .chain(outputs)
+                .map(|((name, variable), direction)| {
+                    format!(
+                        "<argument><name>{name}</name><direction>{direction}</direction>\
+                         <relatedStateVariable>{variable}</relatedStateVariable></argument>"
+                    )
+                })
+                .collect();
+
+            format!(
+                "<action><name>{}</name><argumentList>{arguments}</argumentList></action>",
+                action.name
+            )
+        })
+        .collect();
+
+    let variables: String = service
+        .variables()
+        .iter()
+        .map(|variable| {
+            let allowed: String = match variable.allowed {
+                [] => String::new(),
+                values => {
+                    let values: String = values
+                        .iter()
+                        .map(|value| format!("<allowedValue>{value}</allowedValue>"))
+                        .collect();
+                    format!("<allowedValueList>{values}</allowedValueList>")
+                }
+            };
+            let range = match variable.range {
+                Some((minimum, maximum)) => format!(
+                    "<allowedValueRange><minimum>{minimum}</minimum>\
+                     <maximum>{maximum}</maximum><step>1</step></allowedValueRange>"
+                ),
+                None => String::new(),
+            };
+
+            format!(
+                r#"<stateVariable sendEvents="{}"><name>{}</name><dataType>{}</dataType>{allowed}{range}</stateVariable>"#,
+                if variable.evented { "yes" } else { "no" },
+                variable.name,
+                variable.data_type,
+            )
+        })
+        .collect();
+
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<scpd xmlns="urn:schemas-upnp-org:service-1-0"><specVersion><major>1</major><minor>0</minor></specVersion><actionList>{actions}</actionList><serviceStateTable>{variables}</serviceStateTable></scpd>"#
+    )
+}
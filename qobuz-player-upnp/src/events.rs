@@ -0,0 +1,338 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, Method, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+use crate::{
+    RendererState, SINK_PROTOCOL_INFO,
+    description::Service,
+    didl,
+    soap::{escape, format_time},
+};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(1800);
+
+/// A control point subscribed to the events of a service
+pub(crate) struct Subscription {
+    sid: String,
+    service: Service,
+    callbacks: Vec<String>,
+    expires: Instant,
+    /// Sequence number of the next event
+    seq: u32,
+}
+
+/// Handles SUBSCRIBE, renewals and UNSUBSCRIBE for a service
+pub(crate) async fn subscription(
+    State(state): State<Arc<RendererState>>,
+    Path(service): Path<String>,
+    method: Method,
+    headers: HeaderMap,
+) -> Response {
+    let Some(service) = Service::from_name(&service) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+    };
+
+    match method.as_str() {
+        "SUBSCRIBE" => {
+            let timeout = parse_timeout(header("timeout"));
+
+            if let Some(sid) = header("sid") {
+                let mut subscriptions = state.subscriptions.lock();
+                return match subscriptions.iter_mut().find(|x| x.sid == sid) {
+                    Some(subscription) => {
+                        subscription.expires = Instant::now() + timeout;
+                        subscribed(sid, timeout)
+                    }
+                    None => StatusCode::PRECONDITION_FAILED.into_response(),
+                };
+            }
+
+            let callbacks = parse_callbacks(header("callback").unwrap_or_default());
+            if header("nt") != Some("upnp:event") || callbacks.is_empty() {
+                return StatusCode::PRECONDITION_FAILED.into_response();
+            }
+
+            let sid = new_sid(&state.uuid);
+            state.subscriptions.lock().push(Subscription {
+                sid: sid.clone(),
+                service,
+                callbacks,
+                expires: Instant::now() + timeout,
+                seq: 0,
+            });
+
+            // The initial event with all evented variables follows the response
+            let initial_state = state.clone();
+            let initial_sid = sid.clone();
+            tokio::spawn(async move {
+                let properties = properties(&initial_state, service);
+                notify(&initial_state, Some(&initial_sid), service, &properties).await;
+            });
+
+            subscribed(&sid, timeout)
+        }
+        "UNSUBSCRIBE" => {
+            let Some(sid) = header("sid") else {
+                return StatusCode::PRECONDITION_FAILED.into_response();
+            };
+
+            let mut subscriptions = state.subscriptions.lock();
+            let count = subscriptions.len();
+            subscriptions.retain(|x| x.sid != sid);
+
+            match subscriptions.len() < count {
+                true => StatusCode::OK.into_response(),
+                false => StatusCode::PRECONDITION_FAILED.into_response(),
+            }
+        }
+        _ => StatusCode::METHOD_NOT_ALLOWED.into_response(),
+    }
+}
+
+fn subscribed(sid: &str, timeout: Duration) -> Response {
+    (
+        [
+            ("SID", sid.to_string()),
+            ("TIMEOUT", format!("Second-{}", timeout.as_secs())),
+        ],
+        StatusCode::OK,
+    )
+        .into_response()
+}
+
+/// `Second-300`, or the default for `infinite` and missing timeouts
+fn parse_timeout(timeout: Option<&str>) -> Duration {
+    timeout
+        .and_then(|timeout| timeout.strip_prefix("Second-"))
+        .and_then(|seconds| seconds.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_TIMEOUT)
+}
+
+/// `<http://host/a><http://host/b>`
+fn parse_callbacks(callback: &str) -> Vec<String> {
+    callback
+        .split('<')
+        .filter_map(|part| part.split_once('>'))
+        .map(|(url, _)| url.trim().to_string())
+        .filter(|url| url.starts_with("http://"))
+        .collect()
+}
+
+fn new_sid(uuid: &str) -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let hash = format!(
+        "{:x}",
+        md5::compute(format!("{uuid}-{count}-{:?}", Instant::now()))
+    );
+
+    format!(
+        "uuid:{}-{}-{}-{}-{}",
+        &hash[0..8],
+        &hash[8..12],
+        &hash[12..16],
+        &hash[16..20],
+        &hash[20..32]
+    )
+}
+
+/// The evented state variables of a service
+fn properties(state: &RendererState, service: Service) -> Vec<(&'static str, String)> {
+    match service {
+        Service::AVTransport => vec![("LastChange", av_transport_last_change(state))],
+        Service::RenderingControl => vec![("LastChange", rendering_control_last_change(state))],
+        Service::ConnectionManager => vec![
+            ("SourceProtocolInfo", String::new()),
+            ("SinkProtocolInfo", SINK_PROTOCOL_INFO.into()),
+            ("CurrentConnectionIDs", "0".into()),
+        ],
+    }
+}
+
+fn last_change(namespace: &str, variables: &[(&str, String)]) -> String {
+    let variables: String = variables
+        .iter()
+        .map(|(name, value)| format!(r#"<{name} val="{}"/>"#, escape(value)))
+        .collect();
+
+    format!(
+        r#"<Event xmlns="urn:schemas-upnp-org:metadata-1-0/{namespace}/"><InstanceID val="0">{variables}</InstanceID></Event>"#
+    )
+}
+
+fn av_transport_last_change(state: &RendererState) -> String {
+    let tracklist = state.tracklist_receiver.borrow();
+    let uri = state.current_track_uri(&tracklist);
+    let (track_number, duration, metadata) = match tracklist.current_track() {
+        Some(track) => (
+            tracklist.current_position() + 1,
+            format_time(Duration::from_secs(u64::from(track.duration_seconds))),
+            didl(track, &uri),
+        ),
+        None => (0, format_time(Duration::ZERO), String::new()),
+    };
+
+    let transport = state.transport.lock();
+    last_change(
+        "AVT",
+        &[
+            ("TransportState", state.transport_state(&tracklist).into()),
+            ("TransportStatus", "OK".into()),
+            (
+                "CurrentTransportActions",
+                state.transport_actions(&tracklist).into(),
+            ),
+            ("NumberOfTracks", tracklist.total().to_string()),
+            ("CurrentTrack", track_number.to_string()),
+            ("CurrentTrackDuration", duration),
+            ("CurrentMediaDuration", state.media_duration(&tracklist)),
+            ("CurrentTrackURI", uri),
+            ("CurrentTrackMetaData", metadata),
+            ("AVTransportURI", transport.uri.clone()),
+            ("AVTransportURIMetaData", transport.uri_metadata.clone()),
+            ("NextAVTransportURI", transport.next_uri.clone()),
+            (
+                "NextAVTransportURIMetaData",
+                transport.next_uri_metadata.clone(),
+            ),
+        ],
+    )
+}
+
+fn rendering_control_last_change(state: &RendererState) -> String {
+    let volume = state.volume();
+    let variables = [
+        ("Volume", volume.to_string()),
+        ("Mute", u8::from(volume == 0).to_string()),
+    ];
+
+    let variables: String = variables
+        .iter()
+        .map(|(name, value)| format!(r#"<{name} channel="Master" val="{value}"/>"#))
+        .collect();
+
+    format!(
+        r#"<Event xmlns="urn:schemas-upnp-org:metadata-1-0/RCS/"><InstanceID val="0">{variables}</InstanceID></Event>"#
+    )
+}
+
+/// Send the properties to the subscription with `sid`, or to every
+/// subscription of the service
+async fn notify(
+    state: &RendererState,
+    sid: Option<&str>,
+    service: Service,
+    properties: &[(&str, String)],
+) {
+    let deliveries: Vec<(String, Vec<String>, u32)> = {
+        let mut subscriptions = state.subscriptions.lock();
+        let now = Instant::now();
+        subscriptions.retain(|x| x.expires > now);
+
+        subscriptions
+            .iter_mut()
+            .filter(|x| x.service == service && sid.is_none_or(|sid| sid == x.sid))
+            .map(|x| {
+                let seq = x.seq;
+                x.seq = x.seq.wrapping_add(1).max(1);
+                (x.sid.clone(), x.callbacks.clone(), seq)
+            })
+            .collect()
+    };
+
+    let properties: String = properties
+        .iter()
+        .map(|(name, value)| {
+            format!(
+                "<e:property><{name}>{}</{name}></e:property>",
+                escape(value)
+            )
+        })
+        .collect();
+    let body = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<e:propertyset xmlns:e="urn:schemas-upnp-org:event-1-0">{properties}</e:propertyset>"#
+    );
+
+    let notify_method = Method::from_bytes(b"NOTIFY").expect("valid method");
+
+    for (sid, callbacks, seq) in deliveries {
+        // Callbacks are tried in order until one takes the event
+        for callback in callbacks {
+            let response = state
+                .http
+                .request(notify_method.clone(), &callback)
+                .header("CONTENT-TYPE", r#"text/xml; charset="utf-8""#)
+                .header("NT", "upnp:event")
+                .header("NTS", "upnp:propchange")
+                .header("SID", &sid)
+                .header("SEQ", seq.to_string())
+                .body(body.clone())
+                .send()
+                .await;
+
+            match response {
+                Ok(response) if response.status().is_success() => break,
+                Ok(response) => {
+                    tracing::debug!("UPnP event to {callback} failed: {}", response.status())
+                }
+                Err(err) => tracing::debug!("UPnP event to {callback} failed: {err}"),
+            }
+        }
+    }
+}
+
+/// Send LastChange events to the subscribers when the player changes
+pub(crate) async fn notify_changes(state: Arc<RendererState>) {
+    let mut tracklist = state.tracklist_receiver.clone();
+    let mut status = state.status_receiver.clone();
+    let mut volume = state.volume_receiver.clone();
+
+    let mut av_transport = av_transport_last_change(&state);
+    let mut rendering_control = rendering_control_last_change(&state);
+
+    loop {
+        let changed = tokio::select! {
+            changed = tracklist.changed() => changed,
+            changed = status.changed() => changed,
+            changed = volume.changed() => changed,
+        };
+        if changed.is_err() {
+            return;
+        }
+        tracklist.mark_unchanged();
+        status.mark_unchanged();
+        volume.mark_unchanged();
+
+        let last_change = av_transport_last_change(&state);
+        if last_change != av_transport {
+            av_transport = last_change;
+            let properties = [("LastChange", av_transport.clone())];
+            notify(&state, None, Service::AVTransport, &properties).await;
+        }
+
+        let last_change = rendering_control_last_change(&state);
+        if last_change != rendering_control {
+            rendering_control = last_change;
+            let properties = [("LastChange", rendering_control.clone())];
+            notify(&state, None, Service::RenderingControl, &properties).await;
+        }
+    }
+}
@@ -0,0 +1,332 @@
+//! A UPnP AV MediaRenderer, so control points like BubbleUPnP can play Qobuz
+//! on the player.
+//!
+//! The renderer announces itself over SSDP and serves the AVTransport,
+//! RenderingControl and ConnectionManager services. Transport URIs are Qobuz
+//! share links or `qobuz:` uris. Setting the transport URI replaces the queue
+//! and starts playing it, and the next transport URI is played after the
+//! current track.
+
+use std::{
+    collections::HashMap,
+    net::{Ipv4Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::{
+    Router,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{any, get, post},
+};
+use parking_lot::Mutex;
+use qobuz_player_controls::{
+    AppResult, PositionReceiver, Status, StatusReceiver, TracklistReceiver, VolumeReceiver,
+    controls::Controls, error::Error, models::Track, tracklist::Tracklist,
+};
+use tokio::net::{TcpListener, UdpSocket};
+
+use crate::{
+    description::Service,
+    soap::{escape, format_time, xml_response},
+};
+
+mod description;
+mod events;
+mod services;
+mod soap;
+mod ssdp;
+
+pub use ssdp::SSDP_PORT;
+
+/// What control points may send. Only Qobuz links play, so plain HTTP
+/// streams are not offered.
+const SINK_PROTOCOL_INFO: &str = "qobuz:*:*:*";
+
+pub struct RendererConfig {
+    /// The name control points show
+    pub friendly_name: String,
+    /// Address of the description, control and event server
+    pub http_address: SocketAddr,
+    /// Address of the SSDP socket. On an unspecified address the renderer
+    /// joins the SSDP multicast group and announces itself, on any other it
+    /// only answers searches sent to it.
+    pub ssdp_address: SocketAddr,
+}
+
+impl RendererConfig {
+    pub fn new(friendly_name: String, http_port: u16) -> Self {
+        Self {
+            friendly_name,
+            http_address: (Ipv4Addr::UNSPECIFIED, http_port).into(),
+            ssdp_address: (Ipv4Addr::UNSPECIFIED, SSDP_PORT).into(),
+        }
+    }
+}
+
+/// What the control points set through AVTransport and RenderingControl
+#[derive(Default)]
+struct Transport {
+    uri: String,
+    uri_metadata: String,
+    next_uri: String,
+    next_uri_metadata: String,
+    /// Queue id of the first track of the next transport URI
+    next_queue_id: Option<u64>,
+    /// Transport URIs by the queue id of their first track, so the current
+    /// track URI is reported the way the control point set it
+    track_uris: HashMap<u64, String>,
+    /// Volume to restore when unmuting
+    muted_volume: Option<f32>,
+}
+
+struct RendererState {
+    uuid: String,
+    friendly_name: String,
+    controls: Controls,
+    position_receiver: PositionReceiver,
+    tracklist_receiver: TracklistReceiver,
+    volume_receiver: VolumeReceiver,
+    status_receiver: StatusReceiver,
+    /// Lock this after borrowing a watch, never borrow one while holding it.
+    /// A waiting watch update blocks new borrows, so the other order can
+    /// deadlock.
+    transport: Mutex<Transport>,
+    subscriptions: Mutex<Vec<events::Subscription>>,
+    http: reqwest::Client,
+}
+
+impl RendererState {
+    fn transport_state(&self, tracklist: &Tracklist) -> &'static str {
+        if tracklist.total() == 0 {
+            return "NO_MEDIA_PRESENT";
+        }
+        if tracklist.current_queue_id().is_none() {
+            return "STOPPED";
+        }
+
+        match *self.status_receiver.borrow() {
+            Status::Playing => "PLAYING",
+            Status::Buffering => "TRANSITIONING",
+            Status::Paused => "PAUSED_PLAYBACK",
+        }
+    }
+
+    fn transport_actions(&self, tracklist: &Tracklist) -> &'static str {
+        match tracklist.total() {
+            0 => "",
+            _ => "Play,Pause,Stop,Seek,Next,Previous",
+        }
+    }
+
+    fn current_track_uri(&self, tracklist: &Tracklist) -> String {
+        let (Some(track), Some(queue_id)) =
+            (tracklist.current_track(), tracklist.current_queue_id())
+        else {
+            return String::new();
+        };
+
+        self.transport
+            .lock()
+            .track_uris
+            .get(&queue_id)
+            .cloned()
+            .unwrap_or_else(|| format!("qobuz:track:{}", track.id))
+    }
+
+    fn media_duration(&self, tracklist: &Tracklist) -> String {
+        let seconds = tracklist
+            .queue()
+            .iter()
+            .map(|item| u64::from(item.track.duration_seconds))
+            .sum();
+        format_time(Duration::from_secs(seconds))
+    }
+
+    fn volume(&self) -> u32 {
+        (*self.volume_receiver.borrow() * 100.0).round() as u32
+    }
+}
+
+/// DIDL-Lite metadata of a track, as control points show it
+fn didl(track: &Track, uri: &str) -> String {
+    let mut item = format!("<dc:title>{}</dc:title>", escape(&track.title));
+    if let Some(artist) = &track.artist_name {
+        item.push_str(&format!(
+            "<upnp:artist>{0}</upnp:artist><dc:creator>{0}</dc:creator>",
+            escape(artist)
+        ));
+    }
+    if let Some(album) = &track.album_title {
+        item.push_str(&format!("<upnp:album>{}</upnp:album>", escape(album)));
+    }
+    if let Some(image) = &track.image {
+        item.push_str(&format!(
+            "<upnp:albumArtURI>{}</upnp:albumArtURI>",
+            escape(image)
+        ));
+    }
+
+    format!(
+        r#"<DIDL-Lite xmlns="urn:schemas-upnp-org:metadata-1-0/DIDL-Lite/" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:upnp="urn:schemas-upnp-org:metadata-1-0/upnp/"><item id="{}" parentID="0" restricted="1">{item}<upnp:originalTrackNumber>{}</upnp:originalTrackNumber><upnp:class>object.item.audioItem.musicTrack</upnp:class><res protocolInfo="qobuz:*:*:*" duration="{}">{}</res></item></DIDL-Lite>"#,
+        track.id,
+        track.number,
+        format_time(Duration::from_secs(u64::from(track.duration_seconds))),
+        escape(uri),
+    )
+}
+
+/// A stable device id, so control points recognise the renderer after a
+/// restart
+fn device_uuid(friendly_name: &str) -> String {
+    let hash = format!(
+        "{:x}",
+        md5::compute(format!("qobuz-player-{friendly_name}"))
+    );
+    format!(
+        "uuid:{}-{}-{}-{}-{}",
+        &hash[0..8],
+        &hash[8..12],
+        &hash[12..16],
+        &hash[16..20],
+        &hash[20..32]
+    )
+}
+
+fn upnp_error(message: impl std::fmt::Display) -> Error {
+    Error::UpnpError {
+        message: message.to_string(),
+    }
+}
+
+/// A bound renderer, ready to [`run`](Renderer::run)
+pub struct Renderer {
+    state: Arc<RendererState>,
+    listener: TcpListener,
+    ssdp_socket: UdpSocket,
+    multicast: bool,
+    http_address: SocketAddr,
+    ssdp_address: SocketAddr,
+}
+
+impl Renderer {
+    pub async fn bind(
+        config: RendererConfig,
+        controls: Controls,
+        position_receiver: PositionReceiver,
+        tracklist_receiver: TracklistReceiver,
+        volume_receiver: VolumeReceiver,
+        status_receiver: StatusReceiver,
+    ) -> AppResult<Self> {
+        let listener = TcpListener::bind(config.http_address)
+            .await
+            .or(Err(Error::PortInUse {
+                port: config.http_address.port(),
+            }))?;
+        let http_address = listener.local_addr().map_err(upnp_error)?;
+
+        let (ssdp_socket, multicast) = ssdp::bind(config.ssdp_address).map_err(|err| {
+            upnp_error(format!(
+                "Unable to listen for SSDP on {}: {err}",
+                config.ssdp_address
+            ))
+        })?;
+        let ssdp_address = ssdp_socket.local_addr().map_err(upnp_error)?;
+
+        let state = Arc::new(RendererState {
+            uuid: device_uuid(&config.friendly_name),
+            friendly_name: config.friendly_name,
+            controls,
+            position_receiver,
+            tracklist_receiver,
+            volume_receiver,
+            status_receiver,
+            transport: Default::default(),
+            subscriptions: Default::default(),
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(5))
+                .build()
+                .map_err(upnp_error)?,
+        });
+
+        Ok(Self {
+            state,
+            listener,
+            ssdp_socket,
+            multicast,
+            http_address,
+            ssdp_address,
+        })
+    }
+
+    pub fn http_address(&self) -> SocketAddr {
+        self.http_address
+    }
+
+    pub fn ssdp_address(&self) -> SocketAddr {
+        self.ssdp_address
+    }
+
+    pub async fn run(self) -> AppResult<()> {
+        tracing::info!(
+            "UPnP renderer {} listening on {}",
+            self.state.friendly_name,
+            self.http_address
+        );
+
+        tokio::spawn(ssdp::run(
+            self.state.clone(),
+            self.ssdp_socket,
+            self.multicast,
+            self.http_address,
+        ));
+        tokio::spawn(events::notify_changes(self.state.clone()));
+
+        let router = Router::new()
+            .route("/description.xml", get(description))
+            .route("/{service}/scpd.xml", get(scpd))
+            .route("/{service}/control", post(services::control))
+            .route("/{service}/event", any(events::subscription))
+            .with_state(self.state);
+
+        axum::serve(self.listener, router).await.map_err(upnp_error)
+    }
+}
+
+/// Serve the renderer until the player exits
+pub async fn init(
+    config: RendererConfig,
+    controls: Controls,
+    position_receiver: PositionReceiver,
+    tracklist_receiver: TracklistReceiver,
+    volume_receiver: VolumeReceiver,
+    status_receiver: StatusReceiver,
+) -> AppResult<()> {
+    Renderer::bind(
+        config,
+        controls,
+        position_receiver,
+        tracklist_receiver,
+        volume_receiver,
+        status_receiver,
+    )
+    .await?
+    .run()
+    .await
+}
+
+async fn description(State(state): State<Arc<RendererState>>) -> Response {
+    xml_response(description::device_description(
+        &state.uuid,
+        &state.friendly_name,
+    ))
+}
+
+async fn scpd(Path(service): Path<String>) -> Response {
+    match Service::from_name(&service) {
+        Some(service) => xml_response(description::scpd(service)),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
@@ -0,0 +1,384 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use qobuz_player_controls::{models::Link, tracklist::Tracklist};
+
+use crate::{
+    RendererState, SINK_PROTOCOL_INFO, Transport,
+    description::Service,
+    didl,
+    soap::{
+        ACTION_FAILED, Fault, ILLEGAL_SEEK_TARGET, INVALID_ACTION, INVALID_ARGS,
+        RESOURCE_NOT_FOUND, SEEK_MODE_NOT_SUPPORTED, action_response, element, format_time,
+        parse_time,
+    },
+};
+
+const INVALID_INSTANCE_ID: u16 = 718;
+/// How long the player may take to queue a transport URI before its queue id
+/// is no longer looked for
+const QUEUE_CHANGE_TIMEOUT: Duration = Duration::from_secs(30);
+const NOT_IMPLEMENTED: &str = "NOT_IMPLEMENTED";
+
+type Outputs = Vec<(&'static str, String)>;
+
+pub(crate) async fn control(
+    State(state): State<Arc<RendererState>>,
+    Path(service): Path<String>,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    let Some(service) = Service::from_name(&service) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    // SOAPACTION: "urn:schemas-upnp-org:service:AVTransport:1#Play"
+    let action = headers
+        .get("soapaction")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim_matches('"').rsplit_once('#'))
+        .map(|(_, action)| action.to_string())
+        .unwrap_or_default();
+
+    let result = check_instance_id(&body).and_then(|()| match service {
+        Service::AVTransport => av_transport(&state, &action, &body),
+        Service::RenderingControl => rendering_control(&state, &action, &body),
+        Service::ConnectionManager => connection_manager(&action, &body),
+    });
+
+    match result {
+        Ok(outputs) => action_response(service, &action, &outputs),
+        Err(fault) => {
+            tracing::debug!("UPnP {action} failed: {}", fault.description);
+            fault.into_response()
+        }
+    }
+}
+
+/// There is a single instance, 0
+fn check_instance_id(body: &str) -> Result<(), Fault> {
+    match element(body, "InstanceID") {
+        Some(id) if id != "0" => Err(Fault::new(INVALID_INSTANCE_ID, "Invalid InstanceID")),
+        _ => Ok(()),
+    }
+}
+
+fn argument(body: &str, name: &str) -> Result<String, Fault> {
+    element(body, name).ok_or_else(|| Fault::new(INVALID_ARGS, format!("Missing {name}")))
+}
+
+fn parse_link(uri: &str) -> Result<Link, Fault> {
+    Link::parse(uri).map_err(|err| Fault::new(RESOURCE_NOT_FOUND, err.to_string()))
+}
+
+/// Remembers the queue id of the first track the player queues for `uri`,
+/// once it shows up in the queue. `is_current` tells whether the control
+/// point still has `uri` set, and skips it otherwise.
+fn track_queue_id(
+    state: &Arc<RendererState>,
+    uri: String,
+    next: bool,
+    is_current: fn(&Transport, &str) -> bool,
+) {
+    let before: Vec<u64> = queue_ids(&state.tracklist_receiver.borrow());
+    let mut tracklist_receiver = state.tracklist_receiver.clone();
+    let state = state.clone();
+
+    tokio::spawn(async move {
+        let added = |tracklist: &Tracklist| {
+            queue_ids(tracklist)
+                .into_iter()
+                .find(|queue_id| !before.contains(queue_id))
+        };
+
+        let Ok(Ok(tracklist)) = tokio::time::timeout(
+            QUEUE_CHANGE_TIMEOUT,
+            tracklist_receiver.wait_for(|tracklist| added(tracklist).is_some()),
+        )
+        .await
+        else {
+            tracing::debug!("The player did not queue {uri}");
+            return;
+        };
+        let Some(queue_id) = added(&tracklist) else {
+            return;
+        };
+        drop(tracklist);
+
+        let mut transport = state.transport.lock();
+        if !is_current(&transport, &uri) {
+            return;
+        }
+        if next {
+            transport.next_queue_id = Some(queue_id);
+        }
+        transport.track_uris.insert(queue_id, uri);
+    });
+}
+
+fn queue_ids(tracklist: &Tracklist) -> Vec<u64> {
+    tracklist.queue().iter().map(|item| item.queue_id).collect()
+}
+
+fn av_transport(state: &Arc<RendererState>, action: &str, body: &str) -> Result<Outputs, Fault> {
+    let controls = &state.controls;
+
+    match action {
+        "SetAVTransportURI" => {
+            let uri = argument(body, "CurrentURI")?;
+            let metadata = element(body, "CurrentURIMetaData").unwrap_or_default();
+
+            if uri.is_empty() {
+                controls.clear_queue();
+                *state.transport.lock() = Default::default();
+                return Ok(vec![]);
+            }
+
+            let link = parse_link(&uri)?;
+            track_queue_id(state, uri.clone(), false, |transport, uri| {
+                transport.uri == uri
+            });
+            controls.play_link(link);
+
+            let mut transport = state.transport.lock();
+            let muted_volume = transport.muted_volume.take();
+            *transport = Default::default();
+            transport.muted_volume = muted_volume;
+            transport.uri = uri;
+            transport.uri_metadata = metadata;
+        }
+        "SetNextAVTransportURI" => {
+            let uri = argument(body, "NextURI")?;
+            let metadata = element(body, "NextURIMetaData").unwrap_or_default();
+            let link = match uri.is_empty() {
+                true => None,
+                false => Some(parse_link(&uri)?),
+            };
+
+            let (current_position, queued) = {
+                let tracklist = state.tracklist_receiver.borrow();
+                (tracklist.current_position(), queue_ids(&tracklist))
+            };
+            if link.is_some() {
+                track_queue_id(state, uri.clone(), true, |transport, uri| {
+                    transport.next_uri == uri
+                });
+            }
+
+            let mut transport = state.transport.lock();
+
+            // A control point changing its mind replaces the next URI it set before
+            if let Some(previous) = transport.next_queue_id.take()
+                && queued
+                    .iter()
+                    .position(|queue_id| *queue_id == previous)
+                    .is_some_and(|position| position > current_position)
+            {
+                controls.remove_queue_item(previous);
+            }
+
+            if let Some(link) = link {
+                match link {
+                    Link::Track(id) => controls.play_tracks_next(vec![id]),
+                    link => controls.queue_link(link),
+                }
+            }
+
+            transport.next_uri = uri;
+            transport.next_uri_metadata = metadata;
+        }
+        "GetMediaInfo" => {
+            let tracklist = state.tracklist_receiver.borrow();
+            let transport = state.transport.lock();
+            let medium = match tracklist.total() {
+                0 => "NONE",
+                _ => "NETWORK",
+            };
+
+            return Ok(vec![
+                ("NrTracks", tracklist.total().to_string()),
+                ("MediaDuration", state.media_duration(&tracklist)),
+                ("CurrentURI", transport.uri.clone()),
+                ("CurrentURIMetaData", transport.uri_metadata.clone()),
+                ("NextURI", transport.next_uri.clone()),
+                ("NextURIMetaData", transport.next_uri_metadata.clone()),
+                ("PlayMedium", medium.into()),
+                ("RecordMedium", NOT_IMPLEMENTED.into()),
+                ("WriteStatus", NOT_IMPLEMENTED.into()),
+            ]);
+        }
+        "GetTransportInfo" => {
+            let tracklist = state.tracklist_receiver.borrow();
+            return Ok(vec![
+                (
+                    "CurrentTransportState",
+                    state.transport_state(&tracklist).into(),
+                ),
+                ("CurrentTransportStatus", "OK".into()),
+                ("CurrentSpeed", "1".into()),
+            ]);
+        }
+        "GetPositionInfo" => {
+            let tracklist = state.tracklist_receiver.borrow();
+            let position = format_time(*state.position_receiver.borrow());
+            let uri = state.current_track_uri(&tracklist);
+
+            let (track_number, duration, metadata) = match tracklist.current_track() {
+                Some(track) => (
+                    tracklist.current_position() + 1,
+                    format_time(Duration::from_secs(u64::from(track.duration_seconds))),
+                    didl(track, &uri),
+                ),
+                None => (0, format_time(Duration::ZERO), String::new()),
+            };
+
+            return Ok(vec![
+                ("Track", track_number.to_string()),
+                ("TrackDuration", duration),
+                ("TrackMetaData", metadata),
+                ("TrackURI", uri),
+                ("RelTime", position.clone()),
+                ("AbsTime", position),
+                ("RelCount", i32::MAX.to_string()),
+                ("AbsCount", i32::MAX.to_string()),
+            ]);
+        }
+        "GetDeviceCapabilities" => {
+            return Ok(vec![
+                ("PlayMedia", "NETWORK".into()),
+                ("RecMedia", NOT_IMPLEMENTED.into()),
+                ("RecQualityModes", NOT_IMPLEMENTED.into()),
+            ]);
+        }
+        "GetTransportSettings" => {
+            return Ok(vec![
+                ("PlayMode", "NORMAL".into()),
+                ("RecQualityMode", NOT_IMPLEMENTED.into()),
+            ]);
+        }
+        "GetCurrentTransportActions" => {
+            let tracklist = state.tracklist_receiver.borrow();
+            return Ok(vec![(
+                "Actions",
+                state.transport_actions(&tracklist).into(),
+            )]);
+        }
+        "Play" => controls.play(),
+        // The player has no stopped state
+        "Stop" | "Pause" => controls.pause(),
+        "Next" => controls.next(),
+        "Previous" => controls.previous(),
+        "Seek" => {
+            let unit = argument(body, "Unit")?;
+            let target = argument(body, "Target")?;
+
+            match unit.as_str() {
+                "REL_TIME" | "ABS_TIME" => {
+                    let time = parse_time(&target)
+                        .ok_or_else(|| Fault::new(ILLEGAL_SEEK_TARGET, "Illegal seek target"))?;
+                    controls.seek(time);
+                }
+                "TRACK_NR" => {
+                    let total = state.tracklist_receiver.borrow().total();
+                    let track = target
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|track| (1..=total).contains(track))
+                        .ok_or_else(|| Fault::new(ILLEGAL_SEEK_TARGET, "Illegal seek target"))?;
+                    controls.skip_to_position(track - 1, true);
+                }
+                _ => {
+                    return Err(Fault::new(
+                        SEEK_MODE_NOT_SUPPORTED,
+                        "Seek mode not supported",
+                    ));
+                }
+            }
+        }
+        _ => return Err(Fault::new(INVALID_ACTION, "Invalid Action")),
+    }
+
+    Ok(vec![])
+}
+
+fn rendering_control(state: &RendererState, action: &str, body: &str) -> Result<Outputs, Fault> {
+    let controls = &state.controls;
+
+    match action {
+        "ListPresets" => Ok(vec![("CurrentPresetNameList", "FactoryDefaults".into())]),
+        "SelectPreset" => match argument(body, "PresetName")?.as_str() {
+            "FactoryDefaults" => Ok(vec![]),
+            _ => Err(Fault::new(INVALID_ARGS, "Unknown preset")),
+        },
+        "GetVolume" => Ok(vec![("CurrentVolume", state.volume().to_string())]),
+        "SetVolume" => {
+            let volume = argument(body, "DesiredVolume")?
+                .parse::<u32>()
+                .ok()
+                .filter(|volume| *volume <= 100)
+                .ok_or_else(|| Fault::new(INVALID_ARGS, "Volume must be between 0 and 100"))?;
+
+            state.transport.lock().muted_volume = None;
+            controls.set_volume(volume as f32 / 100.0);
+            Ok(vec![])
+        }
+        "GetMute" => {
+            let muted = state.volume() == 0;
+            Ok(vec![("CurrentMute", u8::from(muted).to_string())])
+        }
+        "SetMute" => {
+            let mute = match argument(body, "DesiredMute")?.as_str() {
+                "1" | "true" | "yes" => true,
+                "0" | "false" | "no" => false,
+                _ => return Err(Fault::new(INVALID_ARGS, "DesiredMute must be a boolean")),
+            };
+
+            let volume = *state.volume_receiver.borrow();
+            let mut transport = state.transport.lock();
+            match mute {
+                true if volume > 0.0 => {
+                    transport.muted_volume = Some(volume);
+                    controls.set_volume(0.0);
+                }
+                false => {
+                    if let Some(volume) = transport.muted_volume.take() {
+                        controls.set_volume(volume);
+                    }
+                }
+                true => {}
+            }
+            Ok(vec![])
+        }
+        _ => Err(Fault::new(INVALID_ACTION, "Invalid Action")),
+    }
+}
+
+fn connection_manager(action: &str, body: &str) -> Result<Outputs, Fault> {
+    match action {
+        "GetProtocolInfo" => Ok(vec![
+            ("Source", String::new()),
+            ("Sink", SINK_PROTOCOL_INFO.into()),
+        ]),
+        "GetCurrentConnectionIDs" => Ok(vec![("ConnectionIDs", "0".into())]),
+        "GetCurrentConnectionInfo" => {
+            if argument(body, "ConnectionID")? != "0" {
+                return Err(Fault::new(ACTION_FAILED, "Invalid connection reference"));
+            }
+
+            Ok(vec![
+                ("RcsID", "0".into()),
+                ("AVTransportID", "0".into()),
+                ("ProtocolInfo", String::new()),
+                ("PeerConnectionManager", String::new()),
+                ("PeerConnectionID", "-1".into()),
+                ("Direction", "Input".into()),
+                ("Status", "OK".into()),
+            ])
+        }
+        _ => Err(Fault::new(INVALID_ACTION, "Invalid Action")),
+    }
+}
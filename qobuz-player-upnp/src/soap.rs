@@ -0,0 +1,155 @@
+use std::time::Duration;
+
+use axum::{
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+
+use crate::description::Service;
+
+pub(crate) const INVALID_ACTION: u16 = 401;
+pub(crate) const INVALID_ARGS: u16 = 402;
+pub(crate) const ACTION_FAILED: u16 = 501;
+pub(crate) const SEEK_MODE_NOT_SUPPORTED: u16 = 710;
+pub(crate) const ILLEGAL_SEEK_TARGET: u16 = 711;
+pub(crate) const RESOURCE_NOT_FOUND: u16 = 716;
+
+const CONTENT_TYPE: &str = r#"text/xml; charset="utf-8""#;
+
+/// A UPnP error, sent as a SOAP fault
+#[derive(Debug)]
+pub(crate) struct Fault {
+    pub code: u16,
+    pub description: String,
+}
+
+impl Fault {
+    pub fn new(code: u16, description: impl Into<String>) -> Self {
+        Self {
+            code,
+            description: description.into(),
+        }
+    }
+}
+
+impl IntoResponse for Fault {
+    fn into_response(self) -> Response {
+        let body = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/" s:encodingStyle="http://schemas.xmlsoap.org/soap/encoding/"><s:Body><s:Fault><faultcode>s:Client</faultcode><faultstring>UPnPError</faultstring><detail><UPnPError xmlns="urn:schemas-upnp-org:control-1-0"><errorCode>{}</errorCode><errorDescription>{}</errorDescription></UPnPError></detail></s:Fault></s:Body></s:Envelope>"#,
+            self.code,
+            escape(&self.description)
+        );
+
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            [(header::CONTENT_TYPE, CONTENT_TYPE)],
+            body,
+        )
+            .into_response()
+    }
+}
+
+pub(crate) fn xml_response(body: String) -> Response {
+    ([(header::CONTENT_TYPE, CONTENT_TYPE)], body).into_response()
+}
+
+/// The response to an action, with its output arguments in order
+pub(crate) fn action_response(
+    service: Service,
+    action: &str,
+    outputs: &[(&str, String)],
+) -> Response {
+    let arguments: String = outputs
+        .iter()
+        .map(|(name, value)| format!("<{name}>{}</{name}>", escape(value)))
+        .collect();
+
+    xml_response(format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/" s:encodingStyle="http://schemas.xmlsoap.org/soap/encoding/"><s:Body><u:{action}Response xmlns:u="{}">{arguments}</u:{action}Response></s:Body></s:Envelope>"#,
+        service.service_type()
+    ))
+}
+
+/// The text of the first element named `name` in `xml`, with any namespace
+/// prefix. Good enough for the flat argument lists of SOAP requests.
+pub(crate) fn element(xml: &str, name: &str) -> Option<String> {
+    let mut rest = xml;
+
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        let end = rest.find('>')?;
+        let tag = &rest[..end];
+        rest = &rest[end + 1..];
+
+        let tag_name = tag.split_whitespace().next().unwrap_or_default();
+        let local_name = tag_name.rsplit(':').next().unwrap_or_default();
+        if local_name != name || tag.starts_with('/') {
+            continue;
+        }
+
+        if tag.ends_with('/') {
+            return Some(String::new());
+        }
+
+        let close = format!("</{tag_name}>");
+        let content_end = rest.find(&close)?;
+        return Some(unescape(&rest[..content_end]));
+    }
+
+    None
+}
+
+pub(crate) fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+pub(crate) fn unescape(value: &str) -> String {
+    let value = value.trim();
+    if let Some(data) = value
+        .strip_prefix("<![CDATA[")
+        .and_then(|value| value.strip_suffix("]]>"))
+    {
+        return data.to_string();
+    }
+
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// `H:MM:SS`, the time format of AVTransport
+pub(crate) fn format_time(time: Duration) -> String {
+    let seconds = time.as_secs();
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Parse `H:MM:SS` with optional fractions of a second
+pub(crate) fn parse_time(time: &str) -> Option<Duration> {
+    let mut seconds = 0.0;
+    for part in time.trim().split(':') {
+        seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+    }
+
+    (seconds >= 0.0 && seconds.is_finite()).then(|| Duration::from_secs_f64(seconds))
+}
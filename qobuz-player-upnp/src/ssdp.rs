@@ -0,0 +1,200 @@
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::net::UdpSocket;
+
+use crate::{
+    RendererState,
+    description::{DEVICE_TYPE, Service},
+};
+
+pub const SSDP_PORT: u16 = 1900;
+const MULTICAST_ADDRESS: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 250);
+const MAX_AGE: Duration = Duration::from_secs(1800);
+/// Well within the max age, so control points never forget the renderer
+const ANNOUNCE_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Bind the SSDP socket. Other UPnP software usually listens on the SSDP
+/// port too, so the address is shared.
+pub(crate) fn bind(address: SocketAddr) -> io::Result<(UdpSocket, bool)> {
+    let socket = Socket::new(
+        Domain::for_address(address),
+        Type::DGRAM,
+        Some(Protocol::UDP),
+    )?;
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&address.into())?;
+
+    let socket = UdpSocket::from_std(socket.into())?;
+    let multicast = address.ip().is_unspecified();
+    if multicast {
+        socket.join_multicast_v4(MULTICAST_ADDRESS, Ipv4Addr::UNSPECIFIED)?;
+    }
+
+    Ok((socket, multicast))
+}
+
+/// Notification types and unique service names the renderer announces
+fn notification_types(uuid: &str) -> Vec<(String, String)> {
+    let mut types = vec![
+        (
+            "upnp:rootdevice".to_string(),
+            format!("{uuid}::upnp:rootdevice"),
+        ),
+        (uuid.to_string(), uuid.to_string()),
+        (DEVICE_TYPE.to_string(), format!("{uuid}::{DEVICE_TYPE}")),
+    ];
+
+    for service in Service::ALL {
+        let service_type = service.service_type();
+        types.push((service_type.clone(), format!("{uuid}::{service_type}")));
+    }
+
+    types
+}
+
+fn server() -> String {
+    format!(
+        "{}/1.0 UPnP/1.0 qobuz-player/{}",
+        std::env::consts::OS,
+        env!("CARGO_PKG_VERSION")
+    )
+}
+
+/// The address `peer` reaches this machine on
+fn local_ip(peer: IpAddr) -> io::Result<IpAddr> {
+    let socket = std::net::UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    socket.connect((peer, SSDP_PORT))?;
+    Ok(socket.local_addr()?.ip())
+}
+
+fn location(http_address: SocketAddr, peer: IpAddr) -> io::Result<String> {
+    let ip = match http_address.ip() {
+        ip if ip.is_unspecified() => local_ip(peer)?,
+        ip => ip,
+    };
+
+    Ok(format!(
+        "http://{}/description.xml",
+        SocketAddr::new(ip, http_address.port())
+    ))
+}
+
+pub(crate) async fn run(
+    state: Arc<RendererState>,
+    socket: UdpSocket,
+    multicast: bool,
+    http_address: SocketAddr,
+) {
+    let mut announce = tokio::time::interval(ANNOUNCE_INTERVAL);
+    let mut buffer = [0; 2048];
+
+    loop {
+        tokio::select! {
+            _ = announce.tick(), if multicast => {
+                if let Err(err) = send_alive(&state.uuid, &socket, http_address).await {
+                    tracing::warn!("Unable to announce UPnP renderer: {err}");
+                }
+            }
+            received = socket.recv_from(&mut buffer) => {
+                let (length, peer) = match received {
+                    Ok(received) => received,
+                    Err(err) => {
+                        tracing::debug!("SSDP receive failed: {err}");
+                        continue;
+                    }
+                };
+
+                let message = String::from_utf8_lossy(&buffer[..length]);
+                if let Err(err) = answer_search(&state.uuid, &socket, &message, peer, http_address).await {
+                    tracing::debug!("Unable to answer SSDP search from {peer}: {err}");
+                }
+            }
+        }
+    }
+}
+
+async fn send_alive(uuid: &str, socket: &UdpSocket, http_address: SocketAddr) -> io::Result<()> {
+    let target = SocketAddr::from((MULTICAST_ADDRESS, SSDP_PORT));
+    let location = location(http_address, target.ip())?;
+
+    for (notification_type, usn) in notification_types(uuid) {
+        let message = format!(
+            "NOTIFY * HTTP/1.1\r\n\
+             HOST: {target}\r\n\
+             CACHE-CONTROL: max-age={}\r\n\
+             LOCATION: {location}\r\n\
+             NT: {notification_type}\r\n\
+             NTS: ssdp:alive\r\n\
+             SERVER: {}\r\n\
+             USN: {usn}\r\n\r\n",
+            MAX_AGE.as_secs(),
+            server()
+        );
+        socket.send_to(message.as_bytes(), target).await?;
+    }
+
+    Ok(())
+}
+
+/// Answer an M-SEARCH for the renderer, its device type or its services.
+/// Anything else received on the socket is ignored.
+async fn answer_search(
+    uuid: &str,
+    socket: &UdpSocket,
+    message: &str,
+    peer: SocketAddr,
+    http_address: SocketAddr,
+) -> io::Result<()> {
+    if !message
+        .lines()
+        .next()
+        .is_some_and(|line| line.starts_with("M-SEARCH * HTTP/1.1"))
+    {
+        return Ok(());
+    }
+
+    let header = |name: &str| {
+        message.lines().skip(1).find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.trim()
+                .eq_ignore_ascii_case(name)
+                .then(|| value.trim().trim_matches('"'))
+        })
+    };
+
+    if header("MAN") != Some("ssdp:discover") {
+        return Ok(());
+    }
+    let Some(search_target) = header("ST") else {
+        return Ok(());
+    };
+
+    let location = location(http_address, peer.ip())?;
+    for (notification_type, usn) in notification_types(uuid) {
+        if search_target != "ssdp:all" && search_target != notification_type {
+            continue;
+        }
+
+        let message = format!(
+            "HTTP/1.1 200 OK\r\n\
+             CACHE-CONTROL: max-age={}\r\n\
+             EXT:\r\n\
+             LOCATION: {location}\r\n\
+             SERVER: {}\r\n\
+             ST: {notification_type}\r\n\
+             USN: {usn}\r\n\r\n",
+            MAX_AGE.as_secs(),
+            server()
+        );
+        socket.send_to(message.as_bytes(), peer).await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,338 @@
+use std::{net::SocketAddr, time::Duration};
+
+use qobuz_player_controls::{
+    Status,
+    controls::{ControlCommand, Controls},
+    models::{Link, Track, TrackStatus},
+    tracklist::{QueueItem, Tracklist, TracklistType},
+};
+use qobuz_player_upnp::{Renderer, RendererConfig};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, UdpSocket},
+    sync::{mpsc, watch},
+    time::timeout,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+async fn soap(
+    client: &reqwest::Client,
+    base: &str,
+    service: &str,
+    action: &str,
+    arguments: &str,
+) -> (u16, String) {
+    let service_type = format!("urn:schemas-upnp-org:service:{service}:1");
+    let body = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/" s:encodingStyle="http://schemas.xmlsoap.org/soap/encoding/"><s:Body><u:{action} xmlns:u="{service_type}"><InstanceID>0</InstanceID>{arguments}</u:{action}></s:Body></s:Envelope>"#
+    );
+
+    let response = client
+        .post(format!("{base}/{service}/control"))
+        .header("CONTENT-TYPE", r#"text/xml; charset="utf-8""#)
+        .header("SOAPACTION", format!(r#""{service_type}#{action}""#))
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+
+    (response.status().as_u16(), response.text().await.unwrap())
+}
+
+/// Accept one NOTIFY on the control point's event server and return it
+async fn receive_event(listener: &TcpListener) -> String {
+    let (mut stream, _) = timeout(TIMEOUT, listener.accept()).await.unwrap().unwrap();
+    let mut request = Vec::new();
+    let mut buffer = [0; 4096];
+
+    loop {
+        let read = timeout(TIMEOUT, stream.read(&mut buffer))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(read > 0, "connection closed before the event was complete");
+        request.extend_from_slice(&buffer[..read]);
+
+        let text = String::from_utf8_lossy(&request).to_string();
+        let Some((head, body)) = text.split_once("\r\n\r\n") else {
+            continue;
+        };
+        let length = head
+            .lines()
+            .find_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                name.eq_ignore_ascii_case("content-length")
+                    .then(|| value.trim().parse::<usize>().unwrap())
+            })
+            .unwrap_or_default();
+
+        if body.len() >= length {
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            return text;
+        }
+    }
+}
+
+#[tokio::test]
+async fn scripted_control_point() {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let (_position_tx, position_rx) = watch::channel(Duration::ZERO);
+    let (_tracklist_tx, tracklist_rx) = watch::channel(Tracklist::default());
+    let (volume_tx, volume_rx) = watch::channel(0.5);
+    let (_status_tx, status_rx) = watch::channel(Status::Paused);
+
+    let loopback = SocketAddr::from(([127, 0, 0, 1], 0));
+    let config = RendererConfig {
+        friendly_name: "Test renderer".into(),
+        http_address: loopback,
+        ssdp_address: loopback,
+    };
+    let renderer = Renderer::bind(
+        config,
+        Controls::new(tx),
+        position_rx,
+        tracklist_rx,
+        volume_rx,
+        status_rx,
+    )
+    .await
+    .unwrap();
+    let ssdp_address = renderer.ssdp_address();
+    tokio::spawn(renderer.run());
+
+    // Discover the renderer
+    let search = UdpSocket::bind(loopback).await.unwrap();
+    search
+        .send_to(
+            b"M-SEARCH * HTTP/1.1\r\n\
+              HOST: 239.255.255.250:1900\r\n\
+              MAN: \"ssdp:discover\"\r\n\
+              MX: 1\r\n\
+              ST: urn:schemas-upnp-org:device:MediaRenderer:1\r\n\r\n",
+            ssdp_address,
+        )
+        .await
+        .unwrap();
+
+    let mut buffer = [0; 2048];
+    let (length, _) = timeout(TIMEOUT, search.recv_from(&mut buffer))
+        .await
+        .unwrap()
+        .unwrap();
+    let answer = String::from_utf8_lossy(&buffer[..length]).to_string();
+    assert!(answer.starts_with("HTTP/1.1 200 OK"));
+    let location = answer
+        .lines()
+        .find_map(|line| line.strip_prefix("LOCATION: "))
+        .unwrap()
+        .to_string();
+    let base = location.strip_suffix("/description.xml").unwrap();
+
+    let client = reqwest::Client::new();
+    let description = client
+        .get(&location)
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(description.contains("<friendlyName>Test renderer</friendlyName>"));
+    assert!(description.contains("<controlURL>/AVTransport/control</controlURL>"));
+
+    let scpd = client
+        .get(format!("{base}/AVTransport/scpd.xml"))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(scpd.contains("<name>SetAVTransportURI</name>"));
+
+    // Subscribe to volume changes
+    let events = TcpListener::bind(loopback).await.unwrap();
+    let callback = format!("<http://{}/event>", events.local_addr().unwrap());
+    let subscribe = client
+        .request(
+            reqwest::Method::from_bytes(b"SUBSCRIBE").unwrap(),
+            format!("{base}/RenderingControl/event"),
+        )
+        .header("CALLBACK", callback)
+        .header("NT", "upnp:event")
+        .header("TIMEOUT", "Second-300")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(subscribe.status(), 200);
+    assert!(subscribe.headers().contains_key("sid"));
+    assert_eq!(subscribe.headers()["timeout"], "Second-300");
+
+    let initial = receive_event(&events).await;
+    assert!(initial.to_lowercase().contains("seq: 0"));
+    assert!(initial.contains("&lt;Volume channel=&quot;Master&quot; val=&quot;50&quot;/&gt;"));
+
+    volume_tx.send(0.8).unwrap();
+    let changed = receive_event(&events).await;
+    assert!(changed.contains("&lt;Volume channel=&quot;Master&quot; val=&quot;80&quot;/&gt;"));
+
+    // Queue and control playback
+    let (status, _) = soap(
+        &client,
+        base,
+        "AVTransport",
+        "SetAVTransportURI",
+        "<CurrentURI>https://open.qobuz.com/track/42</CurrentURI><CurrentURIMetaData></CurrentURIMetaData>",
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert!(matches!(
+        rx.recv().await,
+        Some(ControlCommand::PlayLink {
+            link: Link::Track(42)
+        })
+    ));
+
+    let (_, media_info) = soap(&client, base, "AVTransport", "GetMediaInfo", "").await;
+    assert!(media_info.contains("<CurrentURI>https://open.qobuz.com/track/42</CurrentURI>"));
+
+    let (status, _) = soap(&client, base, "AVTransport", "Play", "<Speed>1</Speed>").await;
+    assert_eq!(status, 200);
+    assert!(matches!(rx.recv().await, Some(ControlCommand::Play)));
+
+    let (status, _) = soap(
+        &client,
+        base,
+        "RenderingControl",
+        "SetVolume",
+        "<Channel>Master</Channel><DesiredVolume>30</DesiredVolume>",
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert!(matches!(
+        rx.recv().await,
+        Some(ControlCommand::SetVolume { volume }) if (volume - 0.3).abs() < f32::EPSILON
+    ));
+
+    let (_, volume) = soap(
+        &client,
+        base,
+        "RenderingControl",
+        "GetVolume",
+        "<Channel>Master</Channel>",
+    )
+    .await;
+    assert!(volume.contains("<CurrentVolume>80</CurrentVolume>"));
+
+    // Nothing is queued until the player takes the link
+    let (_, transport_info) = soap(&client, base, "AVTransport", "GetTransportInfo", "").await;
+    assert!(
+        transport_info.contains("<CurrentTransportState>NO_MEDIA_PRESENT</CurrentTransportState>")
+    );
+
+    // Only Qobuz links can be played
+    let (status, fault) = soap(
+        &client,
+        base,
+        "AVTransport",
+        "SetAVTransportURI",
+        "<CurrentURI>http://example.com/song.mp3</CurrentURI><CurrentURIMetaData></CurrentURIMetaData>",
+    )
+    .await;
+    assert_eq!(status, 500);
+    assert!(fault.contains("<errorCode>716</errorCode>"));
+
+    let (status, fault) = soap(&client, base, "AVTransport", "Record", "").await;
+    assert_eq!(status, 500);
+    assert!(fault.contains("<errorCode>401</errorCode>"));
+
+    assert!(rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn transport_uri_follows_the_queued_track() {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let (_position_tx, position_rx) = watch::channel(Duration::ZERO);
+    let (tracklist_tx, tracklist_rx) = watch::channel(Tracklist::default());
+    let (_volume_tx, volume_rx) = watch::channel(0.5);
+    let (_status_tx, status_rx) = watch::channel(Status::Playing);
+
+    let loopback = SocketAddr::from(([127, 0, 0, 1], 0));
+    let config = RendererConfig {
+        friendly_name: "Test renderer".into(),
+        http_address: loopback,
+        ssdp_address: loopback,
+    };
+    let renderer = Renderer::bind(
+        config,
+        Controls::new(tx),
+        position_rx,
+        tracklist_rx,
+        volume_rx,
+        status_rx,
+    )
+    .await
+    .unwrap();
+    let base = format!("http://{}", renderer.http_address());
+    tokio::spawn(renderer.run());
+
+    let client = reqwest::Client::new();
+
+    let (_, protocol_info) = soap(&client, &base, "ConnectionManager", "GetProtocolInfo", "").await;
+    assert!(protocol_info.contains("<Sink>qobuz:*:*:*</Sink>"));
+
+    let uri = "https://open.qobuz.com/track/42";
+    let (status, _) = soap(
+        &client,
+        &base,
+        "AVTransport",
+        "SetAVTransportURI",
+        &format!("<CurrentURI>{uri}</CurrentURI><CurrentURIMetaData></CurrentURIMetaData>"),
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert!(matches!(
+        rx.recv().await,
+        Some(ControlCommand::PlayLink {
+            link: Link::Track(42)
+        })
+    ));
+
+    // The player picks a queue id the renderer could not have guessed
+    tracklist_tx
+        .send(Tracklist::new(
+            TracklistType::Tracks,
+            vec![QueueItem {
+                track: Track {
+                    id: 42,
+                    status: TrackStatus::Playing,
+                    ..Default::default()
+                },
+                queue_id: 17,
+                index: 0,
+            }],
+        ))
+        .unwrap();
+
+    let expected = format!("<TrackURI>{uri}</TrackURI>");
+    let found = timeout(TIMEOUT, async {
+        loop {
+            let (_, position_info) =
+                soap(&client, &base, "AVTransport", "GetPositionInfo", "").await;
+            if position_info.contains(&expected) {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await;
+    assert!(
+        found.is_ok(),
+        "the transport URI was not tied to queue id 17"
+    );
+}
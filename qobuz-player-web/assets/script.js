@@ -23,6 +23,16 @@ function initSse() {
     }
   });
 
+  evtSource.addEventListener("queue", () => {
+    for (const el of document.querySelectorAll("[data-sse=queue]")) {
+      htmx.trigger(el, "queue");
+    }
+  });
+
+  evtSource.addEventListener("queue-changes", (event) => {
+    applyQueueChanges(JSON.parse(event.data));
+  });
+
   evtSource.addEventListener("volume", (event) => {
     const slider = document.getElementById("volume-slider");
     if (slider) {
@@ -53,10 +63,58 @@ function initSse() {
 
 initSse();
 
+function queueRow(queueId) {
+  return document.getElementById(`queue-item-${queueId}`)?.closest("li");
+}
+
+function insertQueueRows(list, position, html) {
+  const template = document.createElement("template");
+  template.innerHTML = html;
+  const rows = [...template.content.children];
+  list.insertBefore(template.content, list.children[position] ?? null);
+  for (const row of rows) {
+    htmx.process(row);
+  }
+}
+
+function applyQueueChanges(changes) {
+  const list = document.querySelector("#queue-list form");
+  if (!list) return;
+
+  for (const change of changes) {
+    const row = change.queue_id === undefined ? null : queueRow(change.queue_id);
+
+    switch (change.op) {
+      case "remove":
+        row?.remove();
+        break;
+      case "move":
+        if (row) {
+          row.remove();
+          list.insertBefore(row, list.children[change.position] ?? null);
+        }
+        break;
+      case "insert":
+        insertQueueRows(list, change.position, change.html);
+        break;
+      case "update":
+        if (row) {
+          const position = [...list.children].indexOf(row);
+          row.remove();
+          insertQueueRows(list, position, change.html);
+        }
+        break;
+    }
+  }
+}
+
 function refreshSse() {
   for (const el of document.querySelectorAll("[hx-trigger='tracklist']")) {
     htmx.trigger(el, "tracklist");
   }
+  for (const el of document.querySelectorAll("[hx-trigger='queue']")) {
+    htmx.trigger(el, "queue");
+  }
   for (const el of document.querySelectorAll("[hx-trigger='status']")) {
     htmx.trigger(el, "status");
   }
@@ -1,7 +1,8 @@
 use axum::response::{Html, IntoResponse, Response};
 use futures::try_join;
 use qobuz_player_controls::{
-    AppResult, PositionReceiver, Status, StatusReceiver, TracklistReceiver, VolumeReceiver,
+    AppResult, PositionReceiver, Status, StatusReceiver, TracklistChangeReceiver,
+    TracklistReceiver, VolumeReceiver,
     client::Client,
     controls::Controls,
     database::Database,
@@ -15,21 +16,31 @@ use skabelon::Templates;
 use std::sync::Arc;
 use tokio::sync::{broadcast::Sender, watch};
 
-use crate::{AlbumData, ServerSentEvent};
+use crate::{
+    AlbumData, ServerSentEvent,
+    users::{LoginLimiter, PendingSignIns},
+    webhooks::Webhooks,
+};
 
 pub struct AppState {
     pub tx: Sender<ServerSentEvent>,
     pub web_secret: Option<String>,
+    /// Cookies are only sent over HTTPS when the server terminates TLS
+    pub secure_cookies: bool,
     pub rfid_state: Option<RfidState>,
     pub broadcast: Arc<NotificationBroadcast>,
     pub client: Arc<Client>,
     pub controls: Controls,
     pub position_receiver: PositionReceiver,
     pub tracklist_receiver: TracklistReceiver,
+    pub tracklist_changes: TracklistChangeReceiver,
     pub status_receiver: StatusReceiver,
     pub volume_receiver: VolumeReceiver,
     pub templates: watch::Receiver<Templates>,
     pub database: Arc<Database>,
+    pub webhooks: Arc<Webhooks>,
+    pub login_limiter: LoginLimiter,
+    pub sign_ins: PendingSignIns,
 }
 
 impl AppState {
@@ -110,12 +121,13 @@ impl AppState {
             Notification::Error(message) => (message, 1),
             Notification::Warning(message) => (message, 2),
             Notification::Success(message) => (message, 3),
-            Notification::Info(message) => (message, 4),
+            Notification::Info(message) | Notification::Undoable(message) => (message, 4),
         };
+        let undo = matches!(message, Notification::Undoable(_));
 
         self.render(
             "send-toast.html",
-            &json!({"message": message_string, "severity": severity}),
+            &json!({"message": message_string, "severity": severity, "undo": undo}),
         )
     }
 
@@ -189,7 +201,7 @@ fn entity_playing(tracklist: &Tracklist, current_track: Option<&Track>) -> Entit
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct PlayingInfo {
     title: String,
-    now_playing_id: Option<u32>,
+    pub now_playing_id: Option<u32>,
     artist_link: Option<String>,
     artist_name: Option<String>,
     entity_title: Option<String>,
@@ -8,11 +8,11 @@ use axum::{
 };
 use futures::stream::Stream;
 use qobuz_player_controls::{
-    AppResult, PositionReceiver, Status, StatusReceiver, TracklistReceiver, VolumeReceiver,
+    AppResult, PlayerEventReceiver, PositionReceiver, Status, StatusReceiver,
+    TracklistChangeReceiver, TracklistReceiver, VolumeReceiver,
     client::Client,
     controls::Controls,
     database::Database,
-    error::Error,
     models::{Album, AlbumSimple, Playlist},
     notification::{Notification, NotificationBroadcast},
 };
@@ -27,46 +27,54 @@ use tokio::sync::{
 use tokio_stream::StreamExt as _;
 use tokio_stream::wrappers::BroadcastStream;
 
+pub use server::{ListenAddress, ServerConfig, Tls};
+
 use crate::{
     app_state::AppState,
     routes::{
-        album, api, artist, auth, controls, discover, favorites, now_playing, playlist, queue,
-        search,
+        album, api, api_v1, artist, auth, composer, controls, discover, favorites, label,
+        now_playing, playlist, queue, search, settings, track,
     },
     views::templates,
+    webhooks::Webhooks,
 };
 
 mod app_state;
 mod assets;
 mod routes;
+mod server;
+mod users;
 mod views;
+mod webhooks;
 
 #[allow(clippy::too_many_arguments)]
 pub async fn init(
     controls: Controls,
     position_receiver: PositionReceiver,
     tracklist_receiver: TracklistReceiver,
+    tracklist_changes: TracklistChangeReceiver,
     volume_receiver: VolumeReceiver,
     status_receiver: StatusReceiver,
-    port: u16,
+    player_events: PlayerEventReceiver,
+    server: ServerConfig,
     web_secret: Option<String>,
     rfid_state: Option<RfidState>,
     broadcast: Arc<NotificationBroadcast>,
     client: Arc<Client>,
     database: Arc<Database>,
 ) -> AppResult<()> {
-    let interface = format!("0.0.0.0:{port}");
-    let listener = tokio::net::TcpListener::bind(&interface)
-        .await
-        .or(Err(Error::PortInUse { port }))?;
+    let listeners = server::bind(&server).await?;
 
     let router = create_router(
         controls,
         position_receiver,
         tracklist_receiver,
+        tracklist_changes,
         volume_receiver,
         status_receiver,
+        player_events,
         web_secret,
+        server.tls.is_some(),
         rfid_state,
         broadcast,
         client,
@@ -74,8 +82,7 @@ pub async fn init(
     )
     .await;
 
-    axum::serve(listener, router).await.expect("infallible");
-    Ok(())
+    server::serve(listeners, router).await
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -83,9 +90,12 @@ async fn create_router(
     controls: Controls,
     position_receiver: PositionReceiver,
     tracklist_receiver: TracklistReceiver,
+    tracklist_changes: TracklistChangeReceiver,
     volume_receiver: VolumeReceiver,
     status_receiver: StatusReceiver,
+    player_events: PlayerEventReceiver,
     web_secret: Option<String>,
+    secure_cookies: bool,
     rfid_state: Option<RfidState>,
     broadcast: Arc<NotificationBroadcast>,
     client: Arc<Client>,
@@ -131,19 +141,33 @@ async fn create_router(
         watcher.start();
     }
 
+    let webhooks = Arc::new(Webhooks::new(database.clone()));
+    tokio::spawn(webhooks::run(
+        webhooks.clone(),
+        player_events,
+        status_receiver.clone(),
+        tracklist_receiver.clone(),
+        volume_receiver.clone(),
+    ));
+
     let shared_state = Arc::new(AppState {
         controls,
         web_secret,
+        secure_cookies,
         rfid_state,
         broadcast,
         client,
         tx: tx.clone(),
         position_receiver: position_receiver.clone(),
         tracklist_receiver: tracklist_receiver.clone(),
+        tracklist_changes: tracklist_changes.resubscribe(),
         volume_receiver: volume_receiver.clone(),
         status_receiver: status_receiver.clone(),
         templates: templates_rx.clone(),
         database,
+        webhooks,
+        login_limiter: Default::default(),
+        sign_ins: Default::default(),
     });
 
     tokio::spawn(background_task(
@@ -151,6 +175,7 @@ async fn create_router(
         broadcast_subscribe,
         position_receiver,
         tracklist_receiver,
+        tracklist_changes,
         volume_receiver,
         status_receiver,
         templates_rx,
@@ -161,31 +186,48 @@ async fn create_router(
         .merge(now_playing::routes())
         .merge(queue::routes())
         .merge(api::routes())
+        .merge(api_v1::routes())
         .merge(search::routes())
         .merge(album::routes())
+        .merge(track::routes())
         .merge(artist::routes())
+        .merge(composer::routes())
+        .merge(label::routes())
         .merge(playlist::routes())
         .merge(favorites::routes())
         .merge(discover::routes())
         .merge(controls::routes())
+        .merge(routes::webhooks::routes())
+        .merge(routes::websocket::routes())
+        .merge(routes::users::routes())
+        .merge(settings::routes())
+        .layer(axum::middleware::from_fn_with_state(
+            shared_state.clone(),
+            settings::sign_in_middleware,
+        ))
         .layer(axum::middleware::from_fn_with_state(
             shared_state.clone(),
             auth::auth_middleware,
         ))
         .route("/assets/{*file}", get(static_handler))
         .merge(auth::routes())
+        .merge(settings::callback_routes())
         .with_state(shared_state.clone())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn background_task(
     tx: Sender<ServerSentEvent>,
     mut receiver: Receiver<Notification>,
     mut position: PositionReceiver,
     mut tracklist: TracklistReceiver,
+    mut tracklist_changes: TracklistChangeReceiver,
     mut volume: VolumeReceiver,
     mut status: StatusReceiver,
     templates: watch::Receiver<Templates>,
 ) {
+    let mut queue_items: Vec<_> = tracklist.borrow().queue().into_iter().cloned().collect();
+
     loop {
         tokio::select! {
             Ok(_) = position.changed() => {
@@ -205,6 +247,28 @@ async fn background_task(
                 };
                 _ = tx.send(event);
             },
+            changes = tracklist_changes.recv() => {
+                let operations = match &changes {
+                    Ok(changes) => queue::change_operations(&mut queue_items, changes, &templates.borrow()),
+                    Err(broadcast::error::RecvError::Lagged(_)) => None,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+
+                let event = match operations {
+                    Some(operations) => ServerSentEvent {
+                        event_name: "queue-changes".into(),
+                        event_data: serde_json::to_string(&operations).expect("infallible"),
+                    },
+                    None => {
+                        queue_items = tracklist.borrow().queue().into_iter().cloned().collect();
+                        ServerSentEvent {
+                            event_name: "queue".into(),
+                            event_data: "new queue".into(),
+                        }
+                    }
+                };
+                _ = tx.send(event);
+            },
             Ok(_) = volume.changed() => {
                 let volume = *volume.borrow_and_update();
                 let volume = (volume * 100.0) as u32;
@@ -235,10 +299,11 @@ async fn background_task(
                             Notification::Error(message) => (message, 1),
                             Notification::Warning(message) => (message, 2),
                             Notification::Success(message) => (message, 3),
-                            Notification::Info(message) => (message, 4),
+                            Notification::Info(message) | Notification::Undoable(message) => (message, 4),
                         };
+                    let undo = matches!(message, Notification::Undoable(_));
 
-                    let toast = templates.borrow().render("toast.html", &json!({"message": message_string, "severity": severity}));
+                    let toast = templates.borrow().render("toast.html", &json!({"message": message_string, "severity": severity, "undo": undo}));
                     let event = match message {
                         Notification::Error(_) => ServerSentEvent {
                             event_name: "error".into(),
@@ -256,7 +321,7 @@ async fn background_task(
                                 event_data: toast,
                             }
                         }
-                        Notification::Info(_) => ServerSentEvent {
+                        Notification::Info(_) | Notification::Undoable(_) => ServerSentEvent {
                             event_name: "info".into(),
                             event_data: toast,
                         },
@@ -2,28 +2,45 @@
 use qobuz_player_cli::GpioArgs;
 use qobuz_player_cli::{
     ConnectArgs, DelayArgs, RfidArgs, SharedArgs, SharedCommands, create_player,
-    default_audio_quality, get_client, handle_shared_commands, spawn_clean_up,
+    default_audio_quality, handle_shared_commands, play_url,
 };
 use qobuz_player_rfid::RfidState;
-use std::sync::Arc;
-use tokio::sync::broadcast;
+use qobuz_player_web::{ListenAddress, ServerConfig, Tls};
+use std::{net::SocketAddr, path::PathBuf, sync::Arc};
 
 use clap::Parser;
 use qobuz_player_controls::{
-    AppResult, database::Database, error::Error, notification::NotificationBroadcast,
+    AppResult, client::Client, database::Database, error::Error,
+    notification::NotificationBroadcast,
 };
 
 #[derive(Parser)]
 #[clap(author, version, about, long_about = None)]
 struct Arguments {
     #[clap(long)]
-    /// Secret used for web ui auth
+    /// Password for the web UI until web users are added at /users
     web_secret: Option<String>,
 
     #[clap(long, default_value_t = 9888)]
     /// Specify port for the web server
     port: u16,
 
+    #[clap(long, value_name = "ADDRESS")]
+    /// Address to serve the web UI on, like 127.0.0.1:9888, [::]:9888 or unix:/run/qobuz-player/web.sock. Can be given more than once. Defaults to 0.0.0.0 on --port
+    listen: Vec<ListenAddress>,
+
+    #[clap(long, requires = "tls_key")]
+    /// PEM certificate chain to serve the web UI over HTTPS
+    tls_cert: Option<PathBuf>,
+
+    #[clap(long, requires = "tls_cert")]
+    /// PEM private key for --tls-cert
+    tls_key: Option<PathBuf>,
+
+    #[clap(long, default_value_t = false, conflicts_with = "tls_cert")]
+    /// Serve the web UI over HTTPS with a self-signed certificate
+    tls_self_signed: bool,
+
     #[clap(long, default_value_t = false)]
     /// Enable rfid interface
     rfid: bool,
@@ -70,15 +87,18 @@ pub async fn run() -> AppResult<()> {
         return Ok(());
     }
 
-    let (_, exit_receiver) = broadcast::channel(5);
-
     let max_audio_quality = default_audio_quality(&database, args.shared.max_audio_quality).await?;
-    let client = get_client(&database, max_audio_quality, headless).await?;
+    // Without stored credentials the web UI asks someone to sign in
+    let credentials = database.get_credentials().await?;
+    if credentials.is_none() {
+        tracing::info!("Not signed in to Qobuz. Sign in from the web UI");
+    }
+    let client = Client::new(credentials, max_audio_quality);
     let client = Arc::new(client);
 
     let broadcast = Arc::new(NotificationBroadcast::new());
 
-    let mut player = create_player(
+    let player = create_player(
         args.shared.audio_cache,
         database.clone(),
         client.clone(),
@@ -86,16 +106,43 @@ pub async fn run() -> AppResult<()> {
         args.delay.state_change_delay_ms,
         args.delay.sample_rate_change_delay_ms,
         args.shared.output_device_id,
+        args.shared.audio_cache_time_to_live,
     )
     .await?;
 
+    play_url(&player, args.shared.play_url.as_deref())?;
+
+    #[cfg(unix)]
+    qobuz_player_cli::spawn_control_socket(&player, args.shared.control_socket);
+
+    qobuz_player_cli::spawn_mpd_server(&player, client.clone(), args.shared.mpd);
+    qobuz_player_cli::spawn_upnp_renderer(&player, args.shared.upnp_name, args.shared.upnp_port);
+    qobuz_player_cli::spawn_mqtt_client(&player, args.shared.mqtt);
+
     let rfid_state = args.rfid.then(RfidState::default);
 
+    let server = ServerConfig {
+        listen: if args.listen.is_empty() {
+            vec![ListenAddress::Tcp(SocketAddr::from((
+                [0, 0, 0, 0],
+                args.port,
+            )))]
+        } else {
+            args.listen
+        },
+        tls: match (args.tls_cert, args.tls_key) {
+            (Some(cert), Some(key)) => Some(Tls::Files { cert, key }),
+            _ => args.tls_self_signed.then_some(Tls::SelfSigned),
+        },
+    };
+
     {
         let position_receiver = player.position();
         let tracklist_receiver = player.tracklist();
+        let tracklist_changes = player.tracklist_changes();
         let volume_receiver = player.volume();
         let status_receiver = player.status();
+        let player_events = player.events();
         let controls = player.controls();
         let broadcast = broadcast.clone();
         let client = client.clone();
@@ -107,9 +154,11 @@ pub async fn run() -> AppResult<()> {
                 controls,
                 position_receiver,
                 tracklist_receiver,
+                tracklist_changes,
                 volume_receiver,
                 status_receiver,
-                args.port,
+                player_events,
+                server,
                 args.web_secret,
                 rfid_state,
                 broadcast,
@@ -144,7 +193,7 @@ pub async fn run() -> AppResult<()> {
                 database,
                 broadcast,
                 args.rfid_config.rfid_server_base_address,
-                args.rfid_config.rfid_server_secret,
+                args.rfid_config.rfid_server_token,
             )
             .await
             {
@@ -157,6 +206,7 @@ pub async fn run() -> AppResult<()> {
         let app_id = client.app_id().await?;
         let position_receiver = player.position();
         let tracklist_receiver = player.tracklist();
+        let tracklist_changes = player.tracklist_changes();
         let volume_receiver = player.volume();
         let status_receiver = player.status();
         let controls = player.controls();
@@ -168,6 +218,7 @@ pub async fn run() -> AppResult<()> {
                 controls,
                 position_receiver,
                 tracklist_receiver,
+                tracklist_changes,
                 status_receiver,
                 volume_receiver,
                 max_audio_quality,
@@ -179,8 +230,7 @@ pub async fn run() -> AppResult<()> {
         });
     }
 
-    spawn_clean_up(database, args.shared.audio_cache_time_to_live);
-    player.player_loop(exit_receiver).await?;
+    player.wait().await?;
 
     Ok(())
 }
@@ -1,11 +1,19 @@
 pub mod album;
 pub mod api;
+pub mod api_v1;
 pub mod artist;
 pub mod auth;
+pub mod composer;
 pub mod controls;
 pub mod discover;
 pub mod favorites;
+pub mod label;
 pub mod now_playing;
 pub mod playlist;
 pub mod queue;
 pub mod search;
+pub mod settings;
+pub mod track;
+pub mod users;
+pub mod webhooks;
+pub mod websocket;
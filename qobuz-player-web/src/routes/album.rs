@@ -7,9 +7,9 @@ use axum::{
     routing::{get, put},
 };
 use axum_extra::extract::Form;
-use qobuz_player_controls::notification::Notification;
+use qobuz_player_controls::{models::Album, notification::Notification};
 use serde::Deserialize;
-use serde_json::json;
+use serde_json::{Value, json};
 
 use crate::{AppState, ResponseResult, ok_or_send_error_toast};
 
@@ -22,6 +22,9 @@ pub fn routes() -> Router<std::sync::Arc<crate::AppState>> {
         .route("/album/{id}/unset-favorite", put(unset_favorite))
         .route("/album/{id}/play", put(play))
         .route("/album/{id}/play/{track_position}", put(play_track))
+        .route("/album/{id}/disc/{disc}/play", put(play_disc))
+        .route("/album/{id}/work/{track_id}/play", put(play_work))
+        .route("/album/{id}/play-track/{track_id}", put(play_album_track))
         .route("/album/{id}/link", put(link))
         .route("/album/action", put(action))
 }
@@ -73,6 +76,60 @@ async fn play_track(
     state.controls.play_album(&id, track_position);
 }
 
+/// Plays the album from the first track of the disc, so the queue keeps the
+/// album it came from
+async fn play_disc(
+    State(state): State<Arc<AppState>>,
+    Path((id, disc)): Path<(String, u32)>,
+) -> ResponseResult {
+    let album_data = ok_or_send_error_toast(&state, state.get_album(&id).await)?;
+
+    if let Some(disc) = album_data.album.disc(disc) {
+        state.controls.play_album(&id, disc.first_index);
+    }
+
+    Ok(().into_response())
+}
+
+/// Plays all movements of the work the track is a part of
+async fn play_work(
+    State(state): State<Arc<AppState>>,
+    Path((id, track_id)): Path<(String, u32)>,
+) -> ResponseResult {
+    let album_data = ok_or_send_error_toast(&state, state.get_album(&id).await)?;
+
+    let work = album_data
+        .album
+        .works()
+        .into_iter()
+        .find(|work| work.tracks.iter().any(|t| t.id == track_id));
+
+    if let Some(work) = work {
+        let track_ids = work.tracks.into_iter().map(|x| x.id).collect();
+        state.controls.play_tracks(track_ids, false);
+    }
+
+    Ok(().into_response())
+}
+
+async fn play_album_track(
+    State(state): State<Arc<AppState>>,
+    Path((id, track_id)): Path<(String, u32)>,
+) -> ResponseResult {
+    let album_data = ok_or_send_error_toast(&state, state.get_album(&id).await)?;
+
+    if let Some(position) = album_data
+        .album
+        .tracks
+        .iter()
+        .position(|t| t.id == track_id)
+    {
+        state.controls.play_album(&id, position);
+    }
+
+    Ok(().into_response())
+}
+
 async fn set_favorite(
     State(state): State<Arc<AppState>>,
     Path(id): Path<String>,
@@ -130,6 +187,8 @@ async fn content(State(state): State<Arc<AppState>>, Path(id): Path<String>) ->
     Ok(state.render(
         "album.html",
         &json!({
+            "quality": album_data.album.quality(),
+            "sections": sections(&album_data.album),
             "album": album_data.album,
             "duration": duration,
             "suggested_albums": album_data.suggested_albums,
@@ -150,8 +209,54 @@ async fn album_tracks_partial(
     Ok(state.render(
         "album-tracks.html",
         &json!({
+            "sections": sections(&album),
             "album": album,
             "click": click_string
         }),
     ))
 }
+
+/// Tracks grouped by disc and by work, or nothing for single disc albums
+/// without works
+fn sections(album: &Album) -> Option<Vec<Value>> {
+    let discs = album.discs();
+    if discs.len() < 2 && album.tracks.iter().all(|t| t.work.is_none()) {
+        return None;
+    }
+
+    let multi_disc = discs.len() > 1;
+
+    Some(
+        discs
+            .into_iter()
+            .map(|disc| {
+                let groups: Vec<Value> = disc
+                    .tracks
+                    .chunk_by(|a, b| a.work == b.work)
+                    .map(|tracks| {
+                        let work = tracks[0].work.as_ref();
+                        let play_url =
+                            work.map(|_| format!("/album/{}/work/{}/play", album.id, tracks[0].id));
+
+                        json!({
+                            "work": work,
+                            "composer": work.and(tracks[0].composer.as_ref()),
+                            "play_url": play_url,
+                            "click": format!("/album/{}/play-track/", album.id),
+                            "tracks": tracks,
+                        })
+                    })
+                    .collect();
+
+                let play_url =
+                    multi_disc.then(|| format!("/album/{}/disc/{}/play", album.id, disc.number));
+
+                json!({
+                    "number": multi_disc.then_some(disc.number),
+                    "play_url": play_url,
+                    "groups": groups,
+                })
+            })
+            .collect(),
+    )
+}
@@ -5,8 +5,9 @@ use std::{
 };
 
 use axum::{
-    Json, Router,
+    Extension, Json, Router,
     extract::{Path, State},
+    http::StatusCode,
     response::IntoResponse,
     routing::{get, post, put},
 };
@@ -14,14 +15,16 @@ use axum_extra::extract::Form;
 use qobuz_player_controls::{
     AppResult,
     client::Client,
-    database::ReferenceType,
-    models::{AlbumSimple, Artist, Playlist, Track},
+    database::{ReferenceType, Role},
+    models::{AlbumSimple, Artist, Link, Playlist, Track},
     notification::Notification,
 };
 use qobuz_player_rfid::{LinkAlbumRfid, LinkPlaylistRfid, handle_play_scan};
 use serde::Deserialize;
 
-use crate::{AppState, ResponseResult, hx_redirect, ok_or_send_error_toast};
+use crate::{
+    AppState, ResponseResult, hx_redirect, ok_or_send_error_toast, routes::playlist::moved_index,
+};
 
 pub fn routes() -> Router<Arc<AppState>> {
     Router::new()
@@ -40,9 +43,18 @@ pub fn routes() -> Router<Arc<AppState>> {
             "/api/remove-queue-item/{index}",
             put(remove_index_from_queue),
         )
+        .route(
+            "/api/queue/item/{queue_id}/skip-to",
+            put(skip_to_queue_item),
+        )
+        .route("/api/queue/item/{queue_id}/remove", put(remove_queue_item))
         .route("/api/track/play/{track_id}", put(play_track))
         .route("/api/track/action", put(track_action))
+        .route("/api/link/play", put(play_link))
+        .route("/api/link/queue", put(queue_link))
         .route("/api/queue/reorder", put(reorder_queue))
+        .route("/api/queue/undo", put(undo_queue))
+        .route("/api/queue/redo", put(redo_queue))
         .route("/api/favorites/albums", get(favorite_albums))
         .route("/api/favorites/artists", get(favorite_artists))
         .route("/api/favorites/playlists", get(favorite_playlists))
@@ -71,6 +83,7 @@ enum TrackAction {
     AddToQueue,
     PlayNext,
     AddToPlaylist,
+    ShowInfo,
 }
 #[derive(Deserialize)]
 struct TrackActionParameters {
@@ -79,8 +92,17 @@ struct TrackActionParameters {
 }
 async fn track_action(
     State(state): State<Arc<AppState>>,
+    Extension(role): Extension<Role>,
     Form(req): Form<TrackActionParameters>,
 ) -> ResponseResult {
+    let edits_library = matches!(
+        req.action,
+        TrackAction::AddFavorite | TrackAction::RemoveFavorite | TrackAction::AddToPlaylist
+    );
+    if edits_library && role != Role::Admin {
+        return Err((StatusCode::FORBIDDEN, "Not allowed for your role").into_response());
+    }
+
     match req.action {
         TrackAction::AddFavorite => {
             ok_or_send_error_toast(&state, state.client.add_favorite_track(req.track_id).await)?;
@@ -109,19 +131,84 @@ async fn track_action(
             "/playlist/add-track/{}",
             req.track_id
         ))),
+        TrackAction::ShowInfo => Ok(hx_redirect(&format!("/track/{}", req.track_id))),
     }
 }
 
+#[derive(Deserialize)]
+struct LinkParameters {
+    url: String,
+}
+
+async fn play_link(
+    State(state): State<Arc<AppState>>,
+    Form(req): Form<LinkParameters>,
+) -> ResponseResult {
+    let link = ok_or_send_error_toast(&state, Link::parse(&req.url))?;
+    state.controls.play_link(link);
+    Ok(().into_response())
+}
+
+async fn queue_link(
+    State(state): State<Arc<AppState>>,
+    Form(req): Form<LinkParameters>,
+) -> ResponseResult {
+    let link = ok_or_send_error_toast(&state, Link::parse(&req.url))?;
+    state.controls.queue_link(link);
+    Ok(().into_response())
+}
+
 #[derive(Deserialize)]
 struct ReorderQueueParameters {
-    new_order: Vec<usize>,
+    queue_ids: Vec<u64>,
 }
 
 async fn reorder_queue(
     State(state): State<Arc<AppState>>,
     Form(req): Form<ReorderQueueParameters>,
 ) -> impl IntoResponse {
-    state.controls.reorder_queue(req.new_order);
+    let current: Vec<u64> = state
+        .tracklist_receiver
+        .borrow()
+        .queue()
+        .iter()
+        .map(|item| item.queue_id)
+        .collect();
+
+    match moved_queue_item(&current, &req.queue_ids) {
+        Some((queue_id, new_position)) => state.controls.move_queue_item(queue_id, new_position),
+        None if current != req.queue_ids => {
+            state.send_sse("queue".into(), "queue out of date".into())
+        }
+        None => (),
+    }
+}
+
+/// The item dragged to a new position, going from `current` to `submitted`
+/// order.
+fn moved_queue_item(current: &[u64], submitted: &[u64]) -> Option<(u64, usize)> {
+    if current.len() != submitted.len() {
+        return None;
+    }
+
+    let permutation: Vec<usize> = submitted
+        .iter()
+        .map(|queue_id| current.iter().position(|x| x == queue_id))
+        .collect::<Option<_>>()?;
+
+    let moved = moved_index(&permutation)?;
+    let queue_id = current[moved.moved_index];
+    let new_position = submitted.iter().position(|x| *x == queue_id)?;
+
+    Some((queue_id, new_position))
+}
+
+async fn undo_queue(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    state.controls.undo_queue();
+}
+
+async fn redo_queue(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    state.controls.redo_queue();
 }
 
 async fn remove_index_from_queue(
@@ -131,6 +218,20 @@ async fn remove_index_from_queue(
     state.controls.remove_index_from_queue(index);
 }
 
+async fn remove_queue_item(
+    State(state): State<Arc<AppState>>,
+    Path(queue_id): Path<u64>,
+) -> impl IntoResponse {
+    state.controls.remove_queue_item(queue_id);
+}
+
+async fn skip_to_queue_item(
+    State(state): State<Arc<AppState>>,
+    Path(queue_id): Path<u64>,
+) -> impl IntoResponse {
+    state.controls.skip_to_queue_item(queue_id);
+}
+
 async fn play_track(
     State(state): State<Arc<AppState>>,
     Path(track_id): Path<u32>,
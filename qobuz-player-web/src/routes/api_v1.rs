@@ -0,0 +1,998 @@
+//! The versioned JSON API under `/api/v1`, described by the OpenAPI document
+//! at `/api/v1/openapi.json`.
+//!
+//! The response types are separate from the player models so the API stays
+//! stable when the models change.
+
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    Extension, Json, Router,
+    extract::{FromRequest, Path, Query, Request, State, rejection::JsonRejection},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
+};
+use qobuz_player_controls::{
+    Status,
+    controls::Controls,
+    database::Role,
+    error::Error,
+    models,
+    tracklist::{self, Tracklist},
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+use crate::AppState;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/api/v1/openapi.json", get(openapi))
+        .route("/api/v1/search", get(search))
+        .route("/api/v1/albums/{id}", get(album))
+        .route("/api/v1/artists/{id}", get(artist))
+        .route("/api/v1/playlists/{id}", get(playlist))
+        .route(
+            "/api/v1/queue",
+            get(queue).post(add_to_queue).delete(clear_queue),
+        )
+        .route(
+            "/api/v1/queue/{queue_id}",
+            put(move_queue_item).delete(remove_queue_item),
+        )
+        .route("/api/v1/queue/{queue_id}/play", post(play_queue_item))
+        .route("/api/v1/playback", get(playback).put(set_playback))
+        .route("/api/v1/playback/next", post(next))
+        .route("/api/v1/playback/previous", post(previous))
+        .route("/api/v1/volume", get(volume).put(set_volume))
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "qobuz-player",
+        description = "Browse Qobuz and control the player. Player commands are applied asynchronously and answered with 202 Accepted."
+    ),
+    paths(
+        search,
+        album,
+        artist,
+        playlist,
+        queue,
+        add_to_queue,
+        clear_queue,
+        move_queue_item,
+        remove_queue_item,
+        play_queue_item,
+        playback,
+        set_playback,
+        next,
+        previous,
+        volume,
+        set_volume,
+    ),
+    tags(
+        (name = "library", description = "Albums, artists, playlists and search"),
+        (name = "queue", description = "The play queue"),
+        (name = "playback", description = "Transport and volume"),
+    )
+)]
+pub struct ApiDoc;
+
+async fn openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+#[derive(Serialize, ToSchema)]
+struct ErrorBody {
+    error: String,
+}
+
+/// An error answered with a JSON body and a matching status code
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            message: message.into(),
+        }
+    }
+
+    fn forbidden(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::FORBIDDEN,
+            message: message.into(),
+        }
+    }
+
+    fn not_found(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::NOT_FOUND,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<Error> for ApiError {
+    fn from(error: Error) -> Self {
+        let status = match &error {
+            Error::InvalidLink { .. } => StatusCode::BAD_REQUEST,
+            Error::MissingCredentials => StatusCode::SERVICE_UNAVAILABLE,
+            Error::Client {
+                status: Some(404), ..
+            } => StatusCode::NOT_FOUND,
+            Error::Client { .. } => StatusCode::BAD_GATEWAY,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        Self {
+            status,
+            message: error.to_string(),
+        }
+    }
+}
+
+/// A JSON request body. Bodies that can't be read are answered with the
+/// JSON error body, like every other error.
+struct ApiJson<T>(T);
+
+impl<T, S> FromRequest<S> for ApiJson<T>
+where
+    Json<T>: FromRequest<S, Rejection = JsonRejection>,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        match Json::<T>::from_request(request, state).await {
+            Ok(Json(value)) => Ok(Self(value)),
+            Err(rejection) => Err(ApiError {
+                status: rejection.status(),
+                message: rejection.body_text(),
+            }),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (
+            self.status,
+            Json(ErrorBody {
+                error: self.message,
+            }),
+        )
+            .into_response()
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct Track {
+    id: u32,
+    title: String,
+    /// Position on the disc
+    number: u32,
+    /// Counting from 1. 0 for tracks queued before disc numbers were kept.
+    disc_number: u32,
+    duration_seconds: u32,
+    explicit: bool,
+    hires_available: bool,
+    available: bool,
+    artist_id: Option<u32>,
+    artist_name: Option<String>,
+    album_id: Option<String>,
+    album_title: Option<String>,
+    image: Option<String>,
+}
+
+impl From<models::Track> for Track {
+    fn from(track: models::Track) -> Self {
+        Self {
+            id: track.id,
+            title: track.title,
+            number: track.number,
+            disc_number: track.disc_number,
+            duration_seconds: track.duration_seconds,
+            explicit: track.explicit,
+            hires_available: track.hires_available,
+            available: track.available,
+            artist_id: track.artist_id,
+            artist_name: track.artist_name,
+            album_id: track.album_id,
+            album_title: track.album_title,
+            image: track.image,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct ArtistSummary {
+    id: u32,
+    name: String,
+    image: Option<String>,
+}
+
+impl From<models::Artist> for ArtistSummary {
+    fn from(artist: models::Artist) -> Self {
+        Self {
+            id: artist.id,
+            name: artist.name,
+            image: artist.image,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct AlbumSummary {
+    id: String,
+    title: String,
+    artist: ArtistSummary,
+    release_year: u32,
+    duration_seconds: u32,
+    explicit: bool,
+    hires_available: bool,
+    available: bool,
+    image: String,
+}
+
+impl From<models::AlbumSimple> for AlbumSummary {
+    fn from(album: models::AlbumSimple) -> Self {
+        Self {
+            id: album.id,
+            title: album.title,
+            artist: album.artist.into(),
+            release_year: album.release_year,
+            duration_seconds: album.duration_seconds,
+            explicit: album.explicit,
+            hires_available: album.hires_available,
+            available: album.available,
+            image: album.image,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct Album {
+    id: String,
+    title: String,
+    artist: ArtistSummary,
+    release_year: u32,
+    duration_seconds: u32,
+    explicit: bool,
+    hires_available: bool,
+    available: bool,
+    image: String,
+    description: Option<String>,
+    label: Option<String>,
+    genre: Option<String>,
+    /// Highest bit depth and sample rate, like `24-bit / 96 kHz`
+    quality: Option<String>,
+    tracks: Vec<Track>,
+}
+
+impl From<models::Album> for Album {
+    fn from(album: models::Album) -> Self {
+        Self {
+            quality: album.quality(),
+            id: album.id,
+            title: album.title,
+            artist: album.artist.into(),
+            release_year: album.release_year,
+            duration_seconds: album.duration_seconds,
+            explicit: album.explicit,
+            hires_available: album.hires_available,
+            available: album.available,
+            image: album.image,
+            description: album.description,
+            label: album.label.map(|label| label.name),
+            genre: album.genre.map(|genre| genre.name),
+            tracks: album.tracks.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct Artist {
+    id: u32,
+    name: String,
+    image: Option<String>,
+    description: Option<String>,
+    top_tracks: Vec<Track>,
+    albums: Vec<AlbumSummary>,
+    singles: Vec<AlbumSummary>,
+    live: Vec<AlbumSummary>,
+    compilations: Vec<AlbumSummary>,
+    similar_artists: Vec<ArtistSummary>,
+}
+
+impl From<models::ArtistPage> for Artist {
+    fn from(artist: models::ArtistPage) -> Self {
+        let albums = |albums: Vec<models::AlbumSimple>| -> Vec<AlbumSummary> {
+            albums.into_iter().map(Into::into).collect()
+        };
+
+        Self {
+            id: artist.id,
+            name: artist.name,
+            image: artist.image,
+            description: artist.description,
+            top_tracks: artist.top_tracks.into_iter().map(Into::into).collect(),
+            albums: albums(artist.albums),
+            singles: albums(artist.singles),
+            live: albums(artist.live),
+            compilations: albums(artist.compilations),
+            similar_artists: artist.similar_artists.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct PlaylistSummary {
+    id: u32,
+    title: String,
+    tracks_count: u32,
+    duration_seconds: u32,
+    image: Option<String>,
+    /// Owned by the logged in user
+    is_owned: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+struct Playlist {
+    id: u32,
+    title: String,
+    tracks_count: u32,
+    duration_seconds: u32,
+    image: Option<String>,
+    /// Owned by the logged in user
+    is_owned: bool,
+    tracks: Vec<Track>,
+}
+
+impl From<models::Playlist> for PlaylistSummary {
+    fn from(playlist: models::Playlist) -> Self {
+        Self {
+            id: playlist.id,
+            title: playlist.title,
+            tracks_count: playlist.tracks_count,
+            duration_seconds: playlist.duration_seconds,
+            image: playlist.image,
+            is_owned: playlist.is_owned,
+        }
+    }
+}
+
+impl From<models::Playlist> for Playlist {
+    fn from(playlist: models::Playlist) -> Self {
+        Self {
+            id: playlist.id,
+            title: playlist.title,
+            tracks_count: playlist.tracks_count,
+            duration_seconds: playlist.duration_seconds,
+            image: playlist.image,
+            is_owned: playlist.is_owned,
+            tracks: playlist.tracks.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct SearchResults {
+    query: String,
+    albums: Vec<AlbumSummary>,
+    artists: Vec<ArtistSummary>,
+    playlists: Vec<PlaylistSummary>,
+    tracks: Vec<Track>,
+}
+
+#[derive(Deserialize, IntoParams)]
+struct SearchQuery {
+    /// What to search for
+    q: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/search",
+    tag = "library",
+    params(SearchQuery),
+    responses(
+        (status = 200, body = SearchResults),
+        (status = 400, description = "Empty query", body = ErrorBody),
+        (status = 502, description = "Qobuz error", body = ErrorBody),
+    )
+)]
+async fn search(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SearchQuery>,
+) -> ApiResult<SearchResults> {
+    let query = query.q.trim();
+    if query.is_empty() {
+        return Err(ApiError::bad_request("The query is empty"));
+    }
+
+    let results = state.client.search(query.to_string()).await?;
+
+    Ok(Json(SearchResults {
+        query: results.query,
+        albums: results
+            .albums
+            .into_iter()
+            .map(|album| models::AlbumSimple::from(album).into())
+            .collect(),
+        artists: results.artists.into_iter().map(Into::into).collect(),
+        playlists: results.playlists.into_iter().map(Into::into).collect(),
+        tracks: results.tracks.into_iter().map(Into::into).collect(),
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/albums/{id}",
+    tag = "library",
+    params(("id" = String, Path, description = "Qobuz album id")),
+    responses(
+        (status = 200, body = Album),
+        (status = 404, body = ErrorBody),
+        (status = 502, description = "Qobuz error", body = ErrorBody),
+    )
+)]
+async fn album(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<Album> {
+    Ok(Json(state.client.album(&id).await?.into()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/artists/{id}",
+    tag = "library",
+    params(("id" = u32, Path, description = "Qobuz artist id")),
+    responses(
+        (status = 200, body = Artist),
+        (status = 404, body = ErrorBody),
+        (status = 502, description = "Qobuz error", body = ErrorBody),
+    )
+)]
+async fn artist(State(state): State<Arc<AppState>>, Path(id): Path<u32>) -> ApiResult<Artist> {
+    Ok(Json(state.client.artist_page(id).await?.into()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/playlists/{id}",
+    tag = "library",
+    params(("id" = u32, Path, description = "Qobuz playlist id")),
+    responses(
+        (status = 200, body = Playlist),
+        (status = 404, body = ErrorBody),
+        (status = 502, description = "Qobuz error", body = ErrorBody),
+    )
+)]
+async fn playlist(State(state): State<Arc<AppState>>, Path(id): Path<u32>) -> ApiResult<Playlist> {
+    Ok(Json(state.client.playlist(id).await?.into()))
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct QueueItem {
+    /// Identifies the item for as long as it is in the queue
+    queue_id: u64,
+    track: Track,
+}
+
+impl From<&tracklist::QueueItem> for QueueItem {
+    fn from(item: &tracklist::QueueItem) -> Self {
+        Self {
+            queue_id: item.queue_id,
+            track: item.track.clone().into(),
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct Queue {
+    items: Vec<QueueItem>,
+    /// The item being played
+    current_queue_id: Option<u64>,
+}
+
+impl From<&Tracklist> for Queue {
+    fn from(tracklist: &Tracklist) -> Self {
+        Self {
+            items: tracklist.queue().into_iter().map(Into::into).collect(),
+            current_queue_id: tracklist.current_queue_id(),
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/queue",
+    tag = "queue",
+    responses((status = 200, body = Queue))
+)]
+async fn queue(State(state): State<Arc<AppState>>) -> Json<Queue> {
+    Json((&*state.tracklist_receiver.borrow()).into())
+}
+
+#[derive(Deserialize, ToSchema, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum QueueMode {
+    /// Add to the end of the queue
+    #[default]
+    Append,
+    /// Add after the current track
+    Next,
+    /// Replace the queue and start playing
+    Replace,
+}
+
+/// Either `track_ids` or `url` is given
+#[derive(Deserialize, ToSchema)]
+pub(crate) struct AddToQueue {
+    #[serde(default)]
+    track_ids: Vec<u32>,
+    /// A Qobuz link to an album, playlist, track or artist
+    url: Option<String>,
+    #[serde(default)]
+    mode: QueueMode,
+}
+
+impl AddToQueue {
+    /// Guests may add to the queue but not replace it
+    pub(crate) fn required_role(&self) -> Role {
+        match self.mode {
+            QueueMode::Append | QueueMode::Next => Role::Guest,
+            QueueMode::Replace => Role::Controller,
+        }
+    }
+
+    pub(crate) fn apply(self, controls: &Controls) -> Result<(), String> {
+        match (self.track_ids.is_empty(), self.url) {
+            (false, None) => match self.mode {
+                QueueMode::Append => controls.add_tracks_to_queue(self.track_ids),
+                QueueMode::Next => controls.play_tracks_next(self.track_ids),
+                QueueMode::Replace => controls.play_tracks(self.track_ids, false),
+            },
+            (true, Some(url)) => {
+                let link = models::Link::parse(&url).map_err(|err| err.to_string())?;
+                match self.mode {
+                    QueueMode::Append => controls.queue_link(link),
+                    QueueMode::Replace => controls.play_link(link),
+                    QueueMode::Next => {
+                        return Err("Links can only be appended or replace the queue".into());
+                    }
+                }
+            }
+            _ => return Err("Give either track_ids or a url".into()),
+        }
+
+        Ok(())
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/queue",
+    tag = "queue",
+    request_body = AddToQueue,
+    responses(
+        (status = 202, description = "The tracks are being added"),
+        (status = 400, body = ErrorBody),
+        (status = 403, description = "Guests can't replace the queue", body = ErrorBody),
+    )
+)]
+async fn add_to_queue(
+    State(state): State<Arc<AppState>>,
+    Extension(role): Extension<Role>,
+    ApiJson(req): ApiJson<AddToQueue>,
+) -> Result<StatusCode, ApiError> {
+    if role < req.required_role() {
+        return Err(ApiError::forbidden("Guests can only add to the queue"));
+    }
+
+    req.apply(&state.controls).map_err(ApiError::bad_request)?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/queue",
+    tag = "queue",
+    responses((status = 202, description = "The queue is being cleared"))
+)]
+async fn clear_queue(State(state): State<Arc<AppState>>) -> StatusCode {
+    state.controls.clear_queue();
+    StatusCode::ACCEPTED
+}
+
+fn ensure_queue_item(state: &AppState, queue_id: u64) -> Result<usize, ApiError> {
+    state
+        .tracklist_receiver
+        .borrow()
+        .position_of_queue_id(queue_id)
+        .ok_or_else(|| ApiError::not_found(format!("No queue item {queue_id}")))
+}
+
+#[derive(Deserialize, ToSchema)]
+struct MoveQueueItem {
+    /// The new position in the queue, counting from 0
+    position: usize,
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/queue/{queue_id}",
+    tag = "queue",
+    params(("queue_id" = u64, Path)),
+    request_body = MoveQueueItem,
+    responses(
+        (status = 202, description = "The item is being moved"),
+        (status = 400, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+    )
+)]
+async fn move_queue_item(
+    State(state): State<Arc<AppState>>,
+    Path(queue_id): Path<u64>,
+    ApiJson(req): ApiJson<MoveQueueItem>,
+) -> Result<StatusCode, ApiError> {
+    ensure_queue_item(&state, queue_id)?;
+
+    let total = state.tracklist_receiver.borrow().total();
+    if req.position >= total {
+        return Err(ApiError::bad_request(format!(
+            "Position {} is past the end of the queue",
+            req.position
+        )));
+    }
+
+    state.controls.move_queue_item(queue_id, req.position);
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/queue/{queue_id}",
+    tag = "queue",
+    params(("queue_id" = u64, Path)),
+    responses(
+        (status = 202, description = "The item is being removed"),
+        (status = 404, body = ErrorBody),
+    )
+)]
+async fn remove_queue_item(
+    State(state): State<Arc<AppState>>,
+    Path(queue_id): Path<u64>,
+) -> Result<StatusCode, ApiError> {
+    ensure_queue_item(&state, queue_id)?;
+    state.controls.remove_queue_item(queue_id);
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/queue/{queue_id}/play",
+    tag = "queue",
+    params(("queue_id" = u64, Path)),
+    responses(
+        (status = 202, description = "Skipping to the item"),
+        (status = 404, body = ErrorBody),
+    )
+)]
+async fn play_queue_item(
+    State(state): State<Arc<AppState>>,
+    Path(queue_id): Path<u64>,
+) -> Result<StatusCode, ApiError> {
+    ensure_queue_item(&state, queue_id)?;
+    state.controls.skip_to_queue_item(queue_id);
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum PlaybackStatus {
+    Playing,
+    Paused,
+    Buffering,
+}
+
+impl From<Status> for PlaybackStatus {
+    fn from(status: Status) -> Self {
+        match status {
+            Status::Playing => Self::Playing,
+            Status::Paused => Self::Paused,
+            Status::Buffering => Self::Buffering,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct Playback {
+    status: PlaybackStatus,
+    position_ms: u64,
+    track: Option<Track>,
+    queue_id: Option<u64>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/playback",
+    tag = "playback",
+    responses((status = 200, body = Playback))
+)]
+async fn playback(State(state): State<Arc<AppState>>) -> Json<Playback> {
+    let tracklist = state.tracklist_receiver.borrow();
+
+    Json(Playback {
+        status: (*state.status_receiver.borrow()).into(),
+        position_ms: state.position_receiver.borrow().as_millis() as u64,
+        track: tracklist.current_track().cloned().map(Into::into),
+        queue_id: tracklist.current_queue_id(),
+    })
+}
+
+#[derive(Deserialize, ToSchema)]
+struct SetPlayback {
+    /// `playing` or `paused`
+    status: Option<PlaybackStatus>,
+    /// Seek to this position in the current track
+    position_ms: Option<u64>,
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/playback",
+    tag = "playback",
+    request_body = SetPlayback,
+    responses(
+        (status = 202, description = "The player is being updated"),
+        (status = 400, body = ErrorBody),
+    )
+)]
+async fn set_playback(
+    State(state): State<Arc<AppState>>,
+    ApiJson(req): ApiJson<SetPlayback>,
+) -> Result<StatusCode, ApiError> {
+    if req.status == Some(PlaybackStatus::Buffering) {
+        return Err(ApiError::bad_request("The status is playing or paused"));
+    }
+
+    if let Some(position_ms) = req.position_ms {
+        state.controls.seek(Duration::from_millis(position_ms));
+    }
+
+    match req.status {
+        Some(PlaybackStatus::Playing) => state.controls.play(),
+        Some(PlaybackStatus::Paused) => state.controls.pause(),
+        Some(PlaybackStatus::Buffering) | None => (),
+    }
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/playback/next",
+    tag = "playback",
+    responses((status = 202, description = "Skipping to the next track"))
+)]
+async fn next(State(state): State<Arc<AppState>>) -> StatusCode {
+    state.controls.next();
+    StatusCode::ACCEPTED
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/playback/previous",
+    tag = "playback",
+    responses((status = 202, description = "Skipping to the previous track"))
+)]
+async fn previous(State(state): State<Arc<AppState>>) -> StatusCode {
+    state.controls.previous();
+    StatusCode::ACCEPTED
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+struct Volume {
+    /// 0 to 100
+    #[schema(maximum = 100)]
+    volume: u32,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/volume",
+    tag = "playback",
+    responses((status = 200, body = Volume))
+)]
+async fn volume(State(state): State<Arc<AppState>>) -> Json<Volume> {
+    let volume = *state.volume_receiver.borrow();
+
+    Json(Volume {
+        volume: (volume * 100.0).round() as u32,
+    })
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/volume",
+    tag = "playback",
+    request_body = Volume,
+    responses(
+        (status = 202, description = "The volume is being changed"),
+        (status = 400, body = ErrorBody),
+    )
+)]
+async fn set_volume(
+    State(state): State<Arc<AppState>>,
+    ApiJson(req): ApiJson<Volume>,
+) -> Result<StatusCode, ApiError> {
+    if req.volume > 100 {
+        return Err(ApiError::bad_request("The volume is 0 to 100"));
+    }
+
+    state.controls.set_volume(req.volume as f32 / 100.0);
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn openapi_document() {
+        let document = serde_json::to_value(ApiDoc::openapi()).unwrap();
+
+        for path in [
+            "/api/v1/search",
+            "/api/v1/albums/{id}",
+            "/api/v1/artists/{id}",
+            "/api/v1/playlists/{id}",
+            "/api/v1/queue",
+            "/api/v1/queue/{queue_id}",
+            "/api/v1/queue/{queue_id}/play",
+            "/api/v1/playback",
+            "/api/v1/playback/next",
+            "/api/v1/playback/previous",
+            "/api/v1/volume",
+        ] {
+            assert!(document["paths"].get(path).is_some(), "{path} is missing");
+        }
+
+        assert!(document["paths"]["/api/v1/queue"]["post"].is_object());
+        assert!(document["components"]["schemas"]["Album"].is_object());
+    }
+
+    /// Errors made the way the Qobuz client makes them, against the mock
+    /// Qobuz API
+    #[tokio::test]
+    async fn client_error_status_codes() {
+        use qobuz_player_client::client::{AudioQuality, Client};
+        use qobuz_player_mock::{Fixtures, MockServer, USER_AUTH_TOKEN, USER_ID};
+
+        let status = |error: qobuz_player_client::Error| ApiError::from(Error::from(error)).status;
+
+        let server = MockServer::start(Fixtures::bundled()).await.unwrap();
+        let client = Client::new_with_endpoints(
+            USER_AUTH_TOKEN,
+            USER_ID,
+            AudioQuality::CD,
+            server.endpoints(),
+        )
+        .await
+        .unwrap();
+
+        // The message is the JSON body Qobuz answered with, not a status line
+        let missing = client.album("missingalbum").await.unwrap_err();
+        assert!(missing.to_string().starts_with('{'));
+        assert_eq!(status(missing), StatusCode::NOT_FOUND);
+
+        let refused = reqwest::get("http://127.0.0.1:1").await.unwrap_err();
+        assert_eq!(status(refused.into()), StatusCode::BAD_GATEWAY);
+
+        assert_eq!(
+            ApiError::from(Error::InvalidLink {
+                message: "https://example.com".into()
+            })
+            .status,
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    async fn json_rejection(
+        content_type: Option<&str>,
+        body: &'static str,
+    ) -> (StatusCode, serde_json::Value) {
+        let mut request = Request::builder().method("PUT").uri("/api/v1/volume");
+        if let Some(content_type) = content_type {
+            request = request.header("content-type", content_type);
+        }
+        let request = request.body(axum::body::Body::from(body)).unwrap();
+
+        let Err(error) = ApiJson::<Volume>::from_request(request, &()).await else {
+            panic!("{body} was accepted");
+        };
+        let response = error.into_response();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn json_rejections_have_a_json_body() {
+        let (status, body) =
+            json_rejection(Some("application/json"), r#"{"volume": "loud"}"#).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body["error"].as_str().unwrap().contains("volume"));
+
+        let (status, body) = json_rejection(Some("application/json"), "{").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["error"].is_string());
+
+        let (status, body) = json_rejection(None, r#"{"volume": 10}"#).await;
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert!(body["error"].is_string());
+    }
+
+    /// A guest asking for a track next when nothing is queued
+    #[tokio::test]
+    async fn guest_adds_next_to_an_empty_queue() {
+        use qobuz_player_controls::{
+            catalog::memory::MemoryCatalog, database::Database,
+            notification::NotificationBroadcast, player::Player, tracklist::Tracklist,
+        };
+
+        let path = std::env::temp_dir().join(format!(
+            "qobuz-player-api-next-test-{}.db",
+            std::process::id()
+        ));
+        let database = Arc::new(Database::open(&path).await.unwrap());
+        let mut player = Player::new(
+            Tracklist::default(),
+            Arc::new(MemoryCatalog::fixture()),
+            1.0,
+            Arc::new(NotificationBroadcast::new()),
+            std::env::temp_dir().join("qobuz-player-test-cache"),
+            database,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        let mut tracklist = player.tracklist();
+        let _status = player.status();
+        let _position = player.position();
+        let (exit_tx, exit_rx) = tokio::sync::broadcast::channel(1);
+
+        let request: AddToQueue =
+            serde_json::from_str(r#"{"track_ids": [101], "mode": "next"}"#).unwrap();
+        assert_eq!(request.required_role(), Role::Guest);
+        request.apply(&player.controls()).unwrap();
+
+        let queued = async {
+            tracklist.changed().await.unwrap();
+            let ids: Vec<_> = tracklist
+                .borrow()
+                .queue()
+                .iter()
+                .map(|item| item.track.id)
+                .collect();
+            _ = exit_tx.send(true);
+            ids
+        };
+        let (result, ids) = tokio::join!(player.player_loop(exit_rx), queued);
+        result.unwrap();
+        assert_eq!(ids, vec![101]);
+
+        _ = std::fs::remove_file(path);
+    }
+}
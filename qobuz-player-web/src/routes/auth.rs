@@ -1,9 +1,13 @@
-use crate::AppState;
+use crate::{
+    AppState,
+    server::PeerAddress,
+    users::{LoginKey, new_token, required_role, token_hash, verify_password},
+};
 use axum::{
-    Form, Router,
+    Extension, Form, Router,
     body::Body,
-    extract::{Request, State},
-    http::{Response, StatusCode},
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, Response, StatusCode, header::AUTHORIZATION},
     response::IntoResponse,
     routing::{get, post},
 };
@@ -11,27 +15,116 @@ use axum_extra::extract::{
     CookieJar,
     cookie::{Cookie, SameSite},
 };
+use qobuz_player_controls::database::Role;
 use serde::Deserialize;
 use serde_json::json;
-use std::sync::Arc;
+use std::{net::IpAddr, sync::Arc};
+
+const SECRET_COOKIE: &str = "secret";
+const SESSION_COOKIE: &str = "session";
+const SESSION_MAX_AGE: time::Duration = time::Duration::weeks(1);
 
 pub fn routes() -> Router<Arc<AppState>> {
     Router::new()
         .route("/auth", get(index))
         .route("/auth/login", post(login))
+        .route("/auth/logout", post(logout))
+}
+
+enum Access {
+    /// No users and no web secret
+    Open,
+    Granted(Role),
+    Denied,
+}
+
+/// Once web users exist they log in with their own password, and the web
+/// secret is no longer accepted. Scripts send an API token as a bearer token.
+async fn access(state: &AppState, jar: &CookieJar, headers: &HeaderMap) -> Access {
+    // Fail closed when the database can't tell
+    let has_users = state.database.has_web_users().await.unwrap_or(true);
+    if !has_users && state.web_secret.is_none() {
+        return Access::Open;
+    }
+
+    let bearer = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    if let Some(token) = bearer {
+        if let Ok(Some(role)) = state.database.get_api_token_role(&token_hash(token)).await {
+            return Access::Granted(role);
+        }
+        if !has_users && state.web_secret.as_deref() == Some(token) {
+            return Access::Granted(Role::Admin);
+        }
+        return Access::Denied;
+    }
+
+    if has_users {
+        if let Some(session) = jar.get(SESSION_COOKIE)
+            && let Ok(Some(user)) = state
+                .database
+                .get_web_session_user(&token_hash(session.value()), SESSION_MAX_AGE)
+                .await
+        {
+            return Access::Granted(user.role);
+        }
+        return Access::Denied;
+    }
+
+    match (&state.web_secret, jar.get(SECRET_COOKIE)) {
+        (Some(secret), Some(cookie)) if secret == cookie.value() => Access::Granted(Role::Admin),
+        _ => Access::Denied,
+    }
 }
 
 pub async fn auth_middleware(
     State(state): State<Arc<AppState>>,
     jar: CookieJar,
-    request: Request,
+    mut request: Request,
     next: axum::middleware::Next,
 ) -> (CookieJar, Response<Body>) {
-    let Some(state_secret) = state.web_secret.clone() else {
-        return (jar, next.run(request).await);
+    let role = match access(&state, &jar, request.headers()).await {
+        Access::Open => Role::Admin,
+        Access::Granted(role) => role,
+        Access::Denied if request.headers().contains_key(AUTHORIZATION) => {
+            return (jar, StatusCode::UNAUTHORIZED.into_response());
+        }
+        Access::Denied => return (jar, redirect("/auth")),
+    };
+
+    if role < required_role(request.method(), request.uri().path()) {
+        return (
+            jar,
+            (StatusCode::FORBIDDEN, "Not allowed for your role").into_response(),
+        );
+    }
+
+    request.extensions_mut().insert(role);
+
+    // Keep the web secret cookie alive while it is used
+    let jar = match (&state.web_secret, jar.get(SECRET_COOKIE)) {
+        (Some(secret), Some(_)) => set_cookie(&state, jar, SECRET_COOKIE, secret.clone()),
+        _ => jar,
     };
 
-    let redirect_response = (
+    (jar, next.run(request).await)
+}
+
+fn set_cookie(state: &AppState, jar: CookieJar, name: &'static str, value: String) -> CookieJar {
+    let mut cookie = Cookie::new(name, value);
+    cookie.set_same_site(SameSite::Strict);
+    cookie.set_http_only(true);
+    cookie.set_secure(state.secure_cookies);
+    cookie.set_path("/");
+    cookie.set_max_age(SESSION_MAX_AGE);
+    jar.add(cookie)
+}
+
+fn redirect(location: &'static str) -> Response<Body> {
+    (
         StatusCode::FOUND,
         [
             (
@@ -40,73 +133,153 @@ pub async fn auth_middleware(
             ),
             (
                 axum::http::header::LOCATION,
-                axum::http::HeaderValue::from_static("/auth"),
+                axum::http::HeaderValue::from_static(location),
             ),
         ],
     )
-        .into_response();
-
-    let secret = jar.get("secret");
-
-    match secret {
-        Some(result_secret) => {
-            if state_secret != result_secret.value() {
-                return (jar, redirect_response);
-            }
-        }
-        None => return (jar, redirect_response),
-    }
-
-    (set_auth_cookie(jar, state_secret), next.run(request).await)
-}
-
-fn set_auth_cookie(jar: CookieJar, secret: String) -> CookieJar {
-    let mut cookie = Cookie::new("secret", secret);
-    cookie.set_same_site(SameSite::Strict);
-    cookie.set_path("/");
-    cookie.set_max_age(time::Duration::weeks(1));
-    jar.add(cookie)
+        .into_response()
 }
 
 async fn index(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    state.render("unauthorized-page.html", &json!({}))
+    let users = state.database.has_web_users().await.unwrap_or(true);
+    state.render("unauthorized-page.html", &json!({"users": users}))
 }
 
 #[derive(Deserialize)]
 struct LoginParameters {
-    secret: String,
+    #[serde(default)]
+    username: String,
+    password: String,
 }
 
 async fn login(
     State(state): State<Arc<AppState>>,
+    connect_info: Option<Extension<ConnectInfo<PeerAddress>>>,
+    headers: HeaderMap,
     jar: CookieJar,
     Form(parameters): Form<LoginParameters>,
 ) -> (CookieJar, Response<Body>) {
-    let response = (
-        StatusCode::FOUND,
-        [
-            (
-                axum::http::header::CONTENT_TYPE,
-                axum::http::HeaderValue::from_static(mime::TEXT_HTML_UTF_8.as_ref()),
-            ),
-            (
-                axum::http::header::LOCATION,
-                axum::http::HeaderValue::from_static("/"),
-            ),
-        ],
-        "Success",
-    )
-        .into_response();
-
-    match state.web_secret.clone() {
-        None => return (jar, response),
-        Some(secret) => {
-            if secret == parameters.secret {
-                return (set_auth_cookie(jar, secret), response);
-            };
+    let peer = connect_info.and_then(|Extension(ConnectInfo(PeerAddress(address)))| address);
+    let key = login_key(peer, &headers, &parameters.username);
+
+    if state.login_limiter.is_locked_out(&key) {
+        let response = (
+            StatusCode::TOO_MANY_REQUESTS,
+            "Too many failed logins. Try again later",
+        )
+            .into_response();
+        return (jar, response);
+    }
+
+    let has_users = state.database.has_web_users().await.unwrap_or(true);
+
+    let jar = if has_users {
+        let user = state
+            .database
+            .get_web_user(parameters.username.trim())
+            .await
+            .ok()
+            .flatten()
+            .filter(|user| verify_password(&parameters.password, &user.password_hash));
+
+        let Some(user) = user else {
+            state.login_limiter.failed(&key);
+            return (
+                jar,
+                (StatusCode::UNAUTHORIZED, "Bad credentials").into_response(),
+            );
+        };
+
+        let token = new_token();
+        if let Err(err) = state
+            .database
+            .add_web_session(&token_hash(&token), user.id)
+            .await
+        {
+            let response = (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response();
+            return (jar, response);
+        }
+
+        set_cookie(&state, jar, SESSION_COOKIE, token)
+    } else {
+        match &state.web_secret {
+            None => jar,
+            Some(secret) if *secret == parameters.password => {
+                set_cookie(&state, jar, SECRET_COOKIE, secret.clone())
+            }
+            Some(_) => {
+                state.login_limiter.failed(&key);
+                return (
+                    jar,
+                    (StatusCode::UNAUTHORIZED, "Bad credentials").into_response(),
+                );
+            }
         }
+    };
+
+    state.login_limiter.succeeded(&key);
+    (jar, redirect("/"))
+}
+
+/// Unix socket peers have no address. Only the proxy in front can reach the
+/// socket, so the client address it adds last to `X-Forwarded-For` is
+/// trusted, and without one failures count against the username.
+fn login_key(peer: Option<IpAddr>, headers: &HeaderMap, username: &str) -> LoginKey {
+    if let Some(address) = peer {
+        return LoginKey::Address(address);
     }
 
-    let response = (StatusCode::UNAUTHORIZED, "Bad credentials").into_response();
-    (jar, response)
+    headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .next_back()
+        .and_then(|address| address.trim().parse().ok())
+        .map(LoginKey::Address)
+        .unwrap_or_else(|| LoginKey::Username(username.trim().to_string()))
+}
+
+async fn logout(State(state): State<Arc<AppState>>, jar: CookieJar) -> (CookieJar, Response<Body>) {
+    if let Some(session) = jar.get(SESSION_COOKIE) {
+        _ = state
+            .database
+            .remove_web_session(&token_hash(session.value()))
+            .await;
+    }
+
+    let jar = jar
+        .remove(Cookie::build(SESSION_COOKIE).path("/"))
+        .remove(Cookie::build(SECRET_COOKIE).path("/"));
+
+    (jar, redirect("/auth"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn login_keys() {
+        let address: IpAddr = "192.168.1.20".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "10.0.0.1".parse().unwrap());
+        headers.append(
+            "x-forwarded-for",
+            "203.0.113.7, 192.168.1.30".parse().unwrap(),
+        );
+
+        assert_eq!(
+            login_key(Some(address), &headers, "admin"),
+            LoginKey::Address(address)
+        );
+        assert_eq!(
+            login_key(None, &headers, "admin"),
+            LoginKey::Address("192.168.1.30".parse().unwrap())
+        );
+        assert_eq!(
+            login_key(None, &HeaderMap::new(), " admin "),
+            LoginKey::Username("admin".into())
+        );
+    }
 }
@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    extract::{Path, State},
+    response::IntoResponse,
+    routing::{get, put},
+};
+use serde_json::json;
+
+use crate::{AppState, ResponseResult, ok_or_send_error_toast};
+
+pub fn routes() -> Router<std::sync::Arc<crate::AppState>> {
+    Router::new()
+        .route("/composer/{id}", get(index))
+        .route("/composer/{id}/content", get(content))
+        .route("/composer/{id}/work/{index}/play", put(play_work))
+}
+
+async fn index(State(state): State<Arc<AppState>>, Path(id): Path<u32>) -> impl IntoResponse {
+    let url = format!("/composer/{id}/content");
+    state.render("lazy-load-component.html", &json!({"url": url}))
+}
+
+async fn content(State(state): State<Arc<AppState>>, Path(id): Path<u32>) -> ResponseResult {
+    let composer = ok_or_send_error_toast(&state, state.client.composer_page(id).await)?;
+
+    Ok(state.render(
+        "composer.html",
+        &json!({
+            "composer": composer,
+            "click": "/api/track/play/",
+        }),
+    ))
+}
+
+/// Plays all movements of a work
+async fn play_work(
+    State(state): State<Arc<AppState>>,
+    Path((id, index)): Path<(u32, usize)>,
+) -> ResponseResult {
+    let composer = ok_or_send_error_toast(&state, state.client.composer_page(id).await)?;
+
+    if let Some(work) = composer.works.into_iter().nth(index) {
+        let track_ids = work.tracks.into_iter().map(|x| x.id).collect();
+        state.controls.play_tracks(track_ids, false);
+    }
+
+    Ok(().into_response())
+}
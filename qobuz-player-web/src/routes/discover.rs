@@ -72,6 +72,7 @@ async fn genre_detail(State(state): State<Arc<AppState>>, Path(id): Path<u32>) -
         .find(|x| x.id == id)
         .ok_or_else(|| Error::Client {
             message: "Unable to find genre".into(),
+            status: None,
         });
 
     let genre = ok_or_broadcast(&state.broadcast, genre)?;
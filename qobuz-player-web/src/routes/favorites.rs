@@ -2,45 +2,115 @@ use std::sync::Arc;
 
 use axum::{
     Router,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     response::IntoResponse,
     routing::{get, put},
 };
-use serde_json::json;
+use qobuz_player_controls::models::Favorites;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
 
 use crate::{AppState, ResponseResult, ok_or_error_page, ok_or_send_error_toast};
 
+/// Items rendered at once. The rest of a list loads as its end scrolls into
+/// view.
+const PAGE_SIZE: usize = 60;
+
 #[derive(Clone, PartialEq, serde::Deserialize, serde::Serialize)]
 #[serde(rename_all = "lowercase")]
 enum Tab {
     Albums,
     Artists,
+    Composers,
     Playlists,
     Tracks,
 }
 
+impl Tab {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Tab::Albums => "albums",
+            Tab::Artists => "artists",
+            Tab::Composers => "composers",
+            Tab::Playlists => "playlists",
+            Tab::Tracks => "tracks",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PageParameters {
+    #[serde(default)]
+    offset: usize,
+}
+
+#[derive(Deserialize)]
+struct PartialParameters {
+    #[serde(default)]
+    shown: usize,
+}
+
 pub fn routes() -> Router<std::sync::Arc<crate::AppState>> {
     Router::new()
         .route("/favorites/{tab}", get(index))
+        .route("/favorites/{tab}/more", get(more))
         .route("/favorites/tracks/partial", get(tracks_partial))
         .route("/favorites/tracks/shuffle", put(shuffle_favorite_tracks))
 }
 
 async fn index(State(state): State<Arc<AppState>>, Path(tab): Path<Tab>) -> ResponseResult {
     let favorites = ok_or_error_page(&state, state.get_favorites().await)?;
+    let (items, more_url) = tab_page(favorites, &tab, 0, PAGE_SIZE);
 
     Ok(state.render(
         "favorites.html",
-        &json!({"favorites": favorites, "tab": tab}),
+        &json!({"items": items, "more_url": more_url, "tab": tab}),
     ))
 }
 
-async fn tracks_partial(State(state): State<Arc<AppState>>) -> ResponseResult {
+async fn more(
+    State(state): State<Arc<AppState>>,
+    Path(tab): Path<Tab>,
+    Query(parameters): Query<PageParameters>,
+) -> ResponseResult {
     let favorites = ok_or_send_error_toast(&state, state.get_favorites().await)?;
+    let (items, more_url) = tab_page(favorites, &tab, parameters.offset, PAGE_SIZE);
 
-    Ok(state.render(
+    Ok(match tab {
+        Tab::Albums => state.render(
+            "list-albums.html",
+            &json!({"albums": items, "more_url": more_url}),
+        ),
+        Tab::Artists => state.render(
+            "list-artists.html",
+            &json!({"artists": items, "more_url": more_url}),
+        ),
+        Tab::Composers => state.render(
+            "list-artists.html",
+            &json!({"artists": items, "more_url": more_url, "composers": true}),
+        ),
+        Tab::Playlists => state.render(
+            "list-playlists.html",
+            &json!({"playlists": items, "more_url": more_url}),
+        ),
+        Tab::Tracks => render_tracks(&state, "list-tracks.html", items, more_url),
+    })
+}
+
+/// Re-rendered when the tracklist changes, keeping as many tracks as are
+/// already shown
+async fn tracks_partial(
+    State(state): State<Arc<AppState>>,
+    Query(parameters): Query<PartialParameters>,
+) -> ResponseResult {
+    let favorites = ok_or_send_error_toast(&state, state.get_favorites().await)?;
+    let (items, more_url) = tab_page(favorites, &Tab::Tracks, 0, parameters.shown.max(PAGE_SIZE));
+
+    Ok(render_tracks(
+        &state,
         "favorites-tracks.html",
-        &json!({"tracks": favorites.tracks}),
+        items,
+        more_url,
     ))
 }
 
@@ -52,3 +122,78 @@ async fn shuffle_favorite_tracks(State(state): State<Arc<AppState>>) -> Response
 
     Ok(().into_response())
 }
+
+fn render_tracks(
+    state: &AppState,
+    view: &str,
+    tracks: Value,
+    more_url: Option<String>,
+) -> axum::response::Response {
+    state.render(
+        view,
+        &json!({
+            "tracks": tracks,
+            "more_url": more_url,
+            "now_playing_id": state.playing_info().now_playing_id,
+            "click": "/api/track/play/",
+            "api_by_id": true,
+            "show_track_cover": true,
+            "show_artist": true,
+            "is_favorite": true,
+        }),
+    )
+}
+
+/// `count` items of the tab's list starting at `offset`, and the url of the
+/// items after them
+fn tab_page(
+    favorites: Favorites,
+    tab: &Tab,
+    offset: usize,
+    count: usize,
+) -> (Value, Option<String>) {
+    match tab {
+        Tab::Albums => page(favorites.albums, tab, offset, count),
+        Tab::Artists => page(favorites.artists, tab, offset, count),
+        Tab::Composers => page(favorites.composers(), tab, offset, count),
+        Tab::Playlists => page(favorites.playlists, tab, offset, count),
+        Tab::Tracks => page(favorites.tracks, tab, offset, count),
+    }
+}
+
+fn page<T: Serialize>(
+    items: Vec<T>,
+    tab: &Tab,
+    offset: usize,
+    count: usize,
+) -> (Value, Option<String>) {
+    let total = items.len();
+    let items: Vec<T> = items.into_iter().skip(offset).take(count).collect();
+
+    let next_offset = offset + count;
+    let more_url = (next_offset < total)
+        .then(|| format!("/favorites/{}/more?offset={next_offset}", tab.as_str()));
+
+    (json!(items), more_url)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn last_page_has_no_more_url() {
+        let items: Vec<u32> = (0..130).collect();
+
+        let (first, more_url) = page(items.clone(), &Tab::Albums, 0, PAGE_SIZE);
+        assert_eq!(first.as_array().unwrap().len(), PAGE_SIZE);
+        assert_eq!(
+            more_url.as_deref(),
+            Some("/favorites/albums/more?offset=60")
+        );
+
+        let (last, more_url) = page(items, &Tab::Albums, 120, PAGE_SIZE);
+        assert_eq!(last.as_array().unwrap().len(), 10);
+        assert_eq!(more_url, None);
+    }
+}
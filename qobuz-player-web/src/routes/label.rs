@@ -0,0 +1,60 @@
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    routing::get,
+};
+use qobuz_player_controls::client::LABEL_PAGE_SIZE;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{AppState, ResponseResult, ok_or_send_error_toast};
+
+pub fn routes() -> Router<std::sync::Arc<crate::AppState>> {
+    Router::new()
+        .route("/label/{id}", get(index))
+        .route("/label/{id}/content", get(content))
+        .route("/label/{id}/more", get(more))
+}
+
+#[derive(Deserialize)]
+struct MoreParameters {
+    offset: u32,
+}
+
+async fn index(State(state): State<Arc<AppState>>, Path(id): Path<u32>) -> impl IntoResponse {
+    let url = format!("/label/{id}/content");
+    state.render("lazy-load-component.html", &json!({"url": url}))
+}
+
+async fn content(State(state): State<Arc<AppState>>, Path(id): Path<u32>) -> ResponseResult {
+    let label = ok_or_send_error_toast(&state, state.client.label(id).await)?;
+    let more_url = more_url(id, label.albums.len(), 0);
+
+    Ok(state.render("label.html", &json!({"label": label, "more_url": more_url})))
+}
+
+async fn more(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<u32>,
+    Query(parameters): Query<MoreParameters>,
+) -> ResponseResult {
+    let albums = ok_or_send_error_toast(
+        &state,
+        state.client.label_albums(id, parameters.offset).await,
+    )?;
+    let more_url = more_url(id, albums.len(), parameters.offset);
+
+    Ok(state.render(
+        "list-albums.html",
+        &json!({"albums": albums, "more_url": more_url}),
+    ))
+}
+
+/// Url of the next page of albums, if the page at `offset` was full
+fn more_url(id: u32, count: usize, offset: u32) -> Option<String> {
+    (count >= LABEL_PAGE_SIZE as usize)
+        .then(|| format!("/label/{id}/more?offset={}", offset + LABEL_PAGE_SIZE))
+}
@@ -1,7 +1,9 @@
 use std::sync::Arc;
 
 use axum::{Router, extract::State, response::IntoResponse, routing::get};
-use serde_json::json;
+use qobuz_player_controls::{tracklist::QueueItem, tracklist_change::TracklistChange};
+use serde_json::{Value, json};
+use skabelon::Templates;
 
 use crate::app_state::AppState;
 
@@ -13,36 +15,73 @@ pub fn routes() -> Router<std::sync::Arc<crate::AppState>> {
 
 async fn index(State(state): State<Arc<AppState>>) -> impl IntoResponse {
     let tracklist = state.tracklist_receiver.borrow();
-    let tracks = tracklist
-        .queue()
-        .into_iter()
-        .map(|x| x.track.clone())
-        .collect::<Vec<_>>();
-    let currently_playing_position = tracklist.current_position();
-
-    state.render(
-        "queue.html",
-        &json!({
-            "tracks": tracks,
-            "currently_playing_position": currently_playing_position
-        }),
-    )
+    let items = tracklist.queue();
+
+    state.render("queue.html", &json!({"items": items}))
 }
 
 async fn queue_partial(State(state): State<Arc<AppState>>) -> impl IntoResponse {
     let tracklist = state.tracklist_receiver.borrow();
-    let tracks = tracklist
-        .queue()
-        .into_iter()
-        .map(|x| x.track.clone())
-        .collect::<Vec<_>>();
-    let currently_playing_position = tracklist.current_position();
-
-    state.render(
-        "queue-list.html",
-        &json!({
-            "tracks": tracks,
-            "currently_playing_position": currently_playing_position
-        }),
-    )
+    let items = tracklist.queue();
+
+    state.render("queue-list.html", &json!({"items": items}))
+}
+
+/// Apply the changes to the queue as the browser last saw it and describe
+/// the row updates needed to match. Returns `None` when the page should
+/// reload the whole list instead.
+pub(crate) fn change_operations(
+    queue: &mut Vec<QueueItem>,
+    changes: &[TracklistChange],
+    templates: &Templates,
+) -> Option<Vec<Value>> {
+    let render = |item: &QueueItem| templates.render("queue-item.html", &json!({"item": item}));
+    let mut operations = vec![];
+
+    for change in changes {
+        let previous_statuses: Vec<_> = match change {
+            TracklistChange::CurrentChanged { .. } => {
+                queue.iter().map(|item| item.track.status.clone()).collect()
+            }
+            _ => vec![],
+        };
+
+        if !change.apply(queue) {
+            return None;
+        }
+
+        match change {
+            TracklistChange::Replaced => return None,
+            TracklistChange::ItemsAdded { position, items } => operations.push(json!({
+                "op": "insert",
+                "position": position,
+                "html": items.iter().map(render).collect::<String>(),
+            })),
+            TracklistChange::ItemRemoved { queue_id } => {
+                operations.push(json!({"op": "remove", "queue_id": queue_id}))
+            }
+            TracklistChange::ItemMoved {
+                queue_id,
+                new_position,
+            } => operations.push(json!({
+                "op": "move",
+                "queue_id": queue_id,
+                "position": new_position,
+            })),
+            TracklistChange::CurrentChanged { .. } => {
+                for (item, previous_status) in queue.iter().zip(&previous_statuses) {
+                    if item.track.status != *previous_status {
+                        operations.push(json!({
+                            "op": "update",
+                            "queue_id": item.queue_id,
+                            "html": render(item),
+                        }));
+                    }
+                }
+            }
+            TracklistChange::TypeChanged { .. } => (),
+        }
+    }
+
+    Some(operations)
 }
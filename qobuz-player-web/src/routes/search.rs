@@ -5,7 +5,7 @@ use axum::{
     extract::{Path, Query, State},
     routing::get,
 };
-use qobuz_player_controls::models::SearchResults;
+use qobuz_player_controls::{client::SEARCH_PAGE_SIZE, models::SearchResults};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 
@@ -21,7 +21,9 @@ pub enum Tab {
 use crate::{AppState, ResponseResult, ok_or_error_page, ok_or_send_error_toast};
 
 pub fn routes() -> Router<std::sync::Arc<crate::AppState>> {
-    Router::new().route("/search/{tab}", get(index).post(search))
+    Router::new()
+        .route("/search/{tab}", get(index).post(search))
+        .route("/search/{tab}/more", get(more))
 }
 
 #[derive(Deserialize)]
@@ -29,6 +31,23 @@ struct SearchParameters {
     query: Option<String>,
 }
 
+#[derive(Deserialize)]
+struct MoreParameters {
+    query: String,
+    offset: u32,
+}
+
+impl Tab {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Tab::Albums => "albums",
+            Tab::Artists => "artists",
+            Tab::Playlists => "playlists",
+            Tab::Tracks => "tracks",
+        }
+    }
+}
+
 async fn index(
     State(state): State<Arc<AppState>>,
     Path(tab): Path<Tab>,
@@ -37,14 +56,16 @@ async fn index(
     let query = parameters
         .query
         .and_then(|s| if s.is_empty() { None } else { Some(s) });
-    let search_results = match query {
-        Some(query) => ok_or_error_page(&state, state.client.search(query).await)?,
+    let search_results = match &query {
+        Some(query) => ok_or_error_page(&state, state.client.search(query.clone()).await)?,
         None => SearchResults::default(),
     };
 
+    let more_url = query.and_then(|query| more_url(&tab, &query, &search_results, 0));
+
     Ok(state.render(
         "search.html",
-        &json!({"search_results": search_results, "tab": tab}),
+        &json!({"search_results": search_results, "tab": tab, "more_url": more_url}),
     ))
 }
 
@@ -56,13 +77,57 @@ async fn search(
     let query = parameters
         .query
         .and_then(|s| if s.is_empty() { None } else { Some(s) });
-    let search_results = match query {
-        Some(query) => ok_or_send_error_toast(&state, state.client.search(query).await)?,
+    let search_results = match &query {
+        Some(query) => ok_or_send_error_toast(&state, state.client.search(query.clone()).await)?,
         None => SearchResults::default(),
     };
 
+    let more_url = query.and_then(|query| more_url(&tab, &query, &search_results, 0));
+
+    Ok(state.render(
+        "search-content.html",
+        &json!({"search_results": search_results, "tab": tab, "more_url": more_url}),
+    ))
+}
+
+async fn more(
+    State(state): State<Arc<AppState>>,
+    Path(tab): Path<Tab>,
+    Query(parameters): Query<MoreParameters>,
+) -> ResponseResult {
+    let search_results = ok_or_send_error_toast(
+        &state,
+        state
+            .client
+            .search_page(parameters.query.clone(), parameters.offset)
+            .await,
+    )?;
+    let more_url = more_url(&tab, &parameters.query, &search_results, parameters.offset);
+
     Ok(state.render(
         "search-content.html",
-        &json!({"search_results": search_results, "tab": tab }),
+        &json!({"search_results": search_results, "tab": tab, "more_url": more_url}),
     ))
 }
+
+/// Url of the next page of the tab's results, if the page at `offset` was
+/// full
+fn more_url(tab: &Tab, query: &str, search_results: &SearchResults, offset: u32) -> Option<String> {
+    let count = match tab {
+        Tab::Albums => search_results.albums.len(),
+        Tab::Artists => search_results.artists.len(),
+        Tab::Playlists => search_results.playlists.len(),
+        Tab::Tracks => search_results.tracks.len(),
+    };
+
+    if count < SEARCH_PAGE_SIZE as usize {
+        return None;
+    }
+
+    let query = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("query", query)
+        .append_pair("offset", &(offset + SEARCH_PAGE_SIZE).to_string())
+        .finish();
+
+    Some(format!("/search/{}/more?{query}", tab.as_str()))
+}
@@ -0,0 +1,176 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Router,
+    extract::{Path, Query, Request, State},
+    http::{HeaderMap, Method, header::ACCEPT},
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post},
+};
+use qobuz_player_controls::{
+    AppResult,
+    client::{exchange_oauth_code, get_app_id, oauth_url},
+    database::{Credentials, Role},
+};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{AppState, ResponseResult, hx_redirect, ok_or_send_error_toast};
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/settings", get(index))
+        .route("/settings/qobuz/sign-out", post(sign_out))
+        .route("/qobuz-sign-in", get(sign_in_page))
+        .route("/qobuz-sign-in/start", get(start_sign_in))
+}
+
+/// Qobuz sends the browser back without the session cookie, so the callback
+/// is checked by its sign-in state instead of by the auth middleware
+pub fn callback_routes() -> Router<Arc<AppState>> {
+    Router::new().route("/qobuz-sign-in/callback/{sign_in}", get(sign_in_callback))
+}
+
+/// Sends page loads to the sign-in page while no Qobuz account is signed in
+pub async fn sign_in_middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if needs_sign_in(&state, &request) {
+        return Redirect::to("/qobuz-sign-in").into_response();
+    }
+
+    next.run(request).await
+}
+
+fn needs_sign_in(state: &AppState, request: &Request) -> bool {
+    let headers = request.headers();
+    let page_load = request.method() == Method::GET
+        && !headers.contains_key("HX-Request")
+        && headers
+            .get(ACCEPT)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|accept| accept.contains("text/html"));
+
+    let works_signed_out = ["/qobuz-sign-in", "/settings", "/users", "/webhooks"];
+    let path = request.uri().path();
+
+    page_load
+        && !works_signed_out.iter().any(|page| path.starts_with(page))
+        && !state.client.credentials_is_set().unwrap_or(true)
+}
+
+async fn index(State(state): State<Arc<AppState>>) -> ResponseResult {
+    let credentials = ok_or_send_error_toast(&state, state.database.get_credentials().await)?;
+
+    Ok(state.render(
+        "settings.html",
+        &json!({"qobuz_user_id": credentials.map(|credentials| credentials.user_id)}),
+    ))
+}
+
+async fn sign_out(State(state): State<Arc<AppState>>) -> ResponseResult {
+    ok_or_send_error_toast(&state, state.database.clear_user_auth_token().await)?;
+    ok_or_send_error_toast(&state, state.client.sign_out().await)?;
+
+    Ok(hx_redirect("/qobuz-sign-in"))
+}
+
+async fn sign_in_page(
+    State(state): State<Arc<AppState>>,
+    Extension(role): Extension<Role>,
+) -> impl IntoResponse {
+    render_sign_in(&state, role == Role::Admin, SignIn::NotStarted)
+}
+
+enum SignIn {
+    NotStarted,
+    Failed,
+    Done,
+}
+
+fn render_sign_in(state: &AppState, can_sign_in: bool, sign_in: SignIn) -> Response {
+    state.render(
+        "qobuz-sign-in.html",
+        &json!({
+            "can_sign_in": can_sign_in,
+            "failed": matches!(sign_in, SignIn::Failed),
+            "signed_in": matches!(sign_in, SignIn::Done),
+        }),
+    )
+}
+
+async fn start_sign_in(State(state): State<Arc<AppState>>, headers: HeaderMap) -> Response {
+    let app_id = match get_app_id().await {
+        Ok(app_id) => app_id,
+        Err(err) => {
+            tracing::warn!("Unable to start Qobuz sign-in: {err}");
+            return render_sign_in(&state, true, SignIn::Failed);
+        }
+    };
+
+    let callback = format!(
+        "{}/qobuz-sign-in/callback/{}",
+        origin(&state, &headers),
+        state.sign_ins.start()
+    );
+
+    Redirect::to(&oauth_url(&app_id, &callback)).into_response()
+}
+
+/// The address the browser reaches the player on, which Qobuz sends it back
+/// to. Proxies in front of the player pass theirs in the forwarded headers.
+fn origin(state: &AppState, headers: &HeaderMap) -> String {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+
+    let default_scheme = if state.secure_cookies {
+        "https"
+    } else {
+        "http"
+    };
+    let scheme = header("x-forwarded-proto").unwrap_or(default_scheme);
+    let host = header("x-forwarded-host")
+        .or(header("host"))
+        .unwrap_or("localhost");
+
+    format!("{scheme}://{host}")
+}
+
+#[derive(Deserialize)]
+struct Callback {
+    code_autorisation: Option<String>,
+}
+
+/// Renders a page instead of redirecting, as browsers leave out the session
+/// cookie for the rest of a redirect chain that started at Qobuz
+async fn sign_in_callback(
+    State(state): State<Arc<AppState>>,
+    Path(sign_in): Path<String>,
+    Query(callback): Query<Callback>,
+) -> Response {
+    if !state.sign_ins.finish(&sign_in) {
+        return render_sign_in(&state, false, SignIn::Failed);
+    }
+
+    let Some(code) = callback.code_autorisation else {
+        return render_sign_in(&state, false, SignIn::Failed);
+    };
+
+    match finish_sign_in(&state, &code).await {
+        Ok(()) => render_sign_in(&state, false, SignIn::Done),
+        Err(err) => {
+            tracing::warn!("Qobuz sign-in failed: {err}");
+            render_sign_in(&state, false, SignIn::Failed)
+        }
+    }
+}
+
+async fn finish_sign_in(state: &AppState, code: &str) -> AppResult<()> {
+    let app_id = get_app_id().await?;
+    let credentials: Credentials = exchange_oauth_code(code, &app_id).await?.into();
+
+    state.database.set_credentials(credentials.clone()).await?;
+    state.client.sign_in(credentials).await
+}
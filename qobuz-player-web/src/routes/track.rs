@@ -0,0 +1,33 @@
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    extract::{Path, State},
+    routing::get,
+};
+use serde_json::json;
+
+use crate::{AppState, ResponseResult, ok_or_send_error_toast};
+
+pub fn routes() -> Router<std::sync::Arc<crate::AppState>> {
+    Router::new().route("/track/{id}", get(index))
+}
+
+async fn index(State(state): State<Arc<AppState>>, Path(id): Path<u32>) -> ResponseResult {
+    let track = ok_or_send_error_toast(&state, state.client.track(id).await)?;
+
+    let credits: Vec<_> = track
+        .credits
+        .iter()
+        .map(|credit| json!({"name": credit.name, "roles": credit.roles.join(", ")}))
+        .collect();
+
+    Ok(state.render(
+        "track.html",
+        &json!({
+            "track": track,
+            "credits": credits,
+            "quality": track.quality(),
+        }),
+    ))
+}
@@ -0,0 +1,143 @@
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    extract::{Path, State},
+    routing::{delete, get, post},
+};
+use axum_extra::extract::Form;
+use qobuz_player_controls::{database::Role, notification::Notification};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    AppState, ResponseResult, hx_redirect, ok_or_send_error_toast,
+    users::{hash_password, new_token, token_hash},
+};
+
+const MIN_PASSWORD_LENGTH: usize = 8;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/users", get(index).post(add_user))
+        .route("/users/{id}", delete(remove_user))
+        .route("/users/tokens", post(add_token))
+        .route("/users/tokens/{id}", delete(remove_token))
+}
+
+fn roles() -> Vec<&'static str> {
+    Role::ALL.into_iter().map(Role::as_str).collect()
+}
+
+async fn index(State(state): State<Arc<AppState>>) -> ResponseResult {
+    let users = ok_or_send_error_toast(&state, state.database.get_web_users().await)?;
+    let users: Vec<_> = users
+        .into_iter()
+        .map(|user| json!({"id": user.id, "username": user.username, "role": user.role.as_str()}))
+        .collect();
+    let tokens = ok_or_send_error_toast(&state, state.database.get_api_tokens().await)?;
+
+    Ok(state.render(
+        "users.html",
+        &json!({
+            "users": users,
+            "tokens": tokens,
+            "roles": roles(),
+        }),
+    ))
+}
+
+#[derive(Deserialize)]
+struct AddUser {
+    username: String,
+    password: String,
+    role: Role,
+}
+
+async fn add_user(State(state): State<Arc<AppState>>, Form(req): Form<AddUser>) -> ResponseResult {
+    let username = req.username.trim();
+    if username.is_empty() {
+        return Err(state.send_toast(Notification::Error("A username is needed".into())));
+    }
+    if req.password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(state.send_toast(Notification::Error(format!(
+            "The password needs at least {MIN_PASSWORD_LENGTH} characters"
+        ))));
+    }
+
+    // Logins are required as soon as the first user exists
+    let has_users = ok_or_send_error_toast(&state, state.database.has_web_users().await)?;
+    if !has_users && req.role != Role::Admin {
+        return Err(state.send_toast(Notification::Error(
+            "The first user has to be an admin".into(),
+        )));
+    }
+
+    let password_hash = hash_password(&req.password);
+    ok_or_send_error_toast(
+        &state,
+        state
+            .database
+            .add_web_user(username, &password_hash, req.role)
+            .await,
+    )?;
+
+    Ok(hx_redirect("/users"))
+}
+
+async fn remove_user(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> ResponseResult {
+    let users = ok_or_send_error_toast(&state, state.database.get_web_users().await)?;
+    let admins = users.iter().filter(|user| user.role == Role::Admin).count();
+    let removes_last_admin = users
+        .iter()
+        .any(|user| user.id == id && user.role == Role::Admin)
+        && admins == 1
+        && users.len() > 1;
+
+    if removes_last_admin {
+        return Err(state.send_toast(Notification::Error(
+            "Remove the other users before the last admin".into(),
+        )));
+    }
+
+    ok_or_send_error_toast(&state, state.database.remove_web_user(id).await)?;
+
+    Ok(hx_redirect("/users"))
+}
+
+#[derive(Deserialize)]
+struct AddToken {
+    name: String,
+    role: Role,
+}
+
+/// The token is only shown once, right after it is made
+async fn add_token(
+    State(state): State<Arc<AppState>>,
+    Form(req): Form<AddToken>,
+) -> ResponseResult {
+    let name = req.name.trim();
+    if name.is_empty() {
+        return Err(state.send_toast(Notification::Error("A name is needed".into())));
+    }
+
+    let token = new_token();
+    ok_or_send_error_toast(
+        &state,
+        state
+            .database
+            .add_api_token(name, &token_hash(&token), req.role)
+            .await,
+    )?;
+
+    Ok(state.render(
+        "api-token-created.html",
+        &json!({"name": name, "token": token}),
+    ))
+}
+
+async fn remove_token(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> ResponseResult {
+    ok_or_send_error_toast(&state, state.database.remove_api_token(id).await)?;
+
+    Ok(hx_redirect("/users"))
+}
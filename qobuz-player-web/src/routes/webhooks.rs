@@ -0,0 +1,105 @@
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    extract::{Path, State},
+    response::IntoResponse,
+    routing::{delete, get},
+};
+use axum_extra::extract::Form;
+use qobuz_player_controls::notification::Notification;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    AppState, ResponseResult, hx_redirect, ok_or_send_error_toast, webhooks::WebhookEvent,
+};
+
+pub fn routes() -> Router<std::sync::Arc<crate::AppState>> {
+    Router::new()
+        .route("/webhooks", get(index).post(add))
+        .route("/webhooks/deliveries", get(deliveries))
+        .route("/webhooks/{id}", delete(remove))
+}
+
+fn events() -> Vec<serde_json::Value> {
+    WebhookEvent::ALL
+        .into_iter()
+        .map(|event| json!({"name": event.name(), "label": event.label()}))
+        .collect()
+}
+
+async fn index(State(state): State<Arc<AppState>>) -> ResponseResult {
+    let webhooks = ok_or_send_error_toast(&state, state.database.get_webhooks().await)?;
+    let webhooks: Vec<_> = webhooks
+        .into_iter()
+        .map(|webhook| {
+            let events: Vec<_> = webhook
+                .events
+                .iter()
+                .filter_map(|name| WebhookEvent::from_name(name))
+                .map(WebhookEvent::label)
+                .collect();
+
+            json!({"id": webhook.id, "url": webhook.url, "events": events.join(", ")})
+        })
+        .collect();
+
+    Ok(state.render(
+        "webhooks.html",
+        &json!({
+            "webhooks": webhooks,
+            "events": events(),
+            "deliveries": state.webhooks.log(),
+        }),
+    ))
+}
+
+async fn deliveries(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    state.render(
+        "webhook-deliveries.html",
+        &json!({"deliveries": state.webhooks.log()}),
+    )
+}
+
+#[derive(Deserialize)]
+struct AddWebhook {
+    url: String,
+    secret: String,
+    #[serde(default)]
+    events: Vec<String>,
+}
+
+async fn add(State(state): State<Arc<AppState>>, Form(req): Form<AddWebhook>) -> ResponseResult {
+    let url = req.url.trim();
+    if !url::Url::parse(url).is_ok_and(|url| matches!(url.scheme(), "http" | "https")) {
+        return Err(state.send_toast(Notification::Error(format!(
+            "Not an http or https url: {url}"
+        ))));
+    }
+    if req.secret.is_empty() {
+        return Err(state.send_toast(Notification::Error(
+            "A secret is needed to sign deliveries".into(),
+        )));
+    }
+
+    let events: Vec<String> = req
+        .events
+        .iter()
+        .filter_map(|name| WebhookEvent::from_name(name))
+        .map(|event| event.name().to_string())
+        .collect();
+
+    ok_or_send_error_toast(
+        &state,
+        state.database.add_webhook(url, &events, &req.secret).await,
+    )?;
+
+    Ok(hx_redirect("/webhooks"))
+}
+
+async fn remove(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> ResponseResult {
+    ok_or_send_error_toast(&state, state.database.remove_webhook(id).await)?;
+
+    Ok(hx_redirect("/webhooks"))
+}
@@ -0,0 +1,454 @@
+//! `/ws` keeps a client in sync over one socket. It starts with a snapshot of
+//! the player, follows with typed JSON events and takes control messages.
+
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    Extension, Router,
+    extract::{
+        State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    response::IntoResponse,
+    routing::get,
+};
+use qobuz_player_controls::{
+    TracklistChangeReceiver, TracklistReceiver, database::Role, notification::Notification,
+    tracklist_change::TracklistChange, tracklist_snapshot,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::{
+    AppState,
+    routes::api_v1::{AddToQueue, PlaybackStatus, Queue, QueueItem},
+};
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new().route("/ws", get(upgrade))
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerMessage {
+    /// The full state, sent on connect and on request
+    Snapshot {
+        status: PlaybackStatus,
+        position_ms: u64,
+        volume: u32,
+        queue: Queue,
+    },
+    Status {
+        status: PlaybackStatus,
+    },
+    Position {
+        position_ms: u64,
+    },
+    /// 0 to 100
+    Volume {
+        volume: u32,
+    },
+    /// The whole queue, when it changed too much to describe as changes
+    Queue {
+        queue: Queue,
+    },
+    QueueChanges {
+        changes: Vec<QueueChange>,
+    },
+    Notification {
+        severity: Severity,
+        message: String,
+        /// The change can be reverted with an `undo` message
+        undoable: bool,
+    },
+    /// A control message could not be handled
+    Error {
+        message: String,
+    },
+}
+
+/// Changes are applied in order. Positions refer to the queue as it is after
+/// the previous changes.
+#[derive(Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+enum QueueChange {
+    ItemsAdded {
+        position: usize,
+        items: Vec<QueueItem>,
+    },
+    ItemRemoved {
+        queue_id: u64,
+    },
+    ItemMoved {
+        queue_id: u64,
+        new_position: usize,
+    },
+    CurrentChanged {
+        queue_id: Option<u64>,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Severity {
+    Error,
+    Warning,
+    Success,
+    Info,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    Play,
+    Pause,
+    PlayPause,
+    Next,
+    Previous,
+    Seek {
+        position_ms: u64,
+    },
+    SetVolume {
+        volume: u32,
+    },
+    /// Takes the same fields as `POST /api/v1/queue`
+    AddToQueue(AddToQueue),
+    PlayQueueItem {
+        queue_id: u64,
+    },
+    RemoveQueueItem {
+        queue_id: u64,
+    },
+    MoveQueueItem {
+        queue_id: u64,
+        position: usize,
+    },
+    ClearQueue,
+    Undo,
+    Redo,
+    /// Ask for a new snapshot
+    Snapshot,
+}
+
+async fn upgrade(
+    State(state): State<Arc<AppState>>,
+    Extension(role): Extension<Role>,
+    ws: WebSocketUpgrade,
+) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| connection(socket, state, role))
+}
+
+async fn connection(mut socket: WebSocket, state: Arc<AppState>, role: Role) {
+    let (tracklist, mut tracklist_changes) =
+        tracklist_snapshot(&state.tracklist_receiver, &state.tracklist_changes);
+    let mut notifications = state.broadcast.subscribe();
+    let mut status = state.status_receiver.clone();
+    let mut position = state.position_receiver.clone();
+    let mut volume = state.volume_receiver.clone();
+
+    let snapshot = ServerMessage::Snapshot {
+        status: (*status.borrow_and_update()).into(),
+        position_ms: millis(*position.borrow_and_update()),
+        volume: percent(*volume.borrow_and_update()),
+        queue: (&tracklist).into(),
+    };
+    if send(&mut socket, &snapshot).await.is_err() {
+        return;
+    }
+
+    loop {
+        let message = tokio::select! {
+            received = socket.recv() => {
+                let text = match received {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                    Some(Ok(_)) => continue,
+                };
+
+                match serde_json::from_str::<ClientMessage>(&text) {
+                    Ok(ClientMessage::Snapshot) => ServerMessage::Snapshot {
+                        status: (*status.borrow()).into(),
+                        position_ms: millis(*position.borrow()),
+                        volume: percent(*volume.borrow()),
+                        queue: queue_snapshot(&state.tracklist_receiver, &mut tracklist_changes),
+                    },
+                    Ok(message) => match handle(&state, role, message) {
+                        Ok(()) => continue,
+                        Err(message) => ServerMessage::Error { message },
+                    },
+                    Err(err) => ServerMessage::Error {
+                        message: err.to_string(),
+                    },
+                }
+            }
+            Ok(()) = status.changed() => ServerMessage::Status {
+                status: (*status.borrow_and_update()).into(),
+            },
+            Ok(()) = position.changed() => ServerMessage::Position {
+                position_ms: millis(*position.borrow_and_update()),
+            },
+            Ok(()) = volume.changed() => ServerMessage::Volume {
+                volume: percent(*volume.borrow_and_update()),
+            },
+            changes = tracklist_changes.recv() => {
+                match queue_update(changes, &state.tracklist_receiver, &mut tracklist_changes) {
+                    Some(message) => message,
+                    None => return,
+                }
+            }
+            notification = notifications.recv() => match notification {
+                Ok(notification) => notification_message(notification),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return,
+            },
+        };
+
+        if send(&mut socket, &message).await.is_err() {
+            return;
+        }
+    }
+}
+
+async fn send(socket: &mut WebSocket, message: &ServerMessage) -> Result<(), axum::Error> {
+    let json = serde_json::to_string(message).expect("infallible");
+    socket.send(Message::Text(json.into())).await
+}
+
+fn handle(state: &AppState, role: Role, message: ClientMessage) -> Result<(), String> {
+    let required_role = match &message {
+        ClientMessage::AddToQueue(add) => add.required_role(),
+        _ => Role::Controller,
+    };
+    if role < required_role {
+        return Err("Not allowed for your role".into());
+    }
+
+    let controls = &state.controls;
+    let known_queue_id = |queue_id: u64| {
+        state
+            .tracklist_receiver
+            .borrow()
+            .position_of_queue_id(queue_id)
+            .map(|_| queue_id)
+            .ok_or_else(|| format!("No queue item {queue_id}"))
+    };
+
+    match message {
+        ClientMessage::Play => controls.play(),
+        ClientMessage::Pause => controls.pause(),
+        ClientMessage::PlayPause => controls.play_pause(),
+        ClientMessage::Next => controls.next(),
+        ClientMessage::Previous => controls.previous(),
+        ClientMessage::Seek { position_ms } => controls.seek(Duration::from_millis(position_ms)),
+        ClientMessage::SetVolume { volume } if volume <= 100 => {
+            controls.set_volume(volume as f32 / 100.0)
+        }
+        ClientMessage::SetVolume { .. } => return Err("The volume is 0 to 100".into()),
+        ClientMessage::AddToQueue(add) => add.apply(controls)?,
+        ClientMessage::PlayQueueItem { queue_id } => {
+            controls.skip_to_queue_item(known_queue_id(queue_id)?)
+        }
+        ClientMessage::RemoveQueueItem { queue_id } => {
+            controls.remove_queue_item(known_queue_id(queue_id)?)
+        }
+        ClientMessage::MoveQueueItem { queue_id, position } => {
+            controls.move_queue_item(known_queue_id(queue_id)?, position)
+        }
+        ClientMessage::ClearQueue => controls.clear_queue(),
+        ClientMessage::Undo => controls.undo_queue(),
+        ClientMessage::Redo => controls.redo_queue(),
+        ClientMessage::Snapshot => (),
+    }
+
+    Ok(())
+}
+
+/// Changes as deltas, or the whole queue when the queue was replaced
+/// Read the queue and start over with the changes made after it, so a
+/// change is never in the queue and sent on its own as well
+fn queue_snapshot(
+    tracklist: &TracklistReceiver,
+    tracklist_changes: &mut TracklistChangeReceiver,
+) -> Queue {
+    let (tracklist, changes) = tracklist_snapshot(tracklist, tracklist_changes);
+    *tracklist_changes = changes;
+    (&tracklist).into()
+}
+
+/// The message for changes from the player. A replaced queue or missed
+/// changes send the whole queue. None once the player is gone.
+fn queue_update(
+    changes: Result<Vec<TracklistChange>, RecvError>,
+    tracklist: &TracklistReceiver,
+    tracklist_changes: &mut TracklistChangeReceiver,
+) -> Option<ServerMessage> {
+    let message = match changes {
+        Ok(changes) => queue_message(&changes),
+        Err(RecvError::Lagged(_)) => None,
+        Err(RecvError::Closed) => return None,
+    };
+
+    Some(message.unwrap_or_else(|| ServerMessage::Queue {
+        queue: queue_snapshot(tracklist, tracklist_changes),
+    }))
+}
+
+/// None when the queue was replaced
+fn queue_message(changes: &[TracklistChange]) -> Option<ServerMessage> {
+    let mut queue_changes = vec![];
+
+    for change in changes {
+        queue_changes.push(match change {
+            TracklistChange::Replaced => return None,
+            TracklistChange::ItemsAdded { position, items } => QueueChange::ItemsAdded {
+                position: *position,
+                items: items.iter().map(Into::into).collect(),
+            },
+            TracklistChange::ItemRemoved { queue_id } => QueueChange::ItemRemoved {
+                queue_id: *queue_id,
+            },
+            TracklistChange::ItemMoved {
+                queue_id,
+                new_position,
+            } => QueueChange::ItemMoved {
+                queue_id: *queue_id,
+                new_position: *new_position,
+            },
+            TracklistChange::CurrentChanged { queue_id } => QueueChange::CurrentChanged {
+                queue_id: *queue_id,
+            },
+            // The API has no notion of the list type
+            TracklistChange::TypeChanged { .. } => continue,
+        });
+    }
+
+    Some(ServerMessage::QueueChanges {
+        changes: queue_changes,
+    })
+}
+
+fn notification_message(notification: Notification) -> ServerMessage {
+    let undoable = matches!(notification, Notification::Undoable(_));
+    let (severity, message) = match notification {
+        Notification::Error(message) => (Severity::Error, message),
+        Notification::Warning(message) => (Severity::Warning, message),
+        Notification::Success(message) => (Severity::Success, message),
+        Notification::Info(message) | Notification::Undoable(message) => (Severity::Info, message),
+    };
+
+    ServerMessage::Notification {
+        severity,
+        message,
+        undoable,
+    }
+}
+
+fn millis(position: Duration) -> u64 {
+    position.as_millis() as u64
+}
+
+fn percent(volume: f32) -> u32 {
+    (volume * 100.0).round() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use qobuz_player_controls::{
+        models::Track,
+        tracklist::{Tracklist, TracklistType},
+    };
+    use serde_json::json;
+    use tokio::sync::{broadcast, watch};
+
+    use super::*;
+
+    #[test]
+    fn client_messages() {
+        let message: ClientMessage =
+            serde_json::from_str(r#"{"type": "move_queue_item", "queue_id": 3, "position": 0}"#)
+                .unwrap();
+        assert!(matches!(
+            message,
+            ClientMessage::MoveQueueItem {
+                queue_id: 3,
+                position: 0
+            }
+        ));
+
+        let message: ClientMessage = serde_json::from_str(
+            r#"{"type": "add_to_queue", "track_ids": [1, 2], "mode": "next"}"#,
+        )
+        .unwrap();
+        assert!(matches!(message, ClientMessage::AddToQueue(_)));
+
+        assert!(serde_json::from_str::<ClientMessage>(r#"{"type": "stop"}"#).is_err());
+    }
+
+    #[test]
+    fn queue_changes() {
+        let track = Track {
+            id: 7,
+            title: "Blue in Green".into(),
+            ..Default::default()
+        };
+        let mut tracklist = Tracklist::new(TracklistType::Tracks, vec![]);
+        tracklist.push_track(track);
+        let items: Vec<_> = tracklist.queue().into_iter().cloned().collect();
+
+        let message = queue_message(&[
+            TracklistChange::ItemsAdded { position: 0, items },
+            TracklistChange::CurrentChanged { queue_id: Some(0) },
+        ]);
+        let message = serde_json::to_value(message).unwrap();
+
+        assert_eq!(message["type"], "queue_changes");
+        assert_eq!(message["changes"][0]["change"], "items_added");
+        assert_eq!(message["changes"][0]["items"][0]["track"]["id"], 7);
+        assert_eq!(
+            message["changes"][1],
+            json!({"change": "current_changed", "queue_id": 0})
+        );
+        assert!(queue_message(&[TracklistChange::Replaced]).is_none());
+    }
+
+    /// The player replaces the queue and adds to it before the connection
+    /// gets to the replacement
+    #[test]
+    fn replaced_queue_skips_the_changes_it_contains() {
+        let track = |id| Track {
+            id,
+            ..Default::default()
+        };
+        let mut tracklist = Tracklist::new(TracklistType::Tracks, vec![]);
+        tracklist.push_track(track(1));
+
+        let (tracklist_tx, tracklist_receiver) = watch::channel(tracklist.clone());
+        let (changes_tx, _) = broadcast::channel(10);
+        let mut tracklist_changes = changes_tx.subscribe();
+
+        let mut replaced = Tracklist::new(TracklistType::Tracks, vec![]);
+        replaced.reserve_queue_ids(tracklist.next_queue_id());
+        replaced.push_track(track(2));
+        tracklist_tx.send_modify(|current| {
+            _ = changes_tx.send(vec![TracklistChange::Replaced]);
+            *current = replaced.clone();
+        });
+
+        let mut added = replaced.clone();
+        added.push_track(track(3));
+        let items = vec![added.queue().last().cloned().cloned().unwrap()];
+        tracklist_tx.send_modify(|current| {
+            _ = changes_tx.send(vec![TracklistChange::ItemsAdded { position: 1, items }]);
+            *current = added;
+        });
+
+        let received = tracklist_changes.try_recv().map_err(|_| RecvError::Closed);
+        let message = queue_update(received, &tracklist_receiver, &mut tracklist_changes);
+        let message = serde_json::to_value(message.unwrap()).unwrap();
+        assert_eq!(message["type"], "queue");
+        assert_eq!(message["queue"]["items"][1]["track"]["id"], 3);
+
+        // The added track is in the queue already
+        assert!(tracklist_changes.try_recv().is_err());
+    }
+}
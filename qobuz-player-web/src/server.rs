@@ -0,0 +1,492 @@
+use std::{
+    collections::BTreeSet,
+    fmt::Display,
+    io,
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::{
+    Router,
+    extract::connect_info::Connected,
+    serve::{IncomingStream, Listener},
+};
+use futures::{FutureExt, future::BoxFuture};
+use qobuz_player_controls::{AppResult, error::Error};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+};
+use tokio_rustls::{
+    TlsAcceptor,
+    rustls::{
+        self,
+        pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+    },
+    server::TlsStream,
+};
+
+/// Clients that don't finish the handshake in time are dropped
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Owner and group may connect to a Unix socket listener
+#[cfg(unix)]
+const UNIX_SOCKET_MODE: u32 = 0o660;
+
+/// Where the web server accepts connections
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListenAddress {
+    /// An IPv4 or IPv6 socket address, like `0.0.0.0:9888` or `[::1]:9888`
+    Tcp(SocketAddr),
+    /// A Unix socket path, written as `unix:/run/qobuz-player/web.sock`
+    Unix(PathBuf),
+}
+
+impl FromStr for ListenAddress {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = s.strip_prefix("unix:") {
+            if path.is_empty() {
+                return Err("A Unix socket needs a path, like unix:/run/web.sock".into());
+            }
+            return Ok(Self::Unix(path.into()));
+        }
+
+        s.parse().map(Self::Tcp).map_err(|_| {
+            format!("{s} is not an address like 0.0.0.0:9888, [::]:9888 or unix:/path/to/socket")
+        })
+    }
+}
+
+impl Display for ListenAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tcp(address) => write!(f, "{address}"),
+            Self::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+pub enum Tls {
+    /// PEM files with the certificate chain and the private key
+    Files { cert: PathBuf, key: PathBuf },
+    /// A certificate made on the first start and kept in the data directory
+    SelfSigned,
+}
+
+pub struct ServerConfig {
+    pub listen: Vec<ListenAddress>,
+    /// HTTPS for the TCP addresses. Unix sockets stay plain, a proxy in
+    /// front of them terminates TLS.
+    pub tls: Option<Tls>,
+}
+
+/// The remote address of a connection. Unix socket peers have none.
+#[derive(Debug, Clone, Copy)]
+pub struct PeerAddress(pub Option<IpAddr>);
+
+impl Connected<IncomingStream<'_, TcpListener>> for PeerAddress {
+    fn connect_info(stream: IncomingStream<'_, TcpListener>) -> Self {
+        Self(Some(stream.remote_addr().ip()))
+    }
+}
+
+impl Connected<IncomingStream<'_, TlsListener>> for PeerAddress {
+    fn connect_info(stream: IncomingStream<'_, TlsListener>) -> Self {
+        Self(Some(stream.remote_addr().ip()))
+    }
+}
+
+#[cfg(unix)]
+impl Connected<IncomingStream<'_, tokio::net::UnixListener>> for PeerAddress {
+    fn connect_info(_: IncomingStream<'_, tokio::net::UnixListener>) -> Self {
+        Self(None)
+    }
+}
+
+pub enum BoundListener {
+    Tcp(TcpListener),
+    Tls(TlsListener),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixListener),
+}
+
+/// Binds every address before anything is served, so a taken address stops
+/// the start instead of leaving a half running server.
+pub async fn bind(config: &ServerConfig) -> AppResult<Vec<BoundListener>> {
+    let acceptor = config
+        .tls
+        .as_ref()
+        .map(|tls| tls_acceptor(tls, &config.listen))
+        .transpose()?;
+
+    let mut listeners = Vec::with_capacity(config.listen.len());
+
+    for address in &config.listen {
+        let listener = match address {
+            ListenAddress::Tcp(socket_address) => {
+                let listener = TcpListener::bind(socket_address)
+                    .await
+                    .map_err(|err| listen_error(address, err))?;
+
+                match &acceptor {
+                    Some(acceptor) => BoundListener::Tls(
+                        TlsListener::new(listener, acceptor.clone())
+                            .map_err(|err| listen_error(address, err))?,
+                    ),
+                    None => BoundListener::Tcp(listener),
+                }
+            }
+            ListenAddress::Unix(path) => bind_unix(address, path).await?,
+        };
+
+        let scheme = match &listener {
+            BoundListener::Tls(_) => "https",
+            _ => "http",
+        };
+        tracing::info!("Web server listening on {scheme}://{address}");
+
+        listeners.push(listener);
+    }
+
+    Ok(listeners)
+}
+
+#[cfg(unix)]
+async fn bind_unix(address: &ListenAddress, path: &Path) -> AppResult<BoundListener> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+    use tokio::net::{UnixListener, UnixStream};
+
+    // A socket file left behind by a player that is gone is replaced, any
+    // other file at the path is left alone
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            return Err(listen_error(address, "the path exists and is not a socket"));
+        }
+        if UnixStream::connect(path).await.is_ok() {
+            return Err(listen_error(address, "another process listens there"));
+        }
+        std::fs::remove_file(path).map_err(|err| listen_error(address, err))?;
+    }
+
+    let listener = UnixListener::bind(path).map_err(|err| listen_error(address, err))?;
+    // The proxy in front usually runs as another user, so its group may connect
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(UNIX_SOCKET_MODE))
+        .map_err(|err| listen_error(address, err))?;
+
+    Ok(BoundListener::Unix(listener))
+}
+
+#[cfg(not(unix))]
+async fn bind_unix(address: &ListenAddress, _path: &Path) -> AppResult<BoundListener> {
+    Err(listen_error(address, "Unix sockets are not supported here"))
+}
+
+/// Serves the router on every listener until the process ends
+pub async fn serve(listeners: Vec<BoundListener>, router: Router) -> AppResult<()> {
+    let servers = listeners
+        .into_iter()
+        .map(|listener| -> BoxFuture<'static, io::Result<()>> {
+            let router = router.clone();
+            match listener {
+                BoundListener::Tcp(listener) => axum::serve(
+                    listener,
+                    router.into_make_service_with_connect_info::<PeerAddress>(),
+                )
+                .into_future()
+                .boxed(),
+                BoundListener::Tls(listener) => axum::serve(
+                    listener,
+                    router.into_make_service_with_connect_info::<PeerAddress>(),
+                )
+                .into_future()
+                .boxed(),
+                #[cfg(unix)]
+                BoundListener::Unix(listener) => axum::serve(
+                    listener,
+                    router.into_make_service_with_connect_info::<PeerAddress>(),
+                )
+                .into_future()
+                .boxed(),
+            }
+        });
+
+    futures::future::try_join_all(servers)
+        .await
+        .expect("infallible");
+    Ok(())
+}
+
+/// Accepts TCP connections and hands them on once the TLS handshake is done.
+/// Handshakes run in their own tasks so a slow client doesn't hold up the
+/// others.
+pub struct TlsListener {
+    local_addr: SocketAddr,
+    connections: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+}
+
+impl TlsListener {
+    fn new(mut listener: TcpListener, acceptor: TlsAcceptor) -> io::Result<Self> {
+        let local_addr = listener.local_addr()?;
+        let (sender, connections) = mpsc::channel(32);
+
+        tokio::spawn(async move {
+            while !sender.is_closed() {
+                let (stream, address) = Listener::accept(&mut listener).await;
+                let acceptor = acceptor.clone();
+                let sender = sender.clone();
+
+                tokio::spawn(async move {
+                    match tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await
+                    {
+                        Ok(Ok(stream)) => _ = sender.send((stream, address)).await,
+                        Ok(Err(err)) => {
+                            tracing::debug!("TLS handshake with {address} failed: {err}")
+                        }
+                        Err(_) => tracing::debug!("TLS handshake with {address} timed out"),
+                    }
+                });
+            }
+        });
+
+        Ok(Self {
+            local_addr,
+            connections,
+        })
+    }
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.connections.recv().await {
+            Some(connection) => connection,
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+fn tls_acceptor(tls: &Tls, listen: &[ListenAddress]) -> AppResult<TlsAcceptor> {
+    let (certs, key) = match tls {
+        Tls::Files { cert, key } => (load_certs(cert)?, load_key(key)?),
+        Tls::SelfSigned => self_signed(listen)?,
+    };
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut config = rustls::ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .and_then(|builder| builder.with_no_client_auth().with_single_cert(certs, key))
+        .map_err(tls_error)?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+fn load_certs(path: &Path) -> AppResult<Vec<CertificateDer<'static>>> {
+    CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect())
+        .map_err(|err| tls_error(format!("{}: {err}", path.display())))
+}
+
+fn load_key(path: &Path) -> AppResult<PrivateKeyDer<'static>> {
+    PrivateKeyDer::from_pem_file(path)
+        .map_err(|err| tls_error(format!("{}: {err}", path.display())))
+}
+
+/// Keeps the certificate between starts, so browsers only have to accept it
+/// once. It is made again when the listen addresses name other hosts.
+fn self_signed(
+    listen: &[ListenAddress],
+) -> AppResult<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)> {
+    let Some(directory) = dirs::data_local_dir().map(|dir| dir.join("qobuz-player")) else {
+        return Err(tls_error("Unable to find the data directory"));
+    };
+    self_signed_in(&directory, listen)
+}
+
+fn self_signed_in(
+    directory: &Path,
+    listen: &[ListenAddress],
+) -> AppResult<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)> {
+    let cert_path = directory.join("web-cert.pem");
+    let key_path = directory.join("web-key.pem");
+
+    let mut names = BTreeSet::from(["localhost".to_string()]);
+    names.extend(listen.iter().filter_map(|address| match address {
+        ListenAddress::Tcp(address) if !address.ip().is_unspecified() => {
+            Some(address.ip().to_string())
+        }
+        _ => None,
+    }));
+
+    if cert_path.exists() && key_path.exists() {
+        let certs = load_certs(&cert_path)?;
+        if certs.first().and_then(certificate_names).as_ref() == Some(&names) {
+            return Ok((certs, load_key(&key_path)?));
+        }
+        tracing::info!("The listen addresses changed, making a new certificate");
+    }
+
+    let rcgen::CertifiedKey { cert, signing_key } =
+        rcgen::generate_simple_self_signed(Vec::from_iter(names)).map_err(tls_error)?;
+
+    std::fs::create_dir_all(directory).map_err(tls_error)?;
+    write_private(&key_path, signing_key.serialize_pem().as_bytes()).map_err(tls_error)?;
+    std::fs::write(&cert_path, cert.pem()).map_err(tls_error)?;
+
+    tracing::info!("Made a self-signed certificate at {}", cert_path.display());
+
+    Ok((
+        vec![cert.der().clone()],
+        PrivateKeyDer::Pkcs8(signing_key.serialize_der().into()),
+    ))
+}
+
+/// The DNS names and IP addresses the certificate is for
+fn certificate_names(cert: &CertificateDer) -> Option<BTreeSet<String>> {
+    use x509_parser::extensions::GeneralName;
+
+    let (_, cert) = x509_parser::parse_x509_certificate(cert).ok()?;
+    let names = cert.subject_alternative_name().ok()??;
+
+    let names = names
+        .value
+        .general_names
+        .iter()
+        .filter_map(|name| match name {
+            GeneralName::DNSName(name) => Some(name.to_string()),
+            GeneralName::IPAddress(bytes) => match bytes.len() {
+                4 => Some(IpAddr::from(<[u8; 4]>::try_from(*bytes).ok()?).to_string()),
+                16 => Some(IpAddr::from(<[u8; 16]>::try_from(*bytes).ok()?).to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    Some(names)
+}
+
+/// Writes a new file that only the owner can read from the start
+fn write_private(path: &Path, contents: &[u8]) -> io::Result<()> {
+    use std::io::Write;
+
+    match std::fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+        _ => {}
+    }
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(contents)
+}
+
+fn listen_error(address: &ListenAddress, err: impl Display) -> Error {
+    Error::ListenError {
+        address: address.to_string(),
+        message: err.to_string(),
+    }
+}
+
+fn tls_error(err: impl Display) -> Error {
+    Error::TlsError {
+        message: err.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listen_addresses() {
+        assert_eq!(
+            "0.0.0.0:9888".parse(),
+            Ok(ListenAddress::Tcp(SocketAddr::from(([0, 0, 0, 0], 9888))))
+        );
+        assert_eq!(
+            "[::1]:9888".parse::<ListenAddress>().map(|x| x.to_string()),
+            Ok("[::1]:9888".to_string())
+        );
+        assert_eq!(
+            "unix:/run/qobuz-player/web.sock".parse(),
+            Ok(ListenAddress::Unix("/run/qobuz-player/web.sock".into()))
+        );
+
+        assert!("unix:".parse::<ListenAddress>().is_err());
+        assert!("localhost".parse::<ListenAddress>().is_err());
+        assert!("0.0.0.0".parse::<ListenAddress>().is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn unix_socket_replaces_only_stale_sockets() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path =
+            std::env::temp_dir().join(format!("qobuz-player-web-test-{}.sock", std::process::id()));
+        let address = ListenAddress::Unix(path.clone());
+
+        std::fs::write(&path, "not a socket").unwrap();
+        assert!(bind_unix(&address, &path).await.is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "not a socket");
+        std::fs::remove_file(&path).unwrap();
+
+        // Left behind by a listener that is gone
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        let listener = bind_unix(&address, &path).await.unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, UNIX_SOCKET_MODE);
+
+        assert!(bind_unix(&address, &path).await.is_err());
+
+        drop(listener);
+        _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn self_signed_certificate_follows_the_listen_addresses() {
+        let directory =
+            std::env::temp_dir().join(format!("qobuz-player-web-cert-test-{}", std::process::id()));
+        _ = std::fs::remove_dir_all(&directory);
+        let listen = |address: &str| vec![address.parse::<ListenAddress>().unwrap()];
+
+        let (first, _) = self_signed_in(&directory, &listen("0.0.0.0:9888")).unwrap();
+        assert_eq!(
+            certificate_names(&first[0]),
+            Some(BTreeSet::from(["localhost".to_string()]))
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let key = std::fs::metadata(directory.join("web-key.pem")).unwrap();
+            assert_eq!(key.permissions().mode() & 0o777, 0o600);
+        }
+
+        let (same, _) = self_signed_in(&directory, &listen("0.0.0.0:9999")).unwrap();
+        assert_eq!(same, first);
+
+        let (other, _) = self_signed_in(&directory, &listen("[::1]:9888")).unwrap();
+        assert_ne!(other, first);
+        assert_eq!(
+            certificate_names(&other[0]),
+            Some(BTreeSet::from(["::1".to_string(), "localhost".to_string()]))
+        );
+
+        _ = std::fs::remove_dir_all(&directory);
+    }
+}
@@ -0,0 +1,273 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    time::{Duration, Instant},
+};
+
+use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier, password_hash::SaltString};
+use axum::http::Method;
+use parking_lot::Mutex;
+use qobuz_player_controls::database::Role;
+use sha2::{Digest, Sha256};
+
+/// Failed logins allowed from one address within [`LOGIN_WINDOW`]
+const MAX_FAILED_LOGINS: u32 = 5;
+const LOGIN_WINDOW: Duration = Duration::from_secs(15 * 60);
+/// Time to finish a Qobuz sign-in once it is started
+const SIGN_IN_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+pub fn hash_password(password: &str) -> String {
+    let salt = SaltString::encode_b64(&rand::random::<[u8; 16]>()).expect("valid salt length");
+
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .expect("infallible")
+        .to_string()
+}
+
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash).is_ok_and(|hash| {
+        Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok()
+    })
+}
+
+/// A random token for a session or an API client
+pub fn new_token() -> String {
+    hex(&rand::random::<[u8; 32]>())
+}
+
+/// Tokens are random enough that a plain hash keeps them safe at rest
+pub fn token_hash(token: &str) -> String {
+    hex(&Sha256::digest(token.as_bytes()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// What failed logins are counted against
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LoginKey {
+    /// The client address
+    Address(IpAddr),
+    /// The username tried, when the client address is unknown
+    Username(String),
+}
+
+/// Counts failed logins per key and locks the key out for the rest of the
+/// window after too many.
+#[derive(Default)]
+pub struct LoginLimiter {
+    failures: Mutex<HashMap<LoginKey, (u32, Instant)>>,
+}
+
+impl LoginLimiter {
+    pub fn is_locked_out(&self, key: &LoginKey) -> bool {
+        let mut failures = self.failures.lock();
+        failures.retain(|_, (_, since)| since.elapsed() < LOGIN_WINDOW);
+
+        failures
+            .get(key)
+            .is_some_and(|(count, _)| *count >= MAX_FAILED_LOGINS)
+    }
+
+    pub fn failed(&self, key: &LoginKey) {
+        self.failures
+            .lock()
+            .entry(key.clone())
+            .or_insert((0, Instant::now()))
+            .0 += 1;
+    }
+
+    pub fn succeeded(&self, key: &LoginKey) {
+        self.failures.lock().remove(key);
+    }
+}
+
+/// Qobuz sign-ins started from the web UI, by the state in their callback
+/// url. Qobuz sends the browser back without the session cookie, so the
+/// state is what shows an admin started the sign-in.
+#[derive(Default)]
+pub struct PendingSignIns {
+    states: Mutex<HashMap<String, Instant>>,
+}
+
+impl PendingSignIns {
+    pub fn start(&self) -> String {
+        let state = new_token();
+        let mut states = self.states.lock();
+        states.retain(|_, started| started.elapsed() < SIGN_IN_TIMEOUT);
+        states.insert(state.clone(), Instant::now());
+        state
+    }
+
+    /// A state can only finish one sign-in
+    pub fn finish(&self, state: &str) -> bool {
+        self.states
+            .lock()
+            .remove(state)
+            .is_some_and(|started| started.elapsed() < SIGN_IN_TIMEOUT)
+    }
+}
+
+/// The least role that may make the request. Handlers that mix queue
+/// requests with other actions check the role again themselves.
+pub fn required_role(method: &Method, path: &str) -> Role {
+    let admin_pages = [
+        "/settings",
+        "/qobuz-sign-in/",
+        "/users",
+        "/webhooks",
+        "/playlist/create",
+        "/playlist/add-track",
+    ];
+    if admin_pages.iter().any(|page| path.starts_with(page)) || path.ends_with("/tracks/edit") {
+        return Role::Admin;
+    }
+
+    if method == Method::GET || method == Method::HEAD {
+        return Role::Guest;
+    }
+
+    // Other methods on these paths, like clearing the queue, are not queue
+    // requests
+    let queue_requests = [
+        (Method::PUT, "/api/link/queue"),
+        (Method::PUT, "/api/track/action"),
+        (Method::POST, "/api/v1/queue"),
+        (Method::PUT, "/album/action"),
+        (Method::PUT, "/playlist/action"),
+    ];
+    if path.starts_with("/search/")
+        || queue_requests
+            .iter()
+            .any(|(queue_method, queue_path)| queue_method == method && *queue_path == path)
+    {
+        return Role::Guest;
+    }
+
+    let segments: Vec<&str> = path.split('/').filter(|x| !x.is_empty()).collect();
+    // The first segment names the page, like `playlist`
+    let plays = segments
+        .iter()
+        .skip(1)
+        .any(|segment| segment.starts_with("play") || *segment == "shuffle");
+    let links = segments.last() == Some(&"link");
+    let favorites = segments
+        .last()
+        .is_some_and(|segment| segment.ends_with("favorite"));
+
+    let controls = match segments.as_slice() {
+        ["api", "rfid", "reference", "album" | "playlist"] => false,
+        ["api", "rfid", ..] => true,
+        ["api", "v1", ..] => true,
+        ["api", ..] => !links,
+        _ => plays && !favorites && !links,
+    };
+
+    if controls {
+        Role::Controller
+    } else {
+        Role::Admin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passwords() {
+        let hash = hash_password("correct horse");
+
+        assert!(hash.starts_with("$argon2id$"));
+        assert!(verify_password("correct horse", &hash));
+        assert!(!verify_password("battery staple", &hash));
+        assert!(!verify_password("correct horse", "not a hash"));
+    }
+
+    #[test]
+    fn tokens() {
+        let token = new_token();
+
+        assert_eq!(token.len(), 64);
+        assert_ne!(token, new_token());
+        assert_eq!(token_hash(&token), token_hash(&token));
+        assert_ne!(token_hash(&token), token);
+    }
+
+    #[test]
+    fn login_limiter() {
+        let limiter = LoginLimiter::default();
+        let address = LoginKey::Address("192.168.1.20".parse().unwrap());
+
+        for _ in 0..MAX_FAILED_LOGINS {
+            assert!(!limiter.is_locked_out(&address));
+            limiter.failed(&address);
+        }
+        assert!(limiter.is_locked_out(&address));
+        assert!(!limiter.is_locked_out(&LoginKey::Address("192.168.1.21".parse().unwrap())));
+        assert!(!limiter.is_locked_out(&LoginKey::Username("admin".into())));
+
+        limiter.succeeded(&address);
+        assert!(!limiter.is_locked_out(&address));
+    }
+
+    #[test]
+    fn pending_sign_ins() {
+        let sign_ins = PendingSignIns::default();
+        let state = sign_ins.start();
+
+        assert!(!sign_ins.finish("forged"));
+        assert!(sign_ins.finish(&state));
+        assert!(!sign_ins.finish(&state));
+    }
+
+    #[test]
+    fn roles() {
+        let role = |method: Method, path: &str| required_role(&method, path);
+
+        assert_eq!(role(Method::GET, "/album/123"), Role::Guest);
+        assert_eq!(role(Method::GET, "/ws"), Role::Guest);
+        assert_eq!(role(Method::POST, "/search/albums"), Role::Guest);
+        assert_eq!(role(Method::PUT, "/api/link/queue"), Role::Guest);
+        assert_eq!(role(Method::POST, "/api/v1/queue"), Role::Guest);
+        assert_eq!(role(Method::DELETE, "/api/v1/queue"), Role::Controller);
+
+        assert_eq!(role(Method::PUT, "/api/play-pause"), Role::Controller);
+        assert_eq!(role(Method::POST, "/api/volume"), Role::Controller);
+        assert_eq!(role(Method::PUT, "/api/queue/reorder"), Role::Controller);
+        assert_eq!(role(Method::DELETE, "/api/v1/queue/4"), Role::Controller);
+        assert_eq!(role(Method::PUT, "/album/123/play"), Role::Controller);
+        assert_eq!(
+            role(Method::PUT, "/playlist/9/play/shuffle"),
+            Role::Controller
+        );
+        assert_eq!(
+            role(Method::PUT, "/artist/1/play-top-track/2"),
+            Role::Controller
+        );
+        assert_eq!(
+            role(Method::PUT, "/favorites/tracks/shuffle"),
+            Role::Controller
+        );
+        assert_eq!(
+            role(Method::PUT, "/api/rfid/reference/42"),
+            Role::Controller
+        );
+
+        assert_eq!(role(Method::GET, "/qobuz-sign-in"), Role::Guest);
+        assert_eq!(role(Method::GET, "/qobuz-sign-in/start"), Role::Admin);
+        assert_eq!(role(Method::GET, "/settings"), Role::Admin);
+        assert_eq!(role(Method::GET, "/users"), Role::Admin);
+        assert_eq!(role(Method::GET, "/webhooks"), Role::Admin);
+        assert_eq!(role(Method::DELETE, "/playlist/9"), Role::Admin);
+        assert_eq!(role(Method::PUT, "/album/123/set-favorite"), Role::Admin);
+        assert_eq!(role(Method::PUT, "/album/123/link"), Role::Admin);
+        assert_eq!(role(Method::POST, "/playlist/reorder"), Role::Admin);
+        assert_eq!(role(Method::POST, "/playlist/remove-track"), Role::Admin);
+        assert_eq!(role(Method::POST, "/api/rfid/reference/album"), Role::Admin);
+    }
+}
@@ -0,0 +1,507 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, SystemTime},
+};
+
+use hmac::{Hmac, KeyInit, Mac};
+use parking_lot::Mutex;
+use qobuz_player_controls::{
+    PlayerEventReceiver, Status, StatusReceiver, TracklistReceiver, VolumeReceiver,
+    database::{Database, Webhook},
+    models::Track,
+    player_event::PlayerEventKind,
+};
+use serde::Serialize;
+use sha2::Sha256;
+use tokio::sync::broadcast::error::RecvError;
+
+pub const SIGNATURE_HEADER: &str = "X-Qobuz-Player-Signature";
+const EVENT_HEADER: &str = "X-Qobuz-Player-Event";
+const DELIVERY_HEADER: &str = "X-Qobuz-Player-Delivery";
+
+const MAX_ATTEMPTS: u32 = 5;
+/// Doubled after every failed attempt
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(2);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Number of deliveries kept in the log
+const LOG_SIZE: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    TrackStarted,
+    TrackFinished,
+    Paused,
+    QueueEnded,
+    Error,
+    VolumeChanged,
+}
+
+impl WebhookEvent {
+    pub const ALL: [Self; 6] = [
+        Self::TrackStarted,
+        Self::TrackFinished,
+        Self::Paused,
+        Self::QueueEnded,
+        Self::Error,
+        Self::VolumeChanged,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::TrackStarted => "track_started",
+            Self::TrackFinished => "track_finished",
+            Self::Paused => "paused",
+            Self::QueueEnded => "queue_ended",
+            Self::Error => "error",
+            Self::VolumeChanged => "volume_changed",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::TrackStarted => "Track started",
+            Self::TrackFinished => "Track finished",
+            Self::Paused => "Paused",
+            Self::QueueEnded => "Queue ended",
+            Self::Error => "Error",
+            Self::VolumeChanged => "Volume changed",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|event| event.name() == name)
+    }
+
+    fn is_sent_to(self, webhook: &Webhook) -> bool {
+        webhook.events.is_empty() || webhook.events.iter().any(|name| name == self.name())
+    }
+}
+
+/// The JSON body of a delivery
+#[derive(Serialize)]
+struct Payload<'a> {
+    event: WebhookEvent,
+    /// Seconds since the Unix epoch
+    timestamp: u64,
+    /// The track the event is about, or the current track
+    track: Option<&'a Track>,
+    status: &'static str,
+    /// 0 to 100
+    volume: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<&'a str>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryState {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+/// An entry of the delivery log
+#[derive(Debug, Clone, Serialize)]
+pub struct Delivery {
+    pub id: u64,
+    pub url: String,
+    pub event: WebhookEvent,
+    /// When the event happened, in RFC 3339
+    pub time: String,
+    pub attempts: u32,
+    pub state: DeliveryState,
+    /// The last response status or error
+    pub response: String,
+}
+
+pub struct Webhooks {
+    database: Arc<Database>,
+    http: reqwest::Client,
+    next_delivery_id: AtomicU64,
+    log: Mutex<VecDeque<Delivery>>,
+}
+
+impl Webhooks {
+    pub fn new(database: Arc<Database>) -> Self {
+        Self {
+            database,
+            http: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .user_agent(concat!("qobuz-player/", env!("CARGO_PKG_VERSION")))
+                .build()
+                .expect("infallible"),
+            next_delivery_id: AtomicU64::new(1),
+            log: Default::default(),
+        }
+    }
+
+    /// Deliveries, newest first
+    pub fn log(&self) -> Vec<Delivery> {
+        self.log.lock().iter().cloned().collect()
+    }
+
+    /// Start delivering `body` to every webhook that wants the event
+    async fn dispatch(self: &Arc<Self>, event: WebhookEvent, body: String) {
+        let webhooks = match self.database.get_webhooks().await {
+            Ok(webhooks) => webhooks,
+            Err(err) => {
+                tracing::error!("Unable to load webhooks: {err}");
+                return;
+            }
+        };
+
+        let time = time::OffsetDateTime::now_utc()
+            .format(&time::format_description::well_known::Rfc3339)
+            .expect("infallible");
+
+        for webhook in webhooks.into_iter().filter(|x| event.is_sent_to(x)) {
+            self.start_delivery(webhook, event, body.clone(), &time);
+        }
+    }
+
+    /// Log the delivery as pending and deliver it in the background
+    fn start_delivery(
+        self: &Arc<Self>,
+        webhook: Webhook,
+        event: WebhookEvent,
+        body: String,
+        time: &str,
+    ) -> u64 {
+        let id = self.next_delivery_id.fetch_add(1, Ordering::Relaxed);
+
+        {
+            let mut log = self.log.lock();
+            log.push_front(Delivery {
+                id,
+                url: webhook.url.clone(),
+                event,
+                time: time.to_string(),
+                attempts: 0,
+                state: DeliveryState::Pending,
+                response: String::new(),
+            });
+            log.truncate(LOG_SIZE);
+        }
+
+        tokio::spawn(self.clone().deliver(id, webhook, event, body));
+        id
+    }
+
+    /// Post the body until the webhook takes it, backing off between attempts
+    async fn deliver(
+        self: Arc<Self>,
+        id: u64,
+        webhook: Webhook,
+        event: WebhookEvent,
+        body: String,
+    ) {
+        let signature = signature(&webhook.secret, body.as_bytes());
+        let mut delay = FIRST_RETRY_DELAY;
+
+        for attempt in 1..=MAX_ATTEMPTS {
+            let response = self
+                .http
+                .post(&webhook.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(EVENT_HEADER, event.name())
+                .header(DELIVERY_HEADER, id.to_string())
+                .header(SIGNATURE_HEADER, &signature)
+                .body(body.clone())
+                .send()
+                .await;
+
+            let (state, description) = match response {
+                Ok(response) if response.status().is_success() => {
+                    (DeliveryState::Delivered, response.status().to_string())
+                }
+                // The endpoint rejected the delivery and will keep doing so
+                Ok(response)
+                    if response.status().is_client_error()
+                        && response.status() != reqwest::StatusCode::REQUEST_TIMEOUT
+                        && response.status() != reqwest::StatusCode::TOO_MANY_REQUESTS =>
+                {
+                    (DeliveryState::Failed, response.status().to_string())
+                }
+                Ok(response) => (DeliveryState::Pending, response.status().to_string()),
+                Err(err) => (DeliveryState::Pending, err.to_string()),
+            };
+
+            let state = match state {
+                DeliveryState::Pending if attempt == MAX_ATTEMPTS => DeliveryState::Failed,
+                state => state,
+            };
+
+            self.update(id, |delivery| {
+                delivery.attempts = attempt;
+                delivery.state = state;
+                delivery.response = description;
+            });
+
+            if state != DeliveryState::Pending {
+                if state == DeliveryState::Failed {
+                    tracing::warn!("Webhook delivery {id} to {} failed", webhook.url);
+                }
+                return;
+            }
+
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+    }
+
+    fn update(&self, id: u64, update: impl FnOnce(&mut Delivery)) {
+        if let Some(delivery) = self.log.lock().iter_mut().find(|x| x.id == id) {
+            update(delivery);
+        }
+    }
+}
+
+/// `sha256=<hex>`, the HMAC-SHA256 of the body keyed with the shared secret
+pub fn signature(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("any key length");
+    mac.update(body);
+
+    let hex: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+
+    format!("sha256={hex}")
+}
+
+fn payload(
+    event: WebhookEvent,
+    track: Option<&Track>,
+    message: Option<&str>,
+    status: Status,
+    volume: f32,
+) -> String {
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    let status = match status {
+        Status::Playing => "playing",
+        Status::Buffering => "buffering",
+        Status::Paused => "paused",
+    };
+
+    serde_json::to_string(&Payload {
+        event,
+        timestamp,
+        track,
+        status,
+        volume: (volume * 100.0).round() as u32,
+        message,
+    })
+    .expect("infallible")
+}
+
+/// Turn player events and pauses into webhook deliveries
+pub async fn run(
+    webhooks: Arc<Webhooks>,
+    mut events: PlayerEventReceiver,
+    mut status: StatusReceiver,
+    tracklist: TracklistReceiver,
+    volume: VolumeReceiver,
+) {
+    let mut previous_status = *status.borrow();
+
+    loop {
+        let (event, track, message) = tokio::select! {
+            player_event = events.recv() => {
+                let kind = match player_event {
+                    Ok(player_event) => player_event.kind,
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return,
+                };
+
+                let current_track = || tracklist.borrow().current_track().cloned();
+                match kind {
+                    PlayerEventKind::TrackStarted { track } => {
+                        (WebhookEvent::TrackStarted, Some(track), None)
+                    }
+                    PlayerEventKind::TrackFinished { track } => {
+                        (WebhookEvent::TrackFinished, Some(track), None)
+                    }
+                    PlayerEventKind::QueueEnded => (WebhookEvent::QueueEnded, None, None),
+                    PlayerEventKind::ErrorOccurred { message } => {
+                        (WebhookEvent::Error, current_track(), Some(message))
+                    }
+                    PlayerEventKind::VolumeChanged { .. } => {
+                        (WebhookEvent::VolumeChanged, current_track(), None)
+                    }
+                    _ => continue,
+                }
+            }
+            changed = status.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+
+                let new_status = *status.borrow_and_update();
+                let paused = new_status == Status::Paused && previous_status != Status::Paused;
+                previous_status = new_status;
+                if !paused {
+                    continue;
+                }
+
+                let track = tracklist.borrow().current_track().cloned();
+                (WebhookEvent::Paused, track, None)
+            }
+        };
+
+        let body = payload(
+            event,
+            track.as_ref(),
+            message.as_deref(),
+            *status.borrow(),
+            *volume.borrow(),
+        );
+        webhooks.dispatch(event, body).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, extract::State, http::StatusCode, routing::post};
+    use tokio::{net::TcpListener, time::Instant};
+
+    use super::*;
+
+    /// Answers the first `failures` requests with a server error and the rest
+    /// with OK, and notes when each request came in
+    struct Endpoint {
+        failures: usize,
+        requests: Mutex<Vec<Instant>>,
+    }
+
+    async fn receive(State(endpoint): State<Arc<Endpoint>>) -> StatusCode {
+        let mut requests = endpoint.requests.lock();
+        requests.push(Instant::now());
+
+        match requests.len() <= endpoint.failures {
+            true => StatusCode::INTERNAL_SERVER_ERROR,
+            false => StatusCode::OK,
+        }
+    }
+
+    async fn delivery_after(
+        webhooks: &Webhooks,
+        id: u64,
+        done: impl Fn(&Delivery) -> bool,
+    ) -> Delivery {
+        for _ in 0..1000 {
+            let delivery = webhooks.log().into_iter().find(|x| x.id == id).unwrap();
+            if done(&delivery) {
+                return delivery;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("delivery {id} never got there");
+    }
+
+    #[test]
+    fn signature_is_hmac_sha256() {
+        // RFC 4231, test case 2
+        assert_eq!(
+            signature("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn event_filters() {
+        let webhook = |events: &[&str]| Webhook {
+            id: 1,
+            url: "http://localhost/hook".into(),
+            events: events.iter().map(|x| x.to_string()).collect(),
+            secret: "secret".into(),
+        };
+
+        assert!(WebhookEvent::Paused.is_sent_to(&webhook(&[])));
+        assert!(WebhookEvent::Paused.is_sent_to(&webhook(&["paused", "error"])));
+        assert!(!WebhookEvent::TrackStarted.is_sent_to(&webhook(&["paused"])));
+
+        for event in WebhookEvent::ALL {
+            assert_eq!(WebhookEvent::from_name(event.name()), Some(event));
+        }
+    }
+
+    #[tokio::test]
+    async fn deliveries_retry_with_backoff() {
+        let path = std::env::temp_dir().join(format!(
+            "qobuz-player-webhooks-test-{}.db",
+            std::process::id()
+        ));
+        let database = Arc::new(Database::open(&path).await.unwrap());
+        let webhooks = Arc::new(Webhooks::new(database));
+
+        let endpoint = Arc::new(Endpoint {
+            failures: MAX_ATTEMPTS as usize + 1,
+            requests: Default::default(),
+        });
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let router = Router::new()
+            .route("/hook", post(receive))
+            .with_state(endpoint.clone());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        // The database is set up, so time only moves for the deliveries
+        tokio::time::pause();
+
+        let webhook = Webhook {
+            id: 1,
+            url,
+            events: vec![],
+            secret: "secret".into(),
+        };
+
+        let failing =
+            webhooks.start_delivery(webhook.clone(), WebhookEvent::Paused, "{}".into(), "now");
+        let delivery = delivery_after(&webhooks, failing, |x| x.attempts == 1).await;
+        assert_eq!(delivery.state, DeliveryState::Pending);
+        assert_eq!(delivery.response, "500 Internal Server Error");
+
+        let delivery =
+            delivery_after(&webhooks, failing, |x| x.state != DeliveryState::Pending).await;
+        assert_eq!(delivery.state, DeliveryState::Failed);
+        assert_eq!(delivery.attempts, MAX_ATTEMPTS);
+
+        let delays: Vec<_> = endpoint
+            .requests
+            .lock()
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]).as_secs())
+            .collect();
+        assert_eq!(delays, vec![2, 4, 8, 16]);
+
+        let succeeding = webhooks.start_delivery(webhook, WebhookEvent::Paused, "{}".into(), "now");
+        let delivery = delivery_after(&webhooks, succeeding, |x| x.attempts == 1).await;
+        assert_eq!(delivery.state, DeliveryState::Pending);
+
+        let delivery =
+            delivery_after(&webhooks, succeeding, |x| x.state != DeliveryState::Pending).await;
+        assert_eq!(delivery.state, DeliveryState::Delivered);
+        assert_eq!(delivery.attempts, 2);
+        assert_eq!(delivery.response, "200 OK");
+
+        let requests = endpoint.requests.lock();
+        assert_eq!(requests.len(), MAX_ATTEMPTS as usize + 2);
+        assert_eq!((requests[6] - requests[5]).as_secs(), 2);
+        drop(requests);
+
+        for suffix in ["", "-wal", "-shm"] {
+            _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+        }
+    }
+}